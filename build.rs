@@ -0,0 +1,27 @@
+fn main()
+{
+    #[cfg(feature = "ffi")]
+    generate_header();
+}
+
+// Writes the header of the C API to mdnsresponder.h in OUT_DIR, where `ffi::HEADER` includes it
+// from. The source directory is left alone, it may be read-only.
+#[cfg(feature = "ffi")]
+fn generate_header()
+{
+    let crate_dir = std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo");
+    let out_dir = std::env::var("OUT_DIR").expect("OUT_DIR is set by cargo");
+
+    println!("cargo:rerun-if-changed=src/ffi.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    let config = cbindgen::Config::from_file(format!("{}/cbindgen.toml", crate_dir)).expect("cbindgen.toml is valid");
+
+    cbindgen::Builder::new()
+        // Only the C API, not the constants of the rest of the crate.
+        .with_src(format!("{}/src/ffi.rs", crate_dir))
+        .with_config(config)
+        .generate()
+        .expect("the C API can be translated to a header")
+        .write_to_file(format!("{}/mdnsresponder.h", out_dir));
+}
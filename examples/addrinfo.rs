@@ -0,0 +1,30 @@
+// Watches the IPv4 and IPv6 addresses of a host, printing addresses as they are added, removed
+// or expire without the daemon refreshing them.
+//
+//     cargo run --example addrinfo -- printer.local
+
+use mdnsresponder::{ Interface, MDnsResponder, MDnsResponderEvent, Protocol };
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let hostname = std::env::args().nth(1).unwrap_or_else(|| "localhost.local".to_string());
+
+    let responder = MDnsResponder::builder().expire_records(true).build().await?;
+    let mut lookup = responder.get_addr_info(Interface::Any, hostname, Protocol::Both).await?;
+
+    while let Some(event) = lookup.events.recv().await
+    {
+        match event
+        {
+            MDnsResponderEvent::AddressInfoResolved(info) => println!("+ {} {} (ttl {}s, interface {})", info.hostname, info.address, info.ttl, info.interface_index),
+            MDnsResponderEvent::AddressInfoRemoved(info) => println!("- {} {}", info.hostname, info.address),
+            MDnsResponderEvent::AddressExpired(info) => println!("! {} {} expired", info.hostname, info.address),
+            MDnsResponderEvent::NoSuchRecord { hostname, protocol } => println!("{} has no {:?} address", hostname, protocol),
+            MDnsResponderEvent::OperationFailed { error, .. } => return Err(error.into()),
+            _ => {}
+        }
+    }
+
+    return Ok(());
+}
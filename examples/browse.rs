@@ -0,0 +1,27 @@
+// Browses for services of a type and prints them as they come and go.
+//
+//     cargo run --example browse -- _http._tcp
+
+use mdnsresponder::{ Interface, MDnsResponder, MDnsResponderEvent };
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let service_type = std::env::args().nth(1).unwrap_or_else(|| "_http._tcp".to_string());
+
+    let responder = MDnsResponder::new(10).await?;
+    let mut browse = responder.browse(Interface::Any, service_type, "local".to_string()).await?;
+
+    while let Some(event) = browse.events.recv().await
+    {
+        match event
+        {
+            MDnsResponderEvent::ServiceAdded(service) => println!("+ {} on interface {}", service.display_name, service.interface_index),
+            MDnsResponderEvent::ServiceRemoved(service) => println!("- {} on interface {}", service.display_name, service.interface_index),
+            MDnsResponderEvent::OperationFailed { error, .. } => return Err(error.into()),
+            _ => {}
+        }
+    }
+
+    return Ok(());
+}
@@ -0,0 +1,57 @@
+// Queries records of a name by type number and prints their data, decoded for A, AAAA, PTR
+// and SRV records.
+//
+//     cargo run --example query -- _http._tcp.local 12
+
+use std::net::{ Ipv4Addr, Ipv6Addr };
+
+use mdnsresponder::{ dns_name, Interface, MDnsResponder, MDnsResponderEvent, Record };
+
+const RRTYPE_A: u16 = 1;
+const RRTYPE_PTR: u16 = 12;
+const RRTYPE_AAAA: u16 = 28;
+const RRTYPE_SRV: u16 = 33;
+const RRCLASS_IN: u16 = 1;
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut arguments = std::env::args().skip(1);
+    let name = arguments.next().unwrap_or_else(|| "_services._dns-sd._udp.local".to_string());
+    let rrtype = match arguments.next()
+    {
+        Some(rrtype) => rrtype.parse()?,
+        None => RRTYPE_PTR,
+    };
+
+    // Queries have no operation handle, their answers arrive on the shared events channel.
+    let mut responder = MDnsResponder::new(10).await?;
+    let context = responder.query(Interface::Any, name, rrtype, RRCLASS_IN).await?;
+
+    while let Some(event) = responder.events.recv().await
+    {
+        match event
+        {
+            MDnsResponderEvent::RecordAdded(record) => println!("+ {} {}", record.name, data(&record)),
+            MDnsResponderEvent::RecordRemoved(record) => println!("- {} {}", record.name, data(&record)),
+            MDnsResponderEvent::OperationFailed { context: failed, error } if failed == context => return Err(error.into()),
+            _ => {}
+        }
+    }
+
+    return Ok(());
+}
+
+fn data(record: &Record) -> String
+{
+    let decoded = match record.rrtype
+    {
+        RRTYPE_A => <[u8; 4]>::try_from(record.rdata.as_slice()).ok().map(|octets| Ipv4Addr::from(octets).to_string()),
+        RRTYPE_AAAA => <[u8; 16]>::try_from(record.rdata.as_slice()).ok().map(|octets| Ipv6Addr::from(octets).to_string()),
+        RRTYPE_PTR => dns_name::from_wire(&record.rdata),
+        RRTYPE_SRV => record.srv().map(|srv| format!("{}:{} (priority {}, weight {})", srv.target, srv.port, srv.priority, srv.weight)),
+        _ => None,
+    };
+
+    return decoded.unwrap_or_else(|| format!("{} bytes of type {}", record.rdata.len(), record.rrtype));
+}
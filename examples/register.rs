@@ -0,0 +1,57 @@
+// Registers a web server and updates its TXT record every few seconds, like a service that
+// publishes its state. Deregisters on Ctrl-C.
+//
+//     cargo run --example register -- "My Web Server" 8080
+
+use std::time::Duration;
+
+use mdnsresponder::{ MDnsResponder, MDnsResponderEvent, RegisterOptions, TxtRecord };
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut arguments = std::env::args().skip(1);
+    let name = arguments.next().unwrap_or_else(|| "mdnsresponder example".to_string());
+    let port = match arguments.next()
+    {
+        Some(port) => port.parse()?,
+        None => 8080,
+    };
+
+    let mut responder = MDnsResponder::new(10).await?;
+
+    let txt = TxtRecord::builder().value("path", "/").value("requests", "0").build()?;
+    let mut registration = responder.register_with_options(name, "_http._tcp", port, RegisterOptions::new().txt(&txt)).await?;
+
+    let mut updates = tokio::time::interval(Duration::from_secs(5));
+    let mut requests = 0;
+
+    loop
+    {
+        tokio::select!
+        {
+            event = responder.events.recv() => match event
+            {
+                // The daemon may have renamed the service to avoid a conflict.
+                Some(MDnsResponderEvent::ServiceRegistered(service)) => println!("Registered as {}", service.display_name),
+                Some(MDnsResponderEvent::NameConflict(service)) => println!("Name conflict for {}", service.display_name),
+                Some(MDnsResponderEvent::OperationFailed { error, .. }) => return Err(error.into()),
+                Some(_) => {}
+                None => break,
+            },
+            _ = updates.tick() =>
+            {
+                requests += 7;
+                let txt = TxtRecord::builder().value("path", "/").value("requests", requests.to_string()).build()?;
+                registration.update_txt_record(&txt).await?;
+                println!("Updated TXT record, requests={}", requests);
+            }
+            _ = tokio::signal::ctrl_c() => break,
+        }
+    }
+
+    registration.deregister().await?;
+    responder.close().await;
+
+    return Ok(());
+}
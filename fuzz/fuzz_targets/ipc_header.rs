@@ -0,0 +1,16 @@
+// Parses arbitrary bytes as an IPC message header. Run with `cargo fuzz run ipc_header` from the
+// root of the crate.
+
+#![no_main]
+#![allow(clippy::needless_return)]
+
+use libfuzzer_sys::fuzz_target;
+use mdnsresponder::fuzzing::IpcHeader;
+
+fuzz_target!(|data: &[u8]|
+{
+    if let Some(header) = IpcHeader::decode(data)
+    {
+        assert_eq!(header.encode(), &data[..28]);
+    }
+});
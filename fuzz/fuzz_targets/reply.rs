@@ -0,0 +1,38 @@
+// Parses arbitrary bytes with the parser of every reply operation, the first byte picks the
+// operation. Replies that have an encoder have to decode the same after encoding what was
+// parsed. Run with `cargo fuzz run reply` from the root of the crate.
+
+#![no_main]
+#![allow(clippy::needless_return)]
+
+use libfuzzer_sys::fuzz_target;
+use mdnsresponder::fuzzing::{ parse_reply, AddressInfoReply, BrowseReply, ResolveReply };
+
+// Reply operation codes are 64 to 73, the codes after them are unknown.
+const REPLY_OPERATION_START: u32 = 64;
+const REPLY_OPERATIONS: u32 = 12;
+
+fuzz_target!(|data: &[u8]|
+{
+    let Some((operation, body)) = data.split_first() else
+    {
+        return;
+    };
+
+    parse_reply(REPLY_OPERATION_START + *operation as u32 % REPLY_OPERATIONS, body);
+
+    if let Some(reply) = BrowseReply::decode(body)
+    {
+        assert_eq!(BrowseReply::decode(&reply.encode()), Some(reply));
+    }
+
+    if let Some(reply) = ResolveReply::decode(body)
+    {
+        assert_eq!(ResolveReply::decode(&reply.encode()), Some(reply));
+    }
+
+    if let Some(reply) = AddressInfoReply::decode(body)
+    {
+        assert_eq!(AddressInfoReply::decode(&reply.encode()), Some(reply));
+    }
+});
@@ -0,0 +1,16 @@
+// Parses arbitrary bytes as the header of a reply body. Run with `cargo fuzz run reply_header`
+// from the root of the crate.
+
+#![no_main]
+#![allow(clippy::needless_return)]
+
+use libfuzzer_sys::fuzz_target;
+use mdnsresponder::fuzzing::ReplyHeader;
+
+fuzz_target!(|data: &[u8]|
+{
+    if let Some(header) = ReplyHeader::decode(data)
+    {
+        assert_eq!(header.encode(), &data[..12]);
+    }
+});
@@ -0,0 +1,56 @@
+use std::fmt;
+use std::time::Instant;
+
+/// Kind of request an `ActiveOperation` was started with.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OperationKind
+{
+    Browse,
+    /// A browse for the service types in use, see `MDnsRequester::enumerate_service_types`.
+    ServiceTypes,
+    Resolve,
+    AddrInfo,
+    Register,
+    Query,
+    Enumeration,
+    PortMapping,
+}
+
+impl fmt::Display for OperationKind
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let name = match self
+        {
+            OperationKind::Browse => "browse",
+            OperationKind::ServiceTypes => "service_types",
+            OperationKind::Resolve => "resolve",
+            OperationKind::AddrInfo => "addrinfo",
+            OperationKind::Register => "register",
+            OperationKind::Query => "query",
+            OperationKind::Enumeration => "enumeration",
+            OperationKind::PortMapping => "port_mapping",
+        };
+
+        return write!(f, "{}", name);
+    }
+}
+
+/// An operation that was started and is not cancelled yet, returned by
+/// `MDnsRequester::active_operations`.
+///
+/// Operations stay active until they are cancelled, or their `Operation` handle is dropped. A
+/// registration stays active until it is deregistered, so one that is listed long after it is
+/// needed points to a missing deregister.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ActiveOperation
+{
+    /// The context identifier of the operation, e.g. `Operation::context`.
+    pub context: u64,
+    pub kind: OperationKind,
+    /// What the operation is for: the service type browsed for, resolved or registered, the
+    /// hostname looked up or the record name queried. Empty for enumerations and port mappings.
+    pub subject: String,
+    /// When the request was written.
+    pub started: Instant,
+}
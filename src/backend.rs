@@ -0,0 +1,101 @@
+use std::io;
+use std::path::{ Path, PathBuf };
+
+use crate::builder::DEFAULT_SOCKET_PATH;
+use crate::{ ConnectFuture, Connector, Delegate, Transport };
+
+// Socket of the POSIX build of mDNSResponder, mdnsd, as packaged by Linux distributions.
+const MDNSD_SOCKET_PATH: &str = "/var/run/mdnsd";
+// Socket of mdnsd on Android, which runs it as a system service.
+const ANDROID_SOCKET_PATH: &str = "/dev/socket/mdnsd";
+// Override the socket path, the first one that is set wins. DNSSD_UDS_PATH is also read by the
+// dns_sd client library of mDNSResponder.
+const SOCKET_PATH_VARIABLES: &[&str] = &["MDNS_UDS_PATH", "DNSSD_UDS_PATH"];
+const AVAHI_SOCKET_PATHS: &[&str] = &["/var/run/avahi-daemon/socket", "/run/avahi-daemon/socket"];
+
+/// Which daemon to connect to on Unix systems, see `MDnsResponderBuilder::backend`.
+///
+/// Linux systems run either mdnsd, the POSIX build of mDNSResponder, or Avahi. Avahi only
+/// offers the dns_sd API through its compatibility library, which talks to avahi-daemon over
+/// D-Bus, and has no socket that speaks the mDNSResponder protocol. Where Avahi is the only
+/// daemon, connecting fails with an error that says so.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum Backend
+{
+    /// The socket named by the `MDNS_UDS_PATH` or else the `DNSSD_UDS_PATH` environment
+    /// variable if one of them is set. Otherwise the first socket that exists of
+    /// mDNSResponder, mdnsd and mdnsd on Android at `/dev/socket/mdnsd`, looked up on every
+    /// connect.
+    #[default]
+    Auto,
+    /// mDNSResponder at `/var/run/mDNSResponder`.
+    MDnsResponder,
+    /// mdnsd at `/var/run/mdnsd`.
+    Mdnsd,
+}
+
+impl Backend
+{
+    /// Returns the path of the socket this backend connects to, `None` if there is none.
+    pub fn socket_path(&self) -> Option<PathBuf>
+    {
+        return self.candidates().into_iter().find(|path| path.exists());
+    }
+
+    fn candidates(&self) -> Vec<PathBuf>
+    {
+        return match self
+        {
+            Backend::Auto => match SOCKET_PATH_VARIABLES.iter().find_map(std::env::var_os)
+            {
+                Some(path) => vec![PathBuf::from(path)],
+                None => vec![
+                    PathBuf::from(DEFAULT_SOCKET_PATH),
+                    PathBuf::from(MDNSD_SOCKET_PATH),
+                    PathBuf::from(ANDROID_SOCKET_PATH),
+                ],
+            },
+            Backend::MDnsResponder => vec![PathBuf::from(DEFAULT_SOCKET_PATH)],
+            Backend::Mdnsd => vec![PathBuf::from(MDNSD_SOCKET_PATH)],
+        };
+    }
+
+    fn not_found(&self) -> io::Error
+    {
+        let candidates: Vec<String> = self.candidates().iter().map(|path| path.display().to_string()).collect();
+        let mut message = format!("no daemon socket at {}", candidates.join(" or "));
+
+        if AVAHI_SOCKET_PATHS.iter().any(|path| Path::new(path).exists())
+        {
+            message.push_str(", only avahi-daemon is running, which does not offer the mDNSResponder protocol");
+        }
+
+        return io::Error::new(io::ErrorKind::NotFound, message);
+    }
+}
+
+impl Connector for Backend
+{
+    fn connect(&self, delegate: Option<Delegate>) -> ConnectFuture<'_>
+    {
+        return Box::pin(async move
+        {
+            let Some(socket_path) = self.socket_path() else
+            {
+                return Err(self.not_found());
+            };
+
+            return Transport::Unix(socket_path).connect(delegate).await;
+        });
+    }
+
+    fn daemon_instance(&self) -> Option<u64>
+    {
+        return Transport::Unix(self.socket_path()?).daemon_instance();
+    }
+
+    fn transport(&self) -> Option<Transport>
+    {
+        return Some(Transport::Unix(self.socket_path()?));
+    }
+}
@@ -0,0 +1,340 @@
+// Command line tool in the style of dns-sd, to browse, resolve, register and look up services,
+// records and addresses. Built with the `cli` feature.
+
+use std::net::{ Ipv4Addr, Ipv6Addr };
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::{ Duration, Instant };
+
+use clap::{ Parser, Subcommand };
+use tokio::sync::mpsc;
+
+use mdnsresponder::{ dns_name, Interface, MDnsResponder, MDnsResponderError, MDnsResponderEvent, Protocol, Record };
+
+const RRCLASS_IN: u16 = 1;
+
+// Record types that can be given by name, the others by number.
+const RRTYPES: &[(&str, u16)] = &[
+    ("A", 1),
+    ("NS", 2),
+    ("CNAME", 5),
+    ("PTR", 12),
+    ("HINFO", 13),
+    ("TXT", 16),
+    ("AAAA", 28),
+    ("SRV", 33),
+    ("NSEC", 47),
+    ("HTTPS", 65),
+    ("ANY", 255),
+];
+
+#[derive(Parser)]
+#[command(name = "mdns", version, about = "Browse, resolve, register and look up services with mDNSResponder")]
+struct Arguments
+{
+    /// Print every event as a line of JSON instead of a table.
+    #[arg(long, global = true)]
+    json: bool,
+    /// Index of the interface to use, 0 for all interfaces.
+    #[arg(long, short, global = true, default_value_t = 0)]
+    interface: u32,
+    /// Stop after this many seconds instead of running until interrupted.
+    #[arg(long, short, global = true)]
+    timeout: Option<u64>,
+    /// Path of the socket of the daemon, instead of the default one.
+    #[arg(long, global = true)]
+    socket_path: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command
+{
+    /// Browse for instances of a service type, e.g. _http._tcp.
+    Browse
+    {
+        service_type: String,
+        /// Domain to browse, the default domains if empty.
+        #[arg(default_value = "")]
+        domain: String,
+    },
+    /// Resolve an instance to its host, port and TXT record.
+    Resolve
+    {
+        name: String,
+        service_type: String,
+        #[arg(default_value = "local")]
+        domain: String,
+    },
+    /// Register a service until interrupted.
+    Register
+    {
+        /// Instance name, picked by the daemon if empty.
+        name: String,
+        service_type: String,
+        port: u16,
+        /// TXT strings, e.g. path=/index.html.
+        txt: Vec<String>,
+        /// Domain to register in, the default domains if empty.
+        #[arg(long, default_value = "")]
+        domain: String,
+        /// Host the service runs on, this host if empty.
+        #[arg(long, default_value = "")]
+        host: String,
+    },
+    /// Query for records of a name.
+    Query
+    {
+        name: String,
+        /// Record type by name, e.g. AAAA, or by number.
+        #[arg(default_value = "A")]
+        rrtype: String,
+    },
+    /// Look up the addresses of a host.
+    Addrinfo
+    {
+        hostname: String,
+        /// Only look up IPv4 addresses.
+        #[arg(long, conflicts_with = "v6")]
+        v4: bool,
+        /// Only look up IPv6 addresses.
+        #[arg(long)]
+        v6: bool,
+    },
+}
+
+#[tokio::main]
+async fn main() -> ExitCode
+{
+    return match run(Arguments::parse()).await
+    {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) =>
+        {
+            eprintln!("mdns: {}", e);
+            ExitCode::FAILURE
+        }
+    };
+}
+
+async fn run(arguments: Arguments) -> Result<(), Box<dyn std::error::Error>>
+{
+    let mut builder = MDnsResponder::builder();
+    if let Some(socket_path) = arguments.socket_path
+    {
+        builder = builder.socket_path(socket_path);
+    }
+
+    let mut responder = builder.build().await?;
+    let interface = Interface::from(arguments.interface);
+    let json = arguments.json;
+
+    // Browses, resolves and address lookups deliver their events on the channel of their
+    // operation, registrations and queries on the shared one.
+    let mut operation = None;
+    let mut _registration = None;
+
+    match arguments.command
+    {
+        Command::Browse { service_type, domain } =>
+        {
+            print_header(json, &format!("{:<4} {:>3} {:<24} {:<24} {}", "A/R", "if", "Domain", "Service Type", "Instance Name"));
+            operation = Some(responder.browse(interface, service_type, domain).await?);
+        }
+        Command::Resolve { name, service_type, domain } =>
+        {
+            operation = Some(responder.resolve(interface, name, service_type, domain).await?);
+        }
+        Command::Register { name, service_type, port, txt, domain, host } =>
+        {
+            _registration = Some(responder.register(interface, name, service_type, domain, host, port, txt).await?);
+        }
+        Command::Query { name, rrtype } =>
+        {
+            let Some(rrtype) = parse_rrtype(&rrtype) else
+            {
+                return Err(format!("unknown record type {}", rrtype).into());
+            };
+
+            print_header(json, &format!("{:<4} {:<32} {:<6} {:>6} {}", "A/R", "Name", "Type", "TTL", "Data"));
+            responder.query(interface, name, rrtype, RRCLASS_IN).await?;
+        }
+        Command::Addrinfo { hostname, v4, v6 } =>
+        {
+            let protocol = match (v4, v6)
+            {
+                (true, _) => Protocol::IPv4,
+                (_, true) => Protocol::IPv6,
+                _ => Protocol::Both,
+            };
+
+            print_header(json, &format!("{:<4} {:>3} {:<32} {:<40} {}", "A/R", "if", "Hostname", "Address", "TTL"));
+            operation = Some(responder.get_addr_info(interface, hostname, protocol).await?);
+        }
+    }
+
+    let deadline = arguments.timeout.map(|seconds| Instant::now() + Duration::from_secs(seconds));
+    let mut result: Result<(), Box<dyn std::error::Error>> = Ok(());
+
+    loop
+    {
+        let event = match operation.as_mut()
+        {
+            Some(operation) => next_event(&mut operation.events, deadline).await,
+            None => next_event(&mut responder.events, deadline).await,
+        };
+
+        let Some(event) = event else
+        {
+            break;
+        };
+
+        print_event(json, &event);
+
+        if let MDnsResponderEvent::OperationFailed { error, .. } = event
+        {
+            result = Err(MDnsResponderError::DaemonError(error).into());
+            break;
+        }
+    }
+
+    drop(operation);
+    responder.close().await;
+
+    return result;
+}
+
+// Waits for the next event until the deadline, if there is one.
+async fn next_event(events: &mut mpsc::Receiver<MDnsResponderEvent>, deadline: Option<Instant>) -> Option<MDnsResponderEvent>
+{
+    return match deadline
+    {
+        Some(deadline) => tokio::time::timeout_at(deadline.into(), events.recv()).await.ok().flatten(),
+        None => events.recv().await,
+    };
+}
+
+fn print_header(json: bool, header: &str)
+{
+    if !json
+    {
+        println!("{}", header);
+    }
+}
+
+fn print_event(json: bool, event: &MDnsResponderEvent)
+{
+    if json
+    {
+        match serde_json::to_string(event)
+        {
+            Ok(line) => println!("{}", line),
+            Err(e) => eprintln!("mdns: failed to write event as JSON: {}", e),
+        }
+
+        return;
+    }
+
+    match event
+    {
+        MDnsResponderEvent::ServiceAdded(service) | MDnsResponderEvent::ServiceRemoved(service) =>
+        {
+            println!("{:<4} {:>3} {:<24} {:<24} {}", add_or_remove(service.flags.add), service.interface_index, service.domain, service.service_type, service.display_name);
+        }
+        MDnsResponderEvent::ServiceResolved(resolved) =>
+        {
+            println!("{} can be reached at {}:{} (interface {})", resolved.full_name, resolved.host_target, resolved.port, resolved.interface_index);
+            if !resolved.txt_data.is_empty()
+            {
+                println!(" {}", resolved.txt_data.join(" "));
+            }
+        }
+        MDnsResponderEvent::ServiceRegistered(service) =>
+        {
+            println!("Registered {}.{}{}", service.display_name, service.service_type, service.domain);
+        }
+        MDnsResponderEvent::NameConflict(service) =>
+        {
+            println!("Name conflict for {}.{}{}", service.display_name, service.service_type, service.domain);
+        }
+        MDnsResponderEvent::RecordAdded(record) | MDnsResponderEvent::RecordRemoved(record) =>
+        {
+            println!("{:<4} {:<32} {:<6} {:>6} {}", add_or_remove(record.flags.add), record.name, rrtype_name(record.rrtype), record.ttl, record_data(record));
+        }
+        MDnsResponderEvent::AddressInfoResolved(info) | MDnsResponderEvent::AddressInfoRemoved(info) =>
+        {
+            println!("{:<4} {:>3} {:<32} {:<40} {}", add_or_remove(info.flags.add), info.interface_index, info.hostname, info.address, info.ttl);
+        }
+        MDnsResponderEvent::NoSuchRecord { hostname, protocol } =>
+        {
+            println!("No {:?} address for {}", protocol, hostname);
+        }
+        MDnsResponderEvent::OperationFailed { error, .. } =>
+        {
+            eprintln!("mdns: {}", error);
+        }
+        MDnsResponderEvent::ConnectionLost => eprintln!("mdns: connection to the daemon lost"),
+        MDnsResponderEvent::Reconnected { transport: Some(transport) } => eprintln!("mdns: reconnected to the daemon at {}", transport),
+        MDnsResponderEvent::Reconnected { transport: None } => eprintln!("mdns: reconnected to the daemon"),
+        MDnsResponderEvent::DaemonRestarted => eprintln!("mdns: the daemon restarted"),
+        _ => {}
+    }
+}
+
+fn add_or_remove(add: bool) -> &'static str
+{
+    return if add { "Add" } else { "Rmv" };
+}
+
+fn parse_rrtype(rrtype: &str) -> Option<u16>
+{
+    if let Some((_, value)) = RRTYPES.iter().find(|(name, _)| name.eq_ignore_ascii_case(rrtype))
+    {
+        return Some(*value);
+    }
+
+    return rrtype.parse().ok();
+}
+
+fn rrtype_name(rrtype: u16) -> String
+{
+    return match RRTYPES.iter().find(|(_, value)| *value == rrtype)
+    {
+        Some((name, _)) => name.to_string(),
+        None => rrtype.to_string(),
+    };
+}
+
+// Shows the data of the common record types in their text form and the others in hex.
+fn record_data(record: &Record) -> String
+{
+    let data = &record.rdata;
+
+    let text = match record.rrtype
+    {
+        1 => <[u8; 4]>::try_from(data.as_slice()).ok().map(|octets| Ipv4Addr::from(octets).to_string()),
+        28 => <[u8; 16]>::try_from(data.as_slice()).ok().map(|octets| Ipv6Addr::from(octets).to_string()),
+        2 | 5 | 12 => dns_name::from_wire(data),
+        16 => Some(txt_strings(data)),
+        33 => record.srv().map(|srv| format!("{} {} {} {}", srv.priority, srv.weight, srv.port, srv.target)),
+        _ => None,
+    };
+
+    return text.unwrap_or_else(|| data.iter().map(|byte| format!("{:02x}", byte)).collect());
+}
+
+// The length-prefixed strings of a TXT record, quoted.
+fn txt_strings(mut data: &[u8]) -> String
+{
+    let mut strings = Vec::new();
+
+    while let Some((length, rest)) = data.split_first()
+    {
+        let length = (*length as usize).min(rest.len());
+        strings.push(format!("{:?}", String::from_utf8_lossy(&rest[..length])));
+        data = &rest[length..];
+    }
+
+    return strings.join(" ");
+}
@@ -0,0 +1,6 @@
+// Generates the Kotlin, Swift and Python bindings of the `uniffi` feature, see the `bindings`
+// module.
+fn main()
+{
+    uniffi::uniffi_bindgen_main()
+}
@@ -0,0 +1,396 @@
+//! High-level API for Kotlin, Swift and Python through UniFFI, enabled with the `uniffi` feature.
+//!
+//! `MDnsClient` runs the connection on a thread of its own. Its methods wait for the request to
+//! be sent and events are delivered to `EventListener`s on that thread, so listeners should
+//! return quickly. Browses and resolves report to the listener they were started with, all other
+//! events such as the outcome of registrations go to the listener of the client.
+//!
+//! Generate the bindings from a build of the library, e.g. for Kotlin:
+//!
+//! ```sh
+//! cargo rustc --release --lib --features uniffi --crate-type cdylib
+//! cargo run --features uniffi --bin uniffi-bindgen -- generate --library target/release/libmdnsresponder.so --language kotlin --out-dir bindings
+//! ```
+//!
+//! In Python:
+//!
+//! ```python
+//! from mdnsresponder import EventListener, MDnsClient
+//!
+//! class Printer(EventListener):
+//!     def on_event(self, event):
+//!         if event.is_service_added():
+//!             print(event.service.display_name)
+//!
+//! client = MDnsClient(Printer())
+//! browse = client.browse(0, "_ipp._tcp", "local", Printer())
+//! ```
+
+use std::future::Future;
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use log::error;
+use tokio::sync::mpsc;
+
+use crate::{ Interface, MDnsRequester, MDnsResponder, MDnsResponderBuilder, MDnsResponderError, MDnsResponderEvent, Operation, Resolved, Service, ServiceRegistration };
+
+/// A service found by a browse, or registered.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct ServiceInfo
+{
+    /// The instance name with DNS escapes, pass it to `MDnsClient::resolve` as it is.
+    pub name: String,
+    /// The instance name with DNS escapes undone, for showing to users.
+    pub display_name: String,
+    pub service_type: String,
+    pub domain: String,
+    pub interface_index: u32,
+}
+
+/// The answer to a resolve.
+#[derive(Debug, Clone, uniffi::Record)]
+pub struct ResolvedService
+{
+    pub full_name: String,
+    pub host_target: String,
+    pub port: u16,
+    /// TXT strings with non-printable bytes escaped as `\DDD`.
+    pub txt: Vec<String>,
+    pub interface_index: u32,
+}
+
+/// An event passed to an `EventListener`.
+#[derive(Debug, Clone, uniffi::Enum)]
+pub enum ClientEvent
+{
+    ServiceAdded { service: ServiceInfo },
+    ServiceRemoved { service: ServiceInfo },
+    ServiceResolved { resolved: ResolvedService },
+    ServiceRegistered { service: ServiceInfo },
+    NameConflict { service: ServiceInfo },
+    /// The daemon stopped an operation, with the kDNSServiceErr_* code.
+    OperationFailed { context: u64, error_code: i32 },
+    ConnectionLost,
+    /// The connection was set up again, with the transport it was opened on, e.g.
+    /// `unix:/var/run/mdnsd`.
+    Reconnected { transport: Option<String> },
+}
+
+/// Receives the events of a client or an operation, implemented in the foreign language.
+#[uniffi::export(callback_interface)]
+pub trait EventListener: Send + Sync
+{
+    fn on_event(&self, event: ClientEvent);
+}
+
+/// A connection to mDNSResponder, see the module documentation.
+#[derive(uniffi::Object)]
+pub struct MDnsClient
+{
+    requester: MDnsRequester,
+    // Dropped after the requester, so the cancels of a drop are still queued on the connection.
+    runtime: tokio::runtime::Runtime,
+}
+
+/// A running browse or resolve. Dropping it cancels the operation.
+#[derive(uniffi::Object)]
+pub struct OperationHandle
+{
+    context: u64,
+    cancelled: Arc<AtomicBool>,
+    task: Mutex<Option<tokio::task::JoinHandle<()>>>,
+}
+
+/// A registered service, which stays registered until `deregister` is called or the client is
+/// closed.
+#[derive(uniffi::Object)]
+pub struct RegistrationHandle
+{
+    context: u64,
+    registration: Mutex<Option<ServiceRegistration>>,
+    runtime: tokio::runtime::Handle,
+}
+
+#[uniffi::export]
+impl MDnsClient
+{
+    /// Connects to mDNSResponder at its default socket path. Events that do not belong to a
+    /// browse or resolve go to `listener`.
+    #[uniffi::constructor]
+    pub fn new(listener: Box<dyn EventListener>) -> Result<Arc<Self>, MDnsResponderError>
+    {
+        return Self::create(MDnsResponder::builder(), listener);
+    }
+
+    /// Connects to mDNSResponder at the given socket path.
+    #[uniffi::constructor]
+    pub fn with_socket_path(socket_path: String, listener: Box<dyn EventListener>) -> Result<Arc<Self>, MDnsResponderError>
+    {
+        return Self::create(MDnsResponder::builder().socket_path(socket_path), listener);
+    }
+
+    /// Starts browsing for services of a type, e.g. `_ipp._tcp`, in a domain, e.g. `local` or an
+    /// empty string for the default domains. `interface_index` is 0 for all interfaces.
+    pub fn browse(
+        &self,
+        interface_index: u32,
+        service_type: String,
+        domain: String,
+        listener: Box<dyn EventListener>,
+    ) -> Result<Arc<OperationHandle>, MDnsResponderError>
+    {
+        let operation = self.block_on(self.requester.browse(Interface::from(interface_index), service_type, domain))?;
+
+        return Ok(self.deliver(operation, listener));
+    }
+
+    /// Resolves a service found by a browse to its host, port and TXT record. The resolve keeps
+    /// reporting changes until it is cancelled.
+    pub fn resolve(
+        &self,
+        interface_index: u32,
+        name: String,
+        service_type: String,
+        domain: String,
+        listener: Box<dyn EventListener>,
+    ) -> Result<Arc<OperationHandle>, MDnsResponderError>
+    {
+        let operation = self.block_on(self.requester.resolve(Interface::from(interface_index), name, service_type, domain))?;
+
+        return Ok(self.deliver(operation, listener));
+    }
+
+    /// Registers a service. An empty name lets the daemon pick one. The outcome is reported to
+    /// the listener of the client as `ServiceRegistered` or `NameConflict`.
+    pub fn register(
+        &self,
+        interface_index: u32,
+        name: String,
+        service_type: String,
+        domain: String,
+        port: u16,
+        txt: Vec<String>,
+    ) -> Result<Arc<RegistrationHandle>, MDnsResponderError>
+    {
+        let registration = self.block_on(self.requester.register(Interface::from(interface_index), name, service_type, domain, String::new(), port, txt))?;
+
+        return Ok(Arc::new(RegistrationHandle
+        {
+            context: registration.context(),
+            registration: Mutex::new(Some(registration)),
+            runtime: self.runtime.handle().clone(),
+        }));
+    }
+
+    /// Closes the connection, cancelling all operations and registrations.
+    pub fn close(&self)
+    {
+        self.block_on(self.requester.close());
+    }
+}
+
+impl MDnsClient
+{
+    fn create(builder: MDnsResponderBuilder, listener: Box<dyn EventListener>) -> Result<Arc<Self>, MDnsResponderError>
+    {
+        let runtime = match tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) =>
+            {
+                error!("Failed to start runtime for bindings: {}", e);
+                return Err(MDnsResponderError::RuntimeCreationFailed);
+            }
+        };
+
+        let responder = runtime.block_on(builder.build())?;
+        let requester = responder.requester();
+        runtime.spawn(deliver_shared(responder.events, listener));
+
+        return Ok(Arc::new(MDnsClient
+        {
+            requester,
+            runtime,
+        }));
+    }
+
+    fn block_on<F: Future>(&self, future: F) -> F::Output
+    {
+        return block_on_handle(self.runtime.handle(), future);
+    }
+
+    fn deliver(&self, operation: Operation, listener: Box<dyn EventListener>) -> Arc<OperationHandle>
+    {
+        let context = operation.context();
+        let cancelled = Arc::new(AtomicBool::new(false));
+        let task = self.runtime.spawn(deliver_operation(operation, listener, cancelled.clone()));
+
+        return Arc::new(OperationHandle
+        {
+            context,
+            cancelled,
+            task: Mutex::new(Some(task)),
+        });
+    }
+}
+
+#[uniffi::export]
+impl OperationHandle
+{
+    /// Returns the context identifier of the operation, as reported with `OperationFailed`.
+    pub fn context(&self) -> u64
+    {
+        return self.context;
+    }
+
+    /// Cancels the operation, its listener is not called anymore afterwards unless it is running
+    /// right now.
+    pub fn cancel(&self)
+    {
+        self.cancelled.store(true, Ordering::SeqCst);
+
+        // Dropping the operation with the task cancels it with the daemon.
+        if let Some(task) = self.task.lock().unwrap().take()
+        {
+            task.abort();
+        }
+    }
+}
+
+impl Drop for OperationHandle
+{
+    fn drop(&mut self)
+    {
+        self.cancel();
+    }
+}
+
+#[uniffi::export]
+impl RegistrationHandle
+{
+    /// Returns the context identifier of the registration, as reported with `OperationFailed`.
+    pub fn context(&self) -> u64
+    {
+        return self.context;
+    }
+
+    /// Replaces the TXT record of the service.
+    pub fn update_txt(&self, txt: Vec<String>) -> Result<(), MDnsResponderError>
+    {
+        let mut registration = self.registration.lock().unwrap();
+
+        return match registration.as_mut()
+        {
+            Some(registration) => block_on_handle(&self.runtime, registration.update_txt(txt)),
+            None => Ok(()),
+        };
+    }
+
+    /// Removes the service from the network. Does nothing if it was already deregistered.
+    pub fn deregister(&self) -> Result<(), MDnsResponderError>
+    {
+        let registration = self.registration.lock().unwrap().take();
+
+        return match registration
+        {
+            Some(registration) => block_on_handle(&self.runtime, registration.deregister()),
+            None => Ok(()),
+        };
+    }
+}
+
+// Listeners run on the worker thread of the runtime, which has to hand its work to another thread
+// before it can wait for the runtime.
+fn block_on_handle<F: Future>(runtime: &tokio::runtime::Handle, future: F) -> F::Output
+{
+    if tokio::runtime::Handle::try_current().is_ok()
+    {
+        return tokio::task::block_in_place(|| runtime.block_on(future));
+    }
+
+    return runtime.block_on(future);
+}
+
+// Passes the events of a browse or resolve to its listener until it is cancelled or the
+// connection closes.
+async fn deliver_operation(mut operation: Operation, listener: Box<dyn EventListener>, cancelled: Arc<AtomicBool>)
+{
+    while let Some(event) = operation.events.recv().await
+    {
+        // The task is only stopped at an await that has to wait, so events that are already
+        // queued would still be delivered after a cancel.
+        if cancelled.load(Ordering::SeqCst)
+        {
+            break;
+        }
+
+        for event in client_events(event)
+        {
+            listener.on_event(event);
+        }
+    }
+}
+
+async fn deliver_shared(mut events: mpsc::Receiver<MDnsResponderEvent>, listener: Box<dyn EventListener>)
+{
+    while let Some(event) = events.recv().await
+    {
+        for event in client_events(event)
+        {
+            listener.on_event(event);
+        }
+    }
+}
+
+// Events the bindings have no counterpart for are left out.
+fn client_events(event: MDnsResponderEvent) -> Vec<ClientEvent>
+{
+    return match event
+    {
+        MDnsResponderEvent::ServiceAdded(service) => vec![ClientEvent::ServiceAdded { service: service.into() }],
+        MDnsResponderEvent::ServiceRemoved(service) => vec![ClientEvent::ServiceRemoved { service: service.into() }],
+        MDnsResponderEvent::ServicesBatch { added, removed } => removed
+            .into_iter()
+            .map(|service| ClientEvent::ServiceRemoved { service: service.into() })
+            .chain(added.into_iter().map(|service| ClientEvent::ServiceAdded { service: service.into() }))
+            .collect(),
+        MDnsResponderEvent::ServiceResolved(resolved) => vec![ClientEvent::ServiceResolved { resolved: resolved.into() }],
+        MDnsResponderEvent::ServiceRegistered(service) => vec![ClientEvent::ServiceRegistered { service: service.into() }],
+        MDnsResponderEvent::NameConflict(service) => vec![ClientEvent::NameConflict { service: service.into() }],
+        MDnsResponderEvent::OperationFailed { context, error } => vec![ClientEvent::OperationFailed { context, error_code: error.code() }],
+        MDnsResponderEvent::ConnectionLost => vec![ClientEvent::ConnectionLost],
+        MDnsResponderEvent::Reconnected { transport } => vec![ClientEvent::Reconnected { transport: transport.map(|transport| transport.to_string()) }],
+        _ => vec![],
+    };
+}
+
+impl From<Service> for ServiceInfo
+{
+    fn from(service: Service) -> Self
+    {
+        return ServiceInfo
+        {
+            name: service.name,
+            display_name: service.display_name,
+            service_type: service.service_type,
+            domain: service.domain,
+            interface_index: service.interface_index,
+        };
+    }
+}
+
+impl From<Resolved> for ResolvedService
+{
+    fn from(resolved: Resolved) -> Self
+    {
+        return ResolvedService
+        {
+            full_name: resolved.full_name,
+            host_target: resolved.host_target,
+            port: resolved.port,
+            txt: resolved.txt_data,
+            interface_index: resolved.interface_index,
+        };
+    }
+}
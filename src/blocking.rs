@@ -0,0 +1,196 @@
+//! Blocking API for applications that are not async, such as command line tools.
+//!
+//! `BlockingMDnsResponder` runs the connection on a runtime of its own and waits for each call
+//! to complete. It must not be used from within an async context.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use mdnsresponder::blocking::BlockingMDnsResponder;
+//! use mdnsresponder::{Interface, MDnsResponderEvent};
+//!
+//! fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let responder = BlockingMDnsResponder::new(10)?;
+//!     let mut browse = responder.browse(Interface::Any, "_ipp._tcp", "local")?;
+//!     while let Some(event) = browse.next_event() {
+//!         if let MDnsResponderEvent::ServiceAdded(service) = event {
+//!             let resolved = responder.resolve_once(&service.name, &service.service_type, &service.domain, std::time::Duration::from_secs(5))?;
+//!             println!("{} at {}:{}", service.display_name, resolved.host_target, resolved.port);
+//!         }
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::future::Future;
+use std::time::Duration;
+
+use log::error;
+
+use crate::{ CloseReport, Interface, MDnsRequester, MDnsResponder, MDnsResponderBuilder, MDnsResponderError, MDnsResponderEvent, Operation, Resolved };
+
+/// A `MDnsResponder` with blocking methods, see the module documentation.
+pub struct BlockingMDnsResponder
+{
+    // Dropped before the runtime, so the cancels of a drop are still queued on the connection.
+    responder: MDnsResponder,
+    runtime: tokio::runtime::Runtime,
+}
+
+/// A running browse, resolve or address info operation with blocking methods, returned by
+/// `BlockingMDnsResponder`. Dropping it cancels the operation, like dropping an `Operation`.
+pub struct BlockingOperation
+{
+    operation: Operation,
+    runtime: tokio::runtime::Handle,
+}
+
+impl BlockingMDnsResponder
+{
+    /// Connects to mDNSResponder like `MDnsResponder::new`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::RuntimeCreationFailed)` if the runtime cannot be started,
+    /// or the errors of `MDnsResponder::new`.
+    pub fn new(channel_buffer_size: usize) -> Result<Self, MDnsResponderError>
+    {
+        return Self::with_builder(MDnsResponder::builder().channel_buffer_size(channel_buffer_size));
+    }
+
+    /// Connects to mDNSResponder with the settings of a builder.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::RuntimeCreationFailed)` if the runtime cannot be started,
+    /// or the errors of `MDnsResponderBuilder::build`.
+    pub fn with_builder(builder: MDnsResponderBuilder) -> Result<Self, MDnsResponderError>
+    {
+        // A worker thread of its own keeps the connection going while the caller is not blocked
+        // in one of the calls.
+        let runtime = match tokio::runtime::Builder::new_multi_thread().worker_threads(1).enable_all().build()
+        {
+            Ok(runtime) => runtime,
+            Err(e) =>
+            {
+                error!("Failed to start runtime for blocking API: {}", e);
+                return Err(MDnsResponderError::RuntimeCreationFailed);
+            }
+        };
+
+        let responder = runtime.block_on(builder.build())?;
+
+        return Ok(BlockingMDnsResponder
+        {
+            responder,
+            runtime,
+        });
+    }
+
+    /// Returns the async requester of the connection, for requests that have no blocking method.
+    /// Run them with `block_on`.
+    pub fn requester(&self) -> MDnsRequester
+    {
+        return self.responder.requester();
+    }
+
+    /// Runs a future on the runtime of the connection and waits for it, e.g. one of the methods
+    /// of `requester`.
+    pub fn block_on<F: Future>(&self, future: F) -> F::Output
+    {
+        return self.runtime.block_on(future);
+    }
+
+    // The runtime the connection runs on, for spawning tasks next to it.
+    #[cfg(feature = "ffi")]
+    pub(crate) fn handle(&self) -> &tokio::runtime::Handle
+    {
+        return self.runtime.handle();
+    }
+
+    /// Starts browsing, see `MDnsRequester::browse`.
+    pub fn browse(
+        &self,
+        interface: Interface,
+        service_type: impl Into<String>,
+        service_domain: impl Into<String>,
+    ) -> Result<BlockingOperation, MDnsResponderError>
+    {
+        let operation = self.runtime.block_on(self.responder.browse(interface, service_type, service_domain.into()))?;
+
+        return Ok(BlockingOperation
+        {
+            operation,
+            runtime: self.runtime.handle().clone(),
+        });
+    }
+
+    /// Resolves a service and waits for its first answer, see `MDnsRequester::resolve_once`.
+    pub fn resolve_once(
+        &self,
+        service_name: &str,
+        service_type: &str,
+        service_domain: &str,
+        timeout: Duration,
+    ) -> Result<Resolved, MDnsResponderError>
+    {
+        return self.runtime.block_on(self.responder.resolve_once(
+            service_name.to_string(),
+            service_type.to_string(),
+            service_domain.to_string(),
+            timeout,
+        ));
+    }
+
+    /// Waits for the next event on the shared events channel. Returns `None` once the connection
+    /// is closed.
+    pub fn next_event(&mut self) -> Option<MDnsResponderEvent>
+    {
+        return self.runtime.block_on(self.responder.events.recv());
+    }
+
+    /// Waits at most `timeout` for the next event on the shared events channel, see
+    /// `MDnsResponder::recv_timeout`.
+    pub fn next_event_timeout(&mut self, timeout: Duration) -> Result<Option<MDnsResponderEvent>, MDnsResponderError>
+    {
+        return self.runtime.block_on(self.responder.recv_timeout(timeout));
+    }
+
+    /// Closes the connection, see `MDnsRequester::close`.
+    pub fn close(self) -> CloseReport
+    {
+        return self.runtime.block_on(self.responder.close());
+    }
+}
+
+impl BlockingOperation
+{
+    /// Returns the context identifier of the operation, see `Operation::context`.
+    pub fn context(&self) -> u64
+    {
+        return self.operation.context();
+    }
+
+    /// Waits for the next event of the operation. Returns `None` once the operation is cancelled
+    /// or the connection is closed.
+    pub fn next_event(&mut self) -> Option<MDnsResponderEvent>
+    {
+        return self.operation.events.blocking_recv();
+    }
+
+    /// Waits at most `timeout` for the next event of the operation.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::Timeout)` if no event arrived in time.
+    pub fn next_event_timeout(&mut self, timeout: Duration) -> Result<Option<MDnsResponderEvent>, MDnsResponderError>
+    {
+        let events = &mut self.operation.events;
+
+        return match self.runtime.block_on(crate::runtime::timeout(timeout, events.recv()))
+        {
+            Ok(event) => Ok(event),
+            Err(_) => Err(MDnsResponderError::Timeout),
+        };
+    }
+}
@@ -0,0 +1,283 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(unix)]
+use crate::Backend;
+use crate::{ Connector, DedupPolicy, Delegate, MDnsResponder, MetricsSink, NoopMetrics, OverflowPolicy, ReconnectPolicy, Transport };
+use crate::mdnsresponder_error::MDnsResponderError;
+
+#[cfg(unix)]
+pub(crate) const DEFAULT_SOCKET_PATH: &str = "/var/run/mDNSResponder";
+pub(crate) const DEFAULT_CHANNEL_BUFFER_SIZE: usize = 32;
+pub(crate) const DEFAULT_READ_BUFFER_SIZE: usize = 2048;
+
+/// Builder for a `MDnsResponder` with non-default settings, created with
+/// `MDnsResponder::builder`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{MDnsResponder, ReconnectPolicy};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::builder()
+///         .socket_path("/tmp/mdnsd.sock")
+///         .channel_buffer_size(64)
+///         .reconnect_policy(ReconnectPolicy::default())
+///         .build()
+///         .await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MDnsResponderBuilder
+{
+    pub(crate) connector: Arc<dyn Connector>,
+    pub(crate) channel_buffer_size: usize,
+    pub(crate) operation_buffer_size: usize,
+    pub(crate) broadcast_capacity: usize,
+    pub(crate) read_buffer_size: usize,
+    pub(crate) reconnect_policy: Option<ReconnectPolicy>,
+    pub(crate) delegate: Option<Delegate>,
+    pub(crate) capture_path: Option<PathBuf>,
+    pub(crate) dedup_policy: DedupPolicy,
+    pub(crate) batch_services: bool,
+    pub(crate) expire_records: bool,
+    pub(crate) debounce_window: Option<Duration>,
+    pub(crate) metrics: Arc<dyn MetricsSink>,
+    pub(crate) overflow_policy: OverflowPolicy,
+    pub(crate) synchronous_errors: bool,
+    pub(crate) keepalive_interval: Option<Duration>,
+    #[cfg(all(unix, feature = "multicast-fallback"))]
+    pub(crate) multicast_fallback: bool,
+}
+
+impl MDnsResponderBuilder
+{
+    pub(crate) fn new() -> Self
+    {
+        return MDnsResponderBuilder
+        {
+            connector: default_connector(),
+            channel_buffer_size: DEFAULT_CHANNEL_BUFFER_SIZE,
+            operation_buffer_size: DEFAULT_CHANNEL_BUFFER_SIZE,
+            broadcast_capacity: DEFAULT_CHANNEL_BUFFER_SIZE,
+            read_buffer_size: DEFAULT_READ_BUFFER_SIZE,
+            reconnect_policy: None,
+            delegate: None,
+            capture_path: None,
+            dedup_policy: DedupPolicy::Off,
+            batch_services: false,
+            expire_records: false,
+            debounce_window: None,
+            metrics: Arc::new(NoopMetrics),
+            overflow_policy: OverflowPolicy::Block,
+            synchronous_errors: false,
+            keepalive_interval: None,
+            #[cfg(all(unix, feature = "multicast-fallback"))]
+            multicast_fallback: false,
+        };
+    }
+
+    /// Sets the path of the Unix socket mDNSResponder listens on. By default the socket is looked
+    /// up as for `Backend::Auto`.
+    #[cfg(unix)]
+    pub fn socket_path(mut self, socket_path: impl Into<PathBuf>) -> Self
+    {
+        self.connector = Arc::new(Transport::Unix(socket_path.into()));
+        return self;
+    }
+
+    /// Connects to the socket of a specific daemon on Unix systems, `Backend::Auto` by default.
+    #[cfg(unix)]
+    pub fn backend(mut self, backend: Backend) -> Self
+    {
+        self.connector = Arc::new(backend);
+        return self;
+    }
+
+    /// Sets how to reach mDNSResponder, `Backend::Auto` on Unix systems and
+    /// `Transport::default()` on other platforms by default. Use
+    /// `Transport::bonjour_tcp()` for Bonjour for Windows.
+    pub fn transport(mut self, transport: Transport) -> Self
+    {
+        self.connector = Arc::new(transport);
+        return self;
+    }
+
+    /// Connects through a custom `Connector` instead of one of the built-in transports.
+    pub fn connector(mut self, connector: impl Connector) -> Self
+    {
+        self.connector = Arc::new(connector);
+        return self;
+    }
+
+    /// Sets the size of the buffer of the shared events channel. Must be greater than zero.
+    pub fn channel_buffer_size(mut self, channel_buffer_size: usize) -> Self
+    {
+        self.channel_buffer_size = channel_buffer_size;
+        return self;
+    }
+
+    /// Sets the size of the buffer of the events channel of each `Operation`. Must be greater than zero.
+    pub fn operation_buffer_size(mut self, operation_buffer_size: usize) -> Self
+    {
+        self.operation_buffer_size = operation_buffer_size;
+        return self;
+    }
+
+    /// Sets how many events each subscriber from `MDnsRequester::subscribe` can fall behind before
+    /// it misses the oldest ones. Must be greater than zero.
+    pub fn broadcast_capacity(mut self, broadcast_capacity: usize) -> Self
+    {
+        self.broadcast_capacity = broadcast_capacity;
+        return self;
+    }
+
+    /// Sets how many bytes are read from the socket at a time. Must be greater than zero.
+    pub fn read_buffer_size(mut self, read_buffer_size: usize) -> Self
+    {
+        self.read_buffer_size = read_buffer_size;
+        return self;
+    }
+
+    /// Reconnects according to the given policy when the connection to mDNSResponder is lost,
+    /// see `MDnsResponder::with_reconnect`. By default the connection is not set up again.
+    pub fn reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> Self
+    {
+        self.reconnect_policy = Some(reconnect_policy);
+        return self;
+    }
+
+    /// Issues all requests on behalf of another process, see `MDnsResponder::new_delegate`.
+    /// Without a delegate, the daemon attributes the requests to the process that connected.
+    pub fn delegate(mut self, delegate: Delegate) -> Self
+    {
+        self.delegate = Some(delegate);
+        return self;
+    }
+
+    /// Sets what happens to events when the channel they are sent on is full, the shared one or
+    /// the one of an `Operation`. By default the connection waits for room, see `OverflowPolicy`.
+    pub fn overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self
+    {
+        self.overflow_policy = overflow_policy;
+        return self;
+    }
+
+    /// Suppresses repeated browse answers for the same service according to the given policy.
+    /// By default every answer is delivered.
+    pub fn dedup_policy(mut self, dedup_policy: DedupPolicy) -> Self
+    {
+        self.dedup_policy = dedup_policy;
+        return self;
+    }
+
+    /// Delivers browse answers that arrive in a burst as one `MDnsResponderEvent::ServicesBatch`
+    /// event once the daemon has no more replies queued, instead of one event per answer. Off by
+    /// default.
+    pub fn batch_services(mut self, batch_services: bool) -> Self
+    {
+        self.batch_services = batch_services;
+        return self;
+    }
+
+    /// Tracks the TTL of addresses from `get_addr_info` and records from `query`, and sends
+    /// `AddressExpired` and `RecordExpired` events for answers the daemon neither refreshed nor
+    /// removed before their TTL ran out. Resolve replies carry no TTL and are not tracked. Off by
+    /// default.
+    pub fn expire_records(mut self, expire_records: bool) -> Self
+    {
+        self.expire_records = expire_records;
+        return self;
+    }
+
+    /// Holds back `ServiceRemoved` browse answers for the given window. If the service is added
+    /// again within the window, neither the remove nor the add is delivered, which hides services
+    /// that briefly disappear, e.g. on a Wi-Fi roam. Removes that stand are delivered late by the
+    /// window. Off by default.
+    pub fn debounce_services(mut self, window: Duration) -> Self
+    {
+        self.debounce_window = Some(window);
+        return self;
+    }
+
+    /// Has the daemon report right away whether it accepted each request, so that a request it
+    /// rejects, e.g. for a malformed service type or by policy, fails with
+    /// `MDnsResponderError::DaemonError` instead of never producing events. The daemon answers on
+    /// a socket passed along with the request, which needs the Unix transport and is ignored for
+    /// others. Off by default.
+    pub fn synchronous_errors(mut self, synchronous_errors: bool) -> Self
+    {
+        self.synchronous_errors = synchronous_errors;
+        return self;
+    }
+
+    /// Checks every `interval` that the daemon still answers, by asking for its version on a
+    /// dedicated connection. A daemon that does not answer within the interval is reported with
+    /// `MDnsResponderEvent::DaemonUnresponsive` on the shared events channel, and with
+    /// `DaemonResponsive` once it answers again. Off by default.
+    pub fn keepalive(mut self, interval: Duration) -> Self
+    {
+        self.keepalive_interval = Some(interval);
+        return self;
+    }
+
+    /// Answers requests with the built-in mDNS engine of the `multicast` module when there is no
+    /// daemon to connect to, because its socket does not exist or nothing listens on it, e.g. in
+    /// a container. The engine only supports browsing, resolving, queries and address lookups.
+    /// Off by default.
+    #[cfg(all(unix, feature = "multicast-fallback"))]
+    pub fn multicast_fallback(mut self, multicast_fallback: bool) -> Self
+    {
+        self.multicast_fallback = multicast_fallback;
+        return self;
+    }
+
+    /// Reports counts of requests, replies, dropped events and reconnects to the given sink. By
+    /// default nothing is counted.
+    pub fn metrics(mut self, metrics: impl MetricsSink) -> Self
+    {
+        self.metrics = Arc::new(metrics);
+        return self;
+    }
+
+    /// Records all traffic with mDNSResponder to a file at the given path, replacing an existing
+    /// file. The capture can be read with `capture::CaptureReader` or replayed through the parser
+    /// with `capture::ReplayConnector`.
+    pub fn capture(mut self, path: impl Into<PathBuf>) -> Self
+    {
+        self.capture_path = Some(path.into());
+        return self;
+    }
+
+    /// Connects to mDNSResponder with the configured settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::ChannelCreationFailed)` if a buffer size is zero.
+    /// Returns `Err(MDnsResponderError::DelegateNotSupported)` if the delegate cannot be set on
+    /// the connection, see `Delegate`.
+    /// Returns `Err(MDnsResponderError::IpcConnectionCreationFailed)` if IPC creation fails or the
+    /// capture file cannot be created.
+    pub async fn build(self) -> Result<MDnsResponder, MDnsResponderError>
+    {
+        return MDnsResponder::create(self).await;
+    }
+}
+
+// Looks up the socket on every connect on Unix systems, as the daemon varies between them.
+fn default_connector() -> Arc<dyn Connector>
+{
+    #[cfg(unix)]
+    {
+        return Arc::new(Backend::Auto);
+    }
+
+    #[cfg(not(unix))]
+    {
+        return Arc::new(Transport::default());
+    }
+}
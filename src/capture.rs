@@ -0,0 +1,451 @@
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{ self, BufReader, BufWriter, Read, Write };
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::atomic::{ AtomicU32, Ordering };
+use std::sync::{ Arc, Mutex };
+use std::task::{ Context, Poll };
+use std::time::{ Duration, SystemTime, UNIX_EPOCH };
+
+use log::error;
+use tokio::io::{ AsyncRead, AsyncWrite, ReadBuf };
+
+use crate::{ ipc, ConnectFuture, Connection, Connector, Delegate, Transport };
+
+const MAGIC: &[u8; 4] = b"MDRC";
+const FORMAT_VERSION: u8 = 1;
+// Longest chunk in a capture, a frame of the longest size the parser accepts. Longer reads and
+// writes are recorded as several chunks, so a reader can reject a longer length as corrupt.
+const MAX_CHUNK_LENGTH: usize = ipc::header::IPC_HEADER_SIZE + ipc::MAX_FRAME_DATA_LENGTH;
+
+/// Direction of captured traffic.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction
+{
+    /// Written by the client to the daemon.
+    Outbound,
+    /// Read by the client from the daemon.
+    Inbound,
+}
+
+/// A chunk of IPC traffic as it was written to or read from a connection. Chunks keep the
+/// boundaries of the original reads, so a frame may be split over several chunks or a chunk may
+/// hold several frames.
+#[derive(Debug, Clone)]
+pub struct CapturedFrame
+{
+    pub direction: Direction,
+    pub timestamp: SystemTime,
+    /// Index of the connection the chunk belongs to, in the order the connections were opened.
+    pub connection: u32,
+    pub data: Vec<u8>,
+}
+
+type CaptureFile = Arc<Mutex<BufWriter<File>>>;
+
+/// Connector that records all traffic of the connections opened by another connector to a file,
+/// usually set up with `MDnsResponderBuilder::capture`. The file can be read back with
+/// `CaptureReader` or replayed with `ReplayConnector`.
+///
+/// Captured chunks are written synchronously as they pass, capturing is meant for debugging.
+#[derive(Debug)]
+pub struct CaptureConnector
+{
+    inner: Arc<dyn Connector>,
+    file: CaptureFile,
+    next_connection: AtomicU32,
+}
+
+impl CaptureConnector
+{
+    /// Captures the traffic of `inner` to a new file at `path`, replacing an existing file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be created.
+    pub fn new(inner: impl Connector, path: impl AsRef<Path>) -> io::Result<Self>
+    {
+        return Self::wrap(Arc::new(inner), path);
+    }
+
+    pub(crate) fn wrap(inner: Arc<dyn Connector>, path: impl AsRef<Path>) -> io::Result<Self>
+    {
+        let mut file = BufWriter::new(File::create(path)?);
+        file.write_all(MAGIC)?;
+        file.write_all(&[FORMAT_VERSION])?;
+        file.flush()?;
+
+        return Ok(CaptureConnector
+        {
+            inner,
+            file: Arc::new(Mutex::new(file)),
+            next_connection: AtomicU32::new(0),
+        });
+    }
+}
+
+impl Connector for CaptureConnector
+{
+    fn connect(&self, delegate: Option<Delegate>) -> ConnectFuture<'_>
+    {
+        return Box::pin(async move
+        {
+            let stream = self.inner.connect(delegate).await?;
+
+            return Ok(Box::new(CaptureConnection
+            {
+                inner: stream,
+                file: self.file.clone(),
+                connection: self.next_connection.fetch_add(1, Ordering::Relaxed),
+            }) as Box<dyn Connection>);
+        });
+    }
+
+    fn daemon_instance(&self) -> Option<u64>
+    {
+        return self.inner.daemon_instance();
+    }
+
+    fn transport(&self) -> Option<Transport>
+    {
+        return self.inner.transport();
+    }
+}
+
+struct CaptureConnection
+{
+    inner: Box<dyn Connection>,
+    file: CaptureFile,
+    connection: u32,
+}
+
+impl CaptureConnection
+{
+    fn record(&self, direction: Direction, data: &[u8])
+    {
+        if data.is_empty()
+        {
+            return;
+        }
+
+        let timestamp = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros() as u64;
+
+        let mut file = self.file.lock().unwrap();
+
+        let result = data.chunks(MAX_CHUNK_LENGTH).try_for_each(|chunk|
+            {
+                file.write_all(&[direction as u8])
+                    .and_then(|_| file.write_all(&timestamp.to_be_bytes()))
+                    .and_then(|_| file.write_all(&self.connection.to_be_bytes()))
+                    .and_then(|_| file.write_all(&(chunk.len() as u32).to_be_bytes()))
+                    .and_then(|_| file.write_all(chunk))
+            })
+            .and_then(|_| file.flush());
+
+        if let Err(e) = result
+        {
+            error!("Failed to write IPC capture: {}", e);
+        }
+    }
+}
+
+impl AsyncRead for CaptureConnection
+{
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>>
+    {
+        let filled = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+
+        if let Poll::Ready(Ok(())) = result
+        {
+            self.record(Direction::Inbound, &buf.filled()[filled..]);
+        }
+
+        return result;
+    }
+}
+
+impl AsyncWrite for CaptureConnection
+{
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>
+    {
+        let result = Pin::new(&mut self.inner).poll_write(cx, buf);
+
+        if let Poll::Ready(Ok(n)) = result
+        {
+            self.record(Direction::Outbound, &buf[..n]);
+        }
+
+        return result;
+    }
+
+    fn poll_write_vectored(mut self: Pin<&mut Self>, cx: &mut Context<'_>, bufs: &[io::IoSlice<'_>]) -> Poll<io::Result<usize>>
+    {
+        let result = Pin::new(&mut self.inner).poll_write_vectored(cx, bufs);
+
+        if let Poll::Ready(Ok(n)) = result
+        {
+            // Record what was written as one chunk, like a plain write of the same bytes.
+            let written: Vec<u8> = bufs.iter().flat_map(|buf| buf.iter().copied()).take(n).collect();
+            self.record(Direction::Outbound, &written);
+        }
+
+        return result;
+    }
+
+    fn is_write_vectored(&self) -> bool
+    {
+        return self.inner.is_write_vectored();
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    {
+        return Pin::new(&mut self.inner).poll_flush(cx);
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    {
+        return Pin::new(&mut self.inner).poll_shutdown(cx);
+    }
+}
+
+impl Direction
+{
+    fn from_u8(value: u8) -> Option<Self>
+    {
+        return match value
+        {
+            0 => Some(Direction::Outbound),
+            1 => Some(Direction::Inbound),
+            _ => None,
+        };
+    }
+}
+
+/// Reads the chunks of a file written by `CaptureConnector`, in the order they were captured.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::capture::CaptureReader;
+///
+/// fn main() -> std::io::Result<()> {
+///     for frame in CaptureReader::open("mdns.capture")? {
+///         let frame = frame?;
+///         println!("{:?} {} bytes on connection {}", frame.direction, frame.data.len(), frame.connection);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct CaptureReader
+{
+    file: BufReader<File>,
+}
+
+impl CaptureReader
+{
+    /// Opens a capture file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or is not a capture file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self>
+    {
+        let mut file = BufReader::new(File::open(path)?);
+
+        let mut preamble = [0u8; 5];
+        file.read_exact(&mut preamble)?;
+
+        if &preamble[..4] != MAGIC || preamble[4] != FORMAT_VERSION
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Not an IPC capture file"));
+        }
+
+        return Ok(CaptureReader { file });
+    }
+
+    fn read_frame(&mut self) -> io::Result<Option<CapturedFrame>>
+    {
+        let mut direction = [0u8; 1];
+        match self.file.read_exact(&mut direction)
+        {
+            Ok(()) => {}
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e),
+        }
+
+        let mut header = [0u8; 16];
+        self.file.read_exact(&mut header)?;
+
+        let direction = Direction::from_u8(direction[0])
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "Invalid capture direction"))?;
+        let timestamp = u64::from_be_bytes(header[0..8].try_into().unwrap());
+        let connection = u32::from_be_bytes(header[8..12].try_into().unwrap());
+        let length = u32::from_be_bytes(header[12..16].try_into().unwrap()) as usize;
+
+        if length > MAX_CHUNK_LENGTH
+        {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "Capture chunk is too long"));
+        }
+
+        let mut data = vec![0u8; length];
+        self.file.read_exact(&mut data)?;
+
+        return Ok(Some(CapturedFrame
+        {
+            direction,
+            timestamp: UNIX_EPOCH + Duration::from_micros(timestamp),
+            connection,
+            data,
+        }));
+    }
+}
+
+impl Iterator for CaptureReader
+{
+    type Item = io::Result<CapturedFrame>;
+
+    fn next(&mut self) -> Option<Self::Item>
+    {
+        return self.read_frame().transpose();
+    }
+}
+
+/// Connector that plays back the inbound traffic of a capture file, so captured replies go
+/// through the same parsing and routing as live ones. Each connection opened gets the inbound
+/// chunks of the captured connection with the same index, one chunk per read. Writes are
+/// discarded.
+///
+/// Operations issued on a replayed connection get new client contexts, so replayed replies are
+/// delivered on the shared `events` channel rather than on `Operation` handles.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::MDnsResponder;
+/// use mdnsresponder::capture::ReplayConnector;
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let mut responder = MDnsResponder::builder()
+///         .connector(ReplayConnector::open("mdns.capture")?)
+///         .build()
+///         .await?;
+///     while let Some(event) = responder.events.recv().await {
+///         println!("{:?}", event);
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug)]
+pub struct ReplayConnector
+{
+    connections: Mutex<VecDeque<VecDeque<Vec<u8>>>>,
+}
+
+impl ReplayConnector
+{
+    /// Loads the inbound traffic of a capture file.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be read or is not a capture file.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self>
+    {
+        let mut connections: Vec<VecDeque<Vec<u8>>> = Vec::new();
+
+        for frame in CaptureReader::open(path)?
+        {
+            let frame = frame?;
+            let index = frame.connection as usize;
+
+            if connections.len() <= index
+            {
+                connections.resize_with(index + 1, VecDeque::new);
+            }
+
+            if frame.direction == Direction::Inbound
+            {
+                connections[index].push_back(frame.data);
+            }
+        }
+
+        return Ok(ReplayConnector
+        {
+            connections: Mutex::new(connections.into()),
+        });
+    }
+}
+
+impl Connector for ReplayConnector
+{
+    fn connect(&self, _delegate: Option<Delegate>) -> ConnectFuture<'_>
+    {
+        return Box::pin(async move
+        {
+            match self.connections.lock().unwrap().pop_front()
+            {
+                Some(chunks) => return Ok(Box::new(ReplayConnection { chunks }) as Box<dyn Connection>),
+                None => return Err(io::Error::new(io::ErrorKind::ConnectionRefused, "No more captured connections")),
+            }
+        });
+    }
+}
+
+struct ReplayConnection
+{
+    chunks: VecDeque<Vec<u8>>,
+}
+
+impl AsyncRead for ReplayConnection
+{
+    fn poll_read(mut self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>>
+    {
+        if let Some(mut chunk) = self.chunks.pop_front()
+        {
+            let n = chunk.len().min(buf.remaining());
+            buf.put_slice(&chunk[..n]);
+
+            // Hand out the rest of a chunk that does not fit with the next read.
+            if n < chunk.len()
+            {
+                self.chunks.push_front(chunk.split_off(n));
+            }
+        }
+
+        // An empty read signals the end of the captured connection.
+        return Poll::Ready(Ok(()));
+    }
+}
+
+impl AsyncWrite for ReplayConnection
+{
+    fn poll_write(self: Pin<&mut Self>, _cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>
+    {
+        return Poll::Ready(Ok(buf.len()));
+    }
+
+    fn poll_write_vectored(self: Pin<&mut Self>, _cx: &mut Context<'_>, bufs: &[io::IoSlice<'_>]) -> Poll<io::Result<usize>>
+    {
+        return Poll::Ready(Ok(bufs.iter().map(|buf| buf.len()).sum()));
+    }
+
+    fn is_write_vectored(&self) -> bool
+    {
+        return true;
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    {
+        return Poll::Ready(Ok(()));
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<io::Result<()>>
+    {
+        return Poll::Ready(Ok(()));
+    }
+}
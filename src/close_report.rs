@@ -0,0 +1,13 @@
+/// What `MDnsRequester::close` cleaned up.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CloseReport
+{
+    /// Contexts of the operations that were cancelled with the daemon.
+    pub cancelled: Vec<u64>,
+    /// Contexts of the operations whose cancel could not be written, e.g. because the connection
+    /// was already lost. The daemon ends them once it sees the connection close.
+    pub not_cancelled: Vec<u64>,
+    /// Whether the listener task stopped in time, or had already stopped. One that did not is
+    /// aborted.
+    pub listener_stopped: bool,
+}
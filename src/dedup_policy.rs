@@ -0,0 +1,18 @@
+/// Controls whether repeated browse answers for the same service are delivered, see
+/// `MDnsResponderBuilder::dedup_policy`.
+///
+/// mDNSResponder reports a service once for every interface it is seen on, so a service on a
+/// host with both Wi-Fi and Ethernet is added twice. With deduplication enabled a
+/// `ServiceAdded` event is only delivered for the first answer and `ServiceRemoved` only once
+/// the last one is withdrawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DedupPolicy
+{
+    /// Every answer is delivered as it is received.
+    #[default]
+    Off,
+    /// Answers are deduplicated by name, type, domain and interface.
+    PerInterface,
+    /// Answers are deduplicated by name, type and domain, regardless of the interface.
+    Global,
+}
@@ -0,0 +1,371 @@
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::time::Duration;
+
+use log::{ debug, error };
+use tokio::sync::mpsc;
+
+use crate::{ runtime, AddrInfoOptions, Interface, MDnsRequester, MDnsResponderEvent, Operation, Protocol, ResolveOptions, Resolved, Service, TxtRecord };
+
+// How long to wait for the resolve and for the addresses of a found service.
+const STEP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// A service found by `MDnsRequester::discover`, resolved and with its addresses looked up.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiscoveredService
+{
+    /// The instance name as the daemon reported it, see `Service::name`.
+    pub name: String,
+    /// The instance name with DNS escapes undone, see `Service::display_name`.
+    pub display_name: String,
+    pub service_type: String,
+    pub domain: String,
+    /// Hostname of the service (e.g., "myhost.local.").
+    pub host: String,
+    pub port: u16,
+    pub txt: TxtRecord,
+    /// Addresses of the host, empty if none could be looked up in time.
+    pub addresses: Vec<IpAddr>,
+    /// Index of the interface the service was found on.
+    pub interface_index: u32,
+    /// Name of the interface the service was found on, e.g. "en0", if it has one.
+    pub interface_name: Option<String>,
+}
+
+// Turns the answers of a browse into discovered services until the browse is cancelled. Each
+// added service is resolved in its own task so a slow one does not hold up the others. The
+// tasks hand their result back instead of sending it, so that a removal that arrives while a
+// service is still being resolved supersedes it rather than being overtaken by it.
+pub(crate) async fn run(requester: MDnsRequester, mut browse: Operation, events: mpsc::Sender<MDnsResponderEvent>)
+{
+    let (done_sender, mut done) = mpsc::unbounded_channel();
+    let mut pending = Pending::default();
+
+    loop
+    {
+        let event = tokio::select!
+        {
+            event = browse.events.recv() => match event
+            {
+                Some(event) => event,
+                None => break,
+            },
+            Some((service, id, discovered)) = done.recv() =>
+            {
+                // Results of tasks that a removal or a new add superseded are dropped.
+                if !pending.finish(&service, id)
+                {
+                    continue;
+                }
+
+                match discovered
+                {
+                    Some(discovered) => MDnsResponderEvent::ServiceDiscovered(discovered),
+                    None => continue,
+                }
+            },
+            _ = events.closed() => break,
+        };
+
+        let (added, removed) = match event
+        {
+            MDnsResponderEvent::ServiceAdded(service) => (vec![service], vec![]),
+            MDnsResponderEvent::ServiceRemoved(service) => (vec![], vec![service]),
+            MDnsResponderEvent::ServicesBatch { added, removed } => (added, removed),
+            event =>
+            {
+                if events.send(event).await.is_err()
+                {
+                    return;
+                }
+
+                continue;
+            }
+        };
+
+        for service in added
+        {
+            let id = pending.next_id();
+            let done_sender = done_sender.clone();
+            let task = runtime::spawn(materialize(requester.clone(), service.clone(), id, done_sender));
+
+            pending.start(service, id, task);
+        }
+
+        for service in removed
+        {
+            pending.abort(&service);
+
+            if events.send(MDnsResponderEvent::ServiceRemoved(service)).await.is_err()
+            {
+                return;
+            }
+        }
+    }
+
+    // Dropping `pending` aborts the services still being resolved, along with their operations.
+    debug!("Discovery for browse {} ended", browse.context());
+}
+
+// The services of a discovery that are being resolved, with the tasks resolving them. Each task
+// has an id so that the result of one that was superseded is told apart from its successor's.
+#[derive(Default)]
+struct Pending
+{
+    tasks: HashMap<Service, (u64, runtime::Task)>,
+    last_id: u64,
+}
+
+impl Pending
+{
+    fn next_id(&mut self) -> u64
+    {
+        self.last_id += 1;
+        return self.last_id;
+    }
+
+    // A service added again while being resolved is resolved anew.
+    fn start(&mut self, service: Service, id: u64, task: runtime::Task)
+    {
+        if let Some((_, task)) = self.tasks.insert(service, (id, task))
+        {
+            task.abort();
+        }
+    }
+
+    fn abort(&mut self, service: &Service)
+    {
+        if let Some((_, task)) = self.tasks.remove(service)
+        {
+            task.abort();
+        }
+    }
+
+    // Returns whether the task with `id` is still the one resolving `service`.
+    fn finish(&mut self, service: &Service, id: u64) -> bool
+    {
+        if !matches!(self.tasks.get(service), Some((current, _)) if *current == id)
+        {
+            return false;
+        }
+
+        self.tasks.remove(service);
+        return true;
+    }
+}
+
+impl Drop for Pending
+{
+    fn drop(&mut self)
+    {
+        for (_, (_, task)) in self.tasks.drain()
+        {
+            task.abort();
+        }
+    }
+}
+
+// Passes on the events of a browse, resolving every added service first. Services are resolved
+// one at a time so that an add is never overtaken by a later event for the same service.
+pub(crate) async fn auto_resolve(requester: MDnsRequester, mut browse: Operation, events: mpsc::Sender<MDnsResponderEvent>)
+{
+    while let Some(event) = next_event(&mut browse, &events).await
+    {
+        let event = match event
+        {
+            MDnsResponderEvent::ServiceAdded(service) =>
+            {
+                MDnsResponderEvent::ServiceAdded(with_resolved(&requester, service).await)
+            }
+            MDnsResponderEvent::ServicesBatch { added, removed } =>
+            {
+                let mut resolved = Vec::with_capacity(added.len());
+
+                for service in added
+                {
+                    resolved.push(with_resolved(&requester, service).await);
+                }
+
+                MDnsResponderEvent::ServicesBatch { added: resolved, removed }
+            }
+            event => event,
+        };
+
+        if events.send(event).await.is_err()
+        {
+            return;
+        }
+    }
+
+    debug!("Auto resolve for browse {} ended", browse.context());
+}
+
+// Returns the next event of the browse, or `None` once it ended or the handle passing on its
+// results was dropped. The browse is then cancelled as it is dropped.
+async fn next_event(browse: &mut Operation, events: &mpsc::Sender<MDnsResponderEvent>) -> Option<MDnsResponderEvent>
+{
+    return tokio::select!
+    {
+        event = browse.events.recv() => event,
+        _ = events.closed() => None,
+    };
+}
+
+async fn with_resolved(requester: &MDnsRequester, mut service: Service) -> Service
+{
+    service.resolved = resolve(requester, &service).await.map(Box::new);
+    return service;
+}
+
+pub(crate) async fn resolve(requester: &MDnsRequester, service: &Service) -> Option<Resolved>
+{
+    let options = ResolveOptions::new()
+        .interface(Interface::from(service.interface_index))
+        .domain(service.domain.clone());
+
+    let mut resolve = match requester.resolve_with_options(service.name.clone(), service.service_type.clone(), options).await
+    {
+        Ok(resolve) => resolve,
+        Err(e) =>
+        {
+            error!("Failed to resolve service {}: {}", service.name, e);
+            return None;
+        }
+    };
+
+    let resolved = runtime::timeout(STEP_TIMEOUT, MDnsRequester::first_resolved(&mut resolve)).await;
+    cancel(requester, resolve.context()).await;
+
+    return match resolved
+    {
+        Ok(Ok(resolved)) => Some(resolved),
+        Ok(Err(e)) =>
+        {
+            error!("Failed to resolve service {}: {}", service.name, e);
+            None
+        }
+        Err(_) =>
+        {
+            debug!("Resolving service {} timed out", service.name);
+            None
+        }
+    };
+}
+
+// Resolves a service and looks up its addresses, handing the result to `run` under `id`.
+async fn materialize(
+    requester: MDnsRequester,
+    service: Service,
+    id: u64,
+    done: mpsc::UnboundedSender<(Service, u64, Option<DiscoveredService>)>,
+)
+{
+    let discovered = discovered(&requester, service.clone()).await;
+
+    // Fails only once the discovery ended.
+    let _ = done.send((service, id, discovered));
+}
+
+async fn discovered(requester: &MDnsRequester, service: Service) -> Option<DiscoveredService>
+{
+    let resolved = resolve(requester, &service).await?;
+
+    let interface = Interface::from(service.interface_index);
+
+    let mut addresses = Vec::new();
+
+    match requester.get_addr_info_with_options(resolved.host_target.clone(), AddrInfoOptions::new().interface(interface)).await
+    {
+        Ok(mut lookup) =>
+        {
+            // Addresses found before the timeout are kept.
+            let _ = runtime::timeout(STEP_TIMEOUT, collect_addresses(&mut lookup, &mut addresses)).await;
+            cancel(requester, lookup.context()).await;
+        }
+        Err(e) =>
+        {
+            error!("Failed to look up addresses of {}: {}", resolved.host_target, e);
+        }
+    }
+
+    return Some(DiscoveredService
+    {
+        name: service.name,
+        display_name: service.display_name,
+        service_type: service.service_type,
+        domain: service.domain,
+        host: resolved.host_target,
+        port: resolved.port,
+        txt: resolved.txt,
+        addresses,
+        interface_index: service.interface_index,
+        interface_name: service.interface_name,
+    });
+}
+
+// Collects addresses until both address families have answered, with an address or a negative
+// answer, and the daemon has no more answers queued. The A and AAAA answers of a host come in
+// separate replies, so the end of one burst does not mean all addresses are known.
+async fn collect_addresses(lookup: &mut Operation, addresses: &mut Vec<IpAddr>)
+{
+    let mut ipv4 = false;
+    let mut ipv6 = false;
+
+    while let Some(event) = lookup.events.recv().await
+    {
+        let more_coming = match event
+        {
+            MDnsResponderEvent::AddressInfoResolved(address_info) =>
+            {
+                match address_info.address
+                {
+                    IpAddr::V4(_) => ipv4 = true,
+                    IpAddr::V6(_) => ipv6 = true,
+                }
+
+                if !addresses.contains(&address_info.address)
+                {
+                    addresses.push(address_info.address);
+                }
+
+                address_info.flags.more_coming
+            }
+            MDnsResponderEvent::AddressInfoRemoved(address_info) =>
+            {
+                addresses.retain(|address| *address != address_info.address);
+                address_info.flags.more_coming
+            }
+            MDnsResponderEvent::NoSuchRecord { protocol, .. } =>
+            {
+                match protocol
+                {
+                    Protocol::IPv4 => ipv4 = true,
+                    Protocol::IPv6 => ipv6 = true,
+                    Protocol::Both =>
+                    {
+                        ipv4 = true;
+                        ipv6 = true;
+                    }
+                }
+
+                false
+            }
+            MDnsResponderEvent::OperationFailed { .. } => return,
+            _ => continue,
+        };
+
+        if ipv4 && ipv6 && !more_coming
+        {
+            return;
+        }
+    }
+}
+
+async fn cancel(requester: &MDnsRequester, context: u64)
+{
+    if let Err(e) = requester.cancel(context).await
+    {
+        error!("Failed to cancel operation {}: {}", context, e);
+    }
+}
@@ -0,0 +1,349 @@
+//! Escaping of DNS labels and names in the presentation format used by mDNSResponder.
+//!
+//! Within a label, `.` and `\` are escaped with a backslash and bytes up to and including space
+//! are written as `\DDD` with the decimal value of the byte. Names are the escaped labels joined
+//! with `.` and end with a `.`.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use mdnsresponder::dns_name;
+//!
+//! let name = dns_name::full_name("My Printer", "_ipp._tcp", "local");
+//! assert_eq!(name, "My\\032Printer._ipp._tcp.local.");
+//!
+//! let labels = dns_name::unescape_name(&name).unwrap();
+//! assert_eq!(labels, vec!["My Printer", "_ipp", "_tcp", "local"]);
+//! ```
+
+use std::net::IpAddr;
+
+use crate::MDnsResponderError;
+
+const MAX_LABEL_LENGTH: usize = 63;
+// Longest name on the wire, including the length bytes and the root label.
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Escapes a single label, such as a service instance name.
+pub fn escape_label(label: &str) -> String
+{
+    let mut escaped = String::with_capacity(label.len());
+
+    for c in label.chars()
+    {
+        match c
+        {
+            '.' | '\\' =>
+            {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c <= ' ' =>
+            {
+                escaped.push_str(&format!("\\{:03}", c as u32));
+            }
+            c =>
+            {
+                escaped.push(c);
+            }
+        }
+    }
+
+    return escaped;
+}
+
+/// Reverses `escape_label`. Returns `None` if the label contains an unescaped `.`, an invalid
+/// escape sequence, or escapes bytes that are not valid UTF-8.
+pub fn unescape_label(escaped: &str) -> Option<String>
+{
+    let (label, rest) = unescape_next_label(escaped)?;
+
+    if !rest.is_empty()
+    {
+        return None;
+    }
+
+    return Some(label);
+}
+
+// Unescapes a label for display, taking it as it is if it has no valid escapes, e.g. an
+// instance name with a literal `.`.
+pub(crate) fn display_label(label: &str) -> String
+{
+    return unescape_label(label).unwrap_or_else(|| label.to_string());
+}
+
+/// Joins labels into a name, escaping each label and appending the final `.`.
+pub fn escape_name(labels: &[&str]) -> String
+{
+    let mut name = String::new();
+
+    for label in labels
+    {
+        name.push_str(&escape_label(label));
+        name.push('.');
+    }
+
+    return name;
+}
+
+/// Splits a name into its unescaped labels. The final `.` is optional. Returns `None` if the
+/// name contains an empty label or an invalid escape sequence.
+pub fn unescape_name(name: &str) -> Option<Vec<String>>
+{
+    let mut labels = Vec::new();
+    let mut rest = name;
+
+    while !rest.is_empty()
+    {
+        let (label, remaining) = unescape_next_label(rest)?;
+
+        if label.is_empty()
+        {
+            return None;
+        }
+
+        labels.push(label);
+
+        // Skip the separator, the final one may be missing.
+        rest = remaining.strip_prefix('.').unwrap_or(remaining);
+    }
+
+    return Some(labels);
+}
+
+/// Builds the full name of a service instance, like `DNSServiceConstructFullName`. The instance
+/// name is escaped, the service type and domain are taken as they are and may or may not end
+/// with a `.`.
+pub fn full_name(instance: &str, service_type: &str, domain: &str) -> String
+{
+    let service_type = service_type.trim_end_matches('.');
+    let domain = domain.trim_end_matches('.');
+
+    return format!("{}.{}.{}.", escape_label(instance), service_type, domain);
+}
+
+/// Builds the type to browse for the instances of a service type that have a subtype, e.g.
+/// `_printer._sub._http._tcp` for the subtype `_printer` of `_http._tcp`. The subtype is escaped.
+pub fn subtype_browse_type(subtype: &str, service_type: &str) -> String
+{
+    return format!("{}._sub.{}", escape_label(subtype), service_type);
+}
+
+/// Builds the type to register a service with subtypes under, the service type followed by the
+/// subtypes separated by commas, e.g. `_http._tcp,_printer,_scanner`. The subtypes are escaped,
+/// including commas within them.
+pub fn registration_type(service_type: &str, subtypes: &[&str]) -> String
+{
+    let mut registration_type = service_type.to_string();
+
+    for subtype in subtypes
+    {
+        registration_type.push(',');
+        registration_type.push_str(&escape_label(subtype).replace(',', "\\,"));
+    }
+
+    return registration_type;
+}
+
+/// Builds the name to look up the PTR record of an address under, in "in-addr.arpa." for IPv4
+/// and "ip6.arpa." for IPv6.
+pub fn reverse_name(address: IpAddr) -> String
+{
+    match address
+    {
+        IpAddr::V4(address) =>
+        {
+            let [a, b, c, d] = address.octets();
+            return format!("{}.{}.{}.{}.in-addr.arpa.", d, c, b, a);
+        }
+        IpAddr::V6(address) =>
+        {
+            let mut name = String::with_capacity(72);
+
+            for byte in address.octets().iter().rev()
+            {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0x0f, byte >> 4));
+            }
+
+            name.push_str("ip6.arpa.");
+            return name;
+        }
+    }
+}
+
+/// Decodes a name in DNS wire format, as found in the data of PTR, CNAME and SRV records, into
+/// an escaped name. Returns `None` if the name is truncated, compressed, or has labels that are
+/// not valid UTF-8.
+pub fn from_wire(data: &[u8]) -> Option<String>
+{
+    let mut labels = Vec::new();
+    let mut pos = 0;
+
+    loop
+    {
+        let length = *data.get(pos)? as usize;
+
+        if length == 0
+        {
+            break;
+        }
+
+        // Lengths of 64 and above are compression pointers, which are not used in record data
+        // passed on by the daemon.
+        if length > 63
+        {
+            return None;
+        }
+
+        let label = data.get(pos + 1..pos + 1 + length)?;
+        labels.push(std::str::from_utf8(label).ok()?);
+        pos += 1 + length;
+    }
+
+    if labels.is_empty()
+    {
+        return Some(".".to_string());
+    }
+
+    return Some(escape_name(&labels));
+}
+
+/// Checks that a hostname can be looked up, e.g. with `MDnsRequester::get_addr_info`. The final
+/// `.` is optional.
+///
+/// Names under `local` are looked up with multicast DNS, which takes UTF-8 labels as they are.
+/// Other names go to unicast DNS, where labels with other than ASCII characters have to be given
+/// in their punycode form, e.g. `xn--bcher-kva.example` for `bücher.example`.
+///
+/// # Errors
+///
+/// Returns `Err(MDnsResponderError::InvalidHostname)` describing the first problem found: an
+/// empty name or label, an invalid escape sequence, a label longer than 63 bytes, a name longer
+/// than 253 bytes, or a label that is not ASCII outside `local`.
+///
+/// # Examples
+///
+/// ```rust
+/// use mdnsresponder::dns_name;
+///
+/// assert!(dns_name::check_hostname("printer.local.").is_ok());
+/// assert!(dns_name::check_hostname("drucker-küche.local").is_ok());
+/// assert!(dns_name::check_hostname("printer..local").is_err());
+/// assert!(dns_name::check_hostname("printer.local..").is_err());
+/// assert!(dns_name::check_hostname("printer.local\\.").is_ok());
+/// assert!(dns_name::check_hostname("bücher.example").is_err());
+/// ```
+pub fn check_hostname(hostname: &str) -> Result<(), MDnsResponderError>
+{
+    let invalid = |reason: String| Err(MDnsResponderError::InvalidHostname(reason));
+
+    let name = if ends_with_unescaped_dot(hostname) { &hostname[..hostname.len() - 1] } else { hostname };
+    if name.is_empty()
+    {
+        return invalid("the name is empty".to_string());
+    }
+
+    // Only one final `.` is optional, another one would end an empty label.
+    let Some(labels) = unescape_name(name).filter(|_| !ends_with_unescaped_dot(name)) else
+    {
+        return invalid(format!("{} has an empty label or an invalid escape sequence", hostname));
+    };
+
+    if let Some(label) = labels.iter().find(|label| label.len() > MAX_LABEL_LENGTH)
+    {
+        return invalid(format!("label {} is longer than {} bytes", label, MAX_LABEL_LENGTH));
+    }
+
+    // Each label takes its length plus a length byte on the wire, which holds at most 255 bytes
+    // including the root label.
+    let wire_length: usize = labels.iter().map(|label| label.len() + 1).sum::<usize>() + 1;
+    if wire_length > MAX_NAME_LENGTH
+    {
+        return invalid(format!("{} is longer than {} bytes", hostname, MAX_NAME_LENGTH - 2));
+    }
+
+    let multicast = labels.last().is_some_and(|label| label.eq_ignore_ascii_case("local"));
+    let non_ascii = labels.iter().find(|label| !label.is_ascii());
+    if let (false, Some(label)) = (multicast, non_ascii)
+    {
+        return invalid(format!("label {} is not ASCII, names outside .local need their punycode form", label));
+    }
+
+    return Ok(());
+}
+
+// A `.` is escaped by an odd number of backslashes before it, an even number escape each other.
+fn ends_with_unescaped_dot(name: &str) -> bool
+{
+    let Some(rest) = name.strip_suffix('.') else
+    {
+        return false;
+    };
+
+    let backslashes = rest.bytes().rev().take_while(|b| *b == b'\\').count();
+    return backslashes % 2 == 0;
+}
+
+// Subtypes are a single label, which holds at most 63 bytes.
+pub(crate) fn is_valid_subtype(subtype: &str) -> bool
+{
+    return !subtype.is_empty() && subtype.len() <= MAX_LABEL_LENGTH;
+}
+
+// Form of a name to compare it by: its unescaped labels in lower case, since DNS names are
+// compared ignoring ASCII case. Names that cannot be unescaped are taken as a single label.
+pub(crate) fn canonical_name(name: &str) -> Vec<String>
+{
+    return match unescape_name(name)
+    {
+        Some(labels) => labels.iter().map(|label| label.to_ascii_lowercase()).collect(),
+        None => vec![name.trim_end_matches('.').to_ascii_lowercase()],
+    };
+}
+
+// Unescapes up to the first unescaped '.', returning the label and the rest of the name
+// starting at that '.'.
+fn unescape_next_label(escaped: &str) -> Option<(String, &str)>
+{
+    let bytes = escaped.as_bytes();
+    let mut label = Vec::with_capacity(bytes.len());
+    let mut pos = 0;
+
+    while pos < bytes.len()
+    {
+        match bytes[pos]
+        {
+            b'.' =>
+            {
+                break;
+            }
+            b'\\' =>
+            {
+                let digits = bytes.get(pos + 1..pos + 4).filter(|digits| digits.iter().all(u8::is_ascii_digit));
+
+                match digits
+                {
+                    Some(digits) =>
+                    {
+                        let value = digits.iter().fold(0u32, |value, digit| value * 10 + (digit - b'0') as u32);
+                        label.push(u8::try_from(value).ok()?);
+                        pos += 4;
+                    }
+                    None =>
+                    {
+                        label.push(*bytes.get(pos + 1)?);
+                        pos += 2;
+                    }
+                }
+            }
+            b =>
+            {
+                label.push(b);
+                pos += 1;
+            }
+        }
+    }
+
+    return Some((String::from_utf8(label).ok()?, &escaped[pos..]));
+}
@@ -0,0 +1,210 @@
+use std::pin::Pin;
+use std::task::{ Context, Poll };
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::{ dns_name, MDnsResponderEvent, Service, TxtRecord };
+
+/// `Stream` over the shared events of an `MDnsResponder`, returned by
+/// `MDnsResponder::event_stream`.
+pub struct EventStream<'a>
+{
+    events: &'a mut mpsc::Receiver<MDnsResponderEvent>,
+}
+
+impl<'a> EventStream<'a>
+{
+    pub(crate) fn new(events: &'a mut mpsc::Receiver<MDnsResponderEvent>) -> Self
+    {
+        return EventStream
+        {
+            events,
+        };
+    }
+}
+
+impl Stream for EventStream<'_>
+{
+    type Item = MDnsResponderEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>
+    {
+        return self.events.poll_recv(cx);
+    }
+}
+
+type FilterFn = Box<dyn FnMut(MDnsResponderEvent) -> Option<MDnsResponderEvent> + Send>;
+
+/// `Stream` returned by the adapters of `EventStreamExt`.
+pub struct FilterEvents<S>
+{
+    inner: S,
+    filter: FilterFn,
+}
+
+impl<S> Stream for FilterEvents<S>
+where
+    S: Stream<Item = MDnsResponderEvent> + Unpin,
+{
+    type Item = MDnsResponderEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>
+    {
+        loop
+        {
+            match Pin::new(&mut self.inner).poll_next(cx)
+            {
+                Poll::Ready(Some(event)) =>
+                {
+                    if let Some(event) = (self.filter)(event)
+                    {
+                        return Poll::Ready(Some(event));
+                    }
+                }
+                other => return other,
+            }
+        }
+    }
+}
+
+/// Adapters that narrow a stream of events, implemented for `Operation`, `MDnsEvents`,
+/// `EventStream` and any other `Stream` of events. The adapters can be chained.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use futures::StreamExt;
+/// use mdnsresponder::{BrowseOptions, EventStreamExt, MDnsResponder};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let browse = responder.browse_with_options("_http._tcp".to_string(), BrowseOptions::new().auto_resolve(true)).await?;
+///     let mut events = browse.added_only().with_txt_key("path");
+///     while let Some(event) = events.next().await {
+///         println!("{:?}", event);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub trait EventStreamExt: Stream<Item = MDnsResponderEvent> + Unpin + Sized
+{
+    /// Keeps only the events about services of the given type (e.g., "_http._tcp"). Batches
+    /// keep only the matching services, events that are not about a service are dropped.
+    fn filter_service_type(self, service_type: &str) -> FilterEvents<Self>
+    {
+        let service_type = service_type.trim_end_matches('.').to_string();
+
+        return self.filter_events(move |event| filter_service_type(event, &service_type));
+    }
+
+    /// Drops the events about answers that went away, such as `ServiceRemoved`,
+    /// `AddressInfoRemoved` and expiries. Batches keep only the added services.
+    fn added_only(self) -> FilterEvents<Self>
+    {
+        return self.filter_events(added_only);
+    }
+
+    /// Keeps only the events with a TXT record that has the given key: resolves, discovered
+    /// services and services added with `BrowseOptions::auto_resolve`.
+    fn with_txt_key(self, key: &str) -> FilterEvents<Self>
+    {
+        let key = key.to_string();
+
+        return self.filter_events(move |event|
+        {
+            let has_key = txt(&event).is_some_and(|txt| txt.contains_key(&key));
+            return has_key.then_some(event);
+        });
+    }
+
+    /// Passes every event through `filter`, keeping the events it returns.
+    fn filter_events<F>(self, filter: F) -> FilterEvents<Self>
+    where
+        F: FnMut(MDnsResponderEvent) -> Option<MDnsResponderEvent> + Send + 'static,
+    {
+        return FilterEvents
+        {
+            inner: self,
+            filter: Box::new(filter),
+        };
+    }
+}
+
+impl<S: Stream<Item = MDnsResponderEvent> + Unpin> EventStreamExt for S {}
+
+fn is_service_type(candidate: &str, service_type: &str) -> bool
+{
+    return candidate.trim_end_matches('.').eq_ignore_ascii_case(service_type);
+}
+
+// The service type of a resolve is the two labels following the instance name.
+fn is_full_name_of_type(full_name: &str, service_type: &str) -> bool
+{
+    return match dns_name::unescape_name(full_name)
+    {
+        Some(labels) if labels.len() >= 3 => is_service_type(&format!("{}.{}", labels[1], labels[2]), service_type),
+        _ => false,
+    };
+}
+
+fn filter_service_type(event: MDnsResponderEvent, service_type: &str) -> Option<MDnsResponderEvent>
+{
+    let matches = match &event
+    {
+        MDnsResponderEvent::ServicesBatch { added, removed } =>
+        {
+            let added: Vec<Service> = added.iter().filter(|service| is_service_type(&service.service_type, service_type)).cloned().collect();
+            let removed: Vec<Service> = removed.iter().filter(|service| is_service_type(&service.service_type, service_type)).cloned().collect();
+
+            if added.is_empty() && removed.is_empty()
+            {
+                return None;
+            }
+
+            return Some(MDnsResponderEvent::ServicesBatch { added, removed });
+        }
+        MDnsResponderEvent::ServiceAdded(service)
+        | MDnsResponderEvent::ServiceRemoved(service)
+        | MDnsResponderEvent::ServiceRegistered(service)
+        | MDnsResponderEvent::NameConflict(service) => is_service_type(&service.service_type, service_type),
+        MDnsResponderEvent::ServiceResolved(resolved) => is_full_name_of_type(&resolved.full_name, service_type),
+        MDnsResponderEvent::ServiceDiscovered(discovered) => is_service_type(&discovered.service_type, service_type),
+        MDnsResponderEvent::ServiceTypeDiscovered(found) | MDnsResponderEvent::ServiceTypeRemoved(found) =>
+        {
+            is_service_type(&found.service_type, service_type)
+        }
+        _ => false,
+    };
+
+    return matches.then_some(event);
+}
+
+fn added_only(event: MDnsResponderEvent) -> Option<MDnsResponderEvent>
+{
+    return match event
+    {
+        MDnsResponderEvent::ServicesBatch { added, .. } if added.is_empty() => None,
+        MDnsResponderEvent::ServicesBatch { added, .. } => Some(MDnsResponderEvent::ServicesBatch { added, removed: Vec::new() }),
+        MDnsResponderEvent::ServiceRemoved(_)
+        | MDnsResponderEvent::ServiceTypeRemoved(_)
+        | MDnsResponderEvent::AddressInfoRemoved(_)
+        | MDnsResponderEvent::AddressExpired(_)
+        | MDnsResponderEvent::RecordRemoved(_)
+        | MDnsResponderEvent::RecordExpired(_)
+        | MDnsResponderEvent::DomainRemoved(_) => None,
+        event => Some(event),
+    };
+}
+
+fn txt(event: &MDnsResponderEvent) -> Option<&TxtRecord>
+{
+    return match event
+    {
+        MDnsResponderEvent::ServiceResolved(resolved) => Some(&resolved.txt),
+        MDnsResponderEvent::ServiceDiscovered(discovered) => Some(&discovered.txt),
+        MDnsResponderEvent::ServiceAdded(service) => service.resolved.as_ref().map(|resolved| &resolved.txt),
+        _ => None,
+    };
+}
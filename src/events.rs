@@ -0,0 +1,83 @@
+use std::pin::Pin;
+use std::task::{ Context, Poll };
+use std::time::{ Duration, Instant };
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::{ runtime, MDnsResponderError, MDnsResponderEvent };
+
+/// Receiver of the shared events of a connection, returned by `MDnsResponder::split`.
+///
+/// Also a `Stream` of the events.
+pub struct MDnsEvents
+{
+    events: mpsc::Receiver<MDnsResponderEvent>,
+}
+
+impl MDnsEvents
+{
+    pub(crate) fn new(events: mpsc::Receiver<MDnsResponderEvent>) -> Self
+    {
+        return MDnsEvents
+        {
+            events,
+        };
+    }
+
+    /// Receives the next event, or `None` once the connection is closed.
+    pub async fn recv(&mut self) -> Option<MDnsResponderEvent>
+    {
+        return self.events.recv().await;
+    }
+
+    /// Returns the next event if one is waiting, without waiting for one. Returns `None` if
+    /// there is none or the connection is closed.
+    pub fn try_next_event(&mut self) -> Option<MDnsResponderEvent>
+    {
+        return self.events.try_recv().ok();
+    }
+
+    /// Receives the next event like `recv`, waiting at most `timeout` for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::Timeout)` if no event arrived in time.
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<MDnsResponderEvent>, MDnsResponderError>
+    {
+        return recv_deadline(&mut self.events, Instant::now() + timeout).await;
+    }
+
+    /// Receives the next event like `recv`, waiting for it until `deadline`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::Timeout)` if no event arrived before the deadline.
+    pub async fn recv_deadline(&mut self, deadline: Instant) -> Result<Option<MDnsResponderEvent>, MDnsResponderError>
+    {
+        return recv_deadline(&mut self.events, deadline).await;
+    }
+}
+
+// Shared by `MDnsEvents` and `MDnsResponder`, which both own a receiver of the shared events.
+pub(crate) async fn recv_deadline(
+    events: &mut mpsc::Receiver<MDnsResponderEvent>,
+    deadline: Instant,
+) -> Result<Option<MDnsResponderEvent>, MDnsResponderError>
+{
+    return match runtime::timeout_at(deadline, events.recv()).await
+    {
+        Ok(event) => Ok(event),
+        Err(_) => Err(MDnsResponderError::Timeout),
+    };
+}
+
+impl Stream for MDnsEvents
+{
+    type Item = MDnsResponderEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>
+    {
+        return self.events.poll_recv(cx);
+    }
+}
@@ -0,0 +1,377 @@
+//! C API for applications written in C or C++, enabled with the `ffi` feature.
+//!
+//! The declarations are in `mdnsresponder.h`, which cbindgen generates from this module into the
+//! build's `OUT_DIR` whenever the crate is built with the feature. Its contents are `HEADER`,
+//! for a build script to write where the C code includes it from. Build a library to link
+//! against with e.g. `cargo rustc --release --lib --features ffi --crate-type staticlib` (or
+//! `cdylib`).
+//!
+//! The responder runs the connection on a thread of its own. Browse callbacks are called on that
+//! thread, one at a time, so they must not block for long. The strings passed to a callback are
+//! only valid until it returns.
+//!
+//! ```c
+//! #include "mdnsresponder.h"
+//!
+//! static void on_browse(const MDnsBrowseEvent *event, void *user_data) {
+//!     if (event->kind == MDNS_BROWSE_EVENT_ADDED) {
+//!         printf("found %s\n", event->display_name);
+//!     }
+//! }
+//!
+//! MDnsResponderHandle *responder = mdns_responder_new();
+//! MDnsBrowseHandle *browse = mdns_responder_browse(responder, 0, "_ipp._tcp", "local", on_browse, NULL);
+//! /* ... */
+//! mdns_browse_cancel(browse);
+//! mdns_responder_free(responder);
+//! ```
+
+use std::cell::Cell;
+use std::ffi::{ c_char, c_void, CStr, CString };
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use log::error;
+
+use crate::blocking::BlockingMDnsResponder;
+use crate::{ Interface, MDnsResponder, MDnsResponderEvent, Operation, Service };
+
+// Channel buffer size of responders created through the C API.
+const CHANNEL_BUFFER_SIZE: usize = 32;
+
+/// The C header declaring this module, `mdnsresponder.h`.
+pub const HEADER: &str = include_str!(concat!(env!("OUT_DIR"), "/mdnsresponder.h"));
+
+thread_local!
+{
+    // Set on the thread of a responder while it calls a browse callback.
+    static IN_CALLBACK: Cell<bool> = const { Cell::new(false) };
+}
+
+/// A connection to mDNSResponder, created with `mdns_responder_new` and released with
+/// `mdns_responder_free`.
+pub struct MDnsResponderHandle
+{
+    responder: BlockingMDnsResponder,
+}
+
+/// A running browse, started with `mdns_responder_browse` and released with `mdns_browse_cancel`.
+pub struct MDnsBrowseHandle
+{
+    cancelled: Arc<AtomicBool>,
+    // Held while the callback is called.
+    delivering: Arc<Mutex<()>>,
+    task: tokio::task::JoinHandle<()>,
+}
+
+/// `MDnsBrowseEvent::kind` of a service that was found.
+pub const MDNS_BROWSE_EVENT_ADDED: u32 = 0;
+/// `MDnsBrowseEvent::kind` of a service that was found before and went away.
+pub const MDNS_BROWSE_EVENT_REMOVED: u32 = 1;
+/// `MDnsBrowseEvent::kind` when the daemon stopped the browse, `error` holds the
+/// kDNSServiceErr_* code.
+pub const MDNS_BROWSE_EVENT_FAILED: u32 = 2;
+
+/// An event of a browse, passed to its callback.
+#[repr(C)]
+pub struct MDnsBrowseEvent
+{
+    /// One of the `MDNS_BROWSE_EVENT_*` constants.
+    pub kind: u32,
+    /// The instance name as the daemon reported it, with DNS escapes. Pass it on to resolve the
+    /// service. Empty if the browse failed.
+    pub name: *const c_char,
+    /// The instance name with DNS escapes undone, for showing to users. Empty if the browse failed.
+    pub display_name: *const c_char,
+    /// Empty if the browse failed.
+    pub service_type: *const c_char,
+    /// Empty if the browse failed.
+    pub domain: *const c_char,
+    /// Index of the interface the answer was received on, 0 if the browse failed.
+    pub interface_index: u32,
+    /// The kDNSServiceErr_* code if the browse failed, 0 otherwise.
+    pub error: i32,
+}
+
+/// Called with every event of a browse and the `user_data` the browse was started with.
+pub type MDnsBrowseCallback = Option<unsafe extern "C" fn(event: *const MDnsBrowseEvent, user_data: *mut c_void)>;
+
+// The callback of a browse with its user data, which the caller promised can be used from the
+// thread of the responder.
+struct Callback
+{
+    function: unsafe extern "C" fn(event: *const MDnsBrowseEvent, user_data: *mut c_void),
+    user_data: *mut c_void,
+}
+
+unsafe impl Send for Callback {}
+
+impl Callback
+{
+    fn call(&self, event: &MDnsResponderEvent)
+    {
+        match event
+        {
+            MDnsResponderEvent::ServiceAdded(service) => self.call_service(MDNS_BROWSE_EVENT_ADDED, service),
+            MDnsResponderEvent::ServiceRemoved(service) => self.call_service(MDNS_BROWSE_EVENT_REMOVED, service),
+            MDnsResponderEvent::ServicesBatch { added, removed } =>
+            {
+                for service in removed
+                {
+                    self.call_service(MDNS_BROWSE_EVENT_REMOVED, service);
+                }
+
+                for service in added
+                {
+                    self.call_service(MDNS_BROWSE_EVENT_ADDED, service);
+                }
+            }
+            MDnsResponderEvent::OperationFailed { error, .. } =>
+            {
+                let event = MDnsBrowseEvent
+                {
+                    kind: MDNS_BROWSE_EVENT_FAILED,
+                    name: c"".as_ptr(),
+                    display_name: c"".as_ptr(),
+                    service_type: c"".as_ptr(),
+                    domain: c"".as_ptr(),
+                    interface_index: 0,
+                    error: error.code(),
+                };
+
+                unsafe { (self.function)(&event, self.user_data) };
+            }
+            _ => {}
+        }
+    }
+
+    fn call_service(&self, kind: u32, service: &Service)
+    {
+        // Kept alive until the callback returns.
+        let name = c_string(&service.name);
+        let display_name = c_string(&service.display_name);
+        let service_type = c_string(&service.service_type);
+        let domain = c_string(&service.domain);
+
+        let event = MDnsBrowseEvent
+        {
+            kind,
+            name: name.as_ptr(),
+            display_name: display_name.as_ptr(),
+            service_type: service_type.as_ptr(),
+            domain: domain.as_ptr(),
+            interface_index: service.interface_index,
+            error: 0,
+        };
+
+        unsafe { (self.function)(&event, self.user_data) };
+    }
+}
+
+/// Connects to mDNSResponder at its default socket path.
+///
+/// Returns `NULL` if the connection could not be set up.
+#[unsafe(no_mangle)]
+pub extern "C" fn mdns_responder_new() -> *mut MDnsResponderHandle
+{
+    return create(MDnsResponder::builder().channel_buffer_size(CHANNEL_BUFFER_SIZE));
+}
+
+/// Connects to mDNSResponder at the given socket path.
+///
+/// Returns `NULL` if the path is `NULL` or not valid UTF-8, or if the connection could not be
+/// set up.
+///
+/// # Safety
+///
+/// `socket_path` must be `NULL` or a NUL-terminated string.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdns_responder_new_with_socket_path(socket_path: *const c_char) -> *mut MDnsResponderHandle
+{
+    let Some(socket_path) = (unsafe { str_argument(socket_path) }) else
+    {
+        return ptr::null_mut();
+    };
+
+    return create(MDnsResponder::builder()
+        .channel_buffer_size(CHANNEL_BUFFER_SIZE)
+        .socket_path(PathBuf::from(socket_path)));
+}
+
+/// Starts browsing for services of a type, e.g. `_ipp._tcp`, in a domain, e.g. `local` or an
+/// empty string for the default domains. `interface_index` is 0 for all interfaces.
+///
+/// `callback` is called with every service that is found or goes away until the browse is
+/// cancelled, on the thread of the responder.
+///
+/// Returns `NULL` if an argument is `NULL` or not valid UTF-8, or if the browse could not be
+/// started. Must not be called from a browse callback, where it returns `NULL` as well.
+///
+/// # Safety
+///
+/// `responder` must be `NULL` or a responder that was not freed. `service_type` and `domain`
+/// must be `NULL` or NUL-terminated strings. `user_data` must be safe to use from the thread of
+/// the responder until the browse is cancelled.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdns_responder_browse(
+    responder: *mut MDnsResponderHandle,
+    interface_index: u32,
+    service_type: *const c_char,
+    domain: *const c_char,
+    callback: MDnsBrowseCallback,
+    user_data: *mut c_void,
+) -> *mut MDnsBrowseHandle
+{
+    let Some(responder) = (unsafe { responder.as_ref() }) else
+    {
+        return ptr::null_mut();
+    };
+
+    let (Some(service_type), Some(domain), Some(function)) = (unsafe { str_argument(service_type) }, unsafe { str_argument(domain) }, callback) else
+    {
+        return ptr::null_mut();
+    };
+
+    // Waiting for the browse to start from the thread of the responder would never end, and
+    // tokio refuses to.
+    if tokio::runtime::Handle::try_current().is_ok()
+    {
+        error!("Browse started through the C API from within a runtime, e.g. a browse callback");
+        return ptr::null_mut();
+    }
+
+    let requester = responder.responder.requester();
+    let operation = match responder.responder.block_on(requester.browse(Interface::from(interface_index), service_type, domain.to_string()))
+    {
+        Ok(operation) => operation,
+        Err(e) =>
+        {
+            error!("Failed to start browse through the C API: {}", e);
+            return ptr::null_mut();
+        }
+    };
+
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let delivering = Arc::new(Mutex::new(()));
+    let callback = Callback
+    {
+        function,
+        user_data,
+    };
+    let task = responder.responder.handle().spawn(deliver(operation, callback, cancelled.clone(), delivering.clone()));
+
+    return Box::into_raw(Box::new(MDnsBrowseHandle
+    {
+        cancelled,
+        delivering,
+        task,
+    }));
+}
+
+/// Cancels a browse and releases it. The callback of the browse is not called anymore once this
+/// returns, also when it is called from within that callback.
+///
+/// # Safety
+///
+/// `browse` must be `NULL` or a browse that was not cancelled yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdns_browse_cancel(browse: *mut MDnsBrowseHandle)
+{
+    if browse.is_null()
+    {
+        return;
+    }
+
+    let browse = unsafe { Box::from_raw(browse) };
+    browse.cancelled.store(true, Ordering::SeqCst);
+    browse.task.abort();
+
+    // Waits for a callback running on the thread of the responder to return, unless this is
+    // called from a callback on that thread, where none of the others can be running. Any other
+    // thread waits, also one of another runtime.
+    if !IN_CALLBACK.get()
+    {
+        drop(browse.delivering.lock());
+    }
+}
+
+/// Closes the connection and releases the responder. Cancel its browses first, they do not call
+/// their callbacks anymore once the responder is freed but must still be released with
+/// `mdns_browse_cancel`. Must not be called from a browse callback.
+///
+/// # Safety
+///
+/// `responder` must be `NULL` or a responder that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn mdns_responder_free(responder: *mut MDnsResponderHandle)
+{
+    if responder.is_null()
+    {
+        return;
+    }
+
+    let responder = unsafe { Box::from_raw(responder) };
+    responder.responder.close();
+}
+
+fn create(builder: crate::MDnsResponderBuilder) -> *mut MDnsResponderHandle
+{
+    return match BlockingMDnsResponder::with_builder(builder)
+    {
+        Ok(responder) => Box::into_raw(Box::new(MDnsResponderHandle { responder })),
+        Err(e) =>
+        {
+            error!("Failed to create responder through the C API: {}", e);
+            ptr::null_mut()
+        }
+    };
+}
+
+// Passes the events of a browse to its callback until it is cancelled or the connection closes.
+async fn deliver(mut operation: Operation, callback: Callback, cancelled: Arc<AtomicBool>, delivering: Arc<Mutex<()>>)
+{
+    while let Some(event) = operation.events.recv().await
+    {
+        let _delivering = delivering.lock();
+
+        // The task is only stopped at an await that has to wait, so events that are already
+        // queued would still be delivered after a cancel.
+        if cancelled.load(Ordering::SeqCst)
+        {
+            break;
+        }
+
+        IN_CALLBACK.set(true);
+        callback.call(&event);
+        IN_CALLBACK.set(false);
+    }
+}
+
+unsafe fn str_argument<'a>(argument: *const c_char) -> Option<&'a str>
+{
+    if argument.is_null()
+    {
+        return None;
+    }
+
+    return unsafe { CStr::from_ptr(argument) }.to_str().ok();
+}
+
+// Names from the daemon cannot contain NUL, but an unescaped `\000` in a display name can. The
+// name is cut off there.
+fn c_string(value: &str) -> CString
+{
+    return match CString::new(value)
+    {
+        Ok(value) => value,
+        Err(e) =>
+        {
+            let position = e.nul_position();
+            let mut bytes = e.into_vec();
+            bytes.truncate(position);
+            CString::new(bytes).unwrap_or_default()
+        }
+    };
+}
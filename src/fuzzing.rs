@@ -0,0 +1,279 @@
+//! Entry points into the parsers of the IPC protocol for fuzz targets and property tests,
+//! enabled with the `fuzzing` feature. The parsers themselves are internal, these types mirror
+//! what they read in plain owned fields, so that a parsed message can be compared to the one
+//! that was encoded.
+//!
+//! Not meant for any other use, it changes along with the parsers.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use mdnsresponder::fuzzing::BrowseReply;
+//!
+//! let reply = BrowseReply
+//! {
+//!     flags: 0x2,
+//!     interface_index: 1,
+//!     error: 0,
+//!     service_name: "Printer".to_string(),
+//!     service_type: "_ipp._tcp.".to_string(),
+//!     service_domain: "local.".to_string(),
+//! };
+//!
+//! assert_eq!(BrowseReply::decode(&reply.encode()), Some(reply));
+//! ```
+
+use crate::ipc::header::tlv::Tlv;
+use crate::ipc::header::{ self, reply::ReplyOperation, IpcMessageHeader, Operation };
+use crate::ipc::operation::{ self, ReplyHeader as Header };
+
+/// The header in front of every IPC message.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IpcHeader
+{
+    pub version: u32,
+    pub data_length: u32,
+    pub ipc_flags: u32,
+    pub operation: u32,
+    pub client_context: u64,
+    pub reg_index: u32,
+}
+
+/// The header every reply body starts with.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplyHeader
+{
+    pub flags: u32,
+    pub interface_index: u32,
+    pub error: i32,
+}
+
+/// The body of a browse reply.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrowseReply
+{
+    pub flags: u32,
+    pub interface_index: u32,
+    pub error: i32,
+    pub service_name: String,
+    pub service_type: String,
+    pub service_domain: String,
+}
+
+/// The body of a resolve reply, with the TXT record in wire format.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolveReply
+{
+    pub flags: u32,
+    pub interface_index: u32,
+    pub error: i32,
+    pub full_name: String,
+    pub host_target: String,
+    pub port: u16,
+    pub txt: Vec<u8>,
+}
+
+/// The body of an address info reply, with the trailing TLVs as type and value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressInfoReply
+{
+    pub flags: u32,
+    pub interface_index: u32,
+    pub error: i32,
+    pub name: String,
+    pub rrtype: u16,
+    pub rrclass: u16,
+    pub rdata: Vec<u8>,
+    pub ttl: u32,
+    pub tlvs: Vec<(u16, Vec<u8>)>,
+}
+
+impl IpcHeader
+{
+    pub fn encode(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::with_capacity(header::IPC_HEADER_SIZE);
+
+        buf.extend_from_slice(&self.version.to_be_bytes());
+        buf.extend_from_slice(&self.data_length.to_be_bytes());
+        buf.extend_from_slice(&self.ipc_flags.to_be_bytes());
+        buf.extend_from_slice(&self.operation.to_be_bytes());
+        buf.extend_from_slice(&self.client_context.to_be_bytes());
+        buf.extend_from_slice(&self.reg_index.to_be_bytes());
+
+        return buf;
+    }
+
+    /// Parses the header with `IpcMessageHeader::from`, `None` if it fails.
+    pub fn decode(buf: &[u8]) -> Option<Self>
+    {
+        let header = IpcMessageHeader::from(buf).ok()?;
+
+        return Some(IpcHeader
+        {
+            version: header.version,
+            data_length: header.data_length,
+            ipc_flags: header.ipc_flags,
+            operation: match &header.operation
+            {
+                Operation::Request(operation) => operation.to_u32(),
+                Operation::Reply(operation) => operation.to_u32(),
+            },
+            client_context: header.client_context,
+            reg_index: header.reg_index,
+        });
+    }
+}
+
+impl ReplyHeader
+{
+    pub fn encode(&self) -> Vec<u8>
+    {
+        return Header::new(self.flags, self.interface_index, self.error).to_bytes();
+    }
+
+    /// Parses the header with `ReplyHeader::from_bytes`, `None` if it fails.
+    pub fn decode(buf: &[u8]) -> Option<Self>
+    {
+        return Some(Self::from_header(&Header::from_bytes(buf).ok()?));
+    }
+
+    fn from_header(header: &Header) -> Self
+    {
+        let buf = header.to_bytes();
+
+        return ReplyHeader
+        {
+            flags: u32::from_be_bytes(buf[0..4].try_into().unwrap()),
+            interface_index: u32::from_be_bytes(buf[4..8].try_into().unwrap()),
+            error: i32::from_be_bytes(buf[8..12].try_into().unwrap()),
+        };
+    }
+}
+
+impl BrowseReply
+{
+    pub fn encode(&self) -> Vec<u8>
+    {
+        return operation::browse::Reply
+        {
+            header: Header::new(self.flags, self.interface_index, self.error),
+            service_name: self.service_name.as_str().into(),
+            service_type: self.service_type.as_str().into(),
+            service_domain: self.service_domain.as_str().into(),
+        }
+        .to_bytes();
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self>
+    {
+        let reply = operation::browse::Reply::from_bytes(buf).ok()?;
+        let header = ReplyHeader::from_header(&reply.header);
+
+        return Some(BrowseReply
+        {
+            flags: header.flags,
+            interface_index: header.interface_index,
+            error: header.error,
+            service_name: reply.service_name.into_owned(),
+            service_type: reply.service_type.into_owned(),
+            service_domain: reply.service_domain.into_owned(),
+        });
+    }
+}
+
+impl ResolveReply
+{
+    pub fn encode(&self) -> Vec<u8>
+    {
+        return operation::resolve::Reply
+        {
+            header: Header::new(self.flags, self.interface_index, self.error),
+            full_name: self.full_name.as_str().into(),
+            host_target: self.host_target.as_str().into(),
+            port: self.port,
+            txt_data: Vec::new(),
+            txt_raw: &self.txt,
+        }
+        .to_bytes();
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self>
+    {
+        let reply = operation::resolve::Reply::from_bytes(buf).ok()?;
+        let header = ReplyHeader::from_header(&reply.header);
+
+        return Some(ResolveReply
+        {
+            flags: header.flags,
+            interface_index: header.interface_index,
+            error: header.error,
+            full_name: reply.full_name.into_owned(),
+            host_target: reply.host_target.into_owned(),
+            port: reply.port,
+            txt: reply.txt_raw.to_vec(),
+        });
+    }
+}
+
+impl AddressInfoReply
+{
+    pub fn encode(&self) -> Vec<u8>
+    {
+        return operation::addrinfo::Reply
+        {
+            header: Header::new(self.flags, self.interface_index, self.error),
+            name: self.name.as_str().into(),
+            rrtype: self.rrtype,
+            rrclass: self.rrclass,
+            rdata: &self.rdata,
+            ttl: self.ttl,
+            tlvs: self.tlvs.iter().map(|(tlv_type, value)| Tlv::new(*tlv_type, value.clone())).collect(),
+        }
+        .to_bytes();
+    }
+
+    pub fn decode(buf: &[u8]) -> Option<Self>
+    {
+        let reply = operation::addrinfo::Reply::from_bytes(buf).ok()?;
+        let header = ReplyHeader::from_header(&reply.header);
+
+        return Some(AddressInfoReply
+        {
+            flags: header.flags,
+            interface_index: header.interface_index,
+            error: header.error,
+            name: reply.name.into_owned(),
+            rrtype: reply.rrtype,
+            rrclass: reply.rrclass,
+            rdata: reply.rdata.to_vec(),
+            ttl: reply.ttl,
+            tlvs: reply.tlvs.into_iter().map(|tlv| (tlv.tlv_type, tlv.value)).collect(),
+        });
+    }
+}
+
+/// Runs the parser of the reply with the given operation code on a reply body and returns
+/// whether it accepted it. Codes that are not replies are rejected.
+pub fn parse_reply(operation: u32, body: &[u8]) -> bool
+{
+    let Some(reply) = ReplyOperation::from_u32(operation) else
+    {
+        return false;
+    };
+
+    return match reply
+    {
+        ReplyOperation::Enumeration => operation::enumeration::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::RegisterService => operation::register::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::Browse => operation::browse::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::Resolve => operation::resolve::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::Query => operation::query::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::RegisterRecord => operation::registerrecord::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::GetProperty => operation::getproperty::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::PortMapping => operation::portmapping::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::AddressInfo => operation::addrinfo::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::AsyncError => operation::asyncerror::Reply::from_bytes(body).is_ok(),
+        ReplyOperation::Unknown(_) => false,
+    };
+}
@@ -0,0 +1,81 @@
+// Pseudo-interface indices of the daemon (kDNSServiceInterfaceIndex* in dns_sd.h).
+const INTERFACE_INDEX_LOCAL_ONLY: u32 = u32::MAX;
+const INTERFACE_INDEX_P2P: u32 = u32::MAX - 2;
+const INTERFACE_INDEX_BLE: u32 = u32::MAX - 3;
+
+/// Network interface an operation is scoped to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Interface
+{
+    /// All interfaces the daemon considers suitable.
+    #[default]
+    Any,
+    /// Only this host: services are registered without being advertised on the network and
+    /// browsing only finds services registered on this host.
+    LocalOnly,
+    /// Peer-to-peer interfaces such as AWDL, without any regular interface.
+    P2P,
+    /// Bluetooth Low Energy.
+    Ble,
+    /// A single interface by its index, as returned by `if_nametoindex`.
+    Index(u32),
+}
+
+impl Interface
+{
+    /// Returns the interface index sent to the daemon.
+    pub fn index(&self) -> u32
+    {
+        return match self
+        {
+            Interface::Any => 0,
+            Interface::LocalOnly => INTERFACE_INDEX_LOCAL_ONLY,
+            Interface::P2P => INTERFACE_INDEX_P2P,
+            Interface::Ble => INTERFACE_INDEX_BLE,
+            Interface::Index(index) => *index,
+        };
+    }
+}
+
+impl From<u32> for Interface
+{
+    fn from(index: u32) -> Self
+    {
+        return match index
+        {
+            0 => Interface::Any,
+            INTERFACE_INDEX_LOCAL_ONLY => Interface::LocalOnly,
+            INTERFACE_INDEX_P2P => Interface::P2P,
+            INTERFACE_INDEX_BLE => Interface::Ble,
+            index => Interface::Index(index),
+        };
+    }
+}
+
+// Looks up the name of an interface, such as "en0". `None` for indices that do not belong to an
+// interface, like 0 or the pseudo-interfaces of the daemon.
+#[cfg(unix)]
+pub(crate) fn interface_name(index: u32) -> Option<String>
+{
+    if !matches!(Interface::from(index), Interface::Index(_))
+    {
+        return None;
+    }
+
+    let mut buf = [0 as libc::c_char; libc::IF_NAMESIZE];
+
+    let name = unsafe { libc::if_indextoname(index, buf.as_mut_ptr()) };
+    if name.is_null()
+    {
+        return None;
+    }
+
+    let name = unsafe { std::ffi::CStr::from_ptr(name) };
+    return Some(name.to_string_lossy().into_owned());
+}
+
+#[cfg(not(unix))]
+pub(crate) fn interface_name(_index: u32) -> Option<String>
+{
+    return None;
+}
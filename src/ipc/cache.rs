@@ -0,0 +1,62 @@
+use std::collections::HashMap;
+
+use crate::Service;
+
+// Name, type, domain and interface index.
+type ServiceKey = (String, String, String, u32);
+
+// Services currently known to each browse operation, keyed by name, type, domain and interface
+// since the daemon adds and removes a service separately on every interface.
+pub struct ServiceCache
+{
+    services: HashMap<u64, HashMap<ServiceKey, Service>>,
+}
+
+impl ServiceCache
+{
+    pub fn new() -> Self
+    {
+        return ServiceCache
+        {
+            services: HashMap::new(),
+        };
+    }
+
+    pub fn update(&mut self, context: u64, service: &Service, add: bool)
+    {
+        let key = (
+            service.name.clone(),
+            service.service_type.clone(),
+            service.domain.clone(),
+            service.interface_index,
+        );
+
+        if add
+        {
+            self.services.entry(context).or_default().insert(key, service.clone());
+        }
+        else if let Some(services) = self.services.get_mut(&context)
+        {
+            services.remove(&key);
+        }
+    }
+
+    pub fn services(&self, context: u64) -> Vec<Service>
+    {
+        return match self.services.get(&context)
+        {
+            Some(services) => services.values().cloned().collect(),
+            None => Vec::new(),
+        };
+    }
+
+    pub fn forget(&mut self, context: u64)
+    {
+        self.services.remove(&context);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.services.clear();
+    }
+}
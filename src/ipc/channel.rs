@@ -0,0 +1,149 @@
+use std::collections::VecDeque;
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicBool, AtomicU64, Ordering };
+use tokio::sync::{ mpsc, Notify };
+
+use crate::{ runtime, MDnsResponderEvent, MetricsSink, OverflowPolicy };
+
+type SendResult = Result<(), mpsc::error::SendError<MDnsResponderEvent>>;
+
+// Sending side of an events channel that applies the overflow policy when the channel is full.
+#[derive(Clone)]
+pub struct EventSender
+{
+    sender: mpsc::Sender<MDnsResponderEvent>,
+    policy: OverflowPolicy,
+    // Events waiting for room in the channel, for the policies that queue.
+    queue: Option<Arc<QueueHandle>>,
+    dropped: Arc<AtomicU64>,
+    metrics: Arc<dyn MetricsSink>,
+}
+
+// Events queued behind a channel, handed to the channel in order by a forwarding task.
+struct Queue
+{
+    events: Mutex<VecDeque<MDnsResponderEvent>>,
+    // `None` if the queue is unbounded.
+    capacity: Option<usize>,
+    notify: Notify,
+    closed: AtomicBool,
+}
+
+// Closes the queue once the last sender is gone, the forwarding task ends when it is drained.
+struct QueueHandle(Arc<Queue>);
+
+impl Drop for QueueHandle
+{
+    fn drop(&mut self)
+    {
+        self.0.closed.store(true, Ordering::Release);
+        self.0.notify.notify_one();
+    }
+}
+
+impl EventSender
+{
+    pub fn new(
+        sender: mpsc::Sender<MDnsResponderEvent>,
+        policy: OverflowPolicy,
+        dropped: Arc<AtomicU64>,
+        metrics: Arc<dyn MetricsSink>,
+    ) -> Self
+    {
+        let queued = matches!(policy, OverflowPolicy::DropOldest | OverflowPolicy::Unbounded);
+
+        let queue = queued.then(||
+        {
+            let queue = Arc::new(Queue
+            {
+                events: Mutex::new(VecDeque::new()),
+                capacity: (policy == OverflowPolicy::DropOldest).then(|| sender.max_capacity()),
+                notify: Notify::new(),
+                closed: AtomicBool::new(false),
+            });
+
+            runtime::spawn_detached(Self::forward(queue.clone(), sender.clone()));
+            Arc::new(QueueHandle(queue))
+        });
+
+        return EventSender
+        {
+            sender,
+            policy,
+            queue,
+            dropped,
+            metrics,
+        };
+    }
+
+    // Fails only if the receiver was dropped.
+    pub async fn send(&self, event: MDnsResponderEvent) -> SendResult
+    {
+        if let Some(queue) = &self.queue
+        {
+            if self.sender.is_closed()
+            {
+                return Err(mpsc::error::SendError(event));
+            }
+
+            self.enqueue(&queue.0, event);
+            return Ok(());
+        }
+
+        match self.policy
+        {
+            OverflowPolicy::DropNewest => match self.sender.try_send(event)
+            {
+                Ok(()) => return Ok(()),
+                Err(mpsc::error::TrySendError::Full(_)) =>
+                {
+                    self.count_dropped();
+                    return Ok(());
+                }
+                Err(mpsc::error::TrySendError::Closed(event)) => return Err(mpsc::error::SendError(event)),
+            },
+            _ => return self.sender.send(event).await,
+        }
+    }
+
+    fn enqueue(&self, queue: &Queue, event: MDnsResponderEvent)
+    {
+        let mut events = queue.events.lock().unwrap();
+
+        if queue.capacity.is_some_and(|capacity| events.len() >= capacity)
+        {
+            events.pop_front();
+            self.count_dropped();
+        }
+
+        events.push_back(event);
+        queue.notify.notify_one();
+    }
+
+    fn count_dropped(&self)
+    {
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        self.metrics.event_dropped();
+    }
+
+    async fn forward(queue: Arc<Queue>, sender: mpsc::Sender<MDnsResponderEvent>)
+    {
+        loop
+        {
+            let event = queue.events.lock().unwrap().pop_front();
+
+            match event
+            {
+                Some(event) =>
+                {
+                    if sender.send(event).await.is_err()
+                    {
+                        return;
+                    }
+                }
+                None if queue.closed.load(Ordering::Acquire) => return,
+                None => queue.notify.notified().await,
+            }
+        }
+    }
+}
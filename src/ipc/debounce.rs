@@ -0,0 +1,81 @@
+use std::collections::HashMap;
+use std::time::{ Duration, Instant };
+
+use crate::{ MDnsResponderEvent, Service };
+
+// Context, name, type, domain and interface of a service.
+type ServiceKey = (u64, String, String, String, u32);
+
+// Holds back browse removes for a while, so that a service that goes away and comes back within
+// the window is never reported as removed at all.
+pub struct Debounce
+{
+    window: Duration,
+    removes: HashMap<ServiceKey, (Instant, Service)>,
+}
+
+impl Debounce
+{
+    pub fn new(window: Duration) -> Self
+    {
+        return Debounce
+        {
+            window,
+            removes: HashMap::new(),
+        };
+    }
+
+    // Returns whether the answer should be delivered right away. Removes are always held back,
+    // an add is swallowed if it cancels a held back remove of the same service.
+    pub fn admit(&mut self, context: u64, service: &Service, add: bool) -> bool
+    {
+        let key = (
+            context,
+            service.name.clone(),
+            service.service_type.clone(),
+            service.domain.clone(),
+            service.interface_index,
+        );
+
+        if add
+        {
+            return self.removes.remove(&key).is_none();
+        }
+
+        self.removes.insert(key, (Instant::now() + self.window, service.clone()));
+        return false;
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant>
+    {
+        return self.removes.values().map(|(deadline, _)| *deadline).min();
+    }
+
+    // Removes all removes whose window has passed and returns their events with the context of
+    // the operation they belong to.
+    pub fn take_due(&mut self) -> Vec<(u64, MDnsResponderEvent)>
+    {
+        let now = Instant::now();
+
+        let due: Vec<ServiceKey> = self.removes
+            .iter()
+            .filter(|(_, (deadline, _))| *deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        return due
+            .into_iter()
+            .filter_map(|key| self.removes.remove(&key).map(|(_, service)| (key.0, MDnsResponderEvent::ServiceRemoved(service))))
+            .collect();
+    }
+
+    pub fn forget(&mut self, context: u64)
+    {
+        self.removes.retain(|key, _| key.0 != context);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.removes.clear();
+    }
+}
@@ -0,0 +1,75 @@
+use std::collections::HashMap;
+
+use crate::DedupPolicy;
+
+// Counts the outstanding add answers per service and operation, so that only the first add and
+// the last remove of the same service are passed on.
+pub struct Dedup
+{
+    policy: DedupPolicy,
+    answers: HashMap<(u64, String, String, String, Option<u32>), u32>,
+}
+
+impl Dedup
+{
+    pub fn new(policy: DedupPolicy) -> Self
+    {
+        return Dedup
+        {
+            policy,
+            answers: HashMap::new(),
+        };
+    }
+
+    // Returns whether the answer should be delivered.
+    pub fn admit(&mut self, context: u64, service: &crate::Service, add: bool) -> bool
+    {
+        let interface = match self.policy
+        {
+            DedupPolicy::Off => return true,
+            DedupPolicy::PerInterface => Some(service.interface_index),
+            DedupPolicy::Global => None,
+        };
+
+        let key = (
+            context,
+            service.name.clone(),
+            service.service_type.clone(),
+            service.domain.clone(),
+            interface,
+        );
+
+        if add
+        {
+            let count = self.answers.entry(key).or_insert(0);
+            *count += 1;
+            return *count == 1;
+        }
+
+        match self.answers.get_mut(&key)
+        {
+            Some(count) if *count > 1 =>
+            {
+                *count -= 1;
+                return false;
+            }
+            Some(_) =>
+            {
+                self.answers.remove(&key);
+                return true;
+            }
+            // A remove for a service that was never added is passed on as it is.
+            None => return true,
+        }
+    }
+
+    pub fn forget(&mut self, context: u64)
+    {
+        self.answers.retain(|key, _| key.0 != context);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.answers.clear();
+    }
+}
@@ -0,0 +1,203 @@
+// The daemon reports whether it accepted a request on a separate socket passed along with it,
+// unless the request carries the NoErrSd flag. Passing a socket needs SCM_RIGHTS, so this is only
+// available on Unix sockets.
+
+#[cfg(unix)]
+pub use unix::{ Acknowledgement, ErrorReturn };
+
+#[cfg(not(unix))]
+pub use unsupported::{ Acknowledgement, ErrorReturn };
+
+#[cfg(unix)]
+mod unix
+{
+    #[cfg(not(feature = "tracing"))]
+    use log::debug;
+    #[cfg(feature = "tracing")]
+    use tracing::debug;
+    use std::io;
+    use std::os::fd::{ AsRawFd, BorrowedFd, RawFd };
+    use std::time::Duration;
+    use tokio::io::{ AsyncReadExt, AsyncWriteExt };
+
+    use super::super::header::Frame;
+    use super::super::WriteHalf;
+    use crate::{ runtime, Connection, DnsServiceError };
+
+    // The daemon answers as soon as it has handled the request, this only guards against one
+    // that never does.
+    const ERROR_TIMEOUT: Duration = Duration::from_secs(5);
+
+    pub struct ErrorReturn
+    {
+        // Duplicate of the descriptor of the shared connection, registered with the runtime on
+        // its own so that passing a socket can wait for the connection to become writable.
+        socket: runtime::WritableFd,
+    }
+
+    // The socket a request was written with, on which the daemon reports whether it accepted it.
+    pub struct Acknowledgement
+    {
+        local: runtime::UnixStream,
+        length: usize,
+    }
+
+    impl ErrorReturn
+    {
+        // Returns `None` if the connection is not a Unix socket, e.g. over TCP or when captured.
+        pub fn for_connection(connection: &dyn Connection) -> Option<Self>
+        {
+            let any: &dyn std::any::Any = connection;
+            let stream = any.downcast_ref::<runtime::UnixStream>()?;
+
+            // The connection stays open for as long as the writer holding this does.
+            let fd = unsafe { BorrowedFd::borrow_raw(runtime::unix_stream_fd(stream)) };
+
+            return match fd.try_clone_to_owned().and_then(runtime::WritableFd::new)
+            {
+                Ok(socket) => Some(ErrorReturn { socket }),
+                Err(e) =>
+                {
+                    debug!("Failed to set up passing error sockets, not waiting for requests to be accepted: {}", e);
+                    None
+                }
+            };
+        }
+
+        // Writes the frame with a socket for the daemon to report on. The returned
+        // acknowledgement waits for the daemon's answer.
+        pub async fn write(&self, write_socket: &mut WriteHalf, frame: &Frame) -> io::Result<Acknowledgement>
+        {
+            let buf = frame.to_vec_with_error_return();
+            let (local, remote) = std::os::unix::net::UnixStream::pair()?;
+
+            // The socket is passed along with the last byte of the frame.
+            let (last, rest) = buf.split_last().expect("frame is never empty");
+            write_socket.write_all(rest).await?;
+            write_socket.flush().await?;
+            self.send_with_fd(*last, remote.as_raw_fd()).await?;
+
+            // Only the daemon holds the other end from here on, so a read fails rather than
+            // hangs if it closes it without answering.
+            drop(remote);
+
+            return Ok(Acknowledgement
+            {
+                local: runtime::unix_stream_from_std(local)?,
+                length: buf.len(),
+            });
+        }
+
+        async fn send_with_fd(&self, byte: u8, fd: RawFd) -> io::Result<()>
+        {
+            return self.socket
+                .write_with(|socket| loop
+                {
+                    match send_with_fd(socket.as_raw_fd(), byte, fd)
+                    {
+                        Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                        result => return result,
+                    }
+                })
+                .await;
+        }
+    }
+
+    impl Acknowledgement
+    {
+        // Waits for the daemon's error code. A request the daemon rejected fails with the
+        // `DnsServiceError` as inner error.
+        pub async fn wait(mut self) -> io::Result<usize>
+        {
+            let mut error_buf = [0u8; 4];
+            match runtime::timeout(ERROR_TIMEOUT, self.local.read_exact(&mut error_buf)).await
+            {
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Err(io::Error::new(io::ErrorKind::TimedOut, "No error code from mDNSResponder")),
+            }
+
+            return match DnsServiceError::from_code(i32::from_be_bytes(error_buf))
+            {
+                Some(error) =>
+                {
+                    debug!("mDNSResponder rejected request: {}", error);
+                    Err(io::Error::other(error))
+                }
+                None => Ok(self.length),
+            };
+        }
+    }
+
+    fn send_with_fd(socket: RawFd, byte: u8, fd: RawFd) -> io::Result<()>
+    {
+        let mut data = [byte];
+        let mut iov = libc::iovec
+        {
+            iov_base: data.as_mut_ptr() as *mut libc::c_void,
+            iov_len: data.len(),
+        };
+
+        // Aligned room for a single control message carrying one descriptor.
+        let mut control = [0u64; 8];
+        let control_length = unsafe { libc::CMSG_SPACE(std::mem::size_of::<RawFd>() as u32) } as usize;
+
+        let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+        msg.msg_iov = &mut iov;
+        msg.msg_iovlen = 1;
+        msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+        msg.msg_controllen = control_length as _;
+
+        let result = unsafe {
+            let cmsg = libc::CMSG_FIRSTHDR(&msg);
+            (*cmsg).cmsg_level = libc::SOL_SOCKET;
+            (*cmsg).cmsg_type = libc::SCM_RIGHTS;
+            (*cmsg).cmsg_len = libc::CMSG_LEN(std::mem::size_of::<RawFd>() as u32) as _;
+            std::ptr::copy_nonoverlapping(&fd, libc::CMSG_DATA(cmsg) as *mut RawFd, 1);
+
+            libc::sendmsg(socket, &msg, 0)
+        };
+
+        return match result
+        {
+            1 => Ok(()),
+            0 => Err(io::Error::new(io::ErrorKind::WriteZero, "Failed to pass error socket")),
+            _ => Err(io::Error::last_os_error()),
+        };
+    }
+}
+
+#[cfg(not(unix))]
+mod unsupported
+{
+    use std::io;
+
+    use super::super::header::Frame;
+    use super::super::WriteHalf;
+    use crate::Connection;
+
+    pub enum ErrorReturn {}
+
+    pub enum Acknowledgement {}
+
+    impl ErrorReturn
+    {
+        pub fn for_connection(_connection: &dyn Connection) -> Option<Self>
+        {
+            return None;
+        }
+
+        pub async fn write(&self, _write_socket: &mut WriteHalf, _frame: &Frame) -> io::Result<Acknowledgement>
+        {
+            match *self {}
+        }
+    }
+
+    impl Acknowledgement
+    {
+        pub async fn wait(self) -> io::Result<usize>
+        {
+            match self {}
+        }
+    }
+}
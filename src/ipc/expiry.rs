@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use std::time::{ Duration, Instant };
+
+use crate::{ AddressInfo, MDnsResponderEvent, Record };
+
+// Context, name, record type and record data of an answer.
+type AnswerKey = (u64, String, u16, Vec<u8>);
+
+enum Answer
+{
+    Address(AddressInfo),
+    Record(Record),
+}
+
+// Tracks when the answers delivered for address info and query operations go stale according
+// to their TTL, unless the daemon refreshes or removes them first.
+pub struct Expiry
+{
+    answers: HashMap<AnswerKey, (Instant, Answer)>,
+}
+
+impl Expiry
+{
+    pub fn new() -> Self
+    {
+        return Expiry
+        {
+            answers: HashMap::new(),
+        };
+    }
+
+    pub fn track_address(&mut self, context: u64, address_info: &AddressInfo)
+    {
+        let (rrtype, rdata) = match address_info.address
+        {
+            std::net::IpAddr::V4(address) => (1, address.octets().to_vec()),
+            std::net::IpAddr::V6(address) => (28, address.octets().to_vec()),
+        };
+
+        let key = (context, address_info.hostname.clone(), rrtype, rdata);
+        self.update(key, address_info.flags.add, address_info.ttl, Answer::Address(address_info.clone()));
+    }
+
+    pub fn track_record(&mut self, context: u64, record: &Record, add: bool)
+    {
+        let key = (context, record.name.clone(), record.rrtype, record.rdata.clone());
+        self.update(key, add, record.ttl, Answer::Record(record.clone()));
+    }
+
+    fn update(&mut self, key: AnswerKey, add: bool, ttl: u32, answer: Answer)
+    {
+        if add
+        {
+            let deadline = Instant::now() + Duration::from_secs(ttl as u64);
+            self.answers.insert(key, (deadline, answer));
+        }
+        else
+        {
+            self.answers.remove(&key);
+        }
+    }
+
+    pub fn next_deadline(&self) -> Option<Instant>
+    {
+        return self.answers.values().map(|(deadline, _)| *deadline).min();
+    }
+
+    // Removes all answers that are stale by now and returns their expiry events with the context
+    // of the operation they belong to.
+    pub fn take_expired(&mut self) -> Vec<(u64, MDnsResponderEvent)>
+    {
+        let now = Instant::now();
+
+        let expired: Vec<AnswerKey> = self.answers
+            .iter()
+            .filter(|(_, (deadline, _))| *deadline <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        return expired
+            .into_iter()
+            .filter_map(|key| self.answers.remove(&key).map(|(_, answer)| (key.0, answer)))
+            .map(|(context, answer)| match answer
+            {
+                Answer::Address(address_info) => (context, MDnsResponderEvent::AddressExpired(address_info)),
+                Answer::Record(record) => (context, MDnsResponderEvent::RecordExpired(record)),
+            })
+            .collect();
+    }
+
+    pub fn forget(&mut self, context: u64)
+    {
+        self.answers.retain(|key, _| key.0 != context);
+    }
+
+    pub fn clear(&mut self)
+    {
+        self.answers.clear();
+    }
+}
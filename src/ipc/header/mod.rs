@@ -3,6 +3,7 @@ use std::io;
 
 pub mod reply;
 pub mod request;
+pub mod tlv;
 
 #[derive(Debug)]
 pub enum Operation
@@ -11,14 +12,15 @@ pub enum Operation
     Reply(reply::ReplyOperation),
 }
 
+#[derive(Copy, Clone, Debug)]
 pub enum IpcFlags
 {
-    NoReply = 0x0,
     TrailingTlvs = 0x2,
     NoErrSd = 0x4,
 }
 
 pub const IPC_HEADER_SIZE: usize = 28;
+pub const IPC_VERSION: u32 = 1;
 
 pub struct IpcMessageHeader
 {
@@ -49,8 +51,7 @@ impl IpcMessageHeader
         let client_context = u64::from_be_bytes(buf[16..24].try_into().unwrap());
         let reg_index = u32::from_be_bytes(buf[24..28].try_into().unwrap());
 
-        let operation;
-        if operation_num >= reply::REPLY_OPERATION_START
+        let operation = if operation_num >= reply::REPLY_OPERATION_START
         {
             let reply_operation =
                 reply::ReplyOperation::from_u32(operation_num).ok_or_else(||
@@ -58,7 +59,7 @@ impl IpcMessageHeader
                         io::Error::new(io::ErrorKind::InvalidData, "Invalid reply operation")
                     }
                 )?;
-            operation = Operation::Reply(reply_operation);
+            Operation::Reply(reply_operation)
         }
         else
         {
@@ -68,8 +69,8 @@ impl IpcMessageHeader
                         io::Error::new(io::ErrorKind::InvalidData, "Invalid request operation")
                     }
                 )?;
-            operation = Operation::Request(request_operation);
-        }
+            Operation::Request(request_operation)
+        };
 
         return Ok(IpcMessageHeader
         {
@@ -104,7 +105,7 @@ impl IpcMessageHeader
 
     pub fn to_bytes(&self) -> Vec<u8>
     {
-        let mut buf = Vec::with_capacity(20);
+        let mut buf = Vec::with_capacity(IPC_HEADER_SIZE);
 
         buf.extend_from_slice(&self.version.to_be_bytes());
         buf.extend_from_slice(&self.data_length.to_be_bytes());
@@ -122,6 +123,86 @@ impl IpcMessageHeader
     }
 }
 
+// A complete request frame: the IPC header followed by the request data and any trailing TLVs,
+// which are accounted for in the data length. The parts are kept apart and written with a single
+// vectored write, so the request data is never copied into a frame buffer.
+#[derive(Clone)]
+pub struct Frame
+{
+    header: Vec<u8>,
+    data: Vec<u8>,
+    tlvs: Vec<u8>,
+}
+
+impl Frame
+{
+    // Joins the parts, for the one-off connections that are not worth a vectored write.
+    pub fn to_vec(&self) -> Vec<u8>
+    {
+        return [self.header.as_slice(), &self.data, &self.tlvs].concat();
+    }
+
+    // The frame as sent when the daemon is to report whether it accepted the request on a socket
+    // passed along with it: without the NoErrSd flag, and with an empty path for that socket
+    // ahead of the request data.
+    pub fn to_vec_with_error_return(&self) -> Vec<u8>
+    {
+        let mut buf = self.to_vec();
+
+        let data_length = u32::from_be_bytes(buf[4..8].try_into().unwrap()) + 1;
+        let ipc_flags = u32::from_be_bytes(buf[8..12].try_into().unwrap()) & !(IpcFlags::NoErrSd as u32);
+
+        buf[4..8].copy_from_slice(&data_length.to_be_bytes());
+        buf[8..12].copy_from_slice(&ipc_flags.to_be_bytes());
+        buf.insert(IPC_HEADER_SIZE, 0);
+
+        return buf;
+    }
+
+    pub fn io_slices(&self) -> [io::IoSlice<'_>; 3]
+    {
+        return [
+            io::IoSlice::new(&self.header),
+            io::IoSlice::new(&self.data),
+            io::IoSlice::new(&self.tlvs),
+        ];
+    }
+}
+
+pub fn build_request(
+    operation: request::RequestOperation,
+    client_context: u64,
+    reg_index: u32,
+    request_buf: Vec<u8>,
+    tlvs: &[tlv::Tlv],
+) -> Frame
+{
+    let tlv_buf = tlv::encode(tlvs);
+
+    let mut ipc_flags = IpcFlags::NoErrSd as u32;
+
+    if !tlv_buf.is_empty()
+    {
+        ipc_flags |= IpcFlags::TrailingTlvs as u32;
+    }
+
+    let header = IpcMessageHeader::new(
+        IPC_VERSION,
+        (request_buf.len() + tlv_buf.len()) as u32,
+        ipc_flags,
+        Operation::Request(operation),
+        client_context,
+        reg_index,
+    );
+
+    return Frame
+    {
+        header: header.to_bytes(),
+        data: request_buf,
+        tlvs: tlv_buf,
+    };
+}
+
 impl std::fmt::Debug for IpcMessageHeader
 {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
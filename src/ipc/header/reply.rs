@@ -12,6 +12,8 @@ pub enum ReplyOperation
     PortMapping,
     AddressInfo,
     AsyncError,
+    // An operation added after this crate was written, by its number.
+    Unknown(u32),
 }
 
 impl ReplyOperation
@@ -30,6 +32,7 @@ impl ReplyOperation
             71 => Some(ReplyOperation::PortMapping),
             72 => Some(ReplyOperation::AddressInfo),
             73 => Some(ReplyOperation::AsyncError),
+            value if value >= REPLY_OPERATION_START => Some(ReplyOperation::Unknown(value)),
             _ => None,
         };
     }
@@ -48,6 +51,7 @@ impl ReplyOperation
             ReplyOperation::PortMapping => 71,
             ReplyOperation::AddressInfo => 72,
             ReplyOperation::AsyncError => 73,
+            ReplyOperation::Unknown(value) => *value,
         };
     }
 }
@@ -68,6 +72,7 @@ impl std::fmt::Debug for ReplyOperation
             ReplyOperation::PortMapping => write!(f, "PortMapping"),
             ReplyOperation::AddressInfo => write!(f, "AddressInfo"),
             ReplyOperation::AsyncError => write!(f, "AsyncError"),
+            ReplyOperation::Unknown(value) => write!(f, "Unknown({})", value),
         }
     }
 }
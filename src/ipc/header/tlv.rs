@@ -0,0 +1,73 @@
+// Type-length-value attributes that can trail the data of an IPC message when the
+// `IpcFlags::TrailingTlvs` flag is set. Both type and length are 16-bit big-endian values.
+pub const TLV_HEADER_SIZE: usize = 4;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Tlv
+{
+    pub tlv_type: u16,
+    pub value: Vec<u8>,
+}
+
+impl Tlv
+{
+    pub fn new(tlv_type: u16, value: Vec<u8>) -> Self
+    {
+        return Tlv
+        {
+            tlv_type,
+            value,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::with_capacity(TLV_HEADER_SIZE + self.value.len());
+
+        buf.extend_from_slice(&self.tlv_type.to_be_bytes());
+        buf.extend_from_slice(&(self.value.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.value);
+
+        return buf;
+    }
+}
+
+pub fn encode(tlvs: &[Tlv]) -> Vec<u8>
+{
+    let mut buf = Vec::new();
+
+    for tlv in tlvs
+    {
+        buf.extend_from_slice(&tlv.to_bytes());
+    }
+
+    return buf;
+}
+
+pub fn decode(buf: &[u8]) -> Result<Vec<Tlv>, String>
+{
+    let mut tlvs = Vec::new();
+    let mut offset = 0;
+
+    while offset < buf.len()
+    {
+        if offset + TLV_HEADER_SIZE > buf.len()
+        {
+            return Err(format!("Buffer too short for TLV header at offset {}", offset));
+        }
+
+        let tlv_type = u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+        let length = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]) as usize;
+        offset += TLV_HEADER_SIZE;
+
+        if offset + length > buf.len()
+        {
+            return Err(format!("Buffer too short for TLV value of length {}", length));
+        }
+
+        tlvs.push(Tlv::new(tlv_type, buf[offset..offset + length].to_vec()));
+        offset += length;
+    }
+
+    return Ok(tlvs);
+}
@@ -0,0 +1,80 @@
+use super::header::Frame;
+
+// Requests that are still active on the connection, in the order they were written, so they can
+// be issued again on a new connection after the daemon restarted. The client contexts are kept,
+// which keeps every handle given out to users valid on the new connection.
+pub struct Journal
+{
+    entries: Vec<Entry>,
+}
+
+struct Entry
+{
+    context: u64,
+    kind: EntryKind,
+    frame: Frame,
+}
+
+#[derive(PartialEq, Eq)]
+enum EntryKind
+{
+    // The request that started the operation.
+    Request,
+    // A record added to a registration or registered on the shared connection, by its index.
+    Record(u32),
+    // The latest data of a record, by its index.
+    Update(u32),
+}
+
+impl Journal
+{
+    pub fn new() -> Self
+    {
+        return Journal
+        {
+            entries: Vec::new(),
+        };
+    }
+
+    pub fn record(&mut self, context: u64, frame: Frame)
+    {
+        self.entries.push(Entry { context, kind: EntryKind::Request, frame });
+    }
+
+    pub fn add_record(&mut self, context: u64, reg_index: u32, frame: Frame)
+    {
+        self.entries.push(Entry { context, kind: EntryKind::Record(reg_index), frame });
+    }
+
+    // Only the latest update of a record is issued again, it replaces the earlier one in place so
+    // it stays after the record it updates.
+    pub fn update_record(&mut self, context: u64, reg_index: u32, frame: Frame)
+    {
+        let kind = EntryKind::Update(reg_index);
+
+        match self.entries.iter_mut().find(|entry| entry.context == context && entry.kind == kind)
+        {
+            Some(entry) => entry.frame = frame,
+            None => self.entries.push(Entry { context, kind, frame }),
+        }
+    }
+
+    pub fn remove_record(&mut self, context: u64, reg_index: u32)
+    {
+        self.entries.retain(|entry|
+        {
+            return entry.context != context
+                || (entry.kind != EntryKind::Record(reg_index) && entry.kind != EntryKind::Update(reg_index));
+        });
+    }
+
+    pub fn forget(&mut self, context: u64)
+    {
+        self.entries.retain(|entry| entry.context != context);
+    }
+
+    pub fn frames(&self) -> impl Iterator<Item = &Frame>
+    {
+        return self.entries.iter().map(|entry| &entry.frame);
+    }
+}
@@ -1,124 +1,559 @@
+#[cfg(not(feature = "tracing"))]
 use log::{ debug, error };
+#[cfg(feature = "tracing")]
+use tracing::{ debug, error };
+use bytes::{ Buf, BytesMut };
+use std::collections::HashMap;
 use std::io;
-use tokio::net::{ UnixStream, unix::{OwnedReadHalf, OwnedWriteHalf}, };
 use std::net::IpAddr;
+use std::sync::Arc;
+use std::time::{ Duration, Instant };
+use tokio::io::{ AsyncReadExt, AsyncWriteExt };
 use tokio::select;
-use tokio::sync::mpsc;
-use tokio::task;
+use tokio::sync::{ mpsc, oneshot, watch };
 use tokio_util::sync::CancellationToken;
 
 use crate::mdnsresponder_error::InternalError;
 
-mod header;
-mod operation;
-
-const SOCKET_PATH: &str = "/var/run/mDNSResponder";
+mod cache;
+mod channel;
+mod debounce;
+mod dedup;
+mod error_return;
+mod expiry;
+pub(crate) mod header;
+mod journal;
+pub(crate) mod operation;
+mod router;
+
+const MAX_PROPERTY_LENGTH: usize = 256;
+// Larger than any reply, which holds at most a record of 64 KiB. A header that announces more
+// data is not taken as the header of a frame.
+pub(crate) const MAX_FRAME_DATA_LENGTH: usize = 256 * 1024;
+// How long close waits for the listener task to stop before aborting it.
+const LISTENER_STOP_TIMEOUT: Duration = Duration::from_secs(2);
+// Browsing for this type lists the service types in use in a domain.
+const SERVICE_TYPES_META_QUERY: &str = "_services._dns-sd._udp";
+
+pub use operation::updaterecord::TXT_RECORD_INDEX;
+pub(crate) use operation::ServiceFlags;
+
+type ReadHalf = tokio::io::ReadHalf<Box<dyn super::Connection>>;
+type WriteHalf = tokio::io::WriteHalf<Box<dyn super::Connection>>;
 
 pub struct Ipc
 {
-    listen_task: task::JoinHandle<()>,
+    listen_task: std::sync::Mutex<Option<super::runtime::Task>>,
     cancel_token: CancellationToken,
-    write_socket: OwnedWriteHalf,
+    writer: Writer,
+    router: router::Router,
+    connection_context: u64,
+    connection_state: watch::Receiver<super::ConnectionState>,
+    connector: Arc<dyn super::Connector>,
+    // The transport of the shared connection, replaced on every reconnect.
+    transport: Arc<std::sync::Mutex<Option<super::Transport>>>,
 }
 
-impl Ipc
+// Cloneable handle to the writer task, which owns the write half of the IPC socket. Shared with
+// operation handles.
+#[derive(Clone)]
+pub struct Writer
+{
+    commands: mpsc::UnboundedSender<WriteCommand>,
+    // Operations that were started and not cancelled yet. Kept here as every cancel passes
+    // through the writer, also those from registration handles.
+    operations: Arc<std::sync::Mutex<HashMap<u64, super::ActiveOperation>>>,
+    // Waits for the daemon to accept every request, not only those that ask for it.
+    synchronous_errors: bool,
+    metrics: Arc<dyn super::MetricsSink>,
+}
+
+enum WriteCommand
+{
+    // Writes a frame and then applies the update to the journal. With `acknowledge`, waits for
+    // the daemon to accept the request first.
+    Frame
+    {
+        frame: header::Frame,
+        update: JournalUpdate,
+        acknowledge: bool,
+        done: oneshot::Sender<io::Result<usize>>,
+    },
+    // Flushes the socket once all commands before it are done.
+    Flush
+    {
+        done: oneshot::Sender<io::Result<()>>,
+    },
+    // Applies an update to the journal without writing anything, for operations the daemon
+    // ended itself.
+    Journal
+    {
+        update: JournalUpdate,
+    },
+    // Switches to the socket of a new connection and issues the journaled requests on it.
+    Restore
+    {
+        write_socket: WriteHalf,
+        error_return: Option<error_return::ErrorReturn>,
+        connection_context: u64,
+        delegate: Option<super::Delegate>,
+        done: oneshot::Sender<io::Result<()>>,
+    },
+}
+
+enum JournalUpdate
 {
-    pub async fn new(event_sender: mpsc::Sender<super::MDnsResponderEvent>) -> io::Result<Self>
+    None,
+    Record(u64),
+    AddRecord(u64, u32),
+    UpdateRecord(u64, u32),
+    RemoveRecord(u64, u32),
+    Forget(u64),
+}
+
+impl JournalUpdate
+{
+    // The update that drops a request from the journal again if the daemon rejects it. A
+    // rejected record update stays journaled, the daemon rejects it again after a reconnect.
+    fn undo(&self) -> JournalUpdate
     {
-        let stream = match UnixStream::connect(SOCKET_PATH).await
+        return match *self
         {
-            Ok(s) => s,
-            Err(e) =>
-            {
-                error!("Failed to connect to mDNSResponder socket: {}", e);
-                return Err(e);
-            }
+            JournalUpdate::Record(context) => JournalUpdate::Forget(context),
+            JournalUpdate::AddRecord(context, reg_index) => JournalUpdate::RemoveRecord(context, reg_index),
+            _ => JournalUpdate::None,
         };
+    }
+}
+
+// Everything needed to set up the shared connection again after it was lost.
+struct Reconnector
+{
+    policy: super::ReconnectPolicy,
+    writer: Writer,
+    connection_context: u64,
+    delegate: Option<super::Delegate>,
+    connector: Arc<dyn super::Connector>,
+    // Identifies the running daemon, a different one after reconnecting means it restarted.
+    daemon_instance: Option<u64>,
+    transport: Arc<std::sync::Mutex<Option<super::Transport>>>,
+}
+
+enum ListenerExit
+{
+    Cancelled,
+    Disconnected,
+}
+
+impl Ipc
+{
+    pub async fn new(
+        event_sender: mpsc::Sender<super::MDnsResponderEvent>,
+        config: &super::MDnsResponderBuilder,
+    ) -> io::Result<Self>
+    {
+        let delegate = config.delegate;
+        let reconnect_policy = config.reconnect_policy.clone();
+
+        let stream = Self::connect(config.connector.as_ref(), delegate).await?;
+
+        let transport = config.connector.transport();
+        if let Some(transport) = &transport
+        {
+            debug!("Connected to mDNSResponder at {}", transport);
+        }
+
+        let transport = Arc::new(std::sync::Mutex::new(transport));
 
         let cancel_token = CancellationToken::new();
-        let (read_socket, write_socket) = stream.into_split();
+        let error_return = error_return::ErrorReturn::for_connection(stream.as_ref());
+        let (read_socket, write_socket) = tokio::io::split(stream);
+
+        // Active requests only need to be remembered when they are issued again on reconnect.
+        let journal = reconnect_policy.as_ref().map(|_| journal::Journal::new());
+
+        let writer = Writer::new(write_socket, error_return, journal, config.synchronous_errors, config.metrics.clone());
+
+        // All operations are issued as subordinates of a single shared connection, so they can
+        // be multiplexed on one socket and cancelled individually by their client context.
+        let connection_context = rand::random::<u64>();
+
+        if let Err(e) = writer.write_connection(connection_context, delegate).await
+        {
+            error!("Failed to set up shared connection with mDNSResponder: {}", e);
+            return Err(e);
+        }
+
+        let router = router::Router::new(
+            event_sender,
+            dedup::Dedup::new(config.dedup_policy),
+            config.batch_services,
+            config.expire_records,
+            config.debounce_window,
+            config.metrics.clone(),
+            config.overflow_policy,
+            config.broadcast_capacity,
+        );
+
+        let reconnector = reconnect_policy.map(|policy| Reconnector
+        {
+            policy,
+            writer: writer.clone(),
+            connection_context,
+            delegate,
+            connector: config.connector.clone(),
+            daemon_instance: config.connector.daemon_instance(),
+            transport: transport.clone(),
+        });
 
-        let listen_task = task::spawn(Self::listener(
+        let (state_sender, connection_state) = watch::channel(super::ConnectionState::Connected);
+
+        let listen_task = super::runtime::spawn(Self::listener(
             read_socket,
             cancel_token.clone(),
-            event_sender,
+            router.clone(),
+            writer.clone(),
+            reconnector,
+            state_sender,
+            config.read_buffer_size,
         ));
 
+        if let Some(interval) = config.keepalive_interval
+        {
+            super::runtime::spawn_detached(Self::keepalive(
+                config.connector.clone(),
+                router.clone(),
+                connection_state.clone(),
+                cancel_token.clone(),
+                interval,
+            ));
+        }
+
         return Ok(Ipc
         {
-            listen_task,
+            listen_task: std::sync::Mutex::new(Some(listen_task)),
             cancel_token,
-            write_socket,
+            writer,
+            router,
+            connection_context,
+            connection_state,
+            connector: config.connector.clone(),
+            transport,
         });
     }
 
-    pub async fn close(self)
+    async fn connect(
+        connector: &dyn super::Connector,
+        delegate: Option<super::Delegate>,
+    ) -> io::Result<Box<dyn super::Connection>>
+    {
+        return match connector.connect(delegate).await
+        {
+            Ok(stream) => Ok(stream),
+            Err(e) =>
+            {
+                error!("Failed to connect to mDNSResponder at {:?}: {}", connector, e);
+                Err(e)
+            }
+        };
+    }
+
+    pub fn connection_context(&self) -> u64
+    {
+        return self.connection_context;
+    }
+
+    pub fn connection_state(&self) -> watch::Receiver<super::ConnectionState>
+    {
+        return self.connection_state.clone();
+    }
+
+    pub fn transport(&self) -> Option<super::Transport>
+    {
+        return self.transport.lock().unwrap().clone();
+    }
+
+    pub fn current_services(&self, context: u64) -> Vec<super::Service>
+    {
+        return self.router.current_services(context);
+    }
+
+    pub fn stats(&self, context: u64) -> Option<super::OperationStats>
+    {
+        return self.router.stats(context);
+    }
+
+    pub fn dropped_events(&self) -> u64
+    {
+        return self.router.dropped_events();
+    }
+
+    pub fn active_operations(&self) -> Vec<super::ActiveOperation>
+    {
+        return self.writer.active_operations();
+    }
+
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<super::MDnsResponderEvent>
+    {
+        return self.router.subscribe();
+    }
+
+    fn subordinate_flags(service_flags: u32) -> u32
+    {
+        return service_flags | operation::ServiceFlags::ShareConnection as u32;
+    }
+
+    pub async fn close(&self) -> super::CloseReport
     {
         debug!("Closing IPC connection to mDNSResponder");
+
+        let mut report = super::CloseReport::default();
+
+        // Cancels are queued behind any requests not written yet, so those go out first.
+        for operation in self.writer.active_operations()
+        {
+            match self.write_cancel_request(operation.context).await
+            {
+                Ok(()) => report.cancelled.push(operation.context),
+                Err(_) => report.not_cancelled.push(operation.context),
+            }
+        }
+
+        if let Err(e) = self.writer.flush().await
+        {
+            error!("Failed to flush mDNSResponder socket: {}", e);
+        }
+
         self.cancel_token.cancel();
-        self.listen_task
-            .await
-            .expect("Failed to join IPC listener task");
+
+        // Only the first close has a listener task to wait for.
+        let listen_task = self.listen_task.lock().unwrap().take();
+        report.listener_stopped = match listen_task
+        {
+            Some(mut listen_task) => match super::runtime::timeout(LISTENER_STOP_TIMEOUT, &mut listen_task).await
+            {
+                Ok(Ok(())) => true,
+                Ok(Err(e)) =>
+                {
+                    error!("IPC listener task failed: {}", e);
+                    false
+                }
+                Err(_) =>
+                {
+                    error!("IPC listener task did not stop in time, aborting it");
+                    listen_task.abort();
+                    false
+                }
+            },
+            None => true,
+        };
+
+        return report;
+    }
+
+    // Asks the daemon for its version every interval, on a dedicated connection, and reports when
+    // it stops answering within the interval and when it answers again. The shared connection
+    // can stay open while the daemon hangs, so only the latter shows up as `ConnectionLost`.
+    async fn keepalive(
+        connector: Arc<dyn super::Connector>,
+        router: router::Router,
+        connection_state: watch::Receiver<super::ConnectionState>,
+        cancel_token: CancellationToken,
+        interval: Duration,
+    )
+    {
+        let mut responsive = true;
+
+        loop
+        {
+            select!
+            {
+                _ = cancel_token.cancelled() => return,
+                _ = super::runtime::sleep(interval) => {}
+            }
+
+            // A lost connection is reported on its own.
+            if *connection_state.borrow() != super::ConnectionState::Connected
+            {
+                continue;
+            }
+
+            let answered = select!
+            {
+                _ = cancel_token.cancelled() => return,
+                result = super::runtime::timeout(interval, Self::query_daemon_version(connector.as_ref())) => matches!(result, Ok(Ok(_))),
+            };
+
+            if answered == responsive
+            {
+                continue;
+            }
+
+            responsive = answered;
+
+            let event = if responsive
+            {
+                debug!("mDNSResponder answers keepalive requests again");
+                super::MDnsResponderEvent::DaemonResponsive
+            }
+            else
+            {
+                error!("mDNSResponder did not answer keepalive request within {:?}", interval);
+                super::MDnsResponderEvent::DaemonUnresponsive
+            };
+
+            if let Err(e) = router.send_shared(event).await
+            {
+                debug!("Failed to send keepalive notification: {}", e);
+            }
+        }
     }
 
     async fn listener(
-        read: OwnedReadHalf,
+        read: ReadHalf,
         task_cancel_token: CancellationToken,
-        event_sender: mpsc::Sender<super::MDnsResponderEvent>,
+        router: router::Router,
+        writer: Writer,
+        mut reconnector: Option<Reconnector>,
+        state_sender: watch::Sender<super::ConnectionState>,
+        read_buffer_size: usize,
     )
     {
         debug!("Starting IPC listener for mDNSResponder socket");
 
-        let mut buffer: Vec<u8> = Vec::new();
+        let mut read = read;
+
+        loop
+        {
+            if let ListenerExit::Cancelled = Self::read_frames(&mut read, &task_cancel_token, &router, &writer, read_buffer_size).await
+            {
+                break;
+            }
+
+            router.flush_batches().await;
+            router.reset_answers();
+            Self::send_connection_event(&router, super::MDnsResponderEvent::ConnectionLost).await;
+
+            let Some(reconnector) = &mut reconnector else
+            {
+                break;
+            };
+
+            state_sender.send_replace(super::ConnectionState::Reconnecting);
+
+            match reconnector.reconnect(&task_cancel_token).await
+            {
+                Some((new_read, daemon_restarted, transport)) =>
+                {
+                    read = new_read;
+                    router.metrics().reconnected();
+                    state_sender.send_replace(super::ConnectionState::Connected);
+
+                    if daemon_restarted
+                    {
+                        Self::send_connection_event(&router, super::MDnsResponderEvent::DaemonRestarted).await;
+                    }
+
+                    Self::send_connection_event(&router, super::MDnsResponderEvent::Reconnected { transport }).await;
+                }
+                None => break,
+            }
+        }
+
+        state_sender.send_replace(super::ConnectionState::Disconnected);
+    }
+
+    async fn send_connection_event(router: &router::Router, event: super::MDnsResponderEvent)
+    {
+        if let Err(e) = router.send_shared(event).await
+        {
+            error!("Failed to send connection notification: {}", e);
+        }
+    }
+
+    async fn read_frames(
+        read: &mut ReadHalf,
+        task_cancel_token: &CancellationToken,
+        router: &router::Router,
+        writer: &Writer,
+        read_buffer_size: usize,
+    ) -> ListenerExit
+    {
+        // Frames are parsed in place, consumed bytes are skipped without moving the rest.
+        let mut buffer = BytesMut::with_capacity(read_buffer_size);
 
         loop
         {
+            // Reading into a buffer without spare room would look like the socket was closed.
+            buffer.reserve(read_buffer_size);
+
             select!
             {
                 _ = task_cancel_token.cancelled() =>
                 {
-                    log::debug!("Cancellation token triggered, stopping IPC listener.");
-                    break;
+                    debug!("Cancellation token triggered, stopping IPC listener.");
+                    return ListenerExit::Cancelled;
+                }
+                _ = router.next_expiry() =>
+                {
+                    router.send_expired().await;
+                }
+                _ = router.next_debounce() =>
+                {
+                    router.send_debounced().await;
                 }
-                _ = read.readable() =>
+                result = read.read_buf(&mut buffer) =>
                 {
-                    let mut read_buffer = [0u8; 2048];
-                    match read.try_read(&mut read_buffer)
+                    match result
                     {
                         Ok(0) =>
                         {
                             debug!("No data read, socket may be closed");
-                            break;
+                            return ListenerExit::Disconnected;
                         }
                         Ok(n) =>
                         {
                             debug!("Read {} bytes from IPC socket", n);
 
-                            buffer.extend_from_slice(&read_buffer[..n]);
-
                             // Try to parse as many complete frames as possible.
                             let mut pos = 0;
-                            while pos < buffer.len()
+                            while buffer.len() - pos >= header::IPC_HEADER_SIZE
                             {
-                                match Self::parse_frame(&buffer[pos..], &event_sender).await
+                                // Checked before parsing, as a header announcing more data than a
+                                // frame can hold would otherwise be waited on forever. Without a
+                                // valid header there is no telling where the next frame starts,
+                                // and everything received is dropped.
+                                let Some(frame_length) = Self::frame_length(&buffer[pos..]) else
+                                {
+                                    error!("Invalid frame header, dropping {} received bytes", buffer.len() - pos);
+                                    router.metrics().parse_failed();
+                                    buffer.clear();
+                                    pos = 0;
+                                    break;
+                                };
+
+                                if frame_length > buffer.len() - pos
+                                {
+                                    debug!("Incomplete frame, waiting for more data");
+                                    break;
+                                }
+
+                                match Self::parse_frame(&buffer[pos..], router, writer).await
                                 {
                                     Ok(frame_size) =>
                                     {
                                         debug!("Parsed frame of size {}", frame_size);
+                                        router.metrics().reply_parsed();
                                         pos += frame_size;
                                     }
-                                    Err(InternalError::IncompleteFrame) =>
-                                    {
-                                        debug!("Incomplete frame, waiting for more data");
-                                        break;
-                                    }
                                     Err(e) =>
                                     {
+                                        // The whole frame is buffered, so it is malformed even if
+                                        // the parser asked for more data. It is skipped, so the
+                                        // frames queued behind it are still parsed.
                                         error!("Error parsing frame: {}", e);
-                                        // Clear the entire buffer on parsing error
-                                        buffer.clear();
-                                        pos = 0;
-                                        break;
+                                        router.metrics().parse_failed();
+                                        pos += frame_length;
                                     }
                                 }
                             }
@@ -126,18 +561,13 @@ impl Ipc
                             if pos > 0
                             {
                                 debug!("Processed {} bytes, removing from buffer", pos);
-                                buffer.drain(0..pos);
+                                buffer.advance(pos);
                             }
                         }
-                        Err(ref e) if e.kind() == io::ErrorKind::WouldBlock =>
-                        {
-                            debug!("WouldBlock error occurred, socket is not ready for reading");
-                            continue;
-                        }
                         Err(e) =>
                         {
                             error!("Error reading from mDNSResponder socket: {}", e);
-                            break;
+                            return ListenerExit::Disconnected;
                         }
                     }
                 }
@@ -145,205 +575,565 @@ impl Ipc
         }
     }
 
-    async fn write(&mut self, buf: &[u8]) -> io::Result<usize>
+    pub fn writer(&self) -> Writer
     {
-        self.write_socket
-            .writable()
-            .await
-            .expect("Failed to set writable on stream");
+        return self.writer.clone();
+    }
 
-        match self.write_socket.try_write(buf)
+    // With `acknowledge`, waits for the daemon to accept the request, if the connection allows.
+    async fn write(&self, client_context: u64, buf: header::Frame, acknowledge: bool) -> io::Result<usize>
+    {
+        let result = self.writer.write_request_acknowledged(client_context, buf, acknowledge).await;
+        if result.is_err()
         {
-            Ok(n) =>
-            {
-                debug!("Successfully wrote {} bytes to mDNSResponder socket", n);
-                return Ok(n);
-            }
-            Err(e) =>
-            {
-                error!("Failed to write to mDNSResponder socket: {}", e);
-                return Err(e);
-            }
+            self.writer.untrack_operation(client_context);
+        }
+
+        return result;
+    }
+
+    // Lists the operation as active until it is cancelled, and opens the span that its replies
+    // are recorded in when tracing is enabled.
+    fn track_operation(&self, client_context: u64, kind: super::OperationKind, subject: &str)
+    {
+        #[cfg(feature = "tracing")]
+        self.router.open_span(client_context, kind, subject);
+
+        self.writer.track_operation(super::ActiveOperation
+        {
+            context: client_context,
+            kind,
+            subject: subject.to_string(),
+            started: Instant::now(),
+        });
+    }
+
+    // Routes the replies of the request to its own channel. The route is added before writing so
+    // that no reply can arrive before it is in place.
+    async fn write_routed(
+        &self,
+        client_context: u64,
+        buf: header::Frame,
+        events: mpsc::Sender<super::MDnsResponderEvent>,
+        acknowledge: bool,
+    ) -> io::Result<usize>
+    {
+        self.router.add_route(client_context, events);
+
+        let result = self.write(client_context, buf, acknowledge).await;
+        if result.is_err()
+        {
+            self.router.remove_route(client_context);
         }
+
+        return result;
     }
 
     pub async fn write_browse_request(
-        &mut self,
+        &self,
+        service_flags: u32,
+        interface_index: u32,
         service_type: String,
         service_domain: String,
+        events: mpsc::Sender<super::MDnsResponderEvent>,
+        acknowledge: bool,
     ) -> Result<u64, io::Error>
     {
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::Browse, &service_type);
+
         let request = operation::browse::Request::new(
-            operation::ServiceFlags::None,
-            0, // Interface index, set to 0 for default
+            Self::subordinate_flags(service_flags),
+            interface_index,
             service_type,
             service_domain,
         );
 
-        let request_buf = request.to_bytes();
-
-        let header = header::IpcMessageHeader::new(
-            1, // Version
-            request_buf.len() as u32,
-            header::IpcFlags::NoErrSd as u32,
-            header::Operation::Request(header::request::RequestOperation::Browse),
-            rand::random::<u64>(),
+        let buf = header::build_request(
+            header::request::RequestOperation::Browse,
+            client_context,
             0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
         );
 
-        let header_buf = header.to_bytes();
-
-        let mut buf = Vec::with_capacity(header_buf.len() + request_buf.len());
-        buf.extend_from_slice(&header_buf);
-        buf.extend_from_slice(&request_buf);
+        self.write_routed(client_context, buf, events, acknowledge).await?;
 
-        self.write(&buf).await?;
-
-        return Ok(header.client_context);
+        return Ok(client_context);
     }
 
-    pub async fn write_cancel_request(&mut self, context: u64) -> Result<(), io::Error>
+    // Browses for the service type meta-query, whose answers are decoded as service types.
+    pub async fn write_service_types_request(
+        &self,
+        interface_index: u32,
+        service_domain: String,
+        events: mpsc::Sender<super::MDnsResponderEvent>,
+    ) -> Result<u64, io::Error>
     {
-        let header = header::IpcMessageHeader::new(
-            1, // Version
-            0, // No data
-            header::IpcFlags::NoErrSd as u32,
-            header::Operation::Request(header::request::RequestOperation::Cancel),
-            context,
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::ServiceTypes, SERVICE_TYPES_META_QUERY);
+
+        let request = operation::browse::Request::new(
+            Self::subordinate_flags(operation::ServiceFlags::None as u32),
+            interface_index,
+            SERVICE_TYPES_META_QUERY.to_string(),
+            service_domain,
+        );
+
+        let buf = header::build_request(
+            header::request::RequestOperation::Browse,
+            client_context,
             0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
         );
 
-        let header_buf = header.to_bytes();
+        // Marked before writing, the first answers may arrive before the write returns.
+        self.router.add_service_type_enumeration(client_context);
+        self.write_routed(client_context, buf, events, false).await?;
 
-        self.write(&header_buf).await?;
+        return Ok(client_context);
+    }
 
-        return Ok(());
+    pub async fn write_cancel_request(&self, context: u64) -> Result<(), io::Error>
+    {
+        self.router.remove_route(context);
+        return self.writer.write_cancel_request(context).await;
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn write_resolve_request(
-        &mut self,
+        &self,
+        service_flags: u32,
+        interface_index: u32,
         service_name: String,
         reg_type: String,
         service_domain: String,
+        events: mpsc::Sender<super::MDnsResponderEvent>,
+        acknowledge: bool,
     ) -> Result<u64, io::Error>
     {
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::Resolve, &reg_type);
+
         let request = operation::resolve::Request::new(
-            operation::ServiceFlags::None,
-            0, // Interface index, set to 0 for default
+            Self::subordinate_flags(service_flags),
+            interface_index,
             service_name,
             reg_type,
             service_domain,
         );
 
-        let request_buf = request.to_bytes();
-
-        let header = header::IpcMessageHeader::new(
-            1, // Version
-            request_buf.len() as u32,
-            header::IpcFlags::NoErrSd as u32,
-            header::Operation::Request(header::request::RequestOperation::Resolve),
-            rand::random::<u64>(),
+        let buf = header::build_request(
+            header::request::RequestOperation::Resolve,
+            client_context,
             0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
         );
 
-        let header_buf = header.to_bytes();
-
-        let mut buf = Vec::with_capacity(header_buf.len() + request_buf.len());
-        buf.extend_from_slice(&header_buf);
-        buf.extend_from_slice(&request_buf);
+        self.write_routed(client_context, buf, events, acknowledge).await?;
 
-        self.write(&buf).await?;
-
-        return Ok(header.client_context);
+        return Ok(client_context);
     }
 
     pub async fn write_addrinfo_request(
-        &mut self,
+        &self,
+        service_flags: u32,
+        interface_index: u32,
         protocol: super::Protocol,
-        hostname: String
+        hostname: String,
+        events: mpsc::Sender<super::MDnsResponderEvent>,
     ) -> Result<u64, io::Error>
     {
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::AddrInfo, &hostname);
+
         let request = operation::addrinfo::Request::new(
-            operation::ServiceFlags::None,
-            0, // Interface index, set to 0 for default
+            Self::subordinate_flags(service_flags),
+            interface_index,
             protocol.into(),
             hostname,
         );
 
-        let request_buf = request.to_bytes();
-
-        let header = header::IpcMessageHeader::new(
-            1, // Version
-            request_buf.len() as u32,
-            header::IpcFlags::NoErrSd as u32,
-            header::Operation::Request(header::request::RequestOperation::AddressInfo),
-            rand::random::<u64>(),
+        let buf = header::build_request(
+            header::request::RequestOperation::AddressInfo,
+            client_context,
             0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
         );
 
-        let header_buf = header.to_bytes();
+        self.write_routed(client_context, buf, events, false).await?;
 
-        let mut buf = Vec::with_capacity(header_buf.len() + request_buf.len());
-        buf.extend_from_slice(&header_buf);
-        buf.extend_from_slice(&request_buf);
-
-        self.write(&buf).await?;
-
-        return Ok(header.client_context);
+        return Ok(client_context);
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub async fn write_register_request(
-        &mut self,
+        &self,
         interface_index: u32,
         name: String,
         service_type: String,
         domain: String,
         host: String,
         port: u16,
-        txt_data: Vec<String>
+        txt: Vec<u8>,
+        service_flags: u32,
+        acknowledge: bool,
     ) -> Result<u64, io::Error>
     {
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::Register, &service_type);
+
         let request = operation::register::Request::new(
-            operation::ServiceFlags::None,
+            Self::subordinate_flags(service_flags),
             interface_index,
             name,
             service_type,
             domain,
             host,
             port,
-            txt_data
+            txt,
         );
 
-        let request_buf = request.to_bytes();
-
-        let header = header::IpcMessageHeader::new(
-            1, // Version
-            request_buf.len() as u32,
-            header::IpcFlags::NoErrSd as u32,
-            header::Operation::Request(header::request::RequestOperation::RegisterService),
-            rand::random::<u64>(),
+        let buf = header::build_request(
+            header::request::RequestOperation::RegisterService,
+            client_context,
             0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
         );
 
-        let header_buf = header.to_bytes();
+        self.write(client_context, buf, acknowledge).await?;
+
+        return Ok(client_context);
+    }
+
+    pub async fn write_query_request(
+        &self,
+        service_flags: u32,
+        interface_index: u32,
+        name: String,
+        rrtype: u16,
+        rrclass: u16,
+        events: Option<mpsc::Sender<super::MDnsResponderEvent>>,
+    ) -> Result<u64, io::Error>
+    {
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::Query, &name);
+
+        let request = operation::query::Request::new(
+            Self::subordinate_flags(service_flags),
+            interface_index,
+            name,
+            rrtype,
+            rrclass,
+        );
 
-        let mut buf = Vec::with_capacity(header_buf.len() + request_buf.len());
-        buf.extend_from_slice(&header_buf);
-        buf.extend_from_slice(&request_buf);
+        let buf = header::build_request(
+            header::request::RequestOperation::Query,
+            client_context,
+            0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
 
-        self.write(&buf).await?;
+        match events
+        {
+            Some(events) => self.write_routed(client_context, buf, events, false).await?,
+            None => self.write(client_context, buf, false).await?,
+        };
 
-        return Ok(header.client_context);
+        return Ok(client_context);
     }
 
-    async fn parse_frame(
-        buf: &[u8],
-        event_sender: &mpsc::Sender<super::MDnsResponderEvent>,
-    ) -> Result<usize, InternalError>
+    pub async fn write_enumeration_request(
+        &self,
+        registration_domains: bool,
+    ) -> Result<u64, io::Error>
     {
-        match header::IpcMessageHeader::from(&buf)
+        let service_flags = if registration_domains
         {
-            Ok(header) =>
+            operation::ServiceFlags::RegistrationDomains
+        }
+        else
+        {
+            operation::ServiceFlags::BrowseDomains
+        };
+
+        let request = operation::enumeration::Request::new(
+            Self::subordinate_flags(service_flags as u32),
+            0, // Interface index, set to 0 for default
+        );
+
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::Enumeration, "");
+
+        let buf = header::build_request(
+            header::request::RequestOperation::Enumeration,
+            client_context,
+            0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        self.write(client_context, buf, false).await?;
+
+        return Ok(client_context);
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    pub async fn write_register_record_request(
+        &self,
+        context: u64,
+        reg_index: u32,
+        interface_index: u32,
+        name: String,
+        rrtype: u16,
+        rrclass: u16,
+        rdata: Vec<u8>,
+        ttl: u32,
+        unique: bool,
+    ) -> Result<(), io::Error>
+    {
+        let service_flags = if unique
+        {
+            operation::ServiceFlags::Unique
+        }
+        else
+        {
+            operation::ServiceFlags::Shared
+        };
+
+        let request = operation::registerrecord::Request::new(
+            service_flags as u32,
+            interface_index,
+            name,
+            rrtype,
+            rrclass,
+            rdata,
+            ttl,
+        );
+
+        let buf = header::build_request(
+            header::request::RequestOperation::RegisterRecord,
+            context,
+            reg_index,
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        self.writer.write_record_request(context, reg_index, buf).await?;
+
+        return Ok(());
+    }
+
+    pub async fn write_port_mapping_request(
+        &self,
+        protocol: super::PortMappingProtocol,
+        internal_port: u16,
+        external_port: u16,
+        ttl: u32,
+    ) -> Result<u64, io::Error>
+    {
+        let request = operation::portmapping::Request::new(
+            Self::subordinate_flags(operation::ServiceFlags::None as u32),
+            0, // Interface index, set to 0 for default
+            protocol.into(),
+            internal_port,
+            external_port,
+            ttl,
+        );
+
+        let client_context = rand::random::<u64>();
+        self.track_operation(client_context, super::OperationKind::PortMapping, "");
+
+        let buf = header::build_request(
+            header::request::RequestOperation::PortMapping,
+            client_context,
+            0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        self.write(client_context, buf, false).await?;
+
+        return Ok(client_context);
+    }
+
+    // The daemon replies to GetProperty with an unframed message, so the request is sent on a
+    // dedicated connection rather than interleaved with the framed replies on the shared one.
+    pub async fn read_daemon_version(&self) -> Result<u32, io::Error>
+    {
+        return Self::query_daemon_version(self.connector.as_ref()).await;
+    }
+
+    async fn query_daemon_version(connector: &dyn super::Connector) -> Result<u32, io::Error>
+    {
+        let mut stream = Self::connect(connector, None).await?;
+
+        let request = operation::getproperty::Request::new(
+            operation::getproperty::PROPERTY_DAEMON_VERSION.to_string(),
+        );
+
+        let client_context = rand::random::<u64>();
+
+        let buf = header::build_request(
+            header::request::RequestOperation::GetProperty,
+            client_context,
+            0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        stream.write_all(&buf.to_vec()).await?;
+
+        // The connection is not closed after the reply, so read exactly the announced length.
+        let mut reply_buf = vec![0u8; 4];
+        stream.read_exact(&mut reply_buf).await?;
+
+        if reply_buf == [0u8; 4]
+        {
+            let mut length_buf = [0u8; 4];
+            stream.read_exact(&mut length_buf).await?;
+            reply_buf.extend_from_slice(&length_buf);
+
+            let length = u32::from_be_bytes(length_buf) as usize;
+
+            // Never trust a length field from an external source.
+            if length > MAX_PROPERTY_LENGTH
+            {
+                return Err(io::Error::new(io::ErrorKind::InvalidData, "Property value too long"));
+            }
+
+            let mut value_buf = vec![0u8; length];
+            stream.read_exact(&mut value_buf).await?;
+            reply_buf.extend_from_slice(&value_buf);
+        }
+
+        let reply = operation::getproperty::Reply::from_bytes(&reply_buf)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+
+        if reply.error != 0
+        {
+            error!("GetProperty request failed with error code {}", reply.error);
+            return Err(io::Error::other(format!("GetProperty failed with error code {}", reply.error)));
+        }
+
+        return reply.as_u32().ok_or_else(||
+            {
+                io::Error::new(io::ErrorKind::InvalidData, "Unexpected daemon version length")
+            }
+        );
+    }
+
+    // SetDomain is a one-shot request answered only with an error code, so it is sent on a
+    // dedicated connection like GetProperty.
+    pub async fn write_set_domain_request(&self, domain: String, add: bool) -> Result<i32, io::Error>
+    {
+        let mut stream = Self::connect(self.connector.as_ref(), None).await?;
+
+        let service_flags = if add
+        {
+            operation::ServiceFlags::Add
+        }
+        else
+        {
+            operation::ServiceFlags::None
+        };
+
+        let request = operation::setdomain::Request::new(service_flags as u32, domain);
+
+        let client_context = rand::random::<u64>();
+
+        let buf = header::build_request(
+            header::request::RequestOperation::SetDomain,
+            client_context,
+            0, // Registration index, set to 0 for default
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        stream.write_all(&buf.to_vec()).await?;
+
+        let mut error_buf = [0u8; 4];
+        stream.read_exact(&mut error_buf).await?;
+
+        return Ok(i32::from_be_bytes(error_buf));
+    }
+
+    // Length of the frame at the start of the buffer as announced by its header, `None` if the
+    // header is not valid.
+    fn frame_length(buf: &[u8]) -> Option<usize>
+    {
+        let version = u32::from_be_bytes(buf.get(0..4)?.try_into().ok()?);
+        let data_length = u32::from_be_bytes(buf.get(4..8)?.try_into().ok()?) as usize;
+
+        if version != header::IPC_VERSION || data_length > MAX_FRAME_DATA_LENGTH
+        {
+            return None;
+        }
+
+        return Some(header::IPC_HEADER_SIZE + data_length);
+    }
+
+    async fn parse_frame(
+        buf: &[u8],
+        router: &router::Router,
+        writer: &Writer,
+    ) -> Result<usize, InternalError>
+    {
+        #[cfg(feature = "debug-frames")]
+        Self::send_raw_frame(buf, router).await;
+
+        // Counted before the reply is dispatched, so the counts are up to date when its events
+        // arrive.
+        router.count_reply(buf);
+
+        let frame_size = Self::dispatch_traced(buf, router, writer).await?;
+
+        // Every reply starts with its flags, held back answers are complete once a reply arrives
+        // without MoreComing.
+        if let Ok(reply_header) = operation::ReplyHeader::from_bytes(&buf[header::IPC_HEADER_SIZE..frame_size])
+            && !reply_header.more_coming()
+        {
+            router.flush_batches().await;
+        }
+
+        return Ok(frame_size);
+    }
+
+    // Dispatches the frame within the span of the request it answers.
+    #[cfg(feature = "tracing")]
+    async fn dispatch_traced(buf: &[u8], router: &router::Router, writer: &Writer) -> Result<usize, InternalError>
+    {
+        use tracing::Instrument;
+
+        let span = match header::IpcMessageHeader::from(buf)
+        {
+            Ok(header) => router.span(header.client_context),
+            Err(_) => tracing::Span::none(),
+        };
+
+        return Self::dispatch_frame(buf, router, writer).instrument(span).await;
+    }
+
+    #[cfg(not(feature = "tracing"))]
+    async fn dispatch_traced(buf: &[u8], router: &router::Router, writer: &Writer) -> Result<usize, InternalError>
+    {
+        return Self::dispatch_frame(buf, router, writer).await;
+    }
+
+    async fn dispatch_frame(
+        buf: &[u8],
+        router: &router::Router,
+        writer: &Writer,
+    ) -> Result<usize, InternalError>
+    {
+        match header::IpcMessageHeader::from(buf)
+        {
+            Ok(header) =>
             {
                 debug!("Received IPC message: {:?}", header);
 
@@ -353,7 +1143,7 @@ impl Ipc
                     {
                         header::reply::ReplyOperation::Browse =>
                         {
-                            return Self::parse_browse_reply(buf, header.data_length, event_sender)
+                            return Self::parse_browse_reply(buf, header.data_length, header.client_context, router, writer)
                                 .await;
                         }
                         header::reply::ReplyOperation::Resolve =>
@@ -361,24 +1151,76 @@ impl Ipc
                             return Self::parse_resolve_reply(
                                 buf,
                                 header.data_length,
-                                event_sender,
+                                header.client_context,
+                                router,
+                                writer,
                             )
                             .await;
                         }
                         header::reply::ReplyOperation::AddressInfo =>
                         {
-                            return Self::parse_address_info_reply(buf, header.data_length, event_sender)
+                            return Self::parse_address_info_reply(buf, header.data_length, header.client_context, router)
                                 .await;
                         }
                         header::reply::ReplyOperation::RegisterService =>
                         {
-                            return Self::parse_register_service_reply(buf, header.data_length)
+                            return Self::parse_register_service_reply(
+                                buf,
+                                header.data_length,
+                                header.client_context,
+                                router,
+                                writer,
+                            )
+                            .await;
+                        }
+                        header::reply::ReplyOperation::Query =>
+                        {
+                            return Self::parse_query_reply(buf, header.data_length, header.client_context, router)
                                 .await;
                         }
-                        _ =>
+                        header::reply::ReplyOperation::Enumeration =>
+                        {
+                            return Self::parse_enumeration_reply(buf, header.data_length, header.client_context, router)
+                                .await;
+                        }
+                        header::reply::ReplyOperation::PortMapping =>
+                        {
+                            return Self::parse_port_mapping_reply(
+                                buf,
+                                header.data_length,
+                                header.client_context,
+                                router,
+                                writer,
+                            )
+                            .await;
+                        }
+                        header::reply::ReplyOperation::AsyncError =>
+                        {
+                            return Self::parse_async_error_reply(
+                                buf,
+                                header.data_length,
+                                header.client_context,
+                                router,
+                                writer,
+                            )
+                            .await;
+                        }
+                        header::reply::ReplyOperation::RegisterRecord =>
+                        {
+                            return Self::parse_register_record_reply(
+                                buf,
+                                header.data_length,
+                                header.client_context,
+                                header.reg_index,
+                                router,
+                                writer,
+                            )
+                            .await;
+                        }
+                        header::reply::ReplyOperation::GetProperty | header::reply::ReplyOperation::Unknown(_) =>
                         {
-                            debug!("Received other reply operation: {:?}", reply);
-                            return Err(InternalError::FrameParsingFailed);
+                            return Self::skip_unhandled_reply(buf, header.data_length, header.client_context, reply, router)
+                                .await;
                         }
                     },
                     _ =>
@@ -396,10 +1238,77 @@ impl Ipc
         }
     }
 
+    // Sends the frame at the start of the buffer once it is complete. The header is read field
+    // by field so that frames with unknown operations are reported too.
+    #[cfg(feature = "debug-frames")]
+    async fn send_raw_frame(buf: &[u8], router: &router::Router)
+    {
+        if buf.len() < header::IPC_HEADER_SIZE
+        {
+            return;
+        }
+
+        let frame_header = super::FrameHeader
+        {
+            version: u32::from_be_bytes(buf[0..4].try_into().unwrap()),
+            data_length: u32::from_be_bytes(buf[4..8].try_into().unwrap()),
+            ipc_flags: u32::from_be_bytes(buf[8..12].try_into().unwrap()),
+            operation: u32::from_be_bytes(buf[12..16].try_into().unwrap()),
+            client_context: u64::from_be_bytes(buf[16..24].try_into().unwrap()),
+            reg_index: u32::from_be_bytes(buf[24..28].try_into().unwrap()),
+        };
+
+        let stop_pos = header::IPC_HEADER_SIZE + frame_header.data_length as usize;
+        if stop_pos > buf.len()
+        {
+            return;
+        }
+
+        let context = frame_header.client_context;
+        let payload = buf[header::IPC_HEADER_SIZE..stop_pos].to_vec();
+
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::RawFrame { header: frame_header, payload })
+            .await
+        {
+            error!("Failed to send raw frame notification: {}", e);
+        }
+    }
+
+    // Skips a reply that is not parsed by its length, so the replies behind it are still parsed.
+    async fn skip_unhandled_reply(
+        buf: &[u8],
+        data_length: u32,
+        context: u64,
+        reply: header::reply::ReplyOperation,
+        router: &router::Router,
+    ) -> Result<usize, InternalError>
+    {
+        let stop_pos = header::IPC_HEADER_SIZE + data_length as usize;
+
+        if stop_pos > buf.len()
+        {
+            debug!("Incomplete frame (fragmentation): need {} bytes, have {}", stop_pos, buf.len());
+            return Err(InternalError::IncompleteFrame);
+        }
+
+        debug!("Skipping unhandled {:?} reply for context {}", reply, context);
+
+        let event = super::MDnsResponderEvent::UnhandledReply { context, operation: reply.to_u32() };
+        if let Err(e) = router.send(context, event).await
+        {
+            error!("Failed to send unhandled reply notification: {}", e);
+        }
+
+        return Ok(stop_pos);
+    }
+
     async fn parse_browse_reply(
         buf: &[u8],
         data_length: u32,
-        event_sender: &mpsc::Sender<super::MDnsResponderEvent>,
+        context: u64,
+        router: &router::Router,
+        writer: &Writer,
     ) -> Result<usize, InternalError>
     {
         let start_pos = header::IPC_HEADER_SIZE;
@@ -421,19 +1330,73 @@ impl Ipc
             }
         };
 
+        if let Some(error) = browse_reply.error()
+        {
+            error!("Browse failed: {}", error);
+            Self::end_failed_operation(context, error, router, writer).await;
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
         let is_add = browse_reply.is_add();
 
+        if router.is_service_type_enumeration(context)
+        {
+            Self::send_service_type(context, browse_reply, is_add, router).await;
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
         let service = super::Service
         {
-            name: browse_reply.service_name,
-            service_type: browse_reply.service_type,
-            domain: browse_reply.service_domain,
+            display_name: super::dns_name::display_label(&browse_reply.service_name),
+            name: browse_reply.service_name.into_owned(),
+            service_type: browse_reply.service_type.into_owned(),
+            domain: browse_reply.service_domain.into_owned(),
+            interface_index: browse_reply.header.interface_index(),
+            interface_name: super::interface::interface_name(browse_reply.header.interface_index()),
+            flags: browse_reply.header.reply_flags(),
+            resolved: None,
+        };
+
+        let threshold_reached = service.flags.threshold_reached;
+
+        if router.admit_service(context, &service, is_add)
+        {
+            Self::send_browse_answer(context, service, is_add, router).await;
+        }
+        else
+        {
+            debug!("Suppressing duplicate browse answer for {}", service.name);
+        }
+
+        if threshold_reached
+        {
+            Self::send_threshold_reached(context, router).await;
+        }
+
+        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    }
+
+    async fn send_browse_answer(
+        context: u64,
+        service: super::Service,
+        is_add: bool,
+        router: &router::Router,
+    )
+    {
+        if !router.debounce_service(context, &service, is_add)
+        {
+            return;
+        }
+
+        let Some(service) = router.batch_service(context, service, is_add) else
+        {
+            return;
         };
 
         if is_add
         {
-            if let Err(e) = event_sender
-                .send(super::MDnsResponderEvent::ServiceAdded(service))
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::ServiceAdded(service))
                 .await
             {
                 error!("Failed to send service added notification: {}", e);
@@ -441,21 +1404,92 @@ impl Ipc
         }
         else
         {
-            if let Err(e) = event_sender
-                .send(super::MDnsResponderEvent::ServiceRemoved(service))
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::ServiceRemoved(service))
                 .await
             {
                 error!("Failed to send service removed notification: {}", e);
             }
         }
+    }
 
-        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    // Answers to the meta-query are PTR records like "_http._tcp.local.", which the daemon splits
+    // as an instance name "_http" of type "_tcp.local." in the root domain.
+    async fn send_service_type(
+        context: u64,
+        browse_reply: operation::browse::Reply<'_>,
+        is_add: bool,
+        router: &router::Router,
+    )
+    {
+        let (protocol, domain) = browse_reply.service_type
+            .split_once('.')
+            .unwrap_or((&browse_reply.service_type, &browse_reply.service_domain));
+
+        let service_type = super::DiscoveredServiceType
+        {
+            service_type: format!("{}.{}", browse_reply.service_name, protocol),
+            domain: domain.to_string(),
+            interface_index: browse_reply.header.interface_index(),
+            interface_name: super::interface::interface_name(browse_reply.header.interface_index()),
+            flags: browse_reply.header.reply_flags(),
+        };
+
+        let event = if is_add
+        {
+            super::MDnsResponderEvent::ServiceTypeDiscovered(service_type)
+        }
+        else
+        {
+            super::MDnsResponderEvent::ServiceTypeRemoved(service_type)
+        };
+
+        if let Err(e) = router.send(context, event).await
+        {
+            error!("Failed to send service type notification: {}", e);
+        }
+    }
+
+    async fn send_no_such_record(
+        context: u64,
+        addrinfo_reply: operation::addrinfo::Reply<'_>,
+        router: &router::Router,
+    )
+    {
+        let protocol = match addrinfo_reply.rrtype
+        {
+            28 => super::Protocol::IPv6,
+            _ => super::Protocol::IPv4,
+        };
+
+        let event = super::MDnsResponderEvent::NoSuchRecord
+        {
+            hostname: addrinfo_reply.name.into_owned(),
+            protocol,
+        };
+
+        if let Err(e) = router.send(context, event).await
+        {
+            error!("Failed to send no such record notification: {}", e);
+        }
+    }
+
+    async fn send_threshold_reached(context: u64, router: &router::Router)
+    {
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::ThresholdReached { context })
+            .await
+        {
+            error!("Failed to send threshold reached notification: {}", e);
+        }
     }
 
     async fn parse_resolve_reply(
         buf: &[u8],
         data_length: u32,
-        event_sender: &mpsc::Sender<super::MDnsResponderEvent>,
+        context: u64,
+        router: &router::Router,
+        writer: &Writer,
     ) -> Result<usize, InternalError>
     {
         let start_pos = header::IPC_HEADER_SIZE;
@@ -476,16 +1510,28 @@ impl Ipc
             }
         };
 
+        if let Some(error) = resolve_reply.error()
+        {
+            error!("Resolve failed: {}", error);
+            Self::end_failed_operation(context, error, router, writer).await;
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
         let resolved = super::Resolved
         {
-            full_name: resolve_reply.full_name,
-            host_target: resolve_reply.host_target,
+            full_name: resolve_reply.full_name.into_owned(),
+            host_target: resolve_reply.host_target.into_owned(),
             port: resolve_reply.port,
             txt_data: resolve_reply.txt_data,
+            txt: super::TxtRecord::from_bytes(resolve_reply.txt_raw),
+            txt_raw: resolve_reply.txt_raw.to_vec(),
+            interface_index: resolve_reply.header.interface_index(),
+            interface_name: super::interface::interface_name(resolve_reply.header.interface_index()),
+            flags: resolve_reply.header.reply_flags(),
         };
 
-        if let Err(e) = event_sender
-            .send(super::MDnsResponderEvent::ServiceResolved(resolved))
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::ServiceResolved(resolved))
             .await
         {
             error!("Failed to send service resolved notification: {}", e);
@@ -497,7 +1543,8 @@ impl Ipc
     async fn parse_address_info_reply(
         buf: &[u8],
         data_length: u32,
-        event_sender: &mpsc::Sender<super::MDnsResponderEvent>,
+        context: u64,
+        router: &router::Router,
     ) -> Result<usize, InternalError>
     {
         let start_pos = header::IPC_HEADER_SIZE;
@@ -519,6 +1566,45 @@ impl Ipc
             }
         };
 
+        if let Some(error) = addrinfo_reply.error()
+        {
+            debug!("Address lookup for {} failed: {}", addrinfo_reply.name, error);
+
+            match error
+            {
+                super::DnsServiceError::Timeout =>
+                {
+                    Self::send_timeout(context, router).await;
+                }
+                // A negative answer is removed again when addresses show up later, only the
+                // answer itself is reported.
+                super::DnsServiceError::NoSuchRecord if addrinfo_reply.is_add() =>
+                {
+                    Self::send_no_such_record(context, addrinfo_reply, router).await;
+                }
+                super::DnsServiceError::NoSuchRecord => {}
+                // The daemon keeps looking, for example once a policy allows it, so the lookup
+                // is not ended.
+                error =>
+                {
+                    Self::send_operation_failed(context, error, router).await;
+                }
+            }
+
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
+        if !addrinfo_reply.tlvs.is_empty()
+        {
+            debug!("Address info reply carried trailing TLVs: {:?}", addrinfo_reply.tlvs);
+        }
+
+        if addrinfo_reply.rrclass != super::RRCLASS_IN
+        {
+            error!("Unexpected class for IP address: {}", addrinfo_reply.rrclass);
+            return Err(InternalError::FrameParsingFailed);
+        }
+
         let ip_addr = match addrinfo_reply.rdata.len()
         {
             4 =>
@@ -545,13 +1631,27 @@ impl Ipc
 
         let addr_info = super::AddressInfo
         {
-            hostname: addrinfo_reply.name,
+            hostname: addrinfo_reply.name.into_owned(),
             address: ip_addr,
+            ttl: addrinfo_reply.ttl,
+            expires_at: Instant::now() + Duration::from_secs(addrinfo_reply.ttl as u64),
+            interface_index: addrinfo_reply.header.interface_index(),
+            interface_name: super::interface::interface_name(addrinfo_reply.header.interface_index()),
+            flags: addrinfo_reply.header.reply_flags(),
         };
 
-        if let Err(e) = event_sender
-            .send(super::MDnsResponderEvent::AddressInfoResolved(addr_info))
-            .await
+        router.track_address(context, &addr_info);
+
+        let event = if addr_info.flags.add
+        {
+            super::MDnsResponderEvent::AddressInfoResolved(addr_info)
+        }
+        else
+        {
+            super::MDnsResponderEvent::AddressInfoRemoved(addr_info)
+        };
+
+        if let Err(e) = router.send(context, event).await
         {
             error!("Failed to send address info notification: {}", e);
         }
@@ -562,6 +1662,9 @@ impl Ipc
     async fn parse_register_service_reply(
         buf: &[u8],
         data_length: u32,
+        context: u64,
+        router: &router::Router,
+        writer: &Writer,
     ) -> Result<usize, InternalError>
     {
         let start_pos = header::IPC_HEADER_SIZE;
@@ -573,7 +1676,7 @@ impl Ipc
             return Err(InternalError::IncompleteFrame);
         }
 
-        match operation::register::Reply::from_bytes(&buf[start_pos..stop_pos])
+        let register_reply = match operation::register::Reply::from_bytes(&buf[start_pos..stop_pos])
         {
             Ok(reply) => reply,
             Err(e) =>
@@ -583,6 +1686,943 @@ impl Ipc
             }
         };
 
+        let is_name_conflict = register_reply.is_name_conflict();
+
+        if let Some(error) = register_reply.error().filter(|_| !is_name_conflict)
+        {
+            error!("Service registration failed: {}", error);
+            Self::end_failed_operation(context, error, router, writer).await;
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
+        let service = super::Service
+        {
+            display_name: super::dns_name::display_label(&register_reply.name),
+            name: register_reply.name.into_owned(),
+            service_type: register_reply.service_type.into_owned(),
+            domain: register_reply.domain.into_owned(),
+            interface_index: register_reply.header.interface_index(),
+            interface_name: super::interface::interface_name(register_reply.header.interface_index()),
+            flags: register_reply.header.reply_flags(),
+            resolved: None,
+        };
+
+        if is_name_conflict
+        {
+            writer.forget(context);
+
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::NameConflict(service))
+                .await
+            {
+                error!("Failed to send name conflict notification: {}", e);
+            }
+
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::ServiceRegistered(service))
+            .await
+        {
+            error!("Failed to send service registered notification: {}", e);
+        }
+
         return Ok(header::IPC_HEADER_SIZE + data_length as usize);
     }
+
+    async fn parse_query_reply(
+        buf: &[u8],
+        data_length: u32,
+        context: u64,
+        router: &router::Router,
+    ) -> Result<usize, InternalError>
+    {
+        let start_pos = header::IPC_HEADER_SIZE;
+        let stop_pos = start_pos + data_length as usize;
+
+        if stop_pos > buf.len()
+        {
+            debug!("Incomplete frame (fragmentation): need {} bytes, have {}", stop_pos, buf.len());
+            return Err(InternalError::IncompleteFrame);
+        }
+
+        let query_reply = match operation::query::Reply::from_bytes(&buf[start_pos..stop_pos])
+        {
+            Ok(reply) => reply,
+            Err(e) =>
+            {
+                error!("Failed to parse query reply: {}", e);
+                return Err(InternalError::FrameParsingFailed);
+            }
+        };
+
+        if let Some(error) = query_reply.error()
+        {
+            debug!("Query for {} failed: {}", query_reply.name, error);
+
+            match error
+            {
+                super::DnsServiceError::Timeout =>
+                {
+                    Self::send_timeout(context, router).await;
+                }
+                // Negative answers are not reported as records, like for address lookups.
+                super::DnsServiceError::NoSuchRecord => {}
+                // The daemon keeps querying, so the query is not ended.
+                error =>
+                {
+                    Self::send_operation_failed(context, error, router).await;
+                }
+            }
+
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
+        if !query_reply.tlvs.is_empty()
+        {
+            debug!("Query reply carried trailing TLVs: {:?}", query_reply.tlvs);
+        }
+
+        let is_add = query_reply.is_add();
+
+        let record = super::Record
+        {
+            name: query_reply.name.into_owned(),
+            rrtype: query_reply.rrtype,
+            rrclass: query_reply.rrclass,
+            rdata: query_reply.rdata.to_vec(),
+            ttl: query_reply.ttl,
+            flags: query_reply.header.reply_flags(),
+        };
+
+        router.track_record(context, &record, is_add);
+
+        if is_add
+        {
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::RecordAdded(record))
+                .await
+            {
+                error!("Failed to send record added notification: {}", e);
+            }
+        }
+        else
+        {
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::RecordRemoved(record))
+                .await
+            {
+                error!("Failed to send record removed notification: {}", e);
+            }
+        }
+
+        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    }
+
+    async fn parse_enumeration_reply(
+        buf: &[u8],
+        data_length: u32,
+        context: u64,
+        router: &router::Router,
+    ) -> Result<usize, InternalError>
+    {
+        let start_pos = header::IPC_HEADER_SIZE;
+        let stop_pos = start_pos + data_length as usize;
+
+        if stop_pos > buf.len()
+        {
+            debug!("Incomplete frame (fragmentation): need {} bytes, have {}", stop_pos, buf.len());
+            return Err(InternalError::IncompleteFrame);
+        }
+
+        let enumeration_reply = match operation::enumeration::Reply::from_bytes(&buf[start_pos..stop_pos])
+        {
+            Ok(reply) => reply,
+            Err(e) =>
+            {
+                error!("Failed to parse enumeration reply: {}", e);
+                return Err(InternalError::FrameParsingFailed);
+            }
+        };
+
+        let is_add = enumeration_reply.is_add();
+
+        let domain = super::Domain
+        {
+            is_default: enumeration_reply.is_default(),
+            name: enumeration_reply.domain.into_owned(),
+        };
+
+        if is_add
+        {
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::DomainAdded(domain))
+                .await
+            {
+                error!("Failed to send domain added notification: {}", e);
+            }
+        }
+        else
+        {
+            if let Err(e) = router
+                .send(context, super::MDnsResponderEvent::DomainRemoved(domain))
+                .await
+            {
+                error!("Failed to send domain removed notification: {}", e);
+            }
+        }
+
+        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    }
+
+    async fn parse_port_mapping_reply(
+        buf: &[u8],
+        data_length: u32,
+        context: u64,
+        router: &router::Router,
+        writer: &Writer,
+    ) -> Result<usize, InternalError>
+    {
+        let start_pos = header::IPC_HEADER_SIZE;
+        let stop_pos = start_pos + data_length as usize;
+
+        if stop_pos > buf.len()
+        {
+            debug!("Incomplete frame (fragmentation): need {} bytes, have {}", stop_pos, buf.len());
+            return Err(InternalError::IncompleteFrame);
+        }
+
+        let port_mapping_reply = match operation::portmapping::Reply::from_bytes(&buf[start_pos..stop_pos])
+        {
+            Ok(reply) => reply,
+            Err(e) =>
+            {
+                error!("Failed to parse port mapping reply: {}", e);
+                return Err(InternalError::FrameParsingFailed);
+            }
+        };
+
+        if let Some(error) = port_mapping_reply.error()
+        {
+            error!("Port mapping failed: {}", error);
+            Self::end_failed_operation(context, error, router, writer).await;
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
+        let port_mapping = super::PortMapping
+        {
+            external_address: port_mapping_reply.external_address,
+            protocol: super::PortMappingProtocol::from_u8(port_mapping_reply.protocol),
+            internal_port: port_mapping_reply.internal_port,
+            external_port: port_mapping_reply.external_port,
+            ttl: port_mapping_reply.ttl,
+        };
+
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::PortMappingCreated(port_mapping))
+            .await
+        {
+            error!("Failed to send port mapping notification: {}", e);
+        }
+
+        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    }
+
+    async fn parse_register_record_reply(
+        buf: &[u8],
+        data_length: u32,
+        context: u64,
+        record_index: u32,
+        router: &router::Router,
+        writer: &Writer,
+    ) -> Result<usize, InternalError>
+    {
+        let start_pos = header::IPC_HEADER_SIZE;
+        let stop_pos = start_pos + data_length as usize;
+
+        if stop_pos > buf.len()
+        {
+            debug!("Incomplete frame (fragmentation): need {} bytes, have {}", stop_pos, buf.len());
+            return Err(InternalError::IncompleteFrame);
+        }
+
+        let register_record_reply = match operation::registerrecord::Reply::from_bytes(&buf[start_pos..stop_pos])
+        {
+            Ok(reply) => reply,
+            Err(e) =>
+            {
+                error!("Failed to parse register record reply: {}", e);
+                return Err(InternalError::FrameParsingFailed);
+            }
+        };
+
+        if let Some(error) = register_record_reply.error()
+        {
+            error!("Record registration {} failed: {}", record_index, error);
+            writer.forget_record(context, record_index);
+            Self::send_operation_failed(context, error, router).await;
+            return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+        }
+
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::RecordRegistered { context, record_index })
+            .await
+        {
+            error!("Failed to send record registered notification: {}", e);
+        }
+
+        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    }
+
+    async fn parse_async_error_reply(
+        buf: &[u8],
+        data_length: u32,
+        context: u64,
+        router: &router::Router,
+        writer: &Writer,
+    ) -> Result<usize, InternalError>
+    {
+        let start_pos = header::IPC_HEADER_SIZE;
+        let stop_pos = start_pos + data_length as usize;
+
+        if stop_pos > buf.len()
+        {
+            debug!("Incomplete frame (fragmentation): need {} bytes, have {}", stop_pos, buf.len());
+            return Err(InternalError::IncompleteFrame);
+        }
+
+        let async_error_reply = match operation::asyncerror::Reply::from_bytes(&buf[start_pos..stop_pos])
+        {
+            Ok(reply) => reply,
+            Err(e) =>
+            {
+                error!("Failed to parse async error reply: {}", e);
+                return Err(InternalError::FrameParsingFailed);
+            }
+        };
+
+        let error = async_error_reply.error();
+        error!("Operation {} failed: {}", context, error);
+
+        Self::end_failed_operation(context, error, router, writer).await;
+
+        return Ok(header::IPC_HEADER_SIZE + data_length as usize);
+    }
+
+    // Reports an operation that the daemon ended with an error and drops everything kept for it.
+    async fn end_failed_operation(
+        context: u64,
+        error: super::DnsServiceError,
+        router: &router::Router,
+        writer: &Writer,
+    )
+    {
+        writer.forget(context);
+        Self::send_operation_failed(context, error, router).await;
+
+        // Nothing follows the failure, so the handle's events end with it.
+        router.remove_route(context);
+    }
+
+    async fn send_operation_failed(
+        context: u64,
+        error: super::DnsServiceError,
+        router: &router::Router,
+    )
+    {
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::OperationFailed { context, error })
+            .await
+        {
+            error!("Failed to send operation failed notification: {}", e);
+        }
+    }
+
+    async fn send_timeout(context: u64, router: &router::Router)
+    {
+        if let Err(e) = router
+            .send(context, super::MDnsResponderEvent::Timeout { context })
+            .await
+        {
+            error!("Failed to send timeout notification: {}", e);
+        }
+    }
+}
+
+// Dropped once the last `MDnsRequester` is gone. Without a `close`, the operations still running
+// are cancelled on a best-effort basis and the listener is stopped, without waiting for either.
+impl Drop for Ipc
+{
+    fn drop(&mut self)
+    {
+        for operation in self.writer.active_operations()
+        {
+            self.router.remove_route(operation.context);
+            self.writer.cancel_on_drop(operation.context);
+        }
+
+        self.cancel_token.cancel();
+    }
+}
+
+impl Writer
+{
+    fn new(
+        write_socket: WriteHalf,
+        error_return: Option<error_return::ErrorReturn>,
+        journal: Option<journal::Journal>,
+        synchronous_errors: bool,
+        metrics: Arc<dyn super::MetricsSink>,
+    ) -> Self
+    {
+        let (commands, receiver) = mpsc::unbounded_channel();
+        super::runtime::spawn_detached(Self::run(write_socket, error_return, journal, receiver, commands.downgrade()));
+
+        return Writer
+        {
+            commands,
+            operations: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            synchronous_errors,
+            metrics,
+        };
+    }
+
+    // Owns the write half of the socket and carries out the commands one at a time, so a frame is
+    // always written in full before the next one starts. Ends once every handle is dropped.
+    async fn run(
+        mut write_socket: WriteHalf,
+        mut error_return: Option<error_return::ErrorReturn>,
+        mut journal: Option<journal::Journal>,
+        mut commands: mpsc::UnboundedReceiver<WriteCommand>,
+        // For the acknowledgements to undo what the journal recorded for a rejected request.
+        own_commands: mpsc::WeakUnboundedSender<WriteCommand>,
+    )
+    {
+        while let Some(command) = commands.recv().await
+        {
+            match command
+            {
+                WriteCommand::Frame { frame, update, acknowledge, done } =>
+                {
+                    if acknowledge && error_return.is_none()
+                    {
+                        debug!("Connection is not a Unix socket, not waiting for the daemon to accept the request");
+                    }
+
+                    // Only requests are answered with an error code, cancelling is not.
+                    let request = !matches!(update, JournalUpdate::None | JournalUpdate::Forget(_));
+
+                    match &error_return
+                    {
+                        Some(error_return) if acknowledge && request =>
+                        {
+                            // The answer is waited for in a task of its own, so that a slow daemon
+                            // does not hold up the writes queued behind the request. The request
+                            // is journaled as written and dropped again if it is rejected.
+                            match error_return.write(&mut write_socket, &frame).await
+                            {
+                                Ok(acknowledgement) =>
+                                {
+                                    let undo = update.undo();
+                                    Self::update_journal(&mut journal, update, frame, true);
+                                    super::runtime::spawn_detached(Self::acknowledge(acknowledgement, undo, own_commands.clone(), done));
+                                }
+                                Err(e) =>
+                                {
+                                    Self::update_journal(&mut journal, update, frame, false);
+                                    let _ = done.send(Err(e));
+                                }
+                            }
+                        }
+                        _ =>
+                        {
+                            let result = Self::write_to(&mut write_socket, &frame).await;
+                            Self::update_journal(&mut journal, update, frame, result.is_ok());
+                            let _ = done.send(result);
+                        }
+                    }
+                }
+                WriteCommand::Journal { update } =>
+                {
+                    if let Some(journal) = &mut journal
+                    {
+                        match update
+                        {
+                            JournalUpdate::RemoveRecord(context, reg_index) => journal.remove_record(context, reg_index),
+                            JournalUpdate::Forget(context) => journal.forget(context),
+                            _ => {}
+                        }
+                    }
+                }
+                WriteCommand::Flush { done } =>
+                {
+                    let _ = done.send(write_socket.flush().await);
+                }
+                WriteCommand::Restore { write_socket: new_socket, error_return: new_error_return, connection_context, delegate, done } =>
+                {
+                    write_socket = new_socket;
+                    error_return = new_error_return;
+                    let result = Self::restore_on(&mut write_socket, journal.as_ref(), connection_context, delegate).await;
+                    let _ = done.send(result);
+                }
+            }
+        }
+
+        debug!("All writer handles dropped, stopping IPC writer");
+    }
+
+    // A request is only issued again after a reconnect if it was written, a cancelled one or a
+    // removed record never is.
+    fn update_journal(journal: &mut Option<journal::Journal>, update: JournalUpdate, frame: header::Frame, written: bool)
+    {
+        let Some(journal) = journal else
+        {
+            return;
+        };
+
+        match update
+        {
+            JournalUpdate::Record(context) if written => journal.record(context, frame),
+            JournalUpdate::AddRecord(context, reg_index) if written => journal.add_record(context, reg_index, frame),
+            JournalUpdate::UpdateRecord(context, reg_index) if written => journal.update_record(context, reg_index, frame),
+            JournalUpdate::RemoveRecord(context, reg_index) => journal.remove_record(context, reg_index),
+            JournalUpdate::Forget(context) => journal.forget(context),
+            _ => {}
+        }
+    }
+
+    // Waits for the daemon to accept a written request, undoing its journal update if it did not.
+    async fn acknowledge(
+        acknowledgement: error_return::Acknowledgement,
+        undo: JournalUpdate,
+        commands: mpsc::WeakUnboundedSender<WriteCommand>,
+        done: oneshot::Sender<io::Result<usize>>,
+    )
+    {
+        let result = acknowledgement.wait().await;
+
+        // Undone before the result is reported, so the journal is up to date once it is.
+        if let (Err(_), Some(commands)) = (&result, commands.upgrade())
+        {
+            let _ = commands.send(WriteCommand::Journal { update: undo });
+        }
+
+        let _ = done.send(result);
+    }
+
+    fn track_operation(&self, operation: super::ActiveOperation)
+    {
+        self.operations.lock().unwrap().insert(operation.context, operation);
+    }
+
+    fn untrack_operation(&self, context: u64)
+    {
+        self.operations.lock().unwrap().remove(&context);
+    }
+
+    // Returns the active operations, oldest first.
+    fn active_operations(&self) -> Vec<super::ActiveOperation>
+    {
+        let mut operations: Vec<_> = self.operations.lock().unwrap().values().cloned().collect();
+        operations.sort_by_key(|operation| operation.started);
+
+        return operations;
+    }
+
+    async fn submit(&self, frame: header::Frame, update: JournalUpdate, acknowledge: bool) -> io::Result<usize>
+    {
+        return self.send_command(|done| WriteCommand::Frame { frame, update, acknowledge, done }).await;
+    }
+
+    // Queues a command for the writer task and waits for its result.
+    async fn send_command<T>(&self, command: impl FnOnce(oneshot::Sender<io::Result<T>>) -> WriteCommand) -> io::Result<T>
+    {
+        let (done, result) = oneshot::channel();
+
+        if self.commands.send(command(done)).is_err()
+        {
+            return Err(io::Error::new(io::ErrorKind::BrokenPipe, "IPC writer stopped"));
+        }
+
+        return match result.await
+        {
+            Ok(result) => result,
+            Err(_) => Err(io::Error::new(io::ErrorKind::BrokenPipe, "IPC writer stopped")),
+        };
+    }
+
+    // Waits for all writes queued so far and flushes the socket.
+    async fn flush(&self) -> io::Result<()>
+    {
+        return self.send_command(|done| WriteCommand::Flush { done }).await;
+    }
+
+    pub async fn write_connection(&self, client_context: u64, delegate: Option<super::Delegate>) -> io::Result<()>
+    {
+        self.submit(Self::connection_frame(client_context, delegate), JournalUpdate::None, false).await?;
+        return Ok(());
+    }
+
+    fn connection_frame(client_context: u64, delegate: Option<super::Delegate>) -> header::Frame
+    {
+        return match delegate
+        {
+            Some(delegate) =>
+            {
+                let pid = match delegate
+                {
+                    super::Delegate::Pid(pid) => pid,
+                    // The UUID was set on the socket by the connector, which fails to connect
+                    // where it cannot be.
+                    super::Delegate::Uuid(_) => 0,
+                };
+
+                let request = operation::connectiondelegate::Request::new(pid);
+
+                header::build_request(
+                    header::request::RequestOperation::ConnectionDelegate,
+                    client_context,
+                    0, // Registration index, set to 0 for default
+                    request.to_bytes(),
+                    &[], // No trailing TLVs
+                )
+            }
+            None => header::build_request(
+                header::request::RequestOperation::Connection,
+                client_context,
+                0, // Registration index, set to 0 for default
+                Vec::new(), // No data
+                &[], // No trailing TLVs
+            ),
+        };
+    }
+
+    // Writes a request and, with `acknowledge` or synchronous errors, fails if the daemon does
+    // not accept it.
+    pub async fn write_request_acknowledged(
+        &self,
+        client_context: u64,
+        buf: header::Frame,
+        acknowledge: bool,
+    ) -> io::Result<usize>
+    {
+        return self.write_journaled(buf, JournalUpdate::Record(client_context), acknowledge).await;
+    }
+
+    // Writes a record request on the connection or registration of the context, which is issued
+    // again after a reconnect until the record is removed.
+    pub async fn write_record_request(&self, context: u64, reg_index: u32, buf: header::Frame) -> io::Result<usize>
+    {
+        return self.write_journaled(buf, JournalUpdate::AddRecord(context, reg_index), false).await;
+    }
+
+    async fn write_journaled(&self, buf: header::Frame, update: JournalUpdate, acknowledge: bool) -> io::Result<usize>
+    {
+        let acknowledge = acknowledge || self.synchronous_errors;
+        let n = self.submit(buf, update, acknowledge).await?;
+        self.metrics.request_sent();
+
+        return Ok(n);
+    }
+
+    // Drops an operation the daemon ended from the journal, so it is not issued again after a
+    // reconnect, and from the active operations, so it is not cancelled.
+    fn forget(&self, context: u64)
+    {
+        self.untrack_operation(context);
+        let _ = self.commands.send(WriteCommand::Journal { update: JournalUpdate::Forget(context) });
+    }
+
+    // Drops a record the daemon refused from the journal.
+    fn forget_record(&self, context: u64, reg_index: u32)
+    {
+        let _ = self.commands.send(WriteCommand::Journal { update: JournalUpdate::RemoveRecord(context, reg_index) });
+    }
+
+    // Writes the whole frame, as the daemon would misread every following request if only a part
+    // of one arrived.
+    async fn write_to(write_socket: &mut WriteHalf, frame: &header::Frame) -> io::Result<usize>
+    {
+        let mut slices = frame.io_slices();
+        let length: usize = slices.iter().map(|slice| slice.len()).sum();
+        let mut remaining = &mut slices[..];
+        let mut written = 0;
+
+        while written < length
+        {
+            match write_socket.write_vectored(remaining).await
+            {
+                Ok(0) =>
+                {
+                    error!("mDNSResponder socket accepted no more data after {} bytes", written);
+                    return Err(io::Error::new(io::ErrorKind::WriteZero, "Failed to write whole frame"));
+                }
+                Ok(n) =>
+                {
+                    written += n;
+                    io::IoSlice::advance_slices(&mut remaining, n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) =>
+                {
+                    error!("Failed to write to mDNSResponder socket: {}", e);
+                    return Err(e);
+                }
+            }
+        }
+
+        debug!("Successfully wrote {} bytes to mDNSResponder socket", written);
+        return Ok(written);
+    }
+
+    // Replaces the socket after a reconnect and issues the shared connection and all journaled
+    // requests on it, before any other write can get in between.
+    async fn restore(
+        &self,
+        write_socket: WriteHalf,
+        error_return: Option<error_return::ErrorReturn>,
+        connection_context: u64,
+        delegate: Option<super::Delegate>,
+    ) -> io::Result<()>
+    {
+        return self
+            .send_command(|done| WriteCommand::Restore { write_socket, error_return, connection_context, delegate, done })
+            .await;
+    }
+
+    async fn restore_on(
+        write_socket: &mut WriteHalf,
+        journal: Option<&journal::Journal>,
+        connection_context: u64,
+        delegate: Option<super::Delegate>,
+    ) -> io::Result<()>
+    {
+        Self::write_to(write_socket, &Self::connection_frame(connection_context, delegate)).await?;
+
+        let Some(journal) = journal else
+        {
+            return Ok(());
+        };
+
+        debug!("Issuing {} active requests again", journal.frames().count());
+
+        for frame in journal.frames()
+        {
+            Self::write_to(write_socket, frame).await?;
+        }
+
+        return Ok(());
+    }
+
+    pub async fn write_cancel_request(&self, context: u64) -> Result<(), io::Error>
+    {
+        self.untrack_operation(context);
+        self.submit(Self::cancel_frame(context), JournalUpdate::Forget(context), false).await?;
+        self.metrics.request_sent();
+
+        return Ok(());
+    }
+
+    // Queues a cancel without waiting for it to be written, for handles that are dropped. Does
+    // nothing if the operation was already cancelled, so an explicit cancel is not repeated.
+    pub fn cancel_on_drop(&self, context: u64)
+    {
+        if self.operations.lock().unwrap().remove(&context).is_none()
+        {
+            return;
+        }
+
+        let (done, _) = oneshot::channel();
+        let command = WriteCommand::Frame
+        {
+            frame: Self::cancel_frame(context),
+            update: JournalUpdate::Forget(context),
+            acknowledge: false,
+            done,
+        };
+
+        if self.commands.send(command).is_ok()
+        {
+            debug!("Cancelling dropped operation {}", context);
+            self.metrics.request_sent();
+        }
+    }
+
+    fn cancel_frame(context: u64) -> header::Frame
+    {
+        return header::build_request(
+            header::request::RequestOperation::Cancel,
+            context,
+            0, // Registration index, set to 0 for default
+            Vec::new(), // No data
+            &[], // No trailing TLVs
+        );
+    }
+
+    pub async fn write_add_record_request(
+        &self,
+        context: u64,
+        reg_index: u32,
+        rrtype: u16,
+        rdata: Vec<u8>,
+        ttl: u32,
+    ) -> Result<(), io::Error>
+    {
+        let request = operation::addrecord::Request::new(
+            operation::ServiceFlags::None as u32,
+            rrtype,
+            rdata,
+            ttl,
+        );
+
+        let buf = header::build_request(
+            header::request::RequestOperation::AddRecord,
+            context,
+            reg_index,
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        self.write_record_request(context, reg_index, buf).await?;
+
+        return Ok(());
+    }
+
+    pub async fn write_update_record_request(
+        &self,
+        context: u64,
+        reg_index: u32,
+        rdata: Vec<u8>,
+        ttl: u32,
+    ) -> Result<(), io::Error>
+    {
+        let request = operation::updaterecord::Request::new(
+            operation::ServiceFlags::None as u32,
+            rdata,
+            ttl,
+        );
+
+        let buf = header::build_request(
+            header::request::RequestOperation::UpdateRecord,
+            context,
+            reg_index,
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        self.write_journaled(buf, JournalUpdate::UpdateRecord(context, reg_index), false).await?;
+
+        return Ok(());
+    }
+
+    pub async fn write_remove_record_request(
+        &self,
+        context: u64,
+        reg_index: u32,
+    ) -> Result<(), io::Error>
+    {
+        let request = operation::removerecord::Request::new(operation::ServiceFlags::None as u32);
+
+        let buf = header::build_request(
+            header::request::RequestOperation::RemoveRecord,
+            context,
+            reg_index,
+            request.to_bytes(),
+            &[], // No trailing TLVs
+        );
+
+        self.write_journaled(buf, JournalUpdate::RemoveRecord(context, reg_index), false).await?;
+
+        return Ok(());
+    }
+
+    pub async fn write_update_txt_request(
+        &self,
+        context: u64,
+        txt: Vec<u8>,
+    ) -> Result<(), io::Error>
+    {
+        return self.write_update_record_request(
+            context,
+            operation::updaterecord::TXT_RECORD_INDEX,
+            txt,
+            0, // Use the default TTL
+        )
+        .await;
+    }
+}
+
+impl Reconnector
+{
+    // Tries to connect again until it succeeds, the policy gives up, or the IPC is closed.
+    // Returns the new read half, whether the daemon restarted in the meantime, and the transport
+    // the new connection was opened on.
+    async fn reconnect(&mut self, cancel_token: &CancellationToken) -> Option<(ReadHalf, bool, Option<super::Transport>)>
+    {
+        let mut backoff = self.policy.initial_backoff;
+        let mut attempt: u32 = 0;
+
+        loop
+        {
+            if self.policy.max_attempts.is_some_and(|max_attempts| attempt >= max_attempts)
+            {
+                error!("Giving up reconnecting to mDNSResponder after {} attempts", attempt);
+                return None;
+            }
+
+            attempt += 1;
+
+            select!
+            {
+                _ = cancel_token.cancelled() =>
+                {
+                    debug!("Cancellation token triggered, stopping reconnect");
+                    return None;
+                }
+                _ = super::runtime::sleep(backoff) => {}
+            }
+
+            debug!("Reconnecting to mDNSResponder, attempt {}", attempt);
+
+            match self.try_reconnect().await
+            {
+                Ok(read_socket) =>
+                {
+                    let transport = self.connector.transport();
+                    match &transport
+                    {
+                        Some(transport) => debug!("Reconnected to mDNSResponder at {}", transport),
+                        None => debug!("Reconnected to mDNSResponder"),
+                    }
+
+                    *self.transport.lock().unwrap() = transport.clone();
+
+                    let daemon_instance = self.connector.daemon_instance();
+                    let daemon_restarted = daemon_instance != self.daemon_instance;
+                    self.daemon_instance = daemon_instance;
+
+                    return Some((read_socket, daemon_restarted, transport));
+                }
+                Err(e) =>
+                {
+                    error!("Failed to reconnect to mDNSResponder: {}", e);
+                    backoff = std::cmp::min(backoff * 2, self.policy.max_backoff);
+                }
+            }
+        }
+    }
+
+    async fn try_reconnect(&self) -> io::Result<ReadHalf>
+    {
+        let stream = Ipc::connect(self.connector.as_ref(), self.delegate).await?;
+        let error_return = error_return::ErrorReturn::for_connection(stream.as_ref());
+        let (read_socket, write_socket) = tokio::io::split(stream);
+
+        self.writer
+            .restore(write_socket, error_return, self.connection_context, self.delegate)
+            .await?;
+
+        return Ok(read_socket);
+    }
 }
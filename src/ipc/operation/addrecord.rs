@@ -0,0 +1,39 @@
+pub struct Request
+{
+    service_flags: u32,
+    rrtype: u16,
+    rdata: Vec<u8>,
+    ttl: u32,
+}
+
+impl Request
+{
+    pub fn new(
+        service_flags: u32,
+        rrtype: u16,
+        rdata: Vec<u8>,
+        ttl: u32,
+    ) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            rrtype,
+            rdata,
+            ttl,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(&self.rrtype.to_be_bytes());
+        buf.extend_from_slice(&(self.rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.rdata);
+        buf.extend_from_slice(&self.ttl.to_be_bytes());
+
+        return buf;
+    }
+}
@@ -1,4 +1,4 @@
-use super::ServiceFlags;
+use std::borrow::Cow;
 
 #[repr(u32)]
 #[derive(Copy, Clone, Debug)]
@@ -11,27 +11,27 @@ pub enum Protocol
 
 pub struct Request
 {
-    service_flags: ServiceFlags,
+    service_flags: u32,
     interface_index: u32,
     protocol: Protocol,
     hostname: String,
 }
 
-pub struct Reply
+pub struct Reply<'a>
 {
     pub header: super::ReplyHeader,
-    pub name: String,
+    pub name: Cow<'a, str>,
     pub rrtype: u16,
     pub rrclass: u16,
-    pub rdlen: u16,
-    pub rdata: Vec<u8>,
+    pub rdata: &'a [u8],
     pub ttl: u32,
+    pub tlvs: Vec<crate::ipc::header::tlv::Tlv>,
 }
 
 impl Request
 {
     pub fn new(
-        service_flags: ServiceFlags,
+        service_flags: u32,
         interface_index: u32,
         protocol: Protocol,
         hostname: String,
@@ -50,7 +50,7 @@ impl Request
     {
         let mut buf = Vec::new();
 
-        buf.extend_from_slice(&(self.service_flags as u32).to_be_bytes());
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
         buf.extend_from_slice(&self.interface_index.to_be_bytes());
         buf.extend_from_slice(&(self.protocol as u32).to_be_bytes());
         buf.extend_from_slice(self.hostname.as_bytes());
@@ -60,22 +60,16 @@ impl Request
     }
 }
 
-impl Reply
+impl<'a> Reply<'a>
 {
-    fn cstr_from_buf(buf: &[u8]) -> String
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, String>
     {
-        let nul_pos = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
-        return String::from_utf8_lossy(&buf[..nul_pos]).into_owned();
-    }
-
-    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
-    {
-        let header = super::ReplyHeader::from_bytes(&buf[0..12])?;
+        let header = super::ReplyHeader::from_bytes(buf)?;
 
         let mut offset = 12;
 
-        let name = Self::cstr_from_buf(&buf[offset..]);
-        offset += name.len() + 1;
+        let (name, next) = super::read_cstr(buf, offset);
+        offset = next;
 
         if buf.len() < offset + 6
         {
@@ -92,7 +86,7 @@ impl Reply
             return Err(format!("Buffer too short for RDATA and TTL: {}", buf.len()));
         }
 
-        let rdata = buf[offset..offset + (rdlen as usize)].to_vec();
+        let rdata = &buf[offset..offset + (rdlen as usize)];
         offset += rdlen as usize;
 
         let ttl = u32::from_be_bytes([
@@ -101,6 +95,10 @@ impl Reply
             buf[offset + 2],
             buf[offset + 3],
         ]);
+        offset += 4;
+
+        // Trailing bytes that do not form valid TLVs are ignored.
+        let tlvs = crate::ipc::header::tlv::decode(&buf[offset..]).unwrap_or_default();
 
         return Ok(Reply
         {
@@ -108,11 +106,38 @@ impl Reply
             name,
             rrtype,
             rrclass,
-            rdlen,
             rdata,
             ttl,
+            tlvs,
         });
     }
+
+    #[cfg(any(all(unix, feature = "testing"), feature = "fuzzing"))]
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = self.header.to_bytes();
+
+        buf.extend_from_slice(self.name.as_bytes());
+        buf.push(0); // Null-terminate the hostname
+        buf.extend_from_slice(&self.rrtype.to_be_bytes());
+        buf.extend_from_slice(&self.rrclass.to_be_bytes());
+        buf.extend_from_slice(&(self.rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(self.rdata);
+        buf.extend_from_slice(&self.ttl.to_be_bytes());
+        buf.extend_from_slice(&crate::ipc::header::tlv::encode(&self.tlvs));
+
+        return buf;
+    }
+
+    pub fn is_add(&self) -> bool
+    {
+        return self.header.has(super::ReplyFlags::Add);
+    }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
+    }
 }
 
 impl From<crate::Protocol> for Protocol
@@ -0,0 +1,25 @@
+#[derive(Debug)]
+pub struct Reply
+{
+    pub header: super::ReplyHeader,
+    // No reply data beyond the header for async error operation
+}
+
+impl Reply
+{
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
+    {
+        let header = super::ReplyHeader::from_bytes(buf)?;
+
+        return Ok(Reply
+        {
+            header,
+        });
+    }
+
+    pub fn error(&self) -> crate::DnsServiceError
+    {
+        // An async error always carries a failure code, treat a zero code as unknown
+        return crate::DnsServiceError::from_code(self.header.error as i32).unwrap_or(crate::DnsServiceError::Unknown);
+    }
+}
@@ -1,26 +1,26 @@
-use super::ServiceFlags;
+use std::borrow::Cow;
 
 pub struct Request
 {
-    service_flags: ServiceFlags,
+    service_flags: u32,
     interface_index: u32,
     reg_type: String,
     domain: String,
 }
 
 #[derive(Debug)]
-pub struct Reply
+pub struct Reply<'a>
 {
     pub header: super::ReplyHeader,
-    pub service_name: String,
-    pub service_type: String,
-    pub service_domain: String,
+    pub service_name: Cow<'a, str>,
+    pub service_type: Cow<'a, str>,
+    pub service_domain: Cow<'a, str>,
 }
 
 impl Request
 {
     pub fn new(
-        service_flags: ServiceFlags,
+        service_flags: u32,
         interface_index: u32,
         reg_type: String,
         domain: String,
@@ -38,7 +38,7 @@ impl Request
     pub fn to_bytes(&self) -> Vec<u8>
     {
         let mut buf = Vec::new();
-        buf.extend_from_slice(&(self.service_flags as u32).to_be_bytes());
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
         buf.extend_from_slice(&self.interface_index.to_be_bytes());
         buf.extend_from_slice(self.reg_type.as_bytes());
         buf.push(0); // Null terminator for string
@@ -48,17 +48,11 @@ impl Request
     }
 }
 
-impl Reply
+impl<'a> Reply<'a>
 {
-    fn cstr_from_buf(buf: &[u8]) -> String
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, String>
     {
-        let nul_pos = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
-        return String::from_utf8_lossy(&buf[..nul_pos]).into_owned();
-    }
-
-    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
-    {
-        let header = super::ReplyHeader::from_bytes(&buf)?;
+        let header = super::ReplyHeader::from_bytes(buf)?;
 
         let mut offset = 12;
 
@@ -67,23 +61,23 @@ impl Reply
             return Err("Buffer too short to contain service name".to_string());
         }
 
-        let service_name = Self::cstr_from_buf(&buf[offset..]);
-        offset += service_name.len() + 1;
+        let (service_name, next) = super::read_cstr(buf, offset);
+        offset = next;
 
         if offset >= buf.len()
         {
             return Err("Buffer too short to contain service type".to_string());
         }
 
-        let service_type = Self::cstr_from_buf(&buf[offset..]);
-        offset += service_type.len() + 1;
+        let (service_type, next) = super::read_cstr(buf, offset);
+        offset = next;
 
         if offset >= buf.len()
         {
             return Err("Buffer too short to contain service domain".to_string());
         }
 
-        let service_domain = Self::cstr_from_buf(&buf[offset..]);
+        let (service_domain, _) = super::read_cstr(buf, offset);
 
         return Ok(Reply
         {
@@ -94,8 +88,26 @@ impl Reply
         });
     }
 
+    #[cfg(any(all(unix, feature = "testing"), feature = "fuzzing"))]
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = self.header.to_bytes();
+        buf.extend_from_slice(self.service_name.as_bytes());
+        buf.push(0); // Null terminator for string
+        buf.extend_from_slice(self.service_type.as_bytes());
+        buf.push(0); // Null terminator for string
+        buf.extend_from_slice(self.service_domain.as_bytes());
+        buf.push(0); // Null terminator for string
+        return buf;
+    }
+
     pub fn is_add(&self) -> bool
     {
-        return self.header.flags.contains(&super::ReplyFlags::Add);
+        return self.header.has(super::ReplyFlags::Add);
+    }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
     }
 }
@@ -0,0 +1,25 @@
+pub struct Request
+{
+    pid: i32,
+}
+
+impl Request
+{
+    pub fn new(pid: i32) -> Self
+    {
+        return Request
+        {
+            pid,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        // A PID of zero tells the daemon to use the delegate UUID set on the socket instead.
+        buf.extend_from_slice(&self.pid.to_be_bytes());
+
+        return buf;
+    }
+}
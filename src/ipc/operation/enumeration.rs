@@ -0,0 +1,72 @@
+use std::borrow::Cow;
+
+pub struct Request
+{
+    service_flags: u32,
+    interface_index: u32,
+}
+
+#[derive(Debug)]
+pub struct Reply<'a>
+{
+    pub header: super::ReplyHeader,
+    pub domain: Cow<'a, str>,
+}
+
+impl Request
+{
+    pub fn new(
+        service_flags: u32,
+        interface_index: u32,
+    ) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            interface_index,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(&self.interface_index.to_be_bytes());
+
+        return buf;
+    }
+}
+
+impl<'a> Reply<'a>
+{
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, String>
+    {
+        let header = super::ReplyHeader::from_bytes(buf)?;
+
+        let offset = 12;
+
+        if offset >= buf.len()
+        {
+            return Err("Buffer too short to contain domain".to_string());
+        }
+
+        let (domain, _) = super::read_cstr(buf, offset);
+
+        return Ok(Reply
+        {
+            header,
+            domain,
+        });
+    }
+
+    pub fn is_add(&self) -> bool
+    {
+        return self.header.has(super::ReplyFlags::Add);
+    }
+
+    pub fn is_default(&self) -> bool
+    {
+        return self.header.has(super::ReplyFlags::Default);
+    }
+}
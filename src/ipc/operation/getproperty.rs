@@ -0,0 +1,87 @@
+// kDNSServiceProperty_DaemonVersion
+pub const PROPERTY_DAEMON_VERSION: &str = "DaemonVersion";
+
+pub struct Request
+{
+    property: String,
+}
+
+// The daemon answers a GetProperty request with an unframed reply written directly to the
+// socket: a 32-bit error code, followed by the length and value of the property on success.
+#[derive(Debug)]
+pub struct Reply
+{
+    pub error: i32,
+    pub value: Vec<u8>,
+}
+
+impl Request
+{
+    pub fn new(property: String) -> Self
+    {
+        return Request
+        {
+            property,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(self.property.as_bytes());
+        buf.push(0); // NUL-terminate
+
+        return buf;
+    }
+}
+
+impl Reply
+{
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
+    {
+        if buf.len() < 4
+        {
+            return Err(format!("Buffer too short for property error code: {}", buf.len()));
+        }
+
+        let error = i32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
+
+        if error != 0
+        {
+            return Ok(Reply
+            {
+                error,
+                value: Vec::new(),
+            });
+        }
+
+        if buf.len() < 8
+        {
+            return Err(format!("Buffer too short for property length: {}", buf.len()));
+        }
+
+        let length = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]) as usize;
+
+        if buf.len() - 8 < length
+        {
+            return Err(format!("Buffer too short for property value: {}", buf.len()));
+        }
+
+        return Ok(Reply
+        {
+            error,
+            value: buf[8..8 + length].to_vec(),
+        });
+    }
+
+    pub fn as_u32(&self) -> Option<u32>
+    {
+        if self.value.len() != 4
+        {
+            return None;
+        }
+
+        return Some(u32::from_be_bytes([self.value[0], self.value[1], self.value[2], self.value[3]]));
+    }
+}
@@ -2,63 +2,113 @@ pub mod browse;
 pub mod resolve;
 pub mod addrinfo;
 pub mod register;
+pub mod addrecord;
+pub mod updaterecord;
+pub mod removerecord;
+pub mod query;
+pub mod enumeration;
+pub mod getproperty;
+pub mod portmapping;
+pub mod registerrecord;
+pub mod setdomain;
+pub mod connectiondelegate;
+pub mod asyncerror;
+
+use std::borrow::Cow;
 
 #[repr(u32)]
 #[derive(Copy, Clone, Debug)]
 pub enum ServiceFlags
 {
     None = 0x0,
-    AutoTrigger = 0x1,
     Add = 0x2,
-    Default = 0x3,
+    NoAutoRename = 0x8,
+    Shared = 0x10,
+    Unique = 0x20,
+    BrowseDomains = 0x40,
+    RegistrationDomains = 0x80,
+    LongLivedQuery = 0x100,
     ForceMulticast = 0x400,
+    ShareConnection = 0x4000,
+    SuppressUnusable = 0x8000,
+    Timeout = 0x10000,
     IncludeP2p = 0x20000,
     IncludeAwdl = 0x100000,
+    Validate = 0x200000,
+    ThresholdOne = 0x2000000,
+    ThresholdFinder = 0x4000000,
+    AllowExpiredAnswers = 0x80000000,
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ReplyFlags
 {
     MoreComing = 0x1,
     Add = 0x2,
+    Default = 0x4,
+    Secure = 0x200010,
+    Insecure = 0x200020,
+    Bogus = 0x200040,
+    Indeterminate = 0x200080,
     ThresholdReached = 0x2000000,
+    // The same bit as ServiceFlags::AllowExpiredAnswers.
+    ExpiredAnswer = 0x80000000,
 }
 
 #[derive(Debug)]
 pub struct ReplyHeader
 {
-    flags: Vec<ReplyFlags>,
+    flags: u32,
     interface_index: u32,
     error: u32,
 }
 
 impl ReplyFlags
 {
-    pub fn from_u32(value: u32) -> Vec<Self>
+    // The validation status reuses low bits that mean something else unless the Validate bit is
+    // set as well, so all bits of a flag have to match.
+    fn is_set(self, value: u32) -> bool
     {
-        let mut flags = Vec::new();
+        return value & (self as u32) == self as u32;
+    }
+}
 
-        if value & (ReplyFlags::MoreComing as u32) != 0
-        {
-            flags.push(ReplyFlags::MoreComing);
-        }
+// Reads the NUL-terminated string starting at `offset`, borrowed from the buffer unless it is not
+// valid UTF-8. Returns the string and the offset after the terminator, which is past the end of
+// the buffer if the terminator is missing.
+pub fn read_cstr(buf: &[u8], offset: usize) -> (Cow<'_, str>, usize)
+{
+    let rest = buf.get(offset..).unwrap_or_default();
+    let nul_pos = rest.iter().position(|&b| b == 0).unwrap_or(rest.len());
+    return (String::from_utf8_lossy(&rest[..nul_pos]), offset + nul_pos + 1);
+}
 
-        if value & (ReplyFlags::Add as u32) != 0
+impl ReplyHeader
+{
+    #[cfg(any(all(unix, feature = "testing"), feature = "fuzzing"))]
+    pub fn new(flags: u32, interface_index: u32, error: i32) -> Self
+    {
+        return ReplyHeader
         {
-            flags.push(ReplyFlags::Add);
-        }
+            flags,
+            interface_index,
+            error: error as u32,
+        };
+    }
 
-        if value & (ReplyFlags::ThresholdReached as u32) != 0
-        {
-            flags.push(ReplyFlags::ThresholdReached);
-        }
+    #[cfg(any(all(unix, feature = "testing"), feature = "fuzzing"))]
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::with_capacity(12);
+
+        buf.extend_from_slice(&self.flags.to_be_bytes());
+        buf.extend_from_slice(&self.interface_index.to_be_bytes());
+        buf.extend_from_slice(&self.error.to_be_bytes());
 
-        return flags;
+        return buf;
     }
-}
 
-impl ReplyHeader
-{
     pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
     {
         if buf.len() < 12
@@ -66,7 +116,7 @@ impl ReplyHeader
             return Err(format!("Buffer too short for ReplyHeader: {}", buf.len()));
         }
 
-        let flags = ReplyFlags::from_u32(u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]));
+        let flags = u32::from_be_bytes([buf[0], buf[1], buf[2], buf[3]]);
         let interface_index = u32::from_be_bytes([buf[4], buf[5], buf[6], buf[7]]);
         let error = u32::from_be_bytes([buf[8], buf[9], buf[10], buf[11]]);
 
@@ -77,4 +127,44 @@ impl ReplyHeader
             error,
         });
     }
+
+    pub fn interface_index(&self) -> u32
+    {
+        return self.interface_index;
+    }
+
+    pub fn has(&self, flag: ReplyFlags) -> bool
+    {
+        return flag.is_set(self.flags);
+    }
+
+    pub fn reply_flags(&self) -> crate::ReplyFlags
+    {
+        return crate::ReplyFlags
+        {
+            add: self.has(ReplyFlags::Add),
+            more_coming: self.has(ReplyFlags::MoreComing),
+            threshold_reached: self.has(ReplyFlags::ThresholdReached),
+            expired: self.has(ReplyFlags::ExpiredAnswer),
+            dnssec: self.dnssec_status(),
+        };
+    }
+
+    fn dnssec_status(&self) -> Option<crate::DnssecStatus>
+    {
+        return [
+            (ReplyFlags::Secure, crate::DnssecStatus::Secure),
+            (ReplyFlags::Insecure, crate::DnssecStatus::Insecure),
+            (ReplyFlags::Bogus, crate::DnssecStatus::Bogus),
+            (ReplyFlags::Indeterminate, crate::DnssecStatus::Indeterminate),
+        ]
+        .into_iter()
+        .find(|(flag, _)| self.has(*flag))
+        .map(|(_, status)| status);
+    }
+
+    pub fn more_coming(&self) -> bool
+    {
+        return self.has(ReplyFlags::MoreComing);
+    }
 }
@@ -0,0 +1,120 @@
+use std::net::Ipv4Addr;
+
+#[repr(u32)]
+#[derive(Copy, Clone, Debug)]
+pub enum Protocol
+{
+    Udp = 0x10,
+    Tcp = 0x20,
+}
+
+pub struct Request
+{
+    service_flags: u32,
+    interface_index: u32,
+    protocol: Protocol,
+    internal_port: u16,
+    external_port: u16,
+    ttl: u32,
+}
+
+#[derive(Debug)]
+pub struct Reply
+{
+    pub header: super::ReplyHeader,
+    pub external_address: Ipv4Addr,
+    pub protocol: u8,
+    pub internal_port: u16,
+    pub external_port: u16,
+    pub ttl: u32,
+}
+
+impl Request
+{
+    pub fn new(
+        service_flags: u32,
+        interface_index: u32,
+        protocol: Protocol,
+        internal_port: u16,
+        external_port: u16,
+        ttl: u32,
+    ) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            interface_index,
+            protocol,
+            internal_port,
+            external_port,
+            ttl,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(&self.interface_index.to_be_bytes());
+        buf.extend_from_slice(&(self.protocol as u32).to_be_bytes());
+        buf.extend_from_slice(&self.internal_port.to_be_bytes());
+        buf.extend_from_slice(&self.external_port.to_be_bytes());
+        buf.extend_from_slice(&self.ttl.to_be_bytes());
+
+        return buf;
+    }
+}
+
+impl Reply
+{
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
+    {
+        let header = super::ReplyHeader::from_bytes(buf)?;
+
+        let offset = 12;
+
+        if buf.len() < offset + 13
+        {
+            return Err(format!("Buffer too short for port mapping fields: {}", buf.len()));
+        }
+
+        let external_address = Ipv4Addr::new(buf[offset], buf[offset + 1], buf[offset + 2], buf[offset + 3]);
+        let protocol = buf[offset + 4];
+        let internal_port = u16::from_be_bytes([buf[offset + 5], buf[offset + 6]]);
+        let external_port = u16::from_be_bytes([buf[offset + 7], buf[offset + 8]]);
+        let ttl = u32::from_be_bytes([
+            buf[offset + 9],
+            buf[offset + 10],
+            buf[offset + 11],
+            buf[offset + 12],
+        ]);
+
+        return Ok(Reply
+        {
+            header,
+            external_address,
+            protocol,
+            internal_port,
+            external_port,
+            ttl,
+        });
+    }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
+    }
+}
+
+impl From<crate::PortMappingProtocol> for Protocol
+{
+    fn from(proto: crate::PortMappingProtocol) -> Self
+    {
+        match proto
+        {
+            crate::PortMappingProtocol::Udp => Protocol::Udp,
+            crate::PortMappingProtocol::Tcp => Protocol::Tcp,
+        }
+    }
+}
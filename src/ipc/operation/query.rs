@@ -0,0 +1,125 @@
+use std::borrow::Cow;
+
+pub struct Request
+{
+    service_flags: u32,
+    interface_index: u32,
+    name: String,
+    rrtype: u16,
+    rrclass: u16,
+}
+
+#[derive(Debug)]
+pub struct Reply<'a>
+{
+    pub header: super::ReplyHeader,
+    pub name: Cow<'a, str>,
+    pub rrtype: u16,
+    pub rrclass: u16,
+    pub rdata: &'a [u8],
+    pub ttl: u32,
+    pub tlvs: Vec<crate::ipc::header::tlv::Tlv>,
+}
+
+impl Request
+{
+    pub fn new(
+        service_flags: u32,
+        interface_index: u32,
+        name: String,
+        rrtype: u16,
+        rrclass: u16,
+    ) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            interface_index,
+            name,
+            rrtype,
+            rrclass,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(&self.interface_index.to_be_bytes());
+        buf.extend_from_slice(self.name.as_bytes());
+        buf.push(0); // NUL-terminate
+        buf.extend_from_slice(&self.rrtype.to_be_bytes());
+        buf.extend_from_slice(&self.rrclass.to_be_bytes());
+
+        return buf;
+    }
+}
+
+impl<'a> Reply<'a>
+{
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, String>
+    {
+        let header = super::ReplyHeader::from_bytes(buf)?;
+
+        let mut offset = 12;
+
+        if offset >= buf.len()
+        {
+            return Err("Buffer too short to contain record name".to_string());
+        }
+
+        let (name, next) = super::read_cstr(buf, offset);
+        offset = next;
+
+        if buf.len() < offset + 6
+        {
+            return Err(format!("Buffer too short for RR fields: {}", buf.len()));
+        }
+
+        let rrtype = u16::from_be_bytes([buf[offset], buf[offset + 1]]);
+        let rrclass = u16::from_be_bytes([buf[offset + 2], buf[offset + 3]]);
+        let rdlen = u16::from_be_bytes([buf[offset + 4], buf[offset + 5]]) as usize;
+        offset += 6;
+
+        if buf.len() < offset + rdlen + 4
+        {
+            return Err(format!("Buffer too short for RDATA and TTL: {}", buf.len()));
+        }
+
+        let rdata = &buf[offset..offset + rdlen];
+        offset += rdlen;
+
+        let ttl = u32::from_be_bytes([
+            buf[offset],
+            buf[offset + 1],
+            buf[offset + 2],
+            buf[offset + 3],
+        ]);
+        offset += 4;
+
+        // Trailing bytes that do not form valid TLVs are ignored.
+        let tlvs = crate::ipc::header::tlv::decode(&buf[offset..]).unwrap_or_default();
+
+        return Ok(Reply
+        {
+            header,
+            name,
+            rrtype,
+            rrclass,
+            rdata,
+            ttl,
+            tlvs,
+        });
+    }
+
+    pub fn is_add(&self) -> bool
+    {
+        return self.header.has(super::ReplyFlags::Add);
+    }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
+    }
+}
@@ -1,34 +1,38 @@
-use super::ServiceFlags;
+use std::borrow::Cow;
 
 pub struct Request
 {
-    service_flags: ServiceFlags,
+    service_flags: u32,
     interface_index: u32,
     name: String,
     service_type: String,
     domain: String,
     host: String,
     port: u16,
-    txt_data: Vec<String>,
+    txt: Vec<u8>,
 }
 
-pub struct Reply
+#[derive(Debug)]
+pub struct Reply<'a>
 {
     pub header: super::ReplyHeader,
-    // No reply data for register operation
+    pub name: Cow<'a, str>,
+    pub service_type: Cow<'a, str>,
+    pub domain: Cow<'a, str>,
 }
 
 impl Request
 {
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
-        service_flags: ServiceFlags,
+        service_flags: u32,
         interface_index: u32,
         name: String,
         service_type: String,
         domain: String,
         host: String,
         port: u16,
-        txt_data: Vec<String>,
+        txt: Vec<u8>,
     ) -> Self
     {
         return Request
@@ -40,7 +44,7 @@ impl Request
             domain,
             host,
             port,
-            txt_data,
+            txt,
         };
     }
 
@@ -48,7 +52,7 @@ impl Request
     {
         let mut buf = Vec::new();
 
-        buf.extend_from_slice(&(self.service_flags as u32).to_be_bytes());
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
         buf.extend_from_slice(&self.interface_index.to_be_bytes());
 
         buf.extend_from_slice(self.name.as_bytes());
@@ -65,28 +69,60 @@ impl Request
 
         buf.extend_from_slice(&self.port.to_be_bytes());
 
-        let txt_len: u16 = self.txt_data.iter().map(|s| s.len() as u16 + 1).sum();
-        buf.extend_from_slice(&txt_len.to_be_bytes());
-
-        for txt in &self.txt_data
-        {
-            buf.push(txt.len() as u8);
-            buf.extend_from_slice(txt.as_bytes());
-        }
+        buf.extend_from_slice(&(self.txt.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.txt);
 
         return buf;
     }
 }
 
-impl Reply
+impl<'a> Reply<'a>
 {
-    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, String>
     {
-        let header = super::ReplyHeader::from_bytes(&buf[0..12])?;
+        let header = super::ReplyHeader::from_bytes(buf)?;
+
+        let mut offset = 12;
+
+        if offset >= buf.len()
+        {
+            return Err("Buffer too short to contain service name".to_string());
+        }
+
+        let (name, next) = super::read_cstr(buf, offset);
+        offset = next;
+
+        if offset >= buf.len()
+        {
+            return Err("Buffer too short to contain service type".to_string());
+        }
+
+        let (service_type, next) = super::read_cstr(buf, offset);
+        offset = next;
+
+        if offset >= buf.len()
+        {
+            return Err("Buffer too short to contain service domain".to_string());
+        }
+
+        let (domain, _) = super::read_cstr(buf, offset);
 
         return Ok(Reply
         {
             header,
+            name,
+            service_type,
+            domain,
         });
     }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
+    }
+
+    pub fn is_name_conflict(&self) -> bool
+    {
+        return self.error() == Some(crate::DnsServiceError::NameConflict);
+    }
 }
@@ -0,0 +1,77 @@
+pub struct Request
+{
+    service_flags: u32,
+    interface_index: u32,
+    name: String,
+    rrtype: u16,
+    rrclass: u16,
+    rdata: Vec<u8>,
+    ttl: u32,
+}
+
+#[derive(Debug)]
+pub struct Reply
+{
+    pub header: super::ReplyHeader,
+    // No reply data beyond the header for register record operation
+}
+
+impl Request
+{
+    pub fn new(
+        service_flags: u32,
+        interface_index: u32,
+        name: String,
+        rrtype: u16,
+        rrclass: u16,
+        rdata: Vec<u8>,
+        ttl: u32,
+    ) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            interface_index,
+            name,
+            rrtype,
+            rrclass,
+            rdata,
+            ttl,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(&self.interface_index.to_be_bytes());
+        buf.extend_from_slice(self.name.as_bytes());
+        buf.push(0); // NUL-terminate
+        buf.extend_from_slice(&self.rrtype.to_be_bytes());
+        buf.extend_from_slice(&self.rrclass.to_be_bytes());
+        buf.extend_from_slice(&(self.rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.rdata);
+        buf.extend_from_slice(&self.ttl.to_be_bytes());
+
+        return buf;
+    }
+}
+
+impl Reply
+{
+    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
+    {
+        let header = super::ReplyHeader::from_bytes(buf)?;
+
+        return Ok(Reply
+        {
+            header,
+        });
+    }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
+    }
+}
@@ -0,0 +1,24 @@
+pub struct Request
+{
+    service_flags: u32,
+}
+
+impl Request
+{
+    pub fn new(service_flags: u32) -> Self
+    {
+        return Request
+        {
+            service_flags,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+
+        return buf;
+    }
+}
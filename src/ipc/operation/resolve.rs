@@ -1,3 +1,5 @@
+use std::borrow::Cow;
+
 const ESCAPED_BYTE_SMALL: &str = "\\000\\001\\002\\003\\004\\005\\006\\007\\008\\009\
      \\010\\011\\012\\013\\014\\015\\016\\017\\018\\019\
      \\020\\021\\022\\023\\024\\025\\026\\027\\028\\029\
@@ -18,11 +20,9 @@ const ESCAPED_BYTE_LARGE: &str = "\\127\\128\\129\
      \\240\\241\\242\\243\\244\\245\\246\\247\\248\\249\
      \\250\\251\\252\\253\\254\\255";
 
-use super::ServiceFlags;
-
 pub struct Request
 {
-    service_flags: ServiceFlags,
+    service_flags: u32,
     interface_index: u32,
     name: String,
     reg_type: String,
@@ -30,19 +30,20 @@ pub struct Request
 }
 
 #[derive(Debug)]
-pub struct Reply
+pub struct Reply<'a>
 {
     pub header: super::ReplyHeader,
-    pub full_name: String,
-    pub host_target: String,
+    pub full_name: Cow<'a, str>,
+    pub host_target: Cow<'a, str>,
     pub port: u16,
     pub txt_data: Vec<String>,
+    pub txt_raw: &'a [u8],
 }
 
 impl Request
 {
     pub fn new(
-        service_flags: ServiceFlags,
+        service_flags: u32,
         interface_index: u32,
         name: String,
         reg_type: String,
@@ -63,7 +64,7 @@ impl Request
     {
         let mut buf = Vec::new();
 
-        buf.extend_from_slice(&(self.service_flags as u32).to_be_bytes());
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
         buf.extend_from_slice(&self.interface_index.to_be_bytes());
         buf.extend_from_slice(self.name.as_bytes());
         buf.push(0); // NUL-terminate
@@ -76,17 +77,11 @@ impl Request
     }
 }
 
-impl Reply
+impl<'a> Reply<'a>
 {
-    fn cstr_from_buf(buf: &[u8]) -> String
-    {
-        let nul_pos = buf.iter().position(|&b| b == 0).unwrap_or(buf.len());
-        return String::from_utf8_lossy(&buf[..nul_pos]).into_owned();
-    }
-
-    pub fn from_bytes(buf: &[u8]) -> Result<Self, String>
+    pub fn from_bytes(buf: &'a [u8]) -> Result<Self, String>
     {
-        let header = super::ReplyHeader::from_bytes(&buf)?;
+        let header = super::ReplyHeader::from_bytes(buf)?;
 
         let mut offset = 12;
 
@@ -95,16 +90,16 @@ impl Reply
             return Err("Buffer too short to contain full name".to_string());
         }
 
-        let full_name = Self::cstr_from_buf(&buf[offset..]);
-        offset += full_name.len() + 1;
+        let (full_name, next) = super::read_cstr(buf, offset);
+        offset = next;
 
         if offset >= buf.len()
         {
             return Err("Buffer too short to contain host target".to_string());
         }
 
-        let host_target = Self::cstr_from_buf(&buf[offset..]);
-        offset += host_target.len() + 1;
+        let (host_target, next) = super::read_cstr(buf, offset);
+        offset = next;
 
         if offset + 2 > buf.len()
         {
@@ -134,10 +129,12 @@ impl Reply
                 host_target,
                 port,
                 txt_data: Vec::new(),
+                txt_raw: &[],
             });
         }
 
-        let (txt_data, _) = unpack_txt(&buf[offset..(offset + actual_txt_len)], 0)?;
+        let txt_raw = &buf[offset..(offset + actual_txt_len)];
+        let (txt_data, _) = unpack_txt(txt_raw, 0)?;
 
         return Ok(Reply
         {
@@ -146,8 +143,31 @@ impl Reply
             host_target,
             port,
             txt_data,
+            txt_raw,
         })
     }
+
+    pub fn error(&self) -> Option<crate::DnsServiceError>
+    {
+        return crate::DnsServiceError::from_code(self.header.error as i32);
+    }
+
+    // The TXT record is written from `txt_raw`, `txt_data` is only its unpacked form.
+    #[cfg(any(all(unix, feature = "testing"), feature = "fuzzing"))]
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = self.header.to_bytes();
+
+        buf.extend_from_slice(self.full_name.as_bytes());
+        buf.push(0); // NUL-terminate
+        buf.extend_from_slice(self.host_target.as_bytes());
+        buf.push(0); // NUL-terminate
+        buf.extend_from_slice(&self.port.to_be_bytes());
+        buf.extend_from_slice(&(self.txt_raw.len() as u16).to_be_bytes());
+        buf.extend_from_slice(self.txt_raw);
+
+        return buf;
+    }
 }
 
 fn escape_byte(b: u8) -> &'static str
@@ -199,7 +219,7 @@ pub fn unpack_string(msg: &[u8], off: usize) -> Result<(String, usize), String>
                 s.push(b as char);
                 consumed = i + 1;
             }
-            b if b < b' ' || b > b'~' =>
+            b if !(b' '..=b'~').contains(&b) =>
             {
                 if consumed == 0
                 {
@@ -220,7 +240,7 @@ pub fn unpack_string(msg: &[u8], off: usize) -> Result<(String, usize), String>
     if consumed == 0
     {
         // no escaping needed
-        return Ok((String::from_utf8_lossy(&slice).to_string(), off + l));
+        return Ok((String::from_utf8_lossy(slice).to_string(), off + l));
     }
 
     s.push_str(&String::from_utf8_lossy(&slice[consumed..]));
@@ -0,0 +1,28 @@
+pub struct Request
+{
+    service_flags: u32,
+    domain: String,
+}
+
+impl Request
+{
+    pub fn new(service_flags: u32, domain: String) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            domain,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(self.domain.as_bytes());
+        buf.push(0); // NUL-terminate
+
+        return buf;
+    }
+}
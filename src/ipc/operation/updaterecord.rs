@@ -0,0 +1,38 @@
+// Registration index used to address the primary TXT record of a registered service.
+pub const TXT_RECORD_INDEX: u32 = u32::MAX;
+
+pub struct Request
+{
+    service_flags: u32,
+    rdata: Vec<u8>,
+    ttl: u32,
+}
+
+impl Request
+{
+    pub fn new(
+        service_flags: u32,
+        rdata: Vec<u8>,
+        ttl: u32,
+    ) -> Self
+    {
+        return Request
+        {
+            service_flags,
+            rdata,
+            ttl,
+        };
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        let mut buf = Vec::new();
+
+        buf.extend_from_slice(&self.service_flags.to_be_bytes());
+        buf.extend_from_slice(&(self.rdata.len() as u16).to_be_bytes());
+        buf.extend_from_slice(&self.rdata);
+        buf.extend_from_slice(&self.ttl.to_be_bytes());
+
+        return buf;
+    }
+}
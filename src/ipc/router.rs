@@ -0,0 +1,420 @@
+#[cfg(not(feature = "tracing"))]
+use log::debug;
+#[cfg(feature = "tracing")]
+use tracing::debug;
+use std::collections::{ HashMap, HashSet };
+use std::sync::{ Arc, Mutex };
+use std::sync::atomic::{ AtomicU64, Ordering };
+use std::time::Duration;
+use tokio::sync::{ broadcast, mpsc };
+
+use crate::{ runtime, MDnsResponderEvent, MetricsSink, OperationStats, OverflowPolicy };
+use super::channel::EventSender;
+use super::cache::ServiceCache;
+use super::debounce::Debounce;
+use super::dedup::Dedup;
+use super::expiry::Expiry;
+
+// Routes events to the channel of the operation that produced them, identified by the client
+// context of the reply. Events of operations without a dedicated channel go to the shared one.
+#[derive(Clone)]
+pub struct Router
+{
+    default: EventSender,
+    // Copies of all events for subscribers, see `MDnsRequester::subscribe`.
+    broadcast: broadcast::Sender<MDnsResponderEvent>,
+    routes: Arc<Mutex<HashMap<u64, EventSender>>>,
+    overflow_policy: OverflowPolicy,
+    // Events dropped because their channel was full, shared by all channels.
+    dropped: Arc<AtomicU64>,
+    dedup: Arc<Mutex<Dedup>>,
+    cache: Arc<Mutex<ServiceCache>>,
+    // Browse answers held back per operation while the daemon has more replies queued, `None`
+    // when batching is off.
+    batches: Option<Arc<Mutex<HashMap<u64, Batch>>>>,
+    // Stale answer tracking, `None` when expiry is off.
+    expiry: Option<Arc<Mutex<Expiry>>>,
+    // Browse removes held back to see if the service comes back, `None` when debouncing is off.
+    debounce: Option<Arc<Mutex<Debounce>>>,
+    // Browse operations for the service type meta-query, whose answers are service types.
+    service_type_enumerations: Arc<Mutex<HashSet<u64>>>,
+    // Span of each request that replies are recorded in.
+    #[cfg(feature = "tracing")]
+    spans: Arc<Mutex<HashMap<u64, tracing::Span>>>,
+    metrics: Arc<dyn MetricsSink>,
+    stats: Arc<Mutex<HashMap<u64, OperationStats>>>,
+}
+
+#[derive(Default)]
+struct Batch
+{
+    added: Vec<crate::Service>,
+    removed: Vec<crate::Service>,
+}
+
+impl Router
+{
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        default: mpsc::Sender<MDnsResponderEvent>,
+        dedup: Dedup,
+        batch_services: bool,
+        expire_records: bool,
+        debounce_window: Option<Duration>,
+        metrics: Arc<dyn MetricsSink>,
+        overflow_policy: OverflowPolicy,
+        broadcast_capacity: usize,
+    ) -> Self
+    {
+        let dropped = Arc::new(AtomicU64::new(0));
+
+        return Router
+        {
+            default: EventSender::new(default, overflow_policy, dropped.clone(), metrics.clone()),
+            broadcast: broadcast::channel(broadcast_capacity).0,
+            routes: Arc::new(Mutex::new(HashMap::new())),
+            overflow_policy,
+            dropped,
+            dedup: Arc::new(Mutex::new(dedup)),
+            cache: Arc::new(Mutex::new(ServiceCache::new())),
+            batches: batch_services.then(|| Arc::new(Mutex::new(HashMap::new()))),
+            expiry: expire_records.then(|| Arc::new(Mutex::new(Expiry::new()))),
+            debounce: debounce_window.map(|window| Arc::new(Mutex::new(Debounce::new(window)))),
+            service_type_enumerations: Arc::new(Mutex::new(HashSet::new())),
+            #[cfg(feature = "tracing")]
+            spans: Arc::new(Mutex::new(HashMap::new())),
+            metrics,
+            stats: Arc::new(Mutex::new(HashMap::new())),
+        };
+    }
+
+    pub fn metrics(&self) -> &dyn MetricsSink
+    {
+        return self.metrics.as_ref();
+    }
+
+    pub fn add_route(&self, context: u64, sender: mpsc::Sender<MDnsResponderEvent>)
+    {
+        let sender = EventSender::new(sender, self.overflow_policy, self.dropped.clone(), self.metrics.clone());
+        self.routes.lock().unwrap().insert(context, sender);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<MDnsResponderEvent>
+    {
+        return self.broadcast.subscribe();
+    }
+
+    pub fn dropped_events(&self) -> u64
+    {
+        return self.dropped.load(Ordering::Relaxed);
+    }
+
+    pub fn remove_route(&self, context: u64)
+    {
+        self.routes.lock().unwrap().remove(&context);
+        self.dedup.lock().unwrap().forget(context);
+        self.cache.lock().unwrap().forget(context);
+
+        if let Some(batches) = &self.batches
+        {
+            batches.lock().unwrap().remove(&context);
+        }
+
+        if let Some(expiry) = &self.expiry
+        {
+            expiry.lock().unwrap().forget(context);
+        }
+
+        if let Some(debounce) = &self.debounce
+        {
+            debounce.lock().unwrap().forget(context);
+        }
+
+        self.service_type_enumerations.lock().unwrap().remove(&context);
+        self.stats.lock().unwrap().remove(&context);
+
+        #[cfg(feature = "tracing")]
+        self.spans.lock().unwrap().remove(&context);
+    }
+
+    // Opens the span the replies to a request are recorded in, it is closed when the route is
+    // removed.
+    #[cfg(feature = "tracing")]
+    pub fn open_span(&self, context: u64, operation: crate::OperationKind, subject: &str)
+    {
+        let span = tracing::debug_span!("mdns_request", context, %operation, subject);
+        span.in_scope(|| debug!("Sending request"));
+        self.spans.lock().unwrap().insert(context, span);
+    }
+
+    #[cfg(feature = "tracing")]
+    pub fn span(&self, context: u64) -> tracing::Span
+    {
+        return self.spans.lock().unwrap().get(&context).cloned().unwrap_or_else(tracing::Span::none);
+    }
+
+    pub fn add_service_type_enumeration(&self, context: u64)
+    {
+        self.service_type_enumerations.lock().unwrap().insert(context);
+    }
+
+    pub fn is_service_type_enumeration(&self, context: u64) -> bool
+    {
+        return self.service_type_enumerations.lock().unwrap().contains(&context);
+    }
+
+    // Records a browse answer in the cache and returns whether it should be delivered according
+    // to the dedup policy.
+    pub fn admit_service(&self, context: u64, service: &crate::Service, add: bool) -> bool
+    {
+        self.cache.lock().unwrap().update(context, service, add);
+        return self.dedup.lock().unwrap().admit(context, service, add);
+    }
+
+    pub fn current_services(&self, context: u64) -> Vec<crate::Service>
+    {
+        return self.cache.lock().unwrap().services(context);
+    }
+
+    // Returns whether a browse answer should be passed on now, or is held back or swallowed to
+    // smooth over a service that flaps.
+    pub fn debounce_service(&self, context: u64, service: &crate::Service, add: bool) -> bool
+    {
+        return match &self.debounce
+        {
+            Some(debounce) => debounce.lock().unwrap().admit(context, service, add),
+            None => true,
+        };
+    }
+
+    // Waits until the window of the next held back remove has passed, forever if there is none.
+    pub async fn next_debounce(&self)
+    {
+        let deadline = self.debounce.as_ref().and_then(|debounce| debounce.lock().unwrap().next_deadline());
+
+        match deadline
+        {
+            Some(deadline) => runtime::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    pub async fn send_debounced(&self)
+    {
+        let due = match &self.debounce
+        {
+            Some(debounce) => debounce.lock().unwrap().take_due(),
+            None => return,
+        };
+
+        for (context, event) in due
+        {
+            if let Err(e) = self.send(context, event).await
+            {
+                debug!("Failed to send service removed notification: {}", e);
+            }
+        }
+    }
+
+    // Counts a reply for the operation it belongs to, given the buffer starting with the frame it
+    // arrives in. Frames that are not complete yet are not counted.
+    pub fn count_reply(&self, buf: &[u8])
+    {
+        let Ok(header) = super::header::IpcMessageHeader::from(buf) else
+        {
+            return;
+        };
+
+        if buf.len() < super::header::IPC_HEADER_SIZE + header.data_length as usize
+        {
+            return;
+        }
+
+        let mut stats = self.stats.lock().unwrap();
+        let stats = stats.entry(header.client_context).or_insert_with(OperationStats::new);
+        stats.replies += 1;
+        stats.last_activity = std::time::Instant::now();
+    }
+
+    pub fn stats(&self, context: u64) -> Option<OperationStats>
+    {
+        return self.stats.lock().unwrap().get(&context).copied();
+    }
+
+    // Holds back a browse answer until the burst it belongs to is complete. Hands the answer back
+    // if batching is off and it should be delivered right away.
+    pub fn batch_service(&self, context: u64, service: crate::Service, add: bool) -> Option<crate::Service>
+    {
+        let Some(batches) = &self.batches else
+        {
+            return Some(service);
+        };
+
+        let mut batches = batches.lock().unwrap();
+        let batch = batches.entry(context).or_default();
+
+        if add
+        {
+            batch.added.push(service);
+        }
+        else
+        {
+            batch.removed.push(service);
+        }
+
+        return None;
+    }
+
+    // Delivers all held back answers, called once the daemon has no more replies queued. The
+    // MoreComing flag covers all operations on the shared connection, so every batch is complete.
+    pub async fn flush_batches(&self)
+    {
+        let batches: Vec<(u64, Batch)> = match &self.batches
+        {
+            Some(batches) => batches.lock().unwrap().drain().collect(),
+            None => return,
+        };
+
+        for (context, batch) in batches
+        {
+            let event = MDnsResponderEvent::ServicesBatch
+            {
+                added: batch.added,
+                removed: batch.removed,
+            };
+
+            if let Err(e) = self.send(context, event).await
+            {
+                debug!("Failed to send services batch: {}", e);
+            }
+        }
+    }
+
+    pub fn track_address(&self, context: u64, address_info: &crate::AddressInfo)
+    {
+        if let Some(expiry) = &self.expiry
+        {
+            expiry.lock().unwrap().track_address(context, address_info);
+        }
+    }
+
+    pub fn track_record(&self, context: u64, record: &crate::Record, add: bool)
+    {
+        if let Some(expiry) = &self.expiry
+        {
+            expiry.lock().unwrap().track_record(context, record, add);
+        }
+    }
+
+    // Waits until the next tracked answer goes stale, forever if there is none.
+    pub async fn next_expiry(&self)
+    {
+        let deadline = self.expiry.as_ref().and_then(|expiry| expiry.lock().unwrap().next_deadline());
+
+        match deadline
+        {
+            Some(deadline) => runtime::sleep_until(deadline).await,
+            None => std::future::pending().await,
+        }
+    }
+
+    pub async fn send_expired(&self)
+    {
+        let expired = match &self.expiry
+        {
+            Some(expiry) => expiry.lock().unwrap().take_expired(),
+            None => return,
+        };
+
+        for (context, event) in expired
+        {
+            if let Err(e) = self.send(context, event).await
+            {
+                debug!("Failed to send expiry notification: {}", e);
+            }
+        }
+    }
+
+    // Forgets all answers, the daemon answers again from scratch after a reconnect.
+    pub fn reset_answers(&self)
+    {
+        self.dedup.lock().unwrap().clear();
+        self.cache.lock().unwrap().clear();
+
+        if let Some(expiry) = &self.expiry
+        {
+            expiry.lock().unwrap().clear();
+        }
+
+        if let Some(debounce) = &self.debounce
+        {
+            debounce.lock().unwrap().clear();
+        }
+    }
+
+    pub async fn send(
+        &self,
+        context: u64,
+        event: MDnsResponderEvent,
+    ) -> Result<(), mpsc::error::SendError<MDnsResponderEvent>>
+    {
+        self.stats.lock().unwrap().entry(context).or_insert_with(OperationStats::new).count_event(&event);
+        self.publish(&event);
+
+        let route = self.routes.lock().unwrap().get(&context).cloned();
+
+        match route
+        {
+            Some(sender) =>
+            {
+                if sender.send(event).await.is_err()
+                {
+                    // The operation handle was dropped, nobody is interested in its events anymore.
+                    debug!("Receiver for operation {} dropped, removing route", context);
+                    self.metrics.event_dropped();
+                    self.remove_route(context);
+                }
+
+                return Ok(());
+            }
+            None =>
+            {
+                return self.send_default(event).await;
+            }
+        }
+    }
+
+    // Sends an event that does not belong to a single operation on the shared channel.
+    pub async fn send_shared(
+        &self,
+        event: MDnsResponderEvent,
+    ) -> Result<(), mpsc::error::SendError<MDnsResponderEvent>>
+    {
+        self.publish(&event);
+        return self.send_default(event).await;
+    }
+
+    // Copies an event to the subscribers, if there are any. A subscriber that falls behind misses
+    // the oldest events rather than holding up delivery.
+    fn publish(&self, event: &MDnsResponderEvent)
+    {
+        if self.broadcast.receiver_count() > 0
+        {
+            let _ = self.broadcast.send(event.clone());
+        }
+    }
+
+    async fn send_default(
+        &self,
+        event: MDnsResponderEvent,
+    ) -> Result<(), mpsc::error::SendError<MDnsResponderEvent>>
+    {
+        let result = self.default.send(event).await;
+        if result.is_err()
+        {
+            self.metrics.event_dropped();
+        }
+
+        return result;
+    }
+}
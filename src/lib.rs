@@ -1,47 +1,497 @@
-use std::net::IpAddr;
+use std::hash::{ Hash, Hasher };
+use std::net::{ IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, SocketAddrV6 };
+use std::ops::Deref;
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicU32, Ordering };
+use std::time::{ Duration, Instant };
 
 use log::error;
-use tokio::sync::mpsc;
+use tokio::sync::{ mpsc, watch };
 
+mod active_operation;
+#[cfg(unix)]
+mod backend;
+#[cfg(feature = "uniffi")]
+pub mod bindings;
+#[cfg(feature = "tokio-runtime")]
+pub mod blocking;
+mod builder;
+pub mod capture;
+mod close_report;
+mod dedup_policy;
+mod discover;
+pub mod dns_name;
+mod event_stream;
+mod events;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+mod interface;
 mod ipc;
 mod mdnsresponder_error;
+mod metrics;
+#[cfg(all(unix, feature = "multicast-fallback"))]
+pub mod multicast;
+mod options;
+mod overflow_policy;
+mod operation;
+mod operation_stats;
+mod reconnect_policy;
+mod record_registration;
+mod runtime;
+mod service_registration;
+mod service_type;
+pub mod service_types;
+#[cfg(all(unix, feature = "testing"))]
+pub mod testing;
+mod transport;
+mod txt_record;
 
+pub use active_operation::{ ActiveOperation, OperationKind };
+#[cfg(unix)]
+pub use backend::Backend;
+pub use builder::MDnsResponderBuilder;
+pub use close_report::CloseReport;
+pub use dedup_policy::DedupPolicy;
+pub use discover::DiscoveredService;
+pub use event_stream::{ EventStream, EventStreamExt, FilterEvents };
+pub use events::MDnsEvents;
+pub use interface::Interface;
+pub use mdnsresponder_error::DnsServiceError;
 pub use mdnsresponder_error::MDnsResponderError;
+pub use metrics::{ MetricsSink, NoopMetrics };
+pub use operation::Operation;
+pub use operation_stats::OperationStats;
+pub use options::{ AddrInfoOptions, BrowseOptions, QueryOptions, RegisterOptions, ResolveOptions };
+pub use overflow_policy::OverflowPolicy;
+pub use reconnect_policy::ReconnectPolicy;
+pub use record_registration::RecordRegistration;
+pub use service_registration::ServiceRegistration;
+pub use service_type::{ ServiceProtocol, ServiceType };
+pub use transport::{ BONJOUR_TCP_PORT, ConnectFuture, Connection, Connector, Transport };
+pub use txt_record::{ TxtRecord, TxtRecordBuilder };
 
-#[derive(Debug)]
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!("mdnsresponder");
+
+const RRTYPE_PTR: u16 = 12;
+const RRTYPE_SRV: u16 = 33;
+const RRCLASS_IN: u16 = 1;
+
+/// Flags the daemon sent with a reply.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ReplyFlags
+{
+    /// The answer was added, it was removed if not set.
+    pub add: bool,
+    /// The daemon has more replies queued that will follow immediately.
+    pub more_coming: bool,
+    /// The number of answers reached the threshold set with `BrowseOptions::threshold_one` or
+    /// `BrowseOptions::threshold_finder`.
+    pub threshold_reached: bool,
+    /// The answer is an expired one from the cache, delivered while the daemon looks for a fresh
+    /// one. Only set for lookups that allow expired answers.
+    pub expired: bool,
+    /// The DNSSEC validation result, only set for lookups with validation enabled.
+    pub dnssec: Option<DnssecStatus>,
+}
+
+/// Result of DNSSEC validation of an answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DnssecStatus
+{
+    /// The answer has a valid chain of trust.
+    Secure,
+    /// The answer is provably not signed.
+    Insecure,
+    /// Validation failed, the answer must not be trusted.
+    Bogus,
+    /// The validation could not be completed.
+    Indeterminate,
+}
+
+/// A service instance found by browsing.
+///
+/// Services are equal if they are the same instance found on the same interface: the name,
+/// service type, domain and interface index match. Names are compared as DNS names, ignoring
+/// ASCII case, escaping and a final `.`, so the type `_ipp._tcp` equals `_IPP._tcp.` and the
+/// domain `my\032domain` equals `my domain`. The flags, interface name and resolve answer are
+/// not compared, so the add and the remove of a service are equal and a `HashSet<Service>` can
+/// track the services that are present.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Service
 {
+    /// The instance name as the daemon reported it, which may contain DNS escapes such as
+    /// `My\032Printer`. Pass it to `resolve` as it is.
     pub name: String,
+    /// The instance name with DNS escapes undone, e.g. `My Printer`, for showing to users. The
+    /// same as `name` if that has no valid escapes.
+    pub display_name: String,
     pub service_type: String,
     pub domain: String,
+    /// Index of the interface the answer was received on.
+    pub interface_index: u32,
+    /// Name of the interface the answer was received on, e.g. "en0", if it has one.
+    pub interface_name: Option<String>,
+    pub flags: ReplyFlags,
+    /// The first resolve answer for an added service when browsing with
+    /// `BrowseOptions::auto_resolve`, `None` otherwise or if the resolve failed.
+    pub resolved: Option<Box<Resolved>>,
 }
 
-#[derive(Debug)]
+/// The answer to a resolve.
+///
+/// Answers are equal if the full name, host, port, TXT record data and interface index match.
+/// Names are compared as DNS names, like for `Service`. The flags and the interface name are not
+/// compared.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Resolved
 {
     pub full_name: String,
     pub host_target: String,
     pub port: u16,
+    /// TXT strings with non-printable bytes escaped as `\DDD`.
     pub txt_data: Vec<String>,
+    /// TXT attributes parsed as `key=value` pairs.
+    pub txt: TxtRecord,
+    /// TXT record data as received: length-prefixed strings, for values that are binary.
+    pub txt_raw: Vec<u8>,
+    /// Index of the interface the answer was received on.
+    pub interface_index: u32,
+    /// Name of the interface the answer was received on, e.g. "en0", if it has one.
+    pub interface_name: Option<String>,
+    pub flags: ReplyFlags,
 }
 
-#[derive(Debug)]
+/// A service type found by `MDnsRequester::enumerate_service_types`.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DiscoveredServiceType
+{
+    /// The service type, e.g. "_http._tcp".
+    pub service_type: String,
+    pub domain: String,
+    /// Index of the interface the answer was received on.
+    pub interface_index: u32,
+    /// Name of the interface the answer was received on, e.g. "en0", if it has one.
+    pub interface_name: Option<String>,
+    pub flags: ReplyFlags,
+}
+
+/// An address of a host.
+///
+/// Addresses are equal if the hostname, address and interface index match. The hostname is
+/// compared as a DNS name, like for `Service`. The TTL, expiry and flags are not compared, so a
+/// refreshed address equals the one it refreshes.
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct AddressInfo
 {
     pub hostname: String,
     pub address: IpAddr,
+    /// Time to live of the answer in seconds, as sent by the daemon.
+    pub ttl: u32,
+    /// When the TTL runs out, counted from when the reply was received. The daemon normally
+    /// refreshes or removes the address before then. Not serialized, as an `Instant` has no
+    /// meaning outside the process, and set to the time of deserialization instead.
+    #[cfg_attr(feature = "serde", serde(skip, default = "Instant::now"))]
+    pub expires_at: Instant,
+    /// Index of the interface the answer was received on.
+    pub interface_index: u32,
+    /// Name of the interface the answer was received on, e.g. "en0", if it has one.
+    pub interface_name: Option<String>,
+    pub flags: ReplyFlags,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Record
+{
+    pub name: String,
+    pub rrtype: u16,
+    pub rrclass: u16,
+    pub rdata: Vec<u8>,
+    pub ttl: u32,
+    pub flags: ReplyFlags,
+}
+
+impl Service
+{
+    fn key(&self) -> (String, Vec<String>, Vec<String>, u32)
+    {
+        // Compared unescaped, so that `Printer\.1` and `Printer\0461` are the same instance.
+        let name = dns_name::unescape_label(&self.name).unwrap_or_else(|| self.name.clone());
+
+        return (
+            name.to_ascii_lowercase(),
+            dns_name::canonical_name(&self.service_type),
+            dns_name::canonical_name(&self.domain),
+            self.interface_index,
+        );
+    }
+}
+
+impl PartialEq for Service
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        return self.key() == other.key();
+    }
+}
+
+impl Eq for Service {}
+
+impl Hash for Service
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.key().hash(state);
+    }
+}
+
+impl Resolved
+{
+    fn key(&self) -> (Vec<String>, Vec<String>, u16, &[u8], u32)
+    {
+        return (
+            dns_name::canonical_name(&self.full_name),
+            dns_name::canonical_name(&self.host_target),
+            self.port,
+            &self.txt_raw,
+            self.interface_index,
+        );
+    }
+}
+
+impl PartialEq for Resolved
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        return self.key() == other.key();
+    }
+}
+
+impl Eq for Resolved {}
+
+impl Hash for Resolved
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.key().hash(state);
+    }
+}
+
+impl PartialEq for AddressInfo
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        return self.key() == other.key();
+    }
+}
+
+impl Eq for AddressInfo {}
+
+impl Hash for AddressInfo
+{
+    fn hash<H: Hasher>(&self, state: &mut H)
+    {
+        self.key().hash(state);
+    }
+}
+
+impl AddressInfo
+{
+    fn key(&self) -> (Vec<String>, IpAddr, u32)
+    {
+        return (dns_name::canonical_name(&self.hostname), self.address, self.interface_index);
+    }
+
+    /// Scope id to connect to the address with, the index of the interface it was found on for
+    /// link-local IPv6 addresses (fe80::/10) and 0 for all other addresses.
+    pub fn scope_id(&self) -> u32
+    {
+        return match self.address
+        {
+            IpAddr::V6(address) if address.segments()[0] & 0xffc0 == 0xfe80 => self.interface_index,
+            _ => 0,
+        };
+    }
+
+    /// Socket address to connect to the given port on, with the scope id set for link-local IPv6
+    /// addresses.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder, MDnsResponderEvent, Protocol};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut lookup = responder.get_addr_info(Interface::Any, "printer.local".to_string(), Protocol::IPv6).await?;
+    ///     if let Some(MDnsResponderEvent::AddressInfoResolved(info)) = lookup.events.recv().await {
+    ///         let stream = tokio::net::TcpStream::connect(info.socket_addr(631)).await?;
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn socket_addr(&self, port: u16) -> SocketAddr
+    {
+        return match self.address
+        {
+            IpAddr::V4(address) => SocketAddr::V4(SocketAddrV4::new(address, port)),
+            IpAddr::V6(address) => SocketAddr::V6(SocketAddrV6::new(address, port, 0, self.scope_id())),
+        };
+    }
+}
+
+/// Data of an SRV record, see `Record::srv`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Srv
+{
+    /// Lower values are preferred, targets with the same priority are chosen by weight.
+    pub priority: u16,
+    /// Relative weight among targets with the same priority.
+    pub weight: u16,
+    pub port: u16,
+    /// Hostname of the target (e.g., "myhost.local.").
+    pub target: String,
+}
+
+impl Record
+{
+    /// Decodes the data of an SRV record. Returns `None` for other record types or malformed
+    /// data.
+    pub fn srv(&self) -> Option<Srv>
+    {
+        if self.rrtype != RRTYPE_SRV || self.rdata.len() < 7
+        {
+            return None;
+        }
+
+        return Some(Srv
+        {
+            priority: u16::from_be_bytes([self.rdata[0], self.rdata[1]]),
+            weight: u16::from_be_bytes([self.rdata[2], self.rdata[3]]),
+            port: u16::from_be_bytes([self.rdata[4], self.rdata[5]]),
+            target: dns_name::from_wire(&self.rdata[6..])?,
+        });
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Domain
+{
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PortMapping
+{
+    pub external_address: Ipv4Addr,
+    pub protocol: Option<PortMappingProtocol>,
+    pub internal_port: u16,
+    pub external_port: u16,
+    pub ttl: u32,
 }
 
-#[derive(Debug)]
+/// IPC header of a frame received from mDNSResponder, see `MDnsResponderEvent::RawFrame`.
+#[cfg(feature = "debug-frames")]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FrameHeader
+{
+    pub version: u32,
+    pub data_length: u32,
+    pub ipc_flags: u32,
+    pub operation: u32,
+    pub client_context: u64,
+    pub reg_index: u32,
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum MDnsResponderEvent
 {
     ServiceAdded(Service),
     ServiceRemoved(Service),
+    /// Browse answers received in one burst, delivered instead of `ServiceAdded` and
+    /// `ServiceRemoved` when `MDnsResponderBuilder::batch_services` is enabled.
+    ServicesBatch { added: Vec<Service>, removed: Vec<Service> },
     ServiceResolved(Resolved),
+    /// A service found by `MDnsRequester::discover`, with its host, port, TXT record and
+    /// addresses.
+    ServiceDiscovered(DiscoveredService),
+    ServiceTypeDiscovered(DiscoveredServiceType),
+    ServiceTypeRemoved(DiscoveredServiceType),
     AddressInfoResolved(AddressInfo),
+    /// An address previously reported with `AddressInfoResolved` is no longer valid.
+    AddressInfoRemoved(AddressInfo),
+    /// The hostname of an address lookup definitely has no address of the given family.
+    NoSuchRecord { hostname: String, protocol: Protocol },
+    ServiceRegistered(Service),
+    NameConflict(Service),
+    RecordAdded(Record),
+    RecordRemoved(Record),
+    /// The TTL of an address went by without the daemon refreshing it, only sent when
+    /// `MDnsResponderBuilder::expire_records` is enabled.
+    AddressExpired(AddressInfo),
+    /// The TTL of a record went by without the daemon refreshing it, only sent when
+    /// `MDnsResponderBuilder::expire_records` is enabled.
+    RecordExpired(Record),
+    DomainAdded(Domain),
+    DomainRemoved(Domain),
+    PortMappingCreated(PortMapping),
+    RecordRegistered { context: u64, record_index: u32 },
+    OperationFailed { context: u64, error: DnsServiceError },
+    /// A browse started with a threshold found enough services, sent after the answer that
+    /// reached it. The daemon stops sending queries but keeps reporting answers.
+    ThresholdReached { context: u64 },
+    /// A query or address lookup started with the timeout option got no answer in time. The
+    /// daemon stops looking, the operation should still be cancelled.
+    Timeout { context: u64 },
+    ConnectionLost,
+    /// The connection was set up again after `ConnectionLost`, on the given transport, e.g. the
+    /// socket path a `Backend` found. `None` for connectors that are not one of the built-in
+    /// transports.
+    Reconnected { transport: Option<Transport> },
+    DaemonRestarted,
+    /// The daemon did not answer a keepalive request in time although the connection is still
+    /// open, only sent when `MDnsResponderBuilder::keepalive` is enabled.
+    DaemonUnresponsive,
+    /// The daemon answers keepalive requests again after `DaemonUnresponsive`.
+    DaemonResponsive,
+    /// A reply with an operation code the parser does not handle, e.g. GetProperty or one
+    /// added in a newer daemon. It is skipped and the replies after it are parsed as usual.
+    UnhandledReply { context: u64, operation: u32 },
+    /// A frame as received, sent before the frame is parsed, including frames the parser does
+    /// not understand.
+    #[cfg(feature = "debug-frames")]
+    RawFrame { header: FrameHeader, payload: Vec<u8> },
+}
+
+/// State of the connection to mDNSResponder, see `MDnsRequester::connection_state`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState
+{
+    Connected,
+    /// The connection was lost and is being set up again according to the `ReconnectPolicy`.
+    Reconnecting,
+    /// The connection was closed, or lost without a `ReconnectPolicy`.
+    Disconnected,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Protocol
 {
     IPv4,
@@ -49,66 +499,1368 @@ pub enum Protocol
     Both,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PortMappingProtocol
+{
+    Udp,
+    Tcp,
+}
+
+impl PortMappingProtocol
+{
+    fn from_u8(value: u8) -> Option<Self>
+    {
+        return match value
+        {
+            0x10 => Some(PortMappingProtocol::Udp),
+            0x20 => Some(PortMappingProtocol::Tcp),
+            _ => None,
+        };
+    }
+}
+
+/// Identity of the process on whose behalf a delegate connection issues requests.
+///
+/// The daemon takes the user ID and, on Apple platforms, the audit token of a connection from
+/// the credentials of the connecting socket, there are no fields in the protocol to set them.
+/// A delegate is the only identity a client can state: the process ID is sent in the delegate
+/// request and, on Apple platforms, set on the socket as well, where the daemon checks that the
+/// two match. The protocol has no field for a UUID, it can only be set on the socket, so
+/// connecting with one fails with `MDnsResponderError::DelegateNotSupported` elsewhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delegate
+{
+    Pid(i32),
+    Uuid([u8; 16]),
+}
+
+/// Connection to mDNSResponder together with the channel its shared events are delivered on.
+///
+/// All request methods are available through `Deref` to `MDnsRequester`. Use `split` or
+/// `requester` to issue requests from other tasks.
 pub struct MDnsResponder
 {
-    ipc: ipc::Ipc,
+    requester: MDnsRequester,
     pub events: mpsc::Receiver<MDnsResponderEvent>,
 }
 
-impl MDnsResponder
-{
-    /// Creates a new instance of `MDnsResponder` with the specified channel buffer size.
+/// Cheaply cloneable handle for issuing requests on a `MDnsResponder` connection from any task.
+#[derive(Clone)]
+pub struct MDnsRequester
+{
+    ipc: Arc<ipc::Ipc>,
+    next_record_index: Arc<AtomicU32>,
+    operation_buffer_size: usize,
+}
+
+impl MDnsResponder
+{
+    /// Creates a new instance of `MDnsResponder` with the specified channel buffer size.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_buffer_size` - The size of the buffer for the internal channels. Must be greater than zero.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::ChannelCreationFailed)` if the buffer size is zero.
+    /// Returns `Err(MDnsResponderError::IpcConnectionCreationFailed)` if IPC creation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn new(
+        channel_buffer_size: usize,
+    ) -> Result<Self, mdnsresponder_error::MDnsResponderError>
+    {
+        return Self::builder()
+            .channel_buffer_size(channel_buffer_size)
+            .operation_buffer_size(channel_buffer_size)
+            .build()
+            .await;
+    }
+
+    /// Creates a new instance of `MDnsResponder` that issues all requests on behalf of another
+    /// process, identified either by its PID or by its UUID.
+    ///
+    /// This is used by helper processes in sandboxed architectures, and requires the calling
+    /// process to be entitled to act as a delegate. The delegate identity is attached to the
+    /// socket only on Apple platforms, elsewhere only a PID can be sent.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_buffer_size` - The size of the buffer for the internal channels. Must be greater than zero.
+    /// * `delegate` - The identity of the process the requests are issued for.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::ChannelCreationFailed)` if the buffer size is zero.
+    /// Returns `Err(MDnsResponderError::DelegateNotSupported)` for a `Delegate::Uuid` anywhere but
+    /// on a Unix socket on Apple platforms.
+    /// Returns `Err(MDnsResponderError::IpcConnectionCreationFailed)` if IPC creation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Delegate, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new_delegate(10, Delegate::Pid(1234)).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn new_delegate(
+        channel_buffer_size: usize,
+        delegate: Delegate,
+    ) -> Result<Self, mdnsresponder_error::MDnsResponderError>
+    {
+        return Self::builder()
+            .channel_buffer_size(channel_buffer_size)
+            .operation_buffer_size(channel_buffer_size)
+            .delegate(delegate)
+            .build()
+            .await;
+    }
+
+    /// Creates a new instance of `MDnsResponder` that reconnects to mDNSResponder when the
+    /// connection is lost, for example because the daemon restarted.
+    ///
+    /// After reconnecting, all browse, resolve, address info, query, enumeration, port mapping
+    /// and registration requests that were not cancelled are issued again. Their context
+    /// identifiers, `Operation` handles and registration handles stay valid.
+    ///
+    /// # Arguments
+    ///
+    /// * `channel_buffer_size` - The size of the buffer for the internal channels. Must be greater than zero.
+    /// * `policy` - How often and for how long to try reconnecting.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::ChannelCreationFailed)` if the buffer size is zero.
+    /// Returns `Err(MDnsResponderError::IpcConnectionCreationFailed)` if IPC creation fails.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{MDnsResponder, ReconnectPolicy};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::with_reconnect(10, ReconnectPolicy::default()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn with_reconnect(
+        channel_buffer_size: usize,
+        policy: ReconnectPolicy,
+    ) -> Result<Self, mdnsresponder_error::MDnsResponderError>
+    {
+        return Self::builder()
+            .channel_buffer_size(channel_buffer_size)
+            .operation_buffer_size(channel_buffer_size)
+            .reconnect_policy(policy)
+            .build()
+            .await;
+    }
+
+    /// Returns a builder for a `MDnsResponder` with a custom socket path, buffer sizes,
+    /// reconnect policy or delegate.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::builder().socket_path("/tmp/mdnsd.sock").build().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn builder() -> MDnsResponderBuilder
+    {
+        return MDnsResponderBuilder::new();
+    }
+
+    pub(crate) async fn create(
+        mut config: MDnsResponderBuilder,
+    ) -> Result<Self, mdnsresponder_error::MDnsResponderError>
+    {
+        if config.channel_buffer_size == 0
+            || config.operation_buffer_size == 0
+            || config.broadcast_capacity == 0
+            || config.read_buffer_size == 0
+        {
+            error!("Channel and read buffer sizes must be greater than zero");
+            return Err(mdnsresponder_error::MDnsResponderError::ChannelCreationFailed);
+        }
+
+        #[cfg(all(unix, feature = "multicast-fallback"))]
+        if config.multicast_fallback
+        {
+            config.connector = Arc::new(multicast::FallbackConnector::wrap(config.connector));
+        }
+
+        if let Some(capture_path) = config.capture_path.take()
+        {
+            config.connector = match capture::CaptureConnector::wrap(config.connector, &capture_path)
+            {
+                Ok(connector) => Arc::new(connector),
+                Err(e) =>
+                {
+                    error!("Failed to create IPC capture file {}: {}", capture_path.display(), e);
+                    return Err(mdnsresponder_error::MDnsResponderError::IpcConnectionCreationFailed);
+                }
+            };
+        }
+
+        let (events_sender, events_receiver) = mpsc::channel(config.channel_buffer_size);
+
+        let ipc = match ipc::Ipc::new(events_sender, &config).await
+        {
+            Ok(ipc) => ipc,
+            Err(e) =>
+            {
+                error!("Failed to create IPC: {}", e);
+                return Err(mdnsresponder_error::MDnsResponderError::from_connect_error(&e));
+            }
+        };
+
+        return Ok(MDnsResponder
+        {
+            requester: MDnsRequester
+            {
+                ipc: Arc::new(ipc),
+                next_record_index: Arc::new(AtomicU32::new(0)),
+                operation_buffer_size: config.operation_buffer_size,
+            },
+            events: events_receiver,
+        });
+    }
+
+    /// Returns a `Stream` over the shared `events` channel, for events of operations that are not
+    /// delivered on their own `Operation` handle.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use futures::StreamExt;
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut responder = MDnsResponder::new(10).await?;
+    ///     responder.enumerate_browse_domains().await?;
+    ///     let mut events = responder.event_stream();
+    ///     while let Some(event) = events.next().await {
+    ///         println!("{:?}", event);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn event_stream(&mut self) -> EventStream<'_>
+    {
+        return EventStream::new(&mut self.events);
+    }
+
+    /// Returns the next event on the shared `events` channel if one is waiting, without waiting
+    /// for one. Returns `None` if there is none or the connection is closed.
+    ///
+    /// Meant for loops that cannot await, e.g. polling once per frame.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut responder = MDnsResponder::new(10).await?;
+    ///     responder.enumerate_browse_domains().await?;
+    ///     while let Some(event) = responder.try_next_event() {
+    ///         println!("{:?}", event);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn try_next_event(&mut self) -> Option<MDnsResponderEvent>
+    {
+        return self.events.try_recv().ok();
+    }
+
+    /// Receives the next event on the shared `events` channel, waiting at most `timeout` for it.
+    /// Returns `Ok(None)` once the connection is closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::Timeout)` if no event arrived in time.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let mut responder = MDnsResponder::new(10).await?;
+    ///     responder.enumerate_browse_domains().await?;
+    ///     while let Ok(Some(event)) = responder.recv_timeout(Duration::from_secs(2)).await {
+    ///         println!("{:?}", event);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn recv_timeout(&mut self, timeout: Duration) -> Result<Option<MDnsResponderEvent>, mdnsresponder_error::MDnsResponderError>
+    {
+        return events::recv_deadline(&mut self.events, Instant::now() + timeout).await;
+    }
+
+    /// Receives the next event on the shared `events` channel, waiting for it until `deadline`.
+    /// Returns `Ok(None)` once the connection is closed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::Timeout)` if no event arrived before the deadline.
+    pub async fn recv_deadline(&mut self, deadline: Instant) -> Result<Option<MDnsResponderEvent>, mdnsresponder_error::MDnsResponderError>
+    {
+        return events::recv_deadline(&mut self.events, deadline).await;
+    }
+
+    /// Closes the `MDnsResponder` instance, releasing any associated resources. See
+    /// `MDnsRequester::close`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     responder.close().await;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn close(self) -> CloseReport
+    {
+        return self.requester.close().await;
+    }
+
+    /// Returns a new handle for issuing requests on this connection.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let requester = responder.requester();
+    ///     tokio::spawn(async move {
+    ///         requester.enumerate_browse_domains().await
+    ///     });
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn requester(&self) -> MDnsRequester
+    {
+        return self.requester.clone();
+    }
+
+    /// Splits the `MDnsResponder` into a cloneable requester and the receiver of its shared events,
+    /// so that both can be moved to different tasks.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (requester, mut events) = MDnsResponder::new(10).await?.split();
+    ///     tokio::spawn(async move {
+    ///         while let Some(event) = events.recv().await {
+    ///             println!("{:?}", event);
+    ///         }
+    ///     });
+    ///     requester.enumerate_browse_domains().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn split(self) -> (MDnsRequester, MDnsEvents)
+    {
+        return (self.requester, MDnsEvents::new(self.events));
+    }
+}
+
+impl Deref for MDnsResponder
+{
+    type Target = MDnsRequester;
+
+    fn deref(&self) -> &MDnsRequester
+    {
+        return &self.requester;
+    }
+}
+
+impl MDnsRequester
+{
+    /// Closes the connection to mDNSResponder for all handles sharing it. Outstanding operations
+    /// are cancelled and no further events are delivered.
+    ///
+    /// Requests still queued are written before the cancels, and the listener task is given a
+    /// moment to stop before it is aborted. The returned report tells which operations could not
+    /// be cancelled and whether the listener stopped.
+    ///
+    /// Once the last handle sharing the connection is dropped without a `close`, the outstanding
+    /// operations are still cancelled and the listener stopped, but without waiting for either.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (requester, events) = MDnsResponder::new(10).await?.split();
+    ///     let report = requester.close().await;
+    ///     if !report.not_cancelled.is_empty() {
+    ///         eprintln!("{} operations were not cancelled", report.not_cancelled.len());
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn close(&self) -> CloseReport
+    {
+        return self.ipc.close().await;
+    }
+
+    /// Returns a receiver that is updated whenever the state of the connection to mDNSResponder
+    /// changes. Changes are also reported as `ConnectionLost`, `Reconnected` and
+    /// `DaemonRestarted` events on the shared events channel.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{ConnectionState, MDnsResponder, ReconnectPolicy};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::with_reconnect(10, ReconnectPolicy::default()).await?;
+    ///     let mut state = responder.connection_state();
+    ///     while state.changed().await.is_ok() {
+    ///         if *state.borrow() == ConnectionState::Reconnecting {
+    ///             println!("Discovery is degraded");
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn connection_state(&self) -> watch::Receiver<ConnectionState>
+    {
+        return self.ipc.connection_state();
+    }
+
+    /// Returns the transport the connection to mDNSResponder is open on, e.g. the socket path a
+    /// `Backend` found, updated on every reconnect. `None` for connectors that are not one of the
+    /// built-in transports.
+    pub fn transport(&self) -> Option<Transport>
+    {
+        return self.ipc.transport();
+    }
+
+    /// Returns the services currently known to a browse operation: every service that was added
+    /// and not removed since, once for each interface it was seen on. Empty if the operation is
+    /// unknown or was cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context identifier of the browse operation, e.g. `Operation::context`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    ///     for service in responder.current_services(browse.context()) {
+    ///         println!("{} on interface {}", service.name, service.interface_index);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn current_services(&self, context: u64) -> Vec<Service>
+    {
+        return self.ipc.current_services(context);
+    }
+
+    /// Returns the counters of an operation: replies received, answers added and removed,
+    /// errors, and when it was last active. `None` if nothing was received for the operation yet,
+    /// or it was cancelled.
+    ///
+    /// # Arguments
+    ///
+    /// * `context` - The context identifier of the operation, e.g. `Operation::context`.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    ///     if let Some(stats) = responder.stats(browse.context()) {
+    ///         println!("{} replies, last at {:?}", stats.replies, stats.last_activity);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn stats(&self, context: u64) -> Option<OperationStats>
+    {
+        return self.ipc.stats(context);
+    }
+
+    /// Returns the operations that were started on this connection and not cancelled yet, oldest
+    /// first, e.g. to find operations that were never cancelled.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     for operation in responder.active_operations() {
+    ///         println!("{} {} for {} since {:?}", operation.context, operation.kind, operation.subject, operation.started);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn active_operations(&self) -> Vec<ActiveOperation>
+    {
+        return self.ipc.active_operations();
+    }
+
+    /// Subscribes to copies of all events of this connection, those of `Operation` handles as
+    /// well as the shared ones, so several parts of an application can each observe them.
+    ///
+    /// Events are still delivered as usual, subscribing only adds copies. A subscriber that falls
+    /// more than `MDnsResponderBuilder::broadcast_capacity` events behind misses the oldest ones
+    /// and gets `RecvError::Lagged` instead. Only events produced after subscribing are received.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut logger = responder.subscribe();
+    ///     tokio::spawn(async move {
+    ///         while let Ok(event) = logger.recv().await {
+    ///             println!("{:?}", event);
+    ///         }
+    ///     });
+    ///     let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<MDnsResponderEvent>
+    {
+        return self.ipc.subscribe();
+    }
+
+    /// Returns how many events were dropped because the channel they were sent on was full,
+    /// with the `OverflowPolicy::DropOldest` or `OverflowPolicy::DropNewest` policy.
+    pub fn dropped_events(&self) -> u64
+    {
+        return self.ipc.dropped_events();
+    }
+
+    /// Starts browsing for services of the specified type and domain.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to browse on, `Interface::Any` for all interfaces.
+    /// * `service_type` - The type of service to browse for (e.g., "_http._tcp"), or a checked `ServiceType`.
+    /// * `service_domain` - The domain in which to browse for the service (e.g., "local").
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives the events of this browse request only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     while let Some(event) = browse.events.recv().await {
+    ///         println!("{:?}", event);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn browse(
+        &self,
+        interface: Interface,
+        service_type: impl Into<String>,
+        service_domain: String
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        let options = BrowseOptions::new().interface(interface).domain(service_domain);
+        return self.browse_with_options(service_type, options).await;
+    }
+
+    /// Starts browsing for services of the specified type with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_type` - The type of service to browse for (e.g., "_http._tcp"), or a checked `ServiceType`.
+    /// * `options` - The interface, domain, subtype and flags to browse with.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives the events of this browse request only.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the service type is malformed, see `ServiceType`.
+    /// Returns `Err(MDnsResponderError::InvalidSubtype)` if the subtype is empty or longer than 63 bytes,
+    /// or both the service type and the options have a subtype.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{BrowseOptions, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut browse = responder.browse_with_options("_airplay._tcp".to_string(), BrowseOptions::new().include_awdl(true)).await?;
+    ///     while let Some(event) = browse.events.recv().await {
+    ///         println!("{:?}", event);
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn browse_with_options(
+        &self,
+        service_type: impl Into<String>,
+        options: BrowseOptions,
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        let browse_type = options.browse_type(service_type.into().parse()?)?;
+        let auto_resolve = options.auto_resolve;
+        let (events_sender, events_receiver) = mpsc::channel(self.operation_buffer_size);
+
+        return match self
+            .ipc
+            .write_browse_request(
+                options.service_flags(),
+                options.interface.index(),
+                browse_type,
+                options.domain,
+                events_sender,
+                options.acknowledge,
+            )
+            .await
+        {
+            Ok(context) if auto_resolve =>
+            {
+                let (resolved_sender, resolved_receiver) = mpsc::channel(self.operation_buffer_size);
+                let browse = Operation::new(context, events_receiver, self.ipc.writer());
+
+                runtime::spawn_detached(discover::auto_resolve(self.clone(), browse, resolved_sender));
+
+                Ok(Operation::new(context, resolved_receiver, self.ipc.writer()))
+            }
+            Ok(context) => Ok(Operation::new(context, events_receiver, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Starts discovering services of the specified type in the default browse domains. Every
+    /// service found is resolved and its addresses looked up before it is reported.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_type` - The type of service to discover (e.g., "_http._tcp").
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives `MDnsResponderEvent::ServiceDiscovered` events
+    /// for found services and `MDnsResponderEvent::ServiceRemoved` events for services that went
+    /// away. Cancelling the operation stops the discovery.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{MDnsResponder, MDnsResponderEvent};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut discovery = responder.discover("_ipp._tcp".to_string()).await?;
+    ///     while let Some(event) = discovery.events.recv().await {
+    ///         if let MDnsResponderEvent::ServiceDiscovered(service) = event {
+    ///             println!("{} at {}:{} {:?}", service.name, service.host, service.port, service.addresses);
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn discover(&self, service_type: String) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        let browse = self.browse_with_options(service_type, BrowseOptions::new()).await?;
+        let context = browse.context();
+        let (events_sender, events_receiver) = mpsc::channel(self.operation_buffer_size);
+
+        runtime::spawn_detached(discover::run(self.clone(), browse, events_sender));
+
+        return Ok(Operation::new(context, events_receiver, self.ipc.writer()));
+    }
+
+    /// Starts browsing for the service types in use in a domain, by browsing for the
+    /// `_services._dns-sd._udp` meta-query.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to list the service types of (e.g., "local"), empty for the
+    ///   default browse domains.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives `MDnsResponderEvent::ServiceTypeDiscovered`
+    /// and `MDnsResponderEvent::ServiceTypeRemoved` events.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{MDnsResponder, MDnsResponderEvent};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut enumeration = responder.enumerate_service_types("local".to_string()).await?;
+    ///     while let Some(event) = enumeration.events.recv().await {
+    ///         if let MDnsResponderEvent::ServiceTypeDiscovered(found) = event {
+    ///             println!("{} in {}", found.service_type, found.domain);
+    ///         }
+    ///     }
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn enumerate_service_types(&self, domain: String) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        let (events_sender, events_receiver) = mpsc::channel(self.operation_buffer_size);
+
+        return match self.ipc.write_service_types_request(Interface::Any.index(), domain, events_sender).await
+        {
+            Ok(context) => Ok(Operation::new(context, events_receiver, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Starts resolving a service with the specified name, type, and domain.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to resolve the service on, `Interface::Any` for all interfaces.
+    /// * `service_name` - The name of the service to resolve (e.g., "My Service").
+    /// * `service_type` - The type of service to resolve (e.g., "_http._tcp"), or a checked `ServiceType`.
+    /// * `service_domain` - The domain in which to resolve the service (e.g., "local").
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives the events of this resolve request only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut resolve = responder.resolve(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     let resolved = resolve.events.recv().await;
+    ///     responder.cancel(resolve.context()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn resolve(
+        &self,
+        interface: Interface,
+        service_name: String,
+        service_type: impl Into<String>,
+        service_domain: String,
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        let options = ResolveOptions::new().interface(interface).domain(service_domain);
+        return self.resolve_with_options(service_name, service_type, options).await;
+    }
+
+    /// Resolves a service and returns its first answer, cancelling the resolve afterwards.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_name` - The name of the service to resolve (e.g., "My Service").
+    /// * `service_type` - The type of service to resolve (e.g., "_http._tcp").
+    /// * `service_domain` - The domain of the service (e.g., "local").
+    /// * `timeout` - How long to wait for an answer.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the service type is malformed, see `ServiceType`.
+    /// Returns `Err(MDnsResponderError::Timeout)` if no answer arrived in time.
+    /// Returns `Err(MDnsResponderError::DaemonError)` if the daemon failed the resolve.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let resolved = responder.resolve_once("My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), Duration::from_secs(5)).await?;
+    ///     println!("{}:{}", resolved.host_target, resolved.port);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn resolve_once(
+        &self,
+        service_name: String,
+        service_type: String,
+        service_domain: String,
+        timeout: Duration,
+    ) -> Result<Resolved, mdnsresponder_error::MDnsResponderError>
+    {
+        let mut operation = self.resolve(Interface::Any, service_name, service_type, service_domain).await?;
+
+        let result = match runtime::timeout(timeout, Self::first_resolved(&mut operation)).await
+        {
+            Ok(result) => result,
+            Err(_) => Err(mdnsresponder_error::MDnsResponderError::Timeout),
+        };
+
+        if let Err(e) = self.cancel(operation.context()).await
+        {
+            error!("Failed to cancel resolve: {}", e);
+        }
+
+        return result;
+    }
+
+    async fn first_resolved(operation: &mut Operation) -> Result<Resolved, mdnsresponder_error::MDnsResponderError>
+    {
+        loop
+        {
+            match operation.events.recv().await
+            {
+                Some(MDnsResponderEvent::ServiceResolved(resolved)) => return Ok(resolved),
+                Some(MDnsResponderEvent::OperationFailed { error, .. }) =>
+                {
+                    return Err(mdnsresponder_error::MDnsResponderError::DaemonError(error));
+                }
+                Some(_) => {}
+                None => return Err(mdnsresponder_error::MDnsResponderError::IpcReadFailed),
+            }
+        }
+    }
+
+    /// Waits until a service with the given name shows up and returns it resolved, for example
+    /// to find a device that is being set up.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_name` - The name of the service to wait for in its display form, without DNS
+    ///   escapes (e.g., "My Service" or "Printer.1"). Compared with `Service::display_name`,
+    ///   ignoring ASCII case like the daemon does.
+    /// * `service_type` - The type of the service (e.g., "_http._tcp").
+    /// * `service_domain` - The domain to browse in (e.g., "local"), empty for the default
+    ///   browse domains.
+    /// * `timeout` - How long to wait for the service to show up and resolve.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the service type is malformed, see `ServiceType`.
+    /// Returns `Err(MDnsResponderError::Timeout)` if the service did not show up in time.
+    /// Returns `Err(MDnsResponderError::DaemonError)` if the daemon failed the browse.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use std::time::Duration;
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let resolved = responder.wait_for_service("Sensor 1234".to_string(), "_hap._tcp".to_string(), "local".to_string(), Duration::from_secs(60)).await?;
+    ///     println!("{}:{}", resolved.host_target, resolved.port);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn wait_for_service(
+        &self,
+        service_name: String,
+        service_type: String,
+        service_domain: String,
+        timeout: Duration,
+    ) -> Result<Resolved, mdnsresponder_error::MDnsResponderError>
+    {
+        let mut browse = self.browse_with_options(service_type, BrowseOptions::new().domain(service_domain)).await?;
+
+        let result = match runtime::timeout(timeout, self.first_matching(&mut browse, &service_name)).await
+        {
+            Ok(result) => result,
+            Err(_) => Err(mdnsresponder_error::MDnsResponderError::Timeout),
+        };
+
+        if let Err(e) = self.cancel(browse.context()).await
+        {
+            error!("Failed to cancel browse: {}", e);
+        }
+
+        return result;
+    }
+
+    // Resolves the services with the given name as they are found until one resolves.
+    async fn first_matching(&self, browse: &mut Operation, service_name: &str) -> Result<Resolved, mdnsresponder_error::MDnsResponderError>
+    {
+        loop
+        {
+            let added = match browse.events.recv().await
+            {
+                Some(MDnsResponderEvent::ServiceAdded(service)) => vec![service],
+                Some(MDnsResponderEvent::ServicesBatch { added, .. }) => added,
+                Some(MDnsResponderEvent::OperationFailed { error, .. }) =>
+                {
+                    return Err(mdnsresponder_error::MDnsResponderError::DaemonError(error));
+                }
+                Some(_) => continue,
+                None => return Err(mdnsresponder_error::MDnsResponderError::IpcReadFailed),
+            };
+
+            for service in added.iter().filter(|service| service.display_name.eq_ignore_ascii_case(service_name))
+            {
+                if let Some(resolved) = discover::resolve(self, service).await
+                {
+                    return Ok(resolved);
+                }
+            }
+        }
+    }
+
+    /// Starts resolving a service with the specified name and type with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `service_name` - The name of the service to resolve (e.g., "My Service").
+    /// * `service_type` - The type of service to resolve (e.g., "_http._tcp"), or a checked `ServiceType`.
+    /// * `options` - The interface, domain and flags to resolve with.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives the events of this resolve request only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder, ResolveOptions};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let options = ResolveOptions::new().interface(Interface::Index(4));
+    ///     let mut resolve = responder.resolve_with_options("My Service".to_string(), "_http._tcp".to_string(), options).await?;
+    ///     let resolved = resolve.events.recv().await;
+    ///     responder.cancel(resolve.context()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn resolve_with_options(
+        &self,
+        service_name: String,
+        service_type: impl Into<String>,
+        options: ResolveOptions,
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        // A subtype only narrows a browse or adds to a registration, an instance is resolved by
+        // its service type alone.
+        let service_type = service_type.into().parse::<ServiceType>()?.without_subtype().to_string();
+
+        let (events_sender, events_receiver) = mpsc::channel(self.operation_buffer_size);
+
+        return match self
+            .ipc
+            .write_resolve_request(
+                options.service_flags(),
+                options.interface.index(),
+                service_name,
+                service_type,
+                options.domain,
+                events_sender,
+                options.acknowledge,
+            )
+            .await
+        {
+            Ok(context) => Ok(Operation::new(context, events_receiver, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Resolves the given hostname to its corresponding IP addresses, IPv4, IPv6, or both.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to resolve the hostname on, `Interface::Any` for all interfaces.
+    /// * `hostname` - The hostname to resolve (e.g., "example.local").
+    /// * `protocol` - The protocol to use for resolution (IPv4, IPv6, or Both).
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives the events of this address info request only.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let lookup = responder.get_addr_info(Interface::Any, "example.local".to_string(), mdnsresponder::Protocol::Both).await?;
+    ///     responder.cancel(lookup.context()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_addr_info(
+        &self,
+        interface: Interface,
+        hostname: String,
+        protocol: Protocol
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        let options = AddrInfoOptions::new().interface(interface).protocol(protocol);
+        return self.get_addr_info_with_options(hostname, options).await;
+    }
+
+    /// Resolves the given hostname to its IP addresses with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `hostname` - The hostname to resolve (e.g., "example.local").
+    /// * `options` - The interface, address families and flags to resolve with.
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives the events of this address info request only.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidHostname)` if the hostname cannot be looked up, see
+    /// `dns_name::check_hostname`.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{AddrInfoOptions, MDnsResponder, Protocol};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let options = AddrInfoOptions::new().protocol(Protocol::IPv4).force_multicast(true);
+    ///     let lookup = responder.get_addr_info_with_options("example.local".to_string(), options).await?;
+    ///     responder.cancel(lookup.context()).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn get_addr_info_with_options(
+        &self,
+        hostname: String,
+        options: AddrInfoOptions,
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
+    {
+        dns_name::check_hostname(&hostname)?;
+
+        let (events_sender, events_receiver) = mpsc::channel(self.operation_buffer_size);
+
+        return match self
+            .ipc
+            .write_addrinfo_request(
+                options.service_flags(),
+                options.interface.index(),
+                options.protocol,
+                hostname,
+                events_sender,
+            )
+            .await
+        {
+            Ok(context) => Ok(Operation::new(context, events_receiver, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Queries for DNS records of the specified name, type, and class.
+    ///
+    /// Answers are delivered as `MDnsResponderEvent::RecordAdded` and
+    /// `MDnsResponderEvent::RecordRemoved` events carrying the raw record data.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to query on, `Interface::Any` for all interfaces.
+    /// * `name` - The full domain name of the record to query (e.g., "My Service._http._tcp.local.").
+    /// * `rrtype` - The DNS resource record type to query (e.g., 16 for TXT, 33 for SRV).
+    /// * `rrclass` - The DNS resource record class to query, usually 1 for IN.
+    ///
+    /// # Returns
+    ///
+    /// Returns a unique context identifier for the query request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let context = responder.query(Interface::Any, "My Service._http._tcp.local.".to_string(), 16, 1).await?;
+    ///     responder.cancel(context).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query(&self, interface: Interface, name: String, rrtype: u16, rrclass: u16) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    {
+        return self.query_with_options(name, rrtype, rrclass, QueryOptions::new().interface(interface)).await;
+    }
+
+    /// Queries for DNS records of the specified name, type, and class with the given options.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The full domain name of the record to query (e.g., "My Service._http._tcp.local.").
+    /// * `rrtype` - The DNS resource record type to query (e.g., 16 for TXT, 33 for SRV).
+    /// * `rrclass` - The DNS resource record class to query, usually 1 for IN.
+    /// * `options` - The interface and flags to query with.
+    ///
+    /// # Returns
+    ///
+    /// Returns a unique context identifier for the query request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{MDnsResponder, QueryOptions};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let options = QueryOptions::new().long_lived_query(true);
+    ///     let context = responder.query_with_options("printer.example.com.".to_string(), 1, 1, options).await?;
+    ///     responder.cancel(context).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn query_with_options(
+        &self,
+        name: String,
+        rrtype: u16,
+        rrclass: u16,
+        options: QueryOptions,
+    ) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    {
+        return match self.ipc.write_query_request(options.service_flags(), options.interface.index(), name, rrtype, rrclass, None).await
+        {
+            Ok(context) => Ok(context),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Starts enumerating the domains recommended for browsing.
+    ///
+    /// Domains are delivered as `MDnsResponderEvent::DomainAdded` and
+    /// `MDnsResponderEvent::DomainRemoved` events.
+    ///
+    /// # Returns
+    ///
+    /// Returns a unique context identifier for the enumeration request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let context = responder.enumerate_browse_domains().await?;
+    ///     responder.cancel(context).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn enumerate_browse_domains(&self) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    {
+        return match self.ipc.write_enumeration_request(false).await
+        {
+            Ok(context) => Ok(context),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Starts enumerating the domains recommended for service registration.
+    ///
+    /// Domains are delivered as `MDnsResponderEvent::DomainAdded` and
+    /// `MDnsResponderEvent::DomainRemoved` events.
+    ///
+    /// # Returns
+    ///
+    /// Returns a unique context identifier for the enumeration request.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let context = responder.enumerate_registration_domains().await?;
+    ///     responder.cancel(context).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn enumerate_registration_domains(&self) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    {
+        return match self.ipc.write_enumeration_request(true).await
+        {
+            Ok(context) => Ok(context),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Requests a port mapping on the NAT gateway using NAT-PMP or PCP.
+    ///
+    /// The mapping granted by the gateway is delivered as a
+    /// `MDnsResponderEvent::PortMappingCreated` event, and again whenever it changes. The
+    /// mapping is kept alive until the request is cancelled.
     ///
     /// # Arguments
     ///
-    /// * `channel_buffer_size` - The size of the buffer for the internal channels. Must be greater than zero.
+    /// * `protocol` - The transport protocol of the mapping (UDP or TCP).
+    /// * `internal_port` - The local port to map.
+    /// * `external_port` - The requested external port, 0 to let the gateway choose.
+    /// * `ttl` - The requested lifetime of the mapping in seconds, 0 for the default.
     ///
-    /// # Errors
+    /// # Returns
     ///
-    /// Returns `Err(MDnsResponderError::ChannelCreationFailed)` if the buffer size is zero.
-    /// Returns `Err(MDnsResponderError::IpcConnectionCreationFailed)` if IPC creation fails.
+    /// Returns a unique context identifier for the port mapping request.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use mdnsresponder::MDnsResponder;
+    /// use mdnsresponder::{MDnsResponder, PortMappingProtocol};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let responder = MDnsResponder::new(10).await?;
+    ///     let context = responder.create_port_mapping(PortMappingProtocol::Tcp, 8080, 0, 0).await?;
+    ///     responder.cancel(context).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn new(
-        channel_buffer_size: usize,
-    ) -> Result<Self, mdnsresponder_error::MDnsResponderError>
+    pub async fn create_port_mapping(
+        &self,
+        protocol: PortMappingProtocol,
+        internal_port: u16,
+        external_port: u16,
+        ttl: u32,
+    ) -> Result<u64, mdnsresponder_error::MDnsResponderError>
     {
-        if channel_buffer_size == 0
+        return match self.ipc.write_port_mapping_request(protocol, internal_port, external_port, ttl).await
         {
-            error!("Channel buffer size must be greater than zero");
-            return Err(mdnsresponder_error::MDnsResponderError::ChannelCreationFailed);
-        }
-
-        let (events_sender, events_receiver) = mpsc::channel(channel_buffer_size);
+            Ok(context) => Ok(context),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
 
-        let ipc = match ipc::Ipc::new(events_sender).await
+    /// Retrieves the version of the mDNSResponder daemon.
+    ///
+    /// The version is encoded as in the `_DNS_SD_H` define of `dns_sd.h`, e.g. 8780101 for
+    /// version 878.1.1.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::IpcReadFailed)` if the daemon did not report its version.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::MDnsResponder;
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let version = responder.daemon_version().await?;
+    ///     println!("mDNSResponder version {}", version);
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn daemon_version(&self) -> Result<u32, mdnsresponder_error::MDnsResponderError>
+    {
+        return match self.ipc.read_daemon_version().await
         {
-            Ok(ipc) => ipc,
+            Ok(version) => Ok(version),
             Err(e) =>
             {
-                error!("Failed to create IPC: {}", e);
-                return Err(mdnsresponder_error::MDnsResponderError::IpcConnectionCreationFailed);
+                error!("Failed to read daemon version: {}", e);
+                Err(mdnsresponder_error::MDnsResponderError::IpcReadFailed)
             }
         };
-
-        return Ok(MDnsResponder
-        {
-            ipc,
-            events: events_receiver,
-        });
     }
 
-    /// Closes the `MDnsResponder` instance, releasing any associated resources.
+    /// Adds a default domain for browsing and registration of the current user.
+    ///
+    /// # Arguments
+    ///
+    /// * `domain` - The domain to add (e.g., "example.com.").
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::DaemonError)` if the daemon rejected the domain.
     ///
     /// # Examples
     ///
@@ -118,25 +1870,24 @@ impl MDnsResponder
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let responder = MDnsResponder::new(10).await?;
-    ///     responder.close().await;
+    ///     responder.set_default_domain("example.com.".to_string()).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn close(self)
+    pub async fn set_default_domain(&self, domain: String) -> Result<(), mdnsresponder_error::MDnsResponderError>
     {
-        self.ipc.close().await;
+        return self.write_set_domain(domain, true).await;
     }
 
-    /// Starts browsing for services of the specified type and domain.
+    /// Removes a default domain previously added with `set_default_domain`.
     ///
     /// # Arguments
     ///
-    /// * `service_type` - The type of service to browse for (e.g., "_http._tcp").
-    /// * `service_domain` - The domain in which to browse for the service (e.g., "local").
+    /// * `domain` - The domain to remove (e.g., "example.com.").
     ///
-    /// # Returns
+    /// # Errors
     ///
-    /// Returns a unique context identifier for the browse request.
+    /// Returns `Err(MDnsResponderError::DaemonError)` if the daemon rejected the domain.
     ///
     /// # Examples
     ///
@@ -145,77 +1896,100 @@ impl MDnsResponder
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let mut responder = MDnsResponder::new(10).await?;
-    ///     let context = responder.browse("_http._tcp".to_string(), "local".to_string()).await;
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     responder.remove_default_domain("example.com.".to_string()).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn browse(
-        &mut self, service_type: String,
-        service_domain: String
-    ) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    pub async fn remove_default_domain(&self, domain: String) -> Result<(), mdnsresponder_error::MDnsResponderError>
     {
-        return match self
-            .ipc
-            .write_browse_request(service_type, service_domain)
-            .await
+        return self.write_set_domain(domain, false).await;
+    }
+
+    async fn write_set_domain(&self, domain: String, add: bool) -> Result<(), mdnsresponder_error::MDnsResponderError>
+    {
+        return match self.ipc.write_set_domain_request(domain, add).await
         {
-            Ok(context) => Ok(context),
-            Err(e) => Err(mdnsresponder_error::MDnsResponderError::IpcWriteFailed),
+            Ok(code) => match DnsServiceError::from_code(code)
+            {
+                None => Ok(()),
+                Some(error) =>
+                {
+                    error!("Daemon rejected default domain: {}", error);
+                    Err(mdnsresponder_error::MDnsResponderError::DaemonError(error))
+                }
+            },
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
         };
     }
 
-    /// Starts resolving a service with the specified name, type, and domain.
+    /// Queries the SRV record of a service, which unlike `resolve` includes the priority and
+    /// weight of the service for clients that choose among several instances.
     ///
     /// # Arguments
     ///
-    /// * `service_name` - The name of the service to resolve (e.g., "My Service").
-    /// * `service_type` - The type of service to resolve (e.g., "_http._tcp").
-    /// * `service_domain` - The domain in which to resolve the service (e.g., "local").
+    /// * `interface` - The interface to query on, `Interface::Any` for all interfaces.
+    /// * `service_name` - The name of the service (e.g., "My Service").
+    /// * `service_type` - The type of the service (e.g., "_http._tcp").
+    /// * `domain` - The domain of the service (e.g., "local").
+    ///
+    /// # Returns
+    ///
+    /// Returns an `Operation` handle that receives `MDnsResponderEvent::RecordAdded` and
+    /// `MDnsResponderEvent::RecordRemoved` events, decoded with `Record::srv`.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use mdnsresponder::MDnsResponder;
+    /// use mdnsresponder::{Interface, MDnsResponder, MDnsResponderEvent};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let mut responder = MDnsResponder::new(10).await?;
-    ///     let context = responder.resolve("My Service".to_string(), "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut query = responder.resolve_srv(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     if let Some(MDnsResponderEvent::RecordAdded(record)) = query.events.recv().await {
+    ///         if let Some(srv) = record.srv() {
+    ///             println!("{}:{} priority {} weight {}", srv.target, srv.port, srv.priority, srv.weight);
+    ///         }
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn resolve(
-        &mut self,
+    pub async fn resolve_srv(
+        &self,
+        interface: Interface,
         service_name: String,
         service_type: String,
-        service_domain: String,
-    ) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+        domain: String,
+    ) -> Result<Operation, mdnsresponder_error::MDnsResponderError>
     {
-        return match self
-            .ipc
-            .write_resolve_request(
-                service_name,
-                service_type,
-                service_domain,
-            )
-            .await
+        let (events_sender, events_receiver) = mpsc::channel(self.operation_buffer_size);
+        let name = dns_name::full_name(&service_name, &service_type, &domain);
+        let service_flags = QueryOptions::new().service_flags();
+
+        return match self.ipc.write_query_request(service_flags, interface.index(), name, RRTYPE_SRV, RRCLASS_IN, Some(events_sender)).await
         {
-            Ok(context) => Ok(context),
-            Err(_) => Err(mdnsresponder_error::MDnsResponderError::IpcWriteFailed),
+            Ok(context) => Ok(Operation::new(context, events_receiver, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
         };
     }
 
-    /// Resolves the given hostname to its corresponding IP addresses, IPv4, IPv6, or both.
+    /// Looks up the hostname of an address with a PTR query, for example to show a name for a
+    /// peer. Waits for the first answer and cancels the query.
     ///
     /// # Arguments
     ///
-    /// * `hostname` - The hostname to resolve (e.g., "example.local").
-    /// * `protocol` - The protocol to use for resolution (IPv4, IPv6, or Both).
+    /// * `address` - The IPv4 or IPv6 address to look up.
     ///
     /// # Returns
     ///
-    /// Returns a unique context identifier for the address info request.
+    /// Returns the hostname of the address (e.g., "printer.local.").
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::DaemonError(DnsServiceError::Timeout))` if the daemon
+    /// found no name within its timeout, or another `DaemonError` if the query failed.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
     ///
     /// # Examples
     ///
@@ -224,46 +1998,83 @@ impl MDnsResponder
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let mut responder = MDnsResponder::new(10).await?;
-    ///     let context = responder.get_addr_info("example.local".to_string(), mdnsresponder::Protocol::Both).await?;
-    ///     responder.cancel(context).await?;
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let hostname = responder.reverse_lookup("192.168.1.20".parse()?).await?;
+    ///     println!("{}", hostname);
     ///     Ok(())
     /// }
     /// ```
-    pub async fn get_addr_info(&mut self, hostname: String, protocol: Protocol) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    pub async fn reverse_lookup(&self, address: IpAddr) -> Result<String, mdnsresponder_error::MDnsResponderError>
     {
-        return match self.ipc.write_addrinfo_request(protocol, hostname).await
+        let (events_sender, mut events_receiver) = mpsc::channel(self.operation_buffer_size);
+        let service_flags = QueryOptions::new().timeout(true).service_flags();
+
+        let context = match self
+            .ipc
+            .write_query_request(service_flags, Interface::Any.index(), dns_name::reverse_name(address), RRTYPE_PTR, RRCLASS_IN, Some(events_sender))
+            .await
         {
-            Ok(context) => Ok(context),
-            Err(_) => Err(mdnsresponder_error::MDnsResponderError::IpcWriteFailed),
+            Ok(context) => context,
+            Err(e) => return Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+
+        let result = loop
+        {
+            match events_receiver.recv().await
+            {
+                Some(MDnsResponderEvent::RecordAdded(record)) =>
+                {
+                    if let Some(hostname) = dns_name::from_wire(&record.rdata)
+                    {
+                        break Ok(hostname);
+                    }
+                }
+                Some(MDnsResponderEvent::Timeout { .. }) =>
+                {
+                    break Err(mdnsresponder_error::MDnsResponderError::DaemonError(DnsServiceError::Timeout));
+                }
+                Some(MDnsResponderEvent::OperationFailed { error, .. }) =>
+                {
+                    break Err(mdnsresponder_error::MDnsResponderError::DaemonError(error));
+                }
+                Some(_) => {}
+                None => break Err(mdnsresponder_error::MDnsResponderError::IpcReadFailed),
+            }
         };
+
+        if let Err(e) = self.cancel(context).await
+        {
+            error!("Failed to cancel reverse lookup: {}", e);
+        }
+
+        return result;
     }
 
     /// Cancels an ongoing browse or resolve operation identified by the given context.
     ///
     /// # Arguments
     ///
-    /// * `context` - The unique context identifier returned by `browse`.
+    /// * `context` - The unique context identifier of the operation, e.g. `Operation::context`.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use mdnsresponder::MDnsResponder;
+    /// use mdnsresponder::{Interface, MDnsResponder};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let mut responder = MDnsResponder::new(10).await?;
-    ///     let context = responder.browse("_http._tcp".to_string(), "local".to_string()).await?;
-    ///     responder.cancel(context).await?;
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+    ///     responder.cancel(browse.context()).await?;
     ///     Ok(())
     /// }
     /// ```
-    pub async fn cancel(&mut self, context: u64) -> Result<(), mdnsresponder_error::MDnsResponderError>
+    pub async fn cancel(&self, context: u64) -> Result<(), mdnsresponder_error::MDnsResponderError>
     {
         return match self.ipc.write_cancel_request(context).await
         {
             Ok(_) => Ok(()),
-            Err(_) => Err(mdnsresponder_error::MDnsResponderError::IpcWriteFailed),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
         };
     }
 
@@ -271,7 +2082,113 @@ impl MDnsResponder
     ///
     /// # Arguments
     ///
-    /// * `interface_index` - The index of the network interface to use for registration, 0 for all interfaces.
+    /// * `interface` - The interface to register the service on, `Interface::Any` for all interfaces.
+    /// * `name` - The name of the service to register (e.g., "My Service").
+    /// * `service_type` - The type of service to register (e.g., "_http._tcp"), or a checked `ServiceType`.
+    /// * `domain` - The domain in which to register the service (e.g., "local").
+    /// * `host` - The hostname of the service (e.g., "myhost.local"), empty string for this host.
+    /// * `port` - The port number on which the service is available.
+    /// * `txt_data` - A vector of strings representing the TXT records associated with the service.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `ServiceRegistration` handle used to update and deregister the service. Once the daemon has
+    /// registered the service, a `MDnsResponderEvent::ServiceRegistered` event is emitted with
+    /// the final service name, which may differ from the requested one if it was renamed.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the service type is malformed, see `ServiceType`.
+    /// Returns `Err(MDnsResponderError::InvalidTxtRecord)` if a TXT string is longer than 255 bytes.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "myhost.local".to_string(), 8080, vec!["key=value".to_string()]).await?;
+    ///     registration.deregister().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn register(
+        &self,
+        interface: Interface,
+        name: String,
+        service_type: impl Into<String>,
+        domain: String,
+        host: String,
+        port: u16,
+        txt_data: Vec<String>
+    ) -> Result<ServiceRegistration, mdnsresponder_error::MDnsResponderError>
+    {
+        let mut options = RegisterOptions::new().interface(interface).domain(domain).host(host);
+        options.txt = txt_record::encode_strings(&txt_data)?;
+
+        return self.register_with_options(name, service_type, port, options).await;
+    }
+
+    /// Registers a service with a TXT record built with `TxtRecord::builder`, which may contain
+    /// binary values.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to register the service on, `Interface::Any` for all interfaces.
+    /// * `name` - The name of the service to register (e.g., "My Service").
+    /// * `service_type` - The type of service to register (e.g., "_http._tcp").
+    /// * `domain` - The domain in which to register the service (e.g., "local").
+    /// * `host` - The hostname of the service (e.g., "myhost.local"), empty string for this host.
+    /// * `port` - The port number on which the service is available.
+    /// * `txt` - The TXT record of the service.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `ServiceRegistration` handle used to update and deregister the service.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder, TxtRecord};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let txt = TxtRecord::builder().value("path", "/api").flag("secure").build()?;
+    ///     let registration = responder.register_with_txt(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, &txt).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn register_with_txt(
+        &self,
+        interface: Interface,
+        name: String,
+        service_type: String,
+        domain: String,
+        host: String,
+        port: u16,
+        txt: &TxtRecord
+    ) -> Result<ServiceRegistration, mdnsresponder_error::MDnsResponderError>
+    {
+        let options = RegisterOptions::new().interface(interface).domain(domain).host(host).txt(txt);
+
+        return self.register_with_options(name, service_type, port, options).await;
+    }
+
+    /// Registers a service that must keep its exact name.
+    ///
+    /// Unlike `register`, the daemon does not automatically rename the service if the name is
+    /// already taken on the network. Instead a `MDnsResponderEvent::NameConflict` event is
+    /// emitted and the registration is no longer active.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to register the service on, `Interface::Any` for all interfaces.
     /// * `name` - The name of the service to register (e.g., "My Service").
     /// * `service_type` - The type of service to register (e.g., "_http._tcp").
     /// * `domain` - The domain in which to register the service (e.g., "local").
@@ -281,35 +2198,162 @@ impl MDnsResponder
     ///
     /// # Returns
     ///
-    /// Returns a unique context identifier for the registration request.
+    /// Returns a `ServiceRegistration` handle used to update and deregister the service.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the service type is malformed, see `ServiceType`.
+    /// Returns `Err(MDnsResponderError::InvalidTxtRecord)` if a TXT string is longer than 255 bytes.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
     ///
     /// # Examples
     ///
     /// ```rust,no_run
-    /// use mdnsresponder::MDnsResponder;
+    /// use mdnsresponder::{Interface, MDnsResponder, MDnsResponderEvent};
     ///
     /// #[tokio::main]
     /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
     ///     let mut responder = MDnsResponder::new(10).await?;
-    ///     let context = responder.register(0, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "myhost.local".to_string(), 8080, vec!["key=value".to_string()]).await?;
+    ///     let registration = responder.register_no_auto_rename(Interface::Any, "Device 1234".to_string(), "_hap._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![]).await?;
+    ///     if let Some(MDnsResponderEvent::NameConflict(service)) = responder.events.recv().await {
+    ///         println!("Name {} is already in use", service.name);
+    ///     }
     ///     Ok(())
     /// }
     /// ```
-    pub async fn register(
-        &mut self,
-        interface_index: u32,
+    #[allow(clippy::too_many_arguments)]
+    pub async fn register_no_auto_rename(
+        &self,
+        interface: Interface,
         name: String,
         service_type: String,
         domain: String,
         host: String,
         port: u16,
         txt_data: Vec<String>
-    ) -> Result<u64, mdnsresponder_error::MDnsResponderError>
+    ) -> Result<ServiceRegistration, mdnsresponder_error::MDnsResponderError>
+    {
+        let mut options = RegisterOptions::new().interface(interface).domain(domain).host(host).no_auto_rename(true);
+        options.txt = txt_record::encode_strings(&txt_data)?;
+
+        return self.register_with_options(name, service_type, port, options).await;
+    }
+
+    /// Registers a service with the given options, such as a TXT record and subtypes.
+    ///
+    /// # Arguments
+    ///
+    /// * `name` - The name of the service to register (e.g., "My Service").
+    /// * `service_type` - The type of service to register (e.g., "_http._tcp"), or a checked `ServiceType`.
+    /// * `port` - The port number on which the service is available.
+    /// * `options` - The interface, domain, host, TXT record, subtypes and flags to register with.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `ServiceRegistration` handle used to update and deregister the service.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the service type is malformed, see `ServiceType`.
+    /// Returns `Err(MDnsResponderError::InvalidSubtype)` if a subtype is empty or longer than 63 bytes.
+    /// Returns `Err(MDnsResponderError::IpcWriteFailed)` if the request cannot be written.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{MDnsResponder, RegisterOptions};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let options = RegisterOptions::new().subtype("_printer");
+    ///     let registration = responder.register_with_options("Office".to_string(), "_http._tcp".to_string(), 8080, options).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn register_with_options(
+        &self,
+        name: String,
+        service_type: impl Into<String>,
+        port: u16,
+        options: RegisterOptions,
+    ) -> Result<ServiceRegistration, mdnsresponder_error::MDnsResponderError>
     {
-        return match self.ipc.write_register_request(interface_index, name, service_type, domain, host, port, txt_data).await
+        let registration_type = options.registration_type(service_type.into().parse()?)?;
+        let service_flags = options.service_flags();
+
+        return match self
+            .ipc
+            .write_register_request(
+                options.interface.index(),
+                name,
+                registration_type,
+                options.domain,
+                options.host,
+                port,
+                options.txt,
+                service_flags,
+                options.acknowledge,
+            )
+            .await
         {
-            Ok(context) => Ok(context),
-            Err(_) => Err(mdnsresponder_error::MDnsResponderError::IpcWriteFailed),
+            Ok(context) => Ok(ServiceRegistration::new(context, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Registers an individual DNS record that is not tied to a service, such as a CNAME or a
+    /// standalone TXT record.
+    ///
+    /// Records are registered directly on the shared connection to the daemon. A
+    /// `MDnsResponderEvent::RecordRegistered` event is emitted once the daemon has registered
+    /// the record.
+    ///
+    /// # Arguments
+    ///
+    /// * `interface` - The interface to register the record on, `Interface::Any` for all interfaces.
+    /// * `name` - The full domain name of the record (e.g., "alias.local.").
+    /// * `rrtype` - The DNS resource record type of the record (e.g., 5 for CNAME).
+    /// * `rrclass` - The DNS resource record class of the record, usually 1 for IN.
+    /// * `rdata` - The raw record data.
+    /// * `ttl` - The time to live of the record in seconds, 0 for the default.
+    /// * `unique` - Whether the record must be unique on the network, otherwise it is shared.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `RecordRegistration` handle used to update and remove the record.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let record = responder.register_record(Interface::Any, "alias.local.".to_string(), 16, 1, vec![5, b'h', b'e', b'l', b'l', b'o'], 0, false).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    #[allow(clippy::too_many_arguments)]
+    pub async fn register_record(
+        &self,
+        interface: Interface,
+        name: String,
+        rrtype: u16,
+        rrclass: u16,
+        rdata: Vec<u8>,
+        ttl: u32,
+        unique: bool,
+    ) -> Result<RecordRegistration, mdnsresponder_error::MDnsResponderError>
+    {
+        let context = self.ipc.connection_context();
+        let record_index = self.next_record_index.fetch_add(1, Ordering::Relaxed);
+
+        return match self.ipc.write_register_record_request(context, record_index, interface.index(), name, rrtype, rrclass, rdata, ttl, unique).await
+        {
+            Ok(_) => Ok(RecordRegistration::new(context, record_index, self.ipc.writer())),
+            Err(e) => Err(mdnsresponder_error::MDnsResponderError::from_write_error(e)),
         };
     }
 }
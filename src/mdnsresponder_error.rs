@@ -28,11 +28,25 @@ impl std::error::Error for InternalError {}
 
 // Public errors that can reach users of the library
 #[derive(Debug)]
+#[cfg_attr(feature = "uniffi", derive(uniffi::Error), uniffi(flat_error))]
 pub enum MDnsResponderError
 {
     ChannelCreationFailed,
+    /// The runtime of `blocking::BlockingMDnsResponder` could not be started.
+    RuntimeCreationFailed,
     IpcConnectionCreationFailed,
     IpcWriteFailed,
+    IpcReadFailed,
+    InvalidTxtRecord,
+    InvalidSubtype,
+    InvalidServiceType,
+    /// The hostname cannot be looked up, with a description of why.
+    InvalidHostname(String),
+    Timeout,
+    DaemonError(DnsServiceError),
+    /// A `Delegate::Uuid` cannot be set on the connection, which is only possible on a Unix
+    /// socket on Apple platforms.
+    DelegateNotSupported,
 }
 
 impl std::fmt::Display for MDnsResponderError
@@ -45,6 +59,10 @@ impl std::fmt::Display for MDnsResponderError
             {
                 write!(f, "Failed to create channel")
             }
+            MDnsResponderError::RuntimeCreationFailed =>
+            {
+                write!(f, "Failed to create runtime")
+            }
             MDnsResponderError::IpcConnectionCreationFailed =>
             {
                 write!(f, "Failed to create IPC connection")
@@ -53,8 +71,253 @@ impl std::fmt::Display for MDnsResponderError
             {
                 write!(f, "Failed to write to IPC")
             }
+            MDnsResponderError::IpcReadFailed =>
+            {
+                write!(f, "Failed to read from IPC")
+            }
+            MDnsResponderError::InvalidTxtRecord =>
+            {
+                write!(f, "Invalid TXT record")
+            }
+            MDnsResponderError::InvalidSubtype =>
+            {
+                write!(f, "Invalid service subtype")
+            }
+            MDnsResponderError::InvalidServiceType =>
+            {
+                write!(f, "Invalid service type")
+            }
+            MDnsResponderError::InvalidHostname(reason) =>
+            {
+                write!(f, "Invalid hostname: {}", reason)
+            }
+            MDnsResponderError::Timeout =>
+            {
+                write!(f, "Timed out waiting for the daemon")
+            }
+            MDnsResponderError::DaemonError(error) =>
+            {
+                write!(f, "Daemon returned error: {}", error)
+            }
+            MDnsResponderError::DelegateNotSupported =>
+            {
+                write!(f, "Delegate UUID is not supported on this connection")
+            }
         }
     }
 }
 
 impl std::error::Error for MDnsResponderError {}
+
+impl MDnsResponderError
+{
+    // A request the daemon rejected right away fails to write with its error, see
+    // `MDnsResponderBuilder::synchronous_errors`.
+    pub(crate) fn from_write_error(error: std::io::Error) -> Self
+    {
+        return match error.get_ref().and_then(|inner| inner.downcast_ref::<DnsServiceError>())
+        {
+            Some(error) => MDnsResponderError::DaemonError(*error),
+            None => MDnsResponderError::IpcWriteFailed,
+        };
+    }
+
+    // A delegate that cannot be set on the connection fails to connect with this error, see
+    // `Delegate`.
+    pub(crate) fn from_connect_error(error: &std::io::Error) -> Self
+    {
+        return match error.get_ref().and_then(|inner| inner.downcast_ref::<MDnsResponderError>())
+        {
+            Some(MDnsResponderError::DelegateNotSupported) => MDnsResponderError::DelegateNotSupported,
+            _ => MDnsResponderError::IpcConnectionCreationFailed,
+        };
+    }
+}
+
+// Error codes reported by the daemon (kDNSServiceErr_* in dns_sd.h)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DnsServiceError
+{
+    Unknown,
+    NoSuchName,
+    NoMemory,
+    BadParam,
+    BadReference,
+    BadState,
+    BadFlags,
+    Unsupported,
+    NotInitialized,
+    AlreadyRegistered,
+    NameConflict,
+    Invalid,
+    Firewall,
+    Incompatible,
+    BadInterfaceIndex,
+    Refused,
+    NoSuchRecord,
+    NoAuth,
+    NoSuchKey,
+    NatTraversal,
+    DoubleNat,
+    BadTime,
+    BadSig,
+    BadKey,
+    Transient,
+    ServiceNotRunning,
+    NatPortMappingUnsupported,
+    NatPortMappingDisabled,
+    NoRouter,
+    PollingMode,
+    Timeout,
+    DefunctConnection,
+    PolicyDenied,
+    NotPermitted,
+    StaleData,
+    Other(i32),
+}
+
+impl DnsServiceError
+{
+    /// Maps a daemon error code to a `DnsServiceError`.
+    ///
+    /// # Returns
+    ///
+    /// `None` for kDNSServiceErr_NoError (0), otherwise the matching error. Codes
+    /// not known to this library are returned as `DnsServiceError::Other`.
+    pub fn from_code(code: i32) -> Option<Self>
+    {
+        return match code
+        {
+            0 => None,
+            -65537 => Some(DnsServiceError::Unknown),
+            -65538 => Some(DnsServiceError::NoSuchName),
+            -65539 => Some(DnsServiceError::NoMemory),
+            -65540 => Some(DnsServiceError::BadParam),
+            -65541 => Some(DnsServiceError::BadReference),
+            -65542 => Some(DnsServiceError::BadState),
+            -65543 => Some(DnsServiceError::BadFlags),
+            -65544 => Some(DnsServiceError::Unsupported),
+            -65545 => Some(DnsServiceError::NotInitialized),
+            -65547 => Some(DnsServiceError::AlreadyRegistered),
+            -65548 => Some(DnsServiceError::NameConflict),
+            -65549 => Some(DnsServiceError::Invalid),
+            -65550 => Some(DnsServiceError::Firewall),
+            -65551 => Some(DnsServiceError::Incompatible),
+            -65552 => Some(DnsServiceError::BadInterfaceIndex),
+            -65553 => Some(DnsServiceError::Refused),
+            -65554 => Some(DnsServiceError::NoSuchRecord),
+            -65555 => Some(DnsServiceError::NoAuth),
+            -65556 => Some(DnsServiceError::NoSuchKey),
+            -65557 => Some(DnsServiceError::NatTraversal),
+            -65558 => Some(DnsServiceError::DoubleNat),
+            -65559 => Some(DnsServiceError::BadTime),
+            -65560 => Some(DnsServiceError::BadSig),
+            -65561 => Some(DnsServiceError::BadKey),
+            -65562 => Some(DnsServiceError::Transient),
+            -65563 => Some(DnsServiceError::ServiceNotRunning),
+            -65564 => Some(DnsServiceError::NatPortMappingUnsupported),
+            -65565 => Some(DnsServiceError::NatPortMappingDisabled),
+            -65566 => Some(DnsServiceError::NoRouter),
+            -65567 => Some(DnsServiceError::PollingMode),
+            -65568 => Some(DnsServiceError::Timeout),
+            -65569 => Some(DnsServiceError::DefunctConnection),
+            -65570 => Some(DnsServiceError::PolicyDenied),
+            -65571 => Some(DnsServiceError::NotPermitted),
+            -65572 => Some(DnsServiceError::StaleData),
+            other => Some(DnsServiceError::Other(other)),
+        };
+    }
+
+    /// Returns the raw kDNSServiceErr_* code for this error.
+    pub fn code(&self) -> i32
+    {
+        return match self
+        {
+            DnsServiceError::Unknown => -65537,
+            DnsServiceError::NoSuchName => -65538,
+            DnsServiceError::NoMemory => -65539,
+            DnsServiceError::BadParam => -65540,
+            DnsServiceError::BadReference => -65541,
+            DnsServiceError::BadState => -65542,
+            DnsServiceError::BadFlags => -65543,
+            DnsServiceError::Unsupported => -65544,
+            DnsServiceError::NotInitialized => -65545,
+            DnsServiceError::AlreadyRegistered => -65547,
+            DnsServiceError::NameConflict => -65548,
+            DnsServiceError::Invalid => -65549,
+            DnsServiceError::Firewall => -65550,
+            DnsServiceError::Incompatible => -65551,
+            DnsServiceError::BadInterfaceIndex => -65552,
+            DnsServiceError::Refused => -65553,
+            DnsServiceError::NoSuchRecord => -65554,
+            DnsServiceError::NoAuth => -65555,
+            DnsServiceError::NoSuchKey => -65556,
+            DnsServiceError::NatTraversal => -65557,
+            DnsServiceError::DoubleNat => -65558,
+            DnsServiceError::BadTime => -65559,
+            DnsServiceError::BadSig => -65560,
+            DnsServiceError::BadKey => -65561,
+            DnsServiceError::Transient => -65562,
+            DnsServiceError::ServiceNotRunning => -65563,
+            DnsServiceError::NatPortMappingUnsupported => -65564,
+            DnsServiceError::NatPortMappingDisabled => -65565,
+            DnsServiceError::NoRouter => -65566,
+            DnsServiceError::PollingMode => -65567,
+            DnsServiceError::Timeout => -65568,
+            DnsServiceError::DefunctConnection => -65569,
+            DnsServiceError::PolicyDenied => -65570,
+            DnsServiceError::NotPermitted => -65571,
+            DnsServiceError::StaleData => -65572,
+            DnsServiceError::Other(code) => *code,
+        };
+    }
+}
+
+impl std::fmt::Display for DnsServiceError
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            DnsServiceError::Unknown => write!(f, "Unknown error ({})", self.code()),
+            DnsServiceError::NoSuchName => write!(f, "No such name ({})", self.code()),
+            DnsServiceError::NoMemory => write!(f, "Out of memory ({})", self.code()),
+            DnsServiceError::BadParam => write!(f, "Bad parameter ({})", self.code()),
+            DnsServiceError::BadReference => write!(f, "Bad reference ({})", self.code()),
+            DnsServiceError::BadState => write!(f, "Bad state ({})", self.code()),
+            DnsServiceError::BadFlags => write!(f, "Bad flags ({})", self.code()),
+            DnsServiceError::Unsupported => write!(f, "Unsupported operation ({})", self.code()),
+            DnsServiceError::NotInitialized => write!(f, "Not initialized ({})", self.code()),
+            DnsServiceError::AlreadyRegistered => write!(f, "Already registered ({})", self.code()),
+            DnsServiceError::NameConflict => write!(f, "Name conflict ({})", self.code()),
+            DnsServiceError::Invalid => write!(f, "Invalid ({})", self.code()),
+            DnsServiceError::Firewall => write!(f, "Blocked by firewall ({})", self.code()),
+            DnsServiceError::Incompatible => write!(f, "Client library incompatible with daemon ({})", self.code()),
+            DnsServiceError::BadInterfaceIndex => write!(f, "Bad interface index ({})", self.code()),
+            DnsServiceError::Refused => write!(f, "Refused ({})", self.code()),
+            DnsServiceError::NoSuchRecord => write!(f, "No such record ({})", self.code()),
+            DnsServiceError::NoAuth => write!(f, "No authentication ({})", self.code()),
+            DnsServiceError::NoSuchKey => write!(f, "No such key ({})", self.code()),
+            DnsServiceError::NatTraversal => write!(f, "NAT traversal failed ({})", self.code()),
+            DnsServiceError::DoubleNat => write!(f, "Double NAT ({})", self.code()),
+            DnsServiceError::BadTime => write!(f, "Bad time ({})", self.code()),
+            DnsServiceError::BadSig => write!(f, "Bad signature ({})", self.code()),
+            DnsServiceError::BadKey => write!(f, "Bad key ({})", self.code()),
+            DnsServiceError::Transient => write!(f, "Transient failure ({})", self.code()),
+            DnsServiceError::ServiceNotRunning => write!(f, "Service not running ({})", self.code()),
+            DnsServiceError::NatPortMappingUnsupported => write!(f, "NAT port mapping unsupported ({})", self.code()),
+            DnsServiceError::NatPortMappingDisabled => write!(f, "NAT port mapping disabled ({})", self.code()),
+            DnsServiceError::NoRouter => write!(f, "No router ({})", self.code()),
+            DnsServiceError::PollingMode => write!(f, "Polling mode ({})", self.code()),
+            DnsServiceError::Timeout => write!(f, "Timeout ({})", self.code()),
+            DnsServiceError::DefunctConnection => write!(f, "Defunct connection ({})", self.code()),
+            DnsServiceError::PolicyDenied => write!(f, "Denied by policy ({})", self.code()),
+            DnsServiceError::NotPermitted => write!(f, "Not permitted ({})", self.code()),
+            DnsServiceError::StaleData => write!(f, "Stale data ({})", self.code()),
+            DnsServiceError::Other(code) => write!(f, "Unrecognized error ({})", code),
+        }
+    }
+}
+
+impl std::error::Error for DnsServiceError {}
@@ -0,0 +1,64 @@
+/// Receives counts of what happens on the connection to mDNSResponder, to feed them into a
+/// telemetry system. Set with `MDnsResponderBuilder::metrics`.
+///
+/// Every method does nothing by default, so only the counts of interest need to be implemented.
+/// The methods are called from the listener task and while writing requests, they should return
+/// quickly and must not block.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use std::sync::atomic::{AtomicU64, Ordering};
+/// use mdnsresponder::{MDnsResponder, MetricsSink};
+///
+/// #[derive(Debug, Default)]
+/// struct Counters {
+///     replies: AtomicU64,
+/// }
+///
+/// impl MetricsSink for Counters {
+///     fn reply_parsed(&self) {
+///         self.replies.fetch_add(1, Ordering::Relaxed);
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::builder().metrics(Counters::default()).build().await?;
+///     Ok(())
+/// }
+/// ```
+pub trait MetricsSink: std::fmt::Debug + Send + Sync + 'static
+{
+    /// An operation request or a cancel was written to the daemon. Setting up the connection and
+    /// issuing requests again after a reconnect are not counted.
+    fn request_sent(&self)
+    {
+    }
+
+    /// A reply from the daemon was parsed.
+    fn reply_parsed(&self)
+    {
+    }
+
+    /// A reply from the daemon could not be parsed and was skipped.
+    fn parse_failed(&self)
+    {
+    }
+
+    /// An event could not be delivered because its receiver was dropped.
+    fn event_dropped(&self)
+    {
+    }
+
+    /// The connection to the daemon was set up again after it was lost.
+    fn reconnected(&self)
+    {
+    }
+}
+
+/// A `MetricsSink` that discards all counts, used when none is set.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoopMetrics;
+
+impl MetricsSink for NoopMetrics {}
@@ -0,0 +1,592 @@
+// The engine behind `MulticastConnector`. It serves the IPC protocol on one end of an in-memory
+// stream and answers browse, resolve, query and address info requests with the records that
+// responders on the network send in reply to its mDNS queries.
+
+use std::collections::HashMap;
+use std::io;
+use std::net::SocketAddr;
+use std::time::{ Duration, Instant };
+
+use log::debug;
+use tokio::io::{ AsyncReadExt, AsyncWriteExt, DuplexStream, ReadHalf, WriteHalf };
+use tokio::sync::mpsc;
+
+use crate::dns_name;
+use crate::ipc::header::{ IpcMessageHeader, Operation, IPC_HEADER_SIZE, IPC_VERSION };
+use crate::ipc::header::reply::ReplyOperation;
+use crate::ipc::header::request::RequestOperation;
+use crate::ipc::operation::{ read_cstr, ServiceFlags };
+use crate::runtime;
+use crate::DnsServiceError;
+
+use super::message::{ self, Question, ResourceRecord, CLASS_IN, TYPE_A, TYPE_AAAA, TYPE_ANY, TYPE_PTR, TYPE_SRV, TYPE_TXT };
+
+// Queries are repeated after one second and then at doubling intervals up to this one, see
+// RFC 6762 section 5.2.
+const FIRST_QUERY_INTERVAL: Duration = Duration::from_secs(1);
+const MAX_QUERY_INTERVAL: Duration = Duration::from_secs(60);
+// Answers are not refreshed again when they expire within this time.
+const MIN_REFRESH_INTERVAL: Duration = Duration::from_secs(1);
+// The largest mDNS message, see RFC 6762 section 17.
+const MAX_PACKET_SIZE: usize = 9000;
+// Requests from this crate are far smaller, a larger one means the stream is out of sync.
+const MAX_REQUEST_SIZE: usize = 65536;
+const DEFAULT_DOMAIN: &str = "local.";
+// kDNSServiceProtocol_IPv4 and kDNSServiceProtocol_IPv6, neither of them means both.
+const PROTOCOL_IPV4: u32 = 0x1;
+const PROTOCOL_IPV6: u32 = 0x2;
+
+struct Request
+{
+    header: IpcMessageHeader,
+    data: Vec<u8>,
+}
+
+enum Kind
+{
+    // The number of labels of the browsed domain, to tell the service type from the domain in
+    // the names of instances.
+    Browse { domain_labels: usize },
+    Resolve,
+    Query,
+    AddressInfo,
+}
+
+// A received answer, reported as removed if it is not refreshed before it expires.
+struct Answer
+{
+    expires: Instant,
+    refresh: Instant,
+}
+
+// A running browse, resolve, query or address info request, all of which ask about one name.
+struct Lookup
+{
+    kind: Kind,
+    // The name asked about, in presentation format with a trailing dot.
+    name: String,
+    rrtypes: Vec<u16>,
+    rrclass: u16,
+    query: Vec<u8>,
+    // Keyed by record type and data.
+    answers: HashMap<(u16, Vec<u8>), Answer>,
+    next_query: Instant,
+    query_interval: Duration,
+    // The last resolve reply, so that an unchanged answer is not passed on again.
+    resolved: Option<Vec<u8>>,
+}
+
+struct Engine
+{
+    socket: runtime::UdpSocket,
+    destination: SocketAddr,
+    // Keyed by client context.
+    lookups: HashMap<u64, Lookup>,
+    // Frames to write to the client once the current request, packet or timer is handled.
+    replies: Vec<Vec<u8>>,
+}
+
+// Serves the client on the other end of `stream` until it closes the stream.
+pub async fn run(stream: DuplexStream, socket: runtime::UdpSocket, destination: SocketAddr)
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+    let (request_sender, mut requests) = mpsc::unbounded_channel();
+
+    // Reading a request is not cancel safe, so it is read on a task of its own rather than in
+    // the select below.
+    let reader_task = runtime::spawn(read_requests(reader, request_sender));
+
+    let mut engine = Engine
+    {
+        socket,
+        destination,
+        lookups: HashMap::new(),
+        replies: Vec::new(),
+    };
+
+    let mut packet = vec![0u8; MAX_PACKET_SIZE];
+
+    loop
+    {
+        let deadline = engine.next_deadline();
+
+        tokio::select!
+        {
+            request = requests.recv() => match request
+            {
+                Some(request) => engine.handle_request(request).await,
+                None => break,
+            },
+            received = engine.socket.recv_from(&mut packet) => match received
+            {
+                Ok((length, _)) => engine.handle_packet(&packet[..length]),
+                Err(e) => debug!("Failed to receive mDNS packet: {}", e),
+            },
+            _ = runtime::sleep_until(deadline) => engine.handle_timers().await,
+        }
+
+        if let Err(e) = write_replies(&mut writer, engine.replies.drain(..)).await
+        {
+            debug!("Multicast engine lost its client: {}", e);
+            break;
+        }
+    }
+
+    reader_task.abort();
+}
+
+async fn read_requests(mut reader: ReadHalf<DuplexStream>, requests: mpsc::UnboundedSender<Request>)
+{
+    loop
+    {
+        let mut header = [0u8; IPC_HEADER_SIZE];
+        if reader.read_exact(&mut header).await.is_err()
+        {
+            return;
+        }
+
+        let header = match IpcMessageHeader::from(&header)
+        {
+            Ok(header) => header,
+            Err(e) =>
+            {
+                debug!("Multicast engine received an invalid request: {}", e);
+                return;
+            }
+        };
+
+        let length = header.data_length as usize;
+        if length > MAX_REQUEST_SIZE
+        {
+            debug!("Multicast engine received a request of {} bytes", length);
+            return;
+        }
+
+        let mut data = vec![0u8; length];
+        if reader.read_exact(&mut data).await.is_err()
+        {
+            return;
+        }
+
+        if requests.send(Request { header, data }).is_err()
+        {
+            return;
+        }
+    }
+}
+
+async fn write_replies(writer: &mut WriteHalf<DuplexStream>, replies: impl Iterator<Item = Vec<u8>>) -> io::Result<()>
+{
+    for reply in replies
+    {
+        writer.write_all(&reply).await?;
+    }
+
+    return Ok(());
+}
+
+impl Engine
+{
+    async fn handle_request(&mut self, request: Request)
+    {
+        let context = request.header.client_context;
+        let Operation::Request(operation) = &request.header.operation else
+        {
+            return;
+        };
+
+        match operation
+        {
+            RequestOperation::Connection | RequestOperation::ConnectionDelegate => {}
+            RequestOperation::Cancel =>
+            {
+                self.lookups.remove(&context);
+            }
+            RequestOperation::Browse | RequestOperation::Resolve | RequestOperation::Query | RequestOperation::AddressInfo =>
+            {
+                match Lookup::from_request(operation, &request.data)
+                {
+                    Some(lookup) =>
+                    {
+                        self.send(&lookup.query).await;
+                        self.lookups.insert(context, lookup);
+                    }
+                    None => self.replies.push(reply_frame(ReplyOperation::AsyncError, context, 0, DnsServiceError::BadParam.code(), &[])),
+                }
+            }
+            // Answered with a bare error code, on a connection of their own.
+            RequestOperation::GetProperty | RequestOperation::SetDomain =>
+            {
+                self.replies.push(DnsServiceError::Unsupported.code().to_be_bytes().to_vec());
+            }
+            // Registering needs a responder that defends the names on the network.
+            _ =>
+            {
+                debug!("Multicast engine does not support {:?} requests", operation);
+                self.replies.push(reply_frame(ReplyOperation::AsyncError, context, 0, DnsServiceError::Unsupported.code(), &[]));
+            }
+        }
+    }
+
+    fn handle_packet(&mut self, packet: &[u8])
+    {
+        let Some(records) = message::parse_response(packet) else
+        {
+            return;
+        };
+
+        let now = Instant::now();
+
+        for (context, lookup) in self.lookups.iter_mut()
+        {
+            for record in &records
+            {
+                if lookup.matches(record)
+                {
+                    lookup.answer(*context, record, now, &mut self.replies);
+                }
+            }
+
+            if let Kind::Resolve = lookup.kind
+            {
+                lookup.resolve(*context, &mut self.replies);
+            }
+        }
+    }
+
+    async fn handle_timers(&mut self)
+    {
+        let now = Instant::now();
+        let mut queries = Vec::new();
+
+        for (context, lookup) in self.lookups.iter_mut()
+        {
+            let mut due = lookup.next_query <= now;
+
+            if due
+            {
+                lookup.next_query = now + lookup.query_interval;
+                lookup.query_interval = (lookup.query_interval * 2).min(MAX_QUERY_INTERVAL);
+            }
+
+            // Answers are asked for again from 80% of their TTL on, then each time half of the
+            // remaining time has passed, see RFC 6762 section 5.2.
+            for answer in lookup.answers.values_mut().filter(|answer| answer.refresh <= now)
+            {
+                due = true;
+
+                let remaining = answer.expires.saturating_duration_since(now);
+                answer.refresh = if remaining > MIN_REFRESH_INTERVAL { now + remaining / 2 } else { answer.expires };
+            }
+
+            if due
+            {
+                queries.push(lookup.query.clone());
+            }
+
+            lookup.expire(*context, now, &mut self.replies);
+        }
+
+        for query in queries
+        {
+            self.send(&query).await;
+        }
+    }
+
+    fn next_deadline(&self) -> Instant
+    {
+        let idle = Instant::now() + MAX_QUERY_INTERVAL;
+
+        return self.lookups
+            .values()
+            .flat_map(|lookup|
+                {
+                    let answers = lookup.answers.values().flat_map(|answer| [answer.refresh, answer.expires]);
+                    std::iter::once(lookup.next_query).chain(answers)
+                }
+            )
+            .fold(idle, Instant::min);
+    }
+
+    async fn send(&self, query: &[u8])
+    {
+        if let Err(e) = self.socket.send_to(query, self.destination).await
+        {
+            debug!("Failed to send mDNS query to {}: {}", self.destination, e);
+        }
+    }
+}
+
+impl Lookup
+{
+    // Sets up the lookup for a request, `None` if the request is malformed. Every request
+    // starts with its flags and interface index, the lookup asks on all interfaces.
+    fn from_request(operation: &RequestOperation, data: &[u8]) -> Option<Self>
+    {
+        if data.len() < 8
+        {
+            return None;
+        }
+
+        return match operation
+        {
+            RequestOperation::Browse =>
+            {
+                let (service_type, offset) = read_cstr(data, 8);
+                let (domain, _) = read_cstr(data, offset);
+
+                let domain = domain_or_default(&domain);
+                let domain_labels = dns_name::unescape_name(&domain)?.len();
+                let name = format!("{}.{}", service_type.trim_end_matches('.'), domain);
+
+                Self::new(Kind::Browse { domain_labels }, name, vec![TYPE_PTR], CLASS_IN)
+            }
+            RequestOperation::Resolve =>
+            {
+                let (instance, offset) = read_cstr(data, 8);
+                let (service_type, offset) = read_cstr(data, offset);
+                let (domain, _) = read_cstr(data, offset);
+
+                // Instance names are passed on as browsing reported them, with escapes, but may
+                // also be given as they are shown.
+                let name = dns_name::full_name(&dns_name::display_label(&instance), &service_type, &domain_or_default(&domain));
+
+                Self::new(Kind::Resolve, name, vec![TYPE_SRV, TYPE_TXT], CLASS_IN)
+            }
+            RequestOperation::Query =>
+            {
+                let (name, offset) = read_cstr(data, 8);
+                let fixed = data.get(offset..offset + 4)?;
+
+                let rrtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+                let rrclass = u16::from_be_bytes([fixed[2], fixed[3]]);
+
+                Self::new(Kind::Query, absolute_name(&name), vec![rrtype], rrclass)
+            }
+            RequestOperation::AddressInfo =>
+            {
+                let protocol = u32::from_be_bytes(data.get(8..12)?.try_into().ok()?);
+                let (hostname, _) = read_cstr(data, 12);
+
+                let mut rrtypes = Vec::new();
+                if protocol & PROTOCOL_IPV4 != 0 || protocol & PROTOCOL_IPV6 == 0
+                {
+                    rrtypes.push(TYPE_A);
+                }
+                if protocol & PROTOCOL_IPV6 != 0 || protocol & PROTOCOL_IPV4 == 0
+                {
+                    rrtypes.push(TYPE_AAAA);
+                }
+
+                Self::new(Kind::AddressInfo, absolute_name(&hostname), rrtypes, CLASS_IN)
+            }
+            _ => None,
+        };
+    }
+
+    fn new(kind: Kind, name: String, rrtypes: Vec<u16>, rrclass: u16) -> Option<Self>
+    {
+        if dns_name::unescape_name(&name)?.is_empty()
+        {
+            return None;
+        }
+
+        let questions: Vec<Question> = rrtypes.iter().map(|rrtype| Question { name: name.clone(), rrtype: *rrtype }).collect();
+        let query = message::encode_query(&questions)?;
+
+        return Some(Lookup
+        {
+            kind,
+            name,
+            rrtypes,
+            rrclass,
+            query,
+            answers: HashMap::new(),
+            next_query: Instant::now() + FIRST_QUERY_INTERVAL,
+            query_interval: FIRST_QUERY_INTERVAL * 2,
+            resolved: None,
+        });
+    }
+
+    fn matches(&self, record: &ResourceRecord) -> bool
+    {
+        let rrtype = self.rrtypes.contains(&record.rrtype) || self.rrtypes.contains(&TYPE_ANY);
+
+        return rrtype && record.rrclass == self.rrclass && record.name.eq_ignore_ascii_case(&self.name);
+    }
+
+    fn answer(&mut self, context: u64, record: &ResourceRecord, now: Instant, replies: &mut Vec<Vec<u8>>)
+    {
+        let key = (record.rrtype, record.rdata.clone());
+
+        // A TTL of zero says goodbye to a record that is gone, see RFC 6762 section 10.1.
+        if record.ttl == 0
+        {
+            if self.answers.remove(&key).is_some()
+            {
+                self.reply(context, &key, 0, replies);
+            }
+
+            return;
+        }
+
+        if !self.answers.contains_key(&key)
+        {
+            self.reply(context, &key, record.ttl, replies);
+        }
+
+        let lifetime = Duration::from_secs(record.ttl as u64);
+        self.answers.insert(key, Answer
+        {
+            expires: now + lifetime,
+            refresh: now + lifetime * 4 / 5,
+        });
+    }
+
+    fn expire(&mut self, context: u64, now: Instant, replies: &mut Vec<Vec<u8>>)
+    {
+        let expired: Vec<(u16, Vec<u8>)> = self.answers
+            .iter()
+            .filter(|(_, answer)| answer.expires <= now)
+            .map(|(key, _)| key.clone())
+            .collect();
+
+        for key in expired
+        {
+            self.answers.remove(&key);
+            self.reply(context, &key, 0, replies);
+
+            // Reported again once the service answers again.
+            self.resolved = None;
+        }
+    }
+
+    // Reports an answer that was added, or removed if the TTL is zero. Resolves report their
+    // answers once both records are in, see `resolve`.
+    fn reply(&self, context: u64, (rrtype, rdata): &(u16, Vec<u8>), ttl: u32, replies: &mut Vec<Vec<u8>>)
+    {
+        let flags = if ttl > 0 { ServiceFlags::Add as u32 } else { 0 };
+        let mut fields = Vec::new();
+
+        let operation = match self.kind
+        {
+            Kind::Browse { domain_labels } =>
+            {
+                // The name of an instance is its own label followed by the service type and the
+                // domain.
+                let Some(labels) = message::name_labels(rdata) else
+                {
+                    return;
+                };
+
+                if labels.len() < domain_labels + 2
+                {
+                    return;
+                }
+
+                let (instance, rest) = labels.split_at(1);
+                let (service_type, domain) = rest.split_at(rest.len() - domain_labels);
+
+                push_cstr(&mut fields, &dns_name::escape_label(&instance[0]));
+                push_cstr(&mut fields, &join_labels(service_type));
+                push_cstr(&mut fields, &join_labels(domain));
+                ReplyOperation::Browse
+            }
+            Kind::Resolve => return,
+            Kind::Query | Kind::AddressInfo =>
+            {
+                push_cstr(&mut fields, &self.name);
+                fields.extend_from_slice(&rrtype.to_be_bytes());
+                fields.extend_from_slice(&self.rrclass.to_be_bytes());
+                fields.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+                fields.extend_from_slice(rdata);
+                fields.extend_from_slice(&ttl.to_be_bytes());
+
+                match self.kind
+                {
+                    Kind::Query => ReplyOperation::Query,
+                    _ => ReplyOperation::AddressInfo,
+                }
+            }
+        };
+
+        replies.push(reply_frame(operation, context, flags, 0, &fields));
+    }
+
+    // Reports the host, port and TXT record of the service once its SRV record is in, and again
+    // whenever they change.
+    fn resolve(&mut self, context: u64, replies: &mut Vec<Vec<u8>>)
+    {
+        let record = |rrtype: u16| self.answers.keys().find(|key| key.0 == rrtype).map(|key| key.1.as_slice());
+
+        let Some((port, target)) = record(TYPE_SRV).and_then(message::srv) else
+        {
+            return;
+        };
+
+        // A TXT record that is not in yet is reported as an empty one, which holds a single
+        // empty string, see RFC 6763 section 6.1.
+        let txt = record(TYPE_TXT).unwrap_or(&[0]).to_vec();
+
+        let mut fields = Vec::new();
+        push_cstr(&mut fields, &self.name);
+        push_cstr(&mut fields, &target);
+        fields.extend_from_slice(&port.to_be_bytes());
+        fields.extend_from_slice(&(txt.len() as u16).to_be_bytes());
+        fields.extend_from_slice(&txt);
+
+        if self.resolved.as_ref() == Some(&fields)
+        {
+            return;
+        }
+
+        replies.push(reply_frame(ReplyOperation::Resolve, context, 0, 0, &fields));
+        self.resolved = Some(fields);
+    }
+}
+
+// A reply as the daemon sends it: the IPC header, the reply header with the flags, interface
+// index and error code, then the fields of the reply. Answers are not attributed to an
+// interface.
+fn reply_frame(operation: ReplyOperation, context: u64, flags: u32, error: i32, fields: &[u8]) -> Vec<u8>
+{
+    let mut body = Vec::with_capacity(12 + fields.len());
+    body.extend_from_slice(&flags.to_be_bytes());
+    body.extend_from_slice(&0u32.to_be_bytes()); // Interface index
+    body.extend_from_slice(&error.to_be_bytes());
+    body.extend_from_slice(fields);
+
+    let header = IpcMessageHeader::new(IPC_VERSION, body.len() as u32, 0, Operation::Reply(operation), context, 0);
+
+    let mut frame = header.to_bytes();
+    frame.extend_from_slice(&body);
+
+    return frame;
+}
+
+fn push_cstr(buf: &mut Vec<u8>, value: &str)
+{
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+fn join_labels(labels: &[String]) -> String
+{
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+
+    return dns_name::escape_name(&labels);
+}
+
+fn absolute_name(name: &str) -> String
+{
+    return if name.ends_with('.') { name.to_string() } else { format!("{}.", name) };
+}
+
+fn domain_or_default(domain: &str) -> String
+{
+    return match domain
+    {
+        "" | "." => DEFAULT_DOMAIN.to_string(),
+        domain => absolute_name(domain),
+    };
+}
@@ -0,0 +1,234 @@
+// DNS messages as sent to and received from the mDNS multicast group, see RFC 1035 and RFC 6762.
+
+use crate::dns_name;
+
+pub const TYPE_A: u16 = 1;
+pub const TYPE_NS: u16 = 2;
+pub const TYPE_CNAME: u16 = 5;
+pub const TYPE_PTR: u16 = 12;
+pub const TYPE_TXT: u16 = 16;
+pub const TYPE_AAAA: u16 = 28;
+pub const TYPE_SRV: u16 = 33;
+pub const TYPE_ANY: u16 = 255;
+pub const CLASS_IN: u16 = 1;
+
+const HEADER_SIZE: usize = 12;
+const FLAG_RESPONSE: u16 = 0x8000;
+// The top bit of the class is the cache-flush bit in answers, see RFC 6762 section 10.2.
+const CLASS_MASK: u16 = 0x7fff;
+// More compression pointers than any valid name needs, which stops pointer loops.
+const MAX_POINTERS: usize = 64;
+
+// A question of a query. The name is in presentation format, with escapes and a trailing dot.
+pub struct Question
+{
+    pub name: String,
+    pub rrtype: u16,
+}
+
+// A record of a response. Names within the data of PTR, CNAME, NS and SRV records are expanded,
+// so the data stands on its own like the record data of replies from the daemon.
+pub struct ResourceRecord
+{
+    // In presentation format, with escapes and a trailing dot.
+    pub name: String,
+    pub rrtype: u16,
+    pub rrclass: u16,
+    pub ttl: u32,
+    pub rdata: Vec<u8>,
+}
+
+// Builds a query for the given questions, `None` if a name is not valid.
+pub fn encode_query(questions: &[Question]) -> Option<Vec<u8>>
+{
+    let mut buf = Vec::with_capacity(512);
+
+    buf.extend_from_slice(&0u16.to_be_bytes()); // ID, 0 for multicast queries
+    buf.extend_from_slice(&0u16.to_be_bytes()); // Flags, a standard query
+    buf.extend_from_slice(&(questions.len() as u16).to_be_bytes());
+    buf.extend_from_slice(&[0; 6]); // No answer, authority or additional records
+
+    for question in questions
+    {
+        buf.extend_from_slice(&encode_name(&question.name)?);
+        buf.extend_from_slice(&question.rrtype.to_be_bytes());
+        buf.extend_from_slice(&CLASS_IN.to_be_bytes());
+    }
+
+    return Some(buf);
+}
+
+// Returns the answer, authority and additional records of a response, `None` if the message is
+// a query or malformed.
+pub fn parse_response(packet: &[u8]) -> Option<Vec<ResourceRecord>>
+{
+    if packet.len() < HEADER_SIZE
+    {
+        return None;
+    }
+
+    let flags = u16::from_be_bytes([packet[2], packet[3]]);
+    if flags & FLAG_RESPONSE == 0
+    {
+        return None;
+    }
+
+    let count = |offset: usize| u16::from_be_bytes([packet[offset], packet[offset + 1]]) as usize;
+    let questions = count(4);
+    let records = count(6) + count(8) + count(10);
+
+    let mut offset = HEADER_SIZE;
+
+    for _ in 0..questions
+    {
+        let (_, next) = read_name(packet, offset)?;
+        offset = next + 4; // Type and class
+    }
+
+    let mut parsed = Vec::with_capacity(records);
+
+    for _ in 0..records
+    {
+        let (record, next) = read_record(packet, offset)?;
+        parsed.push(record);
+        offset = next;
+    }
+
+    return Some(parsed);
+}
+
+// Returns the labels of an uncompressed name in wire format, e.g. the data of a PTR record.
+pub fn name_labels(wire: &[u8]) -> Option<Vec<String>>
+{
+    let (labels, _) = read_name(wire, 0)?;
+
+    return Some(labels.iter().map(|label| String::from_utf8_lossy(label).into_owned()).collect());
+}
+
+// Returns the port and target host of the data of a SRV record.
+pub fn srv(rdata: &[u8]) -> Option<(u16, String)>
+{
+    let port = u16::from_be_bytes(rdata.get(4..6)?.try_into().ok()?);
+    let target = dns_name::from_wire(rdata.get(6..)?)?;
+
+    return Some((port, target));
+}
+
+fn read_record(packet: &[u8], offset: usize) -> Option<(ResourceRecord, usize)>
+{
+    let (labels, offset) = read_name(packet, offset)?;
+    let fixed = packet.get(offset..offset + 10)?;
+
+    let rrtype = u16::from_be_bytes([fixed[0], fixed[1]]);
+    let rrclass = u16::from_be_bytes([fixed[2], fixed[3]]) & CLASS_MASK;
+    let ttl = u32::from_be_bytes([fixed[4], fixed[5], fixed[6], fixed[7]]);
+    let length = u16::from_be_bytes([fixed[8], fixed[9]]) as usize;
+
+    let start = offset + 10;
+    let end = start + length;
+    let rdata = packet.get(start..end)?;
+
+    let rdata = match rrtype
+    {
+        TYPE_PTR | TYPE_CNAME | TYPE_NS => encode_labels(&read_name(packet, start)?.0),
+        TYPE_SRV =>
+        {
+            let mut expanded = rdata.get(..6)?.to_vec(); // Priority, weight and port
+            expanded.extend_from_slice(&encode_labels(&read_name(packet, start + 6)?.0));
+            expanded
+        }
+        _ => rdata.to_vec(),
+    };
+
+    let record = ResourceRecord
+    {
+        name: presentation_name(&labels),
+        rrtype,
+        rrclass,
+        ttl,
+        rdata,
+    };
+
+    return Some((record, end));
+}
+
+// Reads a name that may be compressed. Returns its labels and the offset after the name where it
+// started, not where a pointer led.
+fn read_name(packet: &[u8], mut offset: usize) -> Option<(Vec<Vec<u8>>, usize)>
+{
+    let mut labels = Vec::new();
+    let mut end = None;
+    let mut pointers = 0;
+
+    loop
+    {
+        let length = *packet.get(offset)? as usize;
+
+        match length & 0xc0
+        {
+            0x00 if length == 0 =>
+            {
+                return Some((labels, end.unwrap_or(offset + 1)));
+            }
+            0x00 =>
+            {
+                labels.push(packet.get(offset + 1..offset + 1 + length)?.to_vec());
+                offset += 1 + length;
+            }
+            0xc0 =>
+            {
+                pointers += 1;
+                if pointers > MAX_POINTERS
+                {
+                    return None;
+                }
+
+                let low = *packet.get(offset + 1)? as usize;
+                end.get_or_insert(offset + 2);
+                offset = ((length & 0x3f) << 8) | low;
+            }
+            _ => return None,
+        }
+    }
+}
+
+fn encode_name(name: &str) -> Option<Vec<u8>>
+{
+    let labels = dns_name::unescape_name(name)?;
+    let labels: Vec<Vec<u8>> = labels.into_iter().map(String::into_bytes).collect();
+
+    if labels.iter().any(|label| label.len() > 63)
+    {
+        return None;
+    }
+
+    return Some(encode_labels(&labels));
+}
+
+fn encode_labels(labels: &[Vec<u8>]) -> Vec<u8>
+{
+    let mut buf = Vec::new();
+
+    for label in labels
+    {
+        buf.push(label.len() as u8);
+        buf.extend_from_slice(label);
+    }
+
+    buf.push(0);
+
+    return buf;
+}
+
+fn presentation_name(labels: &[Vec<u8>]) -> String
+{
+    if labels.is_empty()
+    {
+        return ".".to_string();
+    }
+
+    let labels: Vec<String> = labels.iter().map(|label| String::from_utf8_lossy(label).into_owned()).collect();
+    let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+
+    return dns_name::escape_name(&labels);
+}
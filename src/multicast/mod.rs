@@ -0,0 +1,250 @@
+//! Built-in mDNS engine for systems where no daemon runs, such as containers and stripped-down
+//! Linux installations, enabled with the `multicast-fallback` feature.
+//!
+//! `MulticastConnector` answers requests itself instead of passing them on to a daemon. It sends
+//! mDNS queries to the multicast group 224.0.0.251 and turns the PTR, SRV, TXT, A and AAAA
+//! records of the responses into the same events a daemon's replies produce. Browsing,
+//! resolving, querying for records and looking up addresses are supported; registering services
+//! is not and fails with `DnsServiceError::Unsupported`. Answers are not attributed to an
+//! interface, their interface index is 0.
+//!
+//! It is usually set up as a fallback with `MDnsResponderBuilder::multicast_fallback`, which
+//! only uses it when there is no daemon to connect to.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use mdnsresponder::{Interface, MDnsResponder};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let responder = MDnsResponder::builder().multicast_fallback(true).build().await?;
+//!     let mut browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+//!     while let Some(event) = browse.events.recv().await {
+//!         println!("{:?}", event);
+//!     }
+//!     Ok(())
+//! }
+//! ```
+
+use std::io;
+use std::net::{ Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket };
+use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd };
+use std::sync::Arc;
+use std::sync::atomic::{ AtomicBool, Ordering };
+
+use log::{ debug, info };
+
+use crate::{ runtime, ConnectFuture, Connection, Connector, Delegate, Transport };
+
+mod engine;
+mod message;
+
+/// The mDNS multicast group and port, see RFC 6762.
+pub const MDNS_GROUP: SocketAddrV4 = SocketAddrV4::new(Ipv4Addr::new(224, 0, 0, 251), 5353);
+
+// Size of the in-memory stream between the client and the engine in each direction.
+const STREAM_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Connector that answers requests with the built-in mDNS engine, see the module documentation.
+/// Every connection runs an engine with a socket of its own.
+#[derive(Debug, Clone)]
+pub struct MulticastConnector
+{
+    destination: SocketAddr,
+}
+
+impl Default for MulticastConnector
+{
+    fn default() -> Self
+    {
+        return MulticastConnector
+        {
+            destination: SocketAddr::V4(MDNS_GROUP),
+        };
+    }
+}
+
+impl MulticastConnector
+{
+    /// Queries the mDNS multicast group.
+    pub fn new() -> Self
+    {
+        return Self::default();
+    }
+
+    /// Sends the queries to another address instead of the multicast group, e.g. a responder on
+    /// the loopback interface in tests. The responder has to answer to the address the queries
+    /// came from.
+    pub fn with_destination(destination: SocketAddr) -> Self
+    {
+        return MulticastConnector
+        {
+            destination,
+        };
+    }
+}
+
+impl Connector for MulticastConnector
+{
+    fn connect(&self, delegate: Option<Delegate>) -> ConnectFuture<'_>
+    {
+        let destination = self.destination;
+
+        return Box::pin(async move
+        {
+            // The engine answers for this process only, it has no socket to set a UUID on.
+            if let Some(Delegate::Uuid(_)) = delegate
+            {
+                return Err(crate::transport::delegate_not_supported());
+            }
+
+            let socket = runtime::udp_socket_from_std(bind(destination)?)?;
+            let (client, server) = tokio::io::duplex(STREAM_BUFFER_SIZE);
+
+            runtime::spawn_detached(engine::run(server, socket, destination));
+
+            return Ok(Box::new(client) as Box<dyn Connection>);
+        });
+    }
+}
+
+// Connects through another connector and falls back to the engine when there is no daemon to
+// connect to, see `MDnsResponderBuilder::multicast_fallback`.
+#[derive(Debug)]
+pub(crate) struct FallbackConnector
+{
+    inner: Arc<dyn Connector>,
+    engine: MulticastConnector,
+    // Whether the last connection was opened on the engine.
+    fell_back: AtomicBool,
+}
+
+impl FallbackConnector
+{
+    pub(crate) fn wrap(inner: Arc<dyn Connector>) -> Self
+    {
+        return FallbackConnector
+        {
+            inner,
+            engine: MulticastConnector::new(),
+            fell_back: AtomicBool::new(false),
+        };
+    }
+}
+
+impl Connector for FallbackConnector
+{
+    fn connect(&self, delegate: Option<Delegate>) -> ConnectFuture<'_>
+    {
+        return Box::pin(async move
+        {
+            // A socket that nothing listens on is left behind by a daemon that stopped.
+            let result = match self.inner.connect(delegate).await
+            {
+                Err(e) if matches!(e.kind(), io::ErrorKind::NotFound | io::ErrorKind::ConnectionRefused) =>
+                {
+                    info!("No daemon to connect to ({}), using the built-in mDNS engine", e);
+                    self.fell_back.store(true, Ordering::Relaxed);
+                    return self.engine.connect(delegate).await;
+                }
+                result => result,
+            };
+
+            self.fell_back.store(false, Ordering::Relaxed);
+            return result;
+        });
+    }
+
+    fn daemon_instance(&self) -> Option<u64>
+    {
+        return self.inner.daemon_instance();
+    }
+
+    fn transport(&self) -> Option<Transport>
+    {
+        if self.fell_back.load(Ordering::Relaxed)
+        {
+            return None;
+        }
+
+        return self.inner.transport();
+    }
+}
+
+// Binds the mDNS port and joins the multicast group, so responses that are sent to the group
+// are received as well. If the port cannot be shared, the queries are sent from another port
+// instead, which responders answer directly, with TTLs of at most 10 seconds, see RFC 6762
+// section 6.7.
+fn bind(destination: SocketAddr) -> io::Result<UdpSocket>
+{
+    if destination == SocketAddr::V4(MDNS_GROUP)
+    {
+        match bind_shared(MDNS_GROUP.port())
+        {
+            Ok(socket) =>
+            {
+                socket.join_multicast_v4(MDNS_GROUP.ip(), &Ipv4Addr::UNSPECIFIED)?;
+                return Ok(socket);
+            }
+            Err(e) => debug!("Failed to bind the mDNS port, sending queries from another port: {}", e),
+        }
+    }
+
+    return UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0));
+}
+
+// Binds a UDP socket to a port that other processes, such as other engines, may bind as well.
+fn bind_shared(port: u16) -> io::Result<UdpSocket>
+{
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if fd < 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    // Closes the socket if setting it up fails.
+    let fd = unsafe { OwnedFd::from_raw_fd(fd) };
+
+    let enable: libc::c_int = 1;
+    for option in [libc::SO_REUSEADDR, libc::SO_REUSEPORT]
+    {
+        let result = unsafe {
+            libc::setsockopt(
+                fd.as_raw_fd(),
+                libc::SOL_SOCKET,
+                option,
+                &enable as *const libc::c_int as *const libc::c_void,
+                std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+
+        if result != 0
+        {
+            return Err(io::Error::last_os_error());
+        }
+    }
+
+    let address = libc::sockaddr_in
+    {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: port.to_be(),
+        sin_addr: libc::in_addr { s_addr: u32::from(Ipv4Addr::UNSPECIFIED).to_be() },
+        ..unsafe { std::mem::zeroed() }
+    };
+
+    let result = unsafe {
+        libc::bind(
+            fd.as_raw_fd(),
+            &address as *const libc::sockaddr_in as *const libc::sockaddr,
+            std::mem::size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+
+    if result != 0
+    {
+        return Err(io::Error::last_os_error());
+    }
+
+    return Ok(UdpSocket::from(fd));
+}
@@ -0,0 +1,78 @@
+use std::pin::Pin;
+use std::task::{ Context, Poll };
+
+use futures_core::Stream;
+use tokio::sync::mpsc;
+
+use crate::ipc;
+use crate::MDnsResponderEvent;
+
+/// Handle to a running browse, resolve or address info operation.
+///
+/// Only events produced by this operation are delivered on its `events` channel, they do not
+/// show up on `MDnsResponder::events`. Dropping the handle cancels the operation with the daemon,
+/// without waiting for the cancel to be written. Use `MDnsResponder::cancel` to know it was.
+///
+/// The handle is also a `Stream` of the operation's events.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use futures::StreamExt;
+/// use mdnsresponder::{Interface, MDnsResponder, MDnsResponderEvent};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+///     let mut added = browse.filter(|event| std::future::ready(matches!(event, MDnsResponderEvent::ServiceAdded(_))));
+///     while let Some(event) = added.next().await {
+///         println!("{:?}", event);
+///     }
+///     Ok(())
+/// }
+/// ```
+pub struct Operation
+{
+    context: u64,
+    pub events: mpsc::Receiver<MDnsResponderEvent>,
+    writer: ipc::Writer,
+}
+
+impl Operation
+{
+    pub(crate) fn new(context: u64, events: mpsc::Receiver<MDnsResponderEvent>, writer: ipc::Writer) -> Self
+    {
+        return Operation
+        {
+            context,
+            events,
+            writer,
+        };
+    }
+
+    /// Returns the context identifier of the operation, used to cancel it.
+    pub fn context(&self) -> u64
+    {
+        return self.context;
+    }
+}
+
+impl Stream for Operation
+{
+    type Item = MDnsResponderEvent;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>>
+    {
+        return self.events.poll_recv(cx);
+    }
+}
+
+impl Drop for Operation
+{
+    fn drop(&mut self)
+    {
+        // Does nothing if the operation was already cancelled.
+        self.writer.cancel_on_drop(self.context);
+    }
+}
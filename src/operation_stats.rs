@@ -0,0 +1,85 @@
+use std::time::Instant;
+
+use crate::MDnsResponderEvent;
+
+/// Counters of a single operation, returned by `MDnsRequester::stats`.
+///
+/// Replies are counted as they arrive. Adds, removes and errors are counted from the events
+/// delivered for the operation, so answers suppressed by deduplication or debouncing are counted
+/// as replies only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct OperationStats
+{
+    /// Replies received from the daemon.
+    pub replies: u64,
+    /// Events delivered that add an answer, e.g. `ServiceAdded` or `AddressInfoResolved`. Each
+    /// service in a `ServicesBatch` is counted.
+    pub adds: u64,
+    /// Events delivered that withdraw an answer, e.g. `ServiceRemoved` or `AddressExpired`.
+    pub removes: u64,
+    /// Events delivered that report a failure, e.g. `OperationFailed` or `Timeout`.
+    pub errors: u64,
+    /// When the last reply was received or event delivered.
+    pub last_activity: Instant,
+}
+
+impl OperationStats
+{
+    pub(crate) fn new() -> Self
+    {
+        return OperationStats
+        {
+            replies: 0,
+            adds: 0,
+            removes: 0,
+            errors: 0,
+            last_activity: Instant::now(),
+        };
+    }
+
+    // Counts an event delivered for the operation.
+    pub(crate) fn count_event(&mut self, event: &MDnsResponderEvent)
+    {
+        match event
+        {
+            MDnsResponderEvent::ServiceAdded(_)
+            | MDnsResponderEvent::ServiceResolved(_)
+            | MDnsResponderEvent::ServiceDiscovered(_)
+            | MDnsResponderEvent::ServiceTypeDiscovered(_)
+            | MDnsResponderEvent::AddressInfoResolved(_)
+            | MDnsResponderEvent::ServiceRegistered(_)
+            | MDnsResponderEvent::RecordAdded(_)
+            | MDnsResponderEvent::DomainAdded(_)
+            | MDnsResponderEvent::PortMappingCreated(_)
+            | MDnsResponderEvent::RecordRegistered { .. } =>
+            {
+                self.adds += 1;
+            }
+            MDnsResponderEvent::ServiceRemoved(_)
+            | MDnsResponderEvent::ServiceTypeRemoved(_)
+            | MDnsResponderEvent::AddressInfoRemoved(_)
+            | MDnsResponderEvent::RecordRemoved(_)
+            | MDnsResponderEvent::AddressExpired(_)
+            | MDnsResponderEvent::RecordExpired(_)
+            | MDnsResponderEvent::DomainRemoved(_)
+            | MDnsResponderEvent::NoSuchRecord { .. } =>
+            {
+                self.removes += 1;
+            }
+            MDnsResponderEvent::ServicesBatch { added, removed } =>
+            {
+                self.adds += added.len() as u64;
+                self.removes += removed.len() as u64;
+            }
+            MDnsResponderEvent::OperationFailed { .. }
+            | MDnsResponderEvent::Timeout { .. }
+            | MDnsResponderEvent::NameConflict(_) =>
+            {
+                self.errors += 1;
+            }
+            _ => {}
+        }
+
+        self.last_activity = Instant::now();
+    }
+}
@@ -0,0 +1,678 @@
+use crate::ipc::ServiceFlags;
+use crate::mdnsresponder_error::MDnsResponderError;
+use crate::{ dns_name, Interface, Protocol, ServiceType, TxtRecord };
+
+/// Options for `MDnsRequester::browse_with_options`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{BrowseOptions, Interface, MDnsResponder};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let options = BrowseOptions::new().interface(Interface::Index(4)).include_p2p(true);
+///     let browse = responder.browse_with_options("_http._tcp".to_string(), options).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct BrowseOptions
+{
+    pub(crate) interface: Interface,
+    pub(crate) domain: String,
+    pub(crate) include_p2p: bool,
+    pub(crate) include_awdl: bool,
+    pub(crate) subtype: Option<String>,
+    pub(crate) threshold_one: bool,
+    pub(crate) threshold_finder: bool,
+    pub(crate) auto_resolve: bool,
+    pub(crate) acknowledge: bool,
+}
+
+impl BrowseOptions
+{
+    /// Browses on all interfaces in the default browse domains.
+    pub fn new() -> Self
+    {
+        return BrowseOptions::default();
+    }
+
+    /// Sets the interface to browse on, `Interface::Any` by default.
+    pub fn interface(mut self, interface: Interface) -> Self
+    {
+        self.interface = interface;
+        return self;
+    }
+
+    /// Sets the domain to browse in (e.g., "local"). By default the daemon browses in its default
+    /// browse domains.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self
+    {
+        self.domain = domain.into();
+        return self;
+    }
+
+    /// Includes peer-to-peer interfaces when browsing on `Interface::Any`.
+    pub fn include_p2p(mut self, include_p2p: bool) -> Self
+    {
+        self.include_p2p = include_p2p;
+        return self;
+    }
+
+    /// Includes AWDL when browsing on `Interface::Any`.
+    pub fn include_awdl(mut self, include_awdl: bool) -> Self
+    {
+        self.include_awdl = include_awdl;
+        return self;
+    }
+
+    /// Only finds the instances that were registered with the given subtype (e.g., "_printer").
+    pub fn subtype(mut self, subtype: impl Into<String>) -> Self
+    {
+        self.subtype = Some(subtype.into());
+        return self;
+    }
+
+    /// Stops sending browse queries once one service is found, reported with a
+    /// `MDnsResponderEvent::ThresholdReached` event.
+    pub fn threshold_one(mut self, threshold_one: bool) -> Self
+    {
+        self.threshold_one = threshold_one;
+        return self;
+    }
+
+    /// Stops sending browse queries once the daemon has found enough services for a peer finder
+    /// to show, reported with a `MDnsResponderEvent::ThresholdReached` event.
+    pub fn threshold_finder(mut self, threshold_finder: bool) -> Self
+    {
+        self.threshold_finder = threshold_finder;
+        return self;
+    }
+
+    /// Resolves every added service before reporting it, with the first answer in
+    /// `Service::resolved`. Events stay in order, so each one waits for the resolve of an added
+    /// service before it, for a few seconds at most.
+    pub fn auto_resolve(mut self, auto_resolve: bool) -> Self
+    {
+        self.auto_resolve = auto_resolve;
+        return self;
+    }
+
+    /// Waits for the daemon to accept the browse before returning, so that one it rejects, e.g.
+    /// for a malformed service type or by policy, fails with `MDnsResponderError::DaemonError`
+    /// instead of never producing events. Needs the Unix transport, see
+    /// `MDnsResponderBuilder::synchronous_errors` to do this for every request.
+    pub fn acknowledge(mut self, acknowledge: bool) -> Self
+    {
+        self.acknowledge = acknowledge;
+        return self;
+    }
+
+    pub(crate) fn service_flags(&self) -> u32
+    {
+        let mut flags = peer_to_peer_flags(self.include_p2p, self.include_awdl);
+
+        if self.threshold_one
+        {
+            flags |= ServiceFlags::ThresholdOne as u32;
+        }
+
+        if self.threshold_finder
+        {
+            flags |= ServiceFlags::ThresholdFinder as u32;
+        }
+
+        return flags;
+    }
+
+    // A browse is for a single subtype, either of the service type or of the options.
+    pub(crate) fn browse_type(&self, service_type: ServiceType) -> Result<String, MDnsResponderError>
+    {
+        return match (&self.subtype, service_type.subtype())
+        {
+            (Some(_), Some(_)) => Err(MDnsResponderError::InvalidSubtype),
+            (Some(subtype), None) if !dns_name::is_valid_subtype(subtype) => Err(MDnsResponderError::InvalidSubtype),
+            (Some(subtype), None) => Ok(dns_name::subtype_browse_type(subtype, &service_type.to_string())),
+            (None, _) => Ok(service_type.to_string()),
+        };
+    }
+}
+
+/// Options for `MDnsRequester::resolve_with_options`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{MDnsResponder, ResolveOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let options = ResolveOptions::new().force_multicast(true);
+///     let resolve = responder.resolve_with_options("My Service".to_string(), "_http._tcp".to_string(), options).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ResolveOptions
+{
+    pub(crate) interface: Interface,
+    pub(crate) domain: String,
+    pub(crate) include_p2p: bool,
+    pub(crate) include_awdl: bool,
+    pub(crate) force_multicast: bool,
+    pub(crate) acknowledge: bool,
+}
+
+impl Default for ResolveOptions
+{
+    fn default() -> Self
+    {
+        return ResolveOptions
+        {
+            interface: Interface::Any,
+            domain: "local.".to_string(),
+            include_p2p: false,
+            include_awdl: false,
+            force_multicast: false,
+            acknowledge: false,
+        };
+    }
+}
+
+impl ResolveOptions
+{
+    /// Resolves on all interfaces in the "local." domain.
+    pub fn new() -> Self
+    {
+        return ResolveOptions::default();
+    }
+
+    /// Sets the interface to resolve on, `Interface::Any` by default. Usually the interface the
+    /// service was found on.
+    pub fn interface(mut self, interface: Interface) -> Self
+    {
+        self.interface = interface;
+        return self;
+    }
+
+    /// Sets the domain of the service, "local." by default.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self
+    {
+        self.domain = domain.into();
+        return self;
+    }
+
+    /// Includes peer-to-peer interfaces when resolving on `Interface::Any`.
+    pub fn include_p2p(mut self, include_p2p: bool) -> Self
+    {
+        self.include_p2p = include_p2p;
+        return self;
+    }
+
+    /// Includes AWDL when resolving on `Interface::Any`.
+    pub fn include_awdl(mut self, include_awdl: bool) -> Self
+    {
+        self.include_awdl = include_awdl;
+        return self;
+    }
+
+    /// Resolves with multicast even if the domain is not "local.".
+    pub fn force_multicast(mut self, force_multicast: bool) -> Self
+    {
+        self.force_multicast = force_multicast;
+        return self;
+    }
+
+    /// Waits for the daemon to accept the resolve before returning, like
+    /// `BrowseOptions::acknowledge`.
+    pub fn acknowledge(mut self, acknowledge: bool) -> Self
+    {
+        self.acknowledge = acknowledge;
+        return self;
+    }
+
+    pub(crate) fn service_flags(&self) -> u32
+    {
+        let mut flags = peer_to_peer_flags(self.include_p2p, self.include_awdl);
+
+        if self.force_multicast
+        {
+            flags |= ServiceFlags::ForceMulticast as u32;
+        }
+
+        return flags;
+    }
+}
+
+/// Options for `MDnsRequester::get_addr_info_with_options`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{AddrInfoOptions, MDnsResponder, Protocol};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let options = AddrInfoOptions::new().protocol(Protocol::IPv6);
+///     let lookup = responder.get_addr_info_with_options("example.local".to_string(), options).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AddrInfoOptions
+{
+    pub(crate) interface: Interface,
+    pub(crate) protocol: Protocol,
+    pub(crate) include_p2p: bool,
+    pub(crate) include_awdl: bool,
+    pub(crate) force_multicast: bool,
+    pub(crate) long_lived_query: bool,
+    pub(crate) timeout: bool,
+    pub(crate) allow_expired_answers: bool,
+    pub(crate) validate: bool,
+    pub(crate) suppress_unusable: bool,
+}
+
+impl Default for AddrInfoOptions
+{
+    fn default() -> Self
+    {
+        return AddrInfoOptions
+        {
+            interface: Interface::Any,
+            protocol: Protocol::Both,
+            include_p2p: false,
+            include_awdl: false,
+            force_multicast: false,
+            long_lived_query: false,
+            timeout: false,
+            allow_expired_answers: false,
+            validate: false,
+            suppress_unusable: false,
+        };
+    }
+}
+
+impl AddrInfoOptions
+{
+    /// Looks up IPv4 and IPv6 addresses on all interfaces.
+    pub fn new() -> Self
+    {
+        return AddrInfoOptions::default();
+    }
+
+    /// Sets the interface to look up the addresses on, `Interface::Any` by default.
+    pub fn interface(mut self, interface: Interface) -> Self
+    {
+        self.interface = interface;
+        return self;
+    }
+
+    /// Sets which address families to look up, `Protocol::Both` by default.
+    pub fn protocol(mut self, protocol: Protocol) -> Self
+    {
+        self.protocol = protocol;
+        return self;
+    }
+
+    /// Includes peer-to-peer interfaces when looking up on `Interface::Any`.
+    pub fn include_p2p(mut self, include_p2p: bool) -> Self
+    {
+        self.include_p2p = include_p2p;
+        return self;
+    }
+
+    /// Includes AWDL when looking up on `Interface::Any`.
+    pub fn include_awdl(mut self, include_awdl: bool) -> Self
+    {
+        self.include_awdl = include_awdl;
+        return self;
+    }
+
+    /// Looks up with multicast even if the hostname is not in the "local." domain.
+    pub fn force_multicast(mut self, force_multicast: bool) -> Self
+    {
+        self.force_multicast = force_multicast;
+        return self;
+    }
+
+    /// Uses a long-lived query for hostnames in wide-area domains, so the server pushes changes
+    /// instead of the answers only being looked up once.
+    pub fn long_lived_query(mut self, long_lived_query: bool) -> Self
+    {
+        self.long_lived_query = long_lived_query;
+        return self;
+    }
+
+    /// Asks the daemon to give up when no answer arrives within its timeout, which is reported
+    /// with a `MDnsResponderEvent::Timeout` event.
+    pub fn timeout(mut self, timeout: bool) -> Self
+    {
+        self.timeout = timeout;
+        return self;
+    }
+
+    /// Delivers expired answers from the cache right away while the daemon looks for fresh
+    /// ones. Expired answers have `ReplyFlags::expired` set.
+    pub fn allow_expired_answers(mut self, allow_expired_answers: bool) -> Self
+    {
+        self.allow_expired_answers = allow_expired_answers;
+        return self;
+    }
+
+    /// Validates the answers with DNSSEC. The result is reported in `ReplyFlags::dnssec`.
+    pub fn validate(mut self, validate: bool) -> Self
+    {
+        self.validate = validate;
+        return self;
+    }
+
+    /// Leaves out addresses of a family the host has no usable route for, such as IPv6 addresses
+    /// on a network without IPv6 connectivity.
+    pub fn suppress_unusable(mut self, suppress_unusable: bool) -> Self
+    {
+        self.suppress_unusable = suppress_unusable;
+        return self;
+    }
+
+    pub(crate) fn service_flags(&self) -> u32
+    {
+        let mut flags = peer_to_peer_flags(self.include_p2p, self.include_awdl);
+
+        if self.force_multicast
+        {
+            flags |= ServiceFlags::ForceMulticast as u32;
+        }
+
+        if self.long_lived_query
+        {
+            flags |= ServiceFlags::LongLivedQuery as u32;
+        }
+
+        if self.timeout
+        {
+            flags |= ServiceFlags::Timeout as u32;
+        }
+
+        if self.allow_expired_answers
+        {
+            flags |= ServiceFlags::AllowExpiredAnswers as u32;
+        }
+
+        if self.validate
+        {
+            flags |= ServiceFlags::Validate as u32;
+        }
+
+        if self.suppress_unusable
+        {
+            flags |= ServiceFlags::SuppressUnusable as u32;
+        }
+
+        return flags;
+    }
+}
+
+/// Options for `MDnsRequester::query_with_options`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{MDnsResponder, QueryOptions};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let options = QueryOptions::new().long_lived_query(true);
+///     let context = responder.query_with_options("_http._tcp.example.com.".to_string(), 12, 1, options).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct QueryOptions
+{
+    pub(crate) interface: Interface,
+    pub(crate) include_p2p: bool,
+    pub(crate) include_awdl: bool,
+    pub(crate) force_multicast: bool,
+    pub(crate) long_lived_query: bool,
+    pub(crate) timeout: bool,
+    pub(crate) allow_expired_answers: bool,
+    pub(crate) validate: bool,
+}
+
+impl QueryOptions
+{
+    /// Queries on all interfaces.
+    pub fn new() -> Self
+    {
+        return QueryOptions::default();
+    }
+
+    /// Sets the interface to query on, `Interface::Any` by default.
+    pub fn interface(mut self, interface: Interface) -> Self
+    {
+        self.interface = interface;
+        return self;
+    }
+
+    /// Includes peer-to-peer interfaces when querying on `Interface::Any`.
+    pub fn include_p2p(mut self, include_p2p: bool) -> Self
+    {
+        self.include_p2p = include_p2p;
+        return self;
+    }
+
+    /// Includes AWDL when querying on `Interface::Any`.
+    pub fn include_awdl(mut self, include_awdl: bool) -> Self
+    {
+        self.include_awdl = include_awdl;
+        return self;
+    }
+
+    /// Queries with multicast even if the name is not in the "local." domain.
+    pub fn force_multicast(mut self, force_multicast: bool) -> Self
+    {
+        self.force_multicast = force_multicast;
+        return self;
+    }
+
+    /// Uses a long-lived query for names in wide-area domains, so the server pushes changes
+    /// instead of the records only being looked up once.
+    pub fn long_lived_query(mut self, long_lived_query: bool) -> Self
+    {
+        self.long_lived_query = long_lived_query;
+        return self;
+    }
+
+    /// Asks the daemon to give up when no answer arrives within its timeout, which is reported
+    /// with a `MDnsResponderEvent::Timeout` event.
+    pub fn timeout(mut self, timeout: bool) -> Self
+    {
+        self.timeout = timeout;
+        return self;
+    }
+
+    /// Delivers expired answers from the cache right away while the daemon looks for fresh
+    /// ones. Expired answers have `ReplyFlags::expired` set.
+    pub fn allow_expired_answers(mut self, allow_expired_answers: bool) -> Self
+    {
+        self.allow_expired_answers = allow_expired_answers;
+        return self;
+    }
+
+    /// Validates the answers with DNSSEC. The result is reported in `ReplyFlags::dnssec`.
+    pub fn validate(mut self, validate: bool) -> Self
+    {
+        self.validate = validate;
+        return self;
+    }
+
+    pub(crate) fn service_flags(&self) -> u32
+    {
+        let mut flags = peer_to_peer_flags(self.include_p2p, self.include_awdl);
+
+        if self.force_multicast
+        {
+            flags |= ServiceFlags::ForceMulticast as u32;
+        }
+
+        if self.long_lived_query
+        {
+            flags |= ServiceFlags::LongLivedQuery as u32;
+        }
+
+        if self.timeout
+        {
+            flags |= ServiceFlags::Timeout as u32;
+        }
+
+        if self.allow_expired_answers
+        {
+            flags |= ServiceFlags::AllowExpiredAnswers as u32;
+        }
+
+        if self.validate
+        {
+            flags |= ServiceFlags::Validate as u32;
+        }
+
+        return flags;
+    }
+}
+
+fn peer_to_peer_flags(include_p2p: bool, include_awdl: bool) -> u32
+{
+    let mut flags = ServiceFlags::None as u32;
+
+    if include_p2p
+    {
+        flags |= ServiceFlags::IncludeP2p as u32;
+    }
+
+    if include_awdl
+    {
+        flags |= ServiceFlags::IncludeAwdl as u32;
+    }
+
+    return flags;
+}
+
+/// Options for `MDnsRequester::register_with_options`.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{MDnsResponder, RegisterOptions, TxtRecord};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let txt = TxtRecord::builder().value("rp", "printers/1").build()?;
+///     let options = RegisterOptions::new().txt(&txt).subtype("_printer").subtype("_scanner");
+///     let registration = responder.register_with_options("Office".to_string(), "_ipp._tcp".to_string(), 631, options).await?;
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RegisterOptions
+{
+    pub(crate) interface: Interface,
+    pub(crate) domain: String,
+    pub(crate) host: String,
+    pub(crate) txt: Vec<u8>,
+    pub(crate) subtypes: Vec<String>,
+    pub(crate) no_auto_rename: bool,
+    pub(crate) acknowledge: bool,
+}
+
+impl RegisterOptions
+{
+    /// Registers on all interfaces in the default registration domains, for this host, with an
+    /// empty TXT record.
+    pub fn new() -> Self
+    {
+        return RegisterOptions::default();
+    }
+
+    /// Sets the interface to register on, `Interface::Any` by default.
+    pub fn interface(mut self, interface: Interface) -> Self
+    {
+        self.interface = interface;
+        return self;
+    }
+
+    /// Sets the domain to register in (e.g., "local"). By default the daemon registers in its
+    /// default registration domains.
+    pub fn domain(mut self, domain: impl Into<String>) -> Self
+    {
+        self.domain = domain.into();
+        return self;
+    }
+
+    /// Sets the hostname the service runs on (e.g., "myhost.local"), this host by default.
+    pub fn host(mut self, host: impl Into<String>) -> Self
+    {
+        self.host = host.into();
+        return self;
+    }
+
+    /// Sets the TXT record of the service.
+    pub fn txt(mut self, txt: &TxtRecord) -> Self
+    {
+        self.txt = txt.to_bytes();
+        return self;
+    }
+
+    /// Adds a subtype (e.g., "_printer") the service can also be browsed for, see
+    /// `BrowseOptions::subtype`.
+    pub fn subtype(mut self, subtype: impl Into<String>) -> Self
+    {
+        self.subtypes.push(subtype.into());
+        return self;
+    }
+
+    /// Keeps the exact name instead of letting the daemon rename the service if the name is
+    /// already taken, see `MDnsRequester::register_no_auto_rename`.
+    pub fn no_auto_rename(mut self, no_auto_rename: bool) -> Self
+    {
+        self.no_auto_rename = no_auto_rename;
+        return self;
+    }
+
+    /// Waits for the daemon to accept the registration before returning, like
+    /// `BrowseOptions::acknowledge`. A name conflict is still reported later, as it is only found
+    /// while the name is probed on the network.
+    pub fn acknowledge(mut self, acknowledge: bool) -> Self
+    {
+        self.acknowledge = acknowledge;
+        return self;
+    }
+
+    pub(crate) fn service_flags(&self) -> u32
+    {
+        if self.no_auto_rename
+        {
+            return ServiceFlags::NoAutoRename as u32;
+        }
+
+        return ServiceFlags::None as u32;
+    }
+
+    // Registers the subtype of the service type along with those of the options.
+    pub(crate) fn registration_type(&self, service_type: ServiceType) -> Result<String, MDnsResponderError>
+    {
+        if !self.subtypes.iter().all(|subtype| dns_name::is_valid_subtype(subtype))
+        {
+            return Err(MDnsResponderError::InvalidSubtype);
+        }
+
+        let subtypes: Vec<&str> = service_type.subtype().into_iter().chain(self.subtypes.iter().map(String::as_str)).collect();
+
+        return Ok(dns_name::registration_type(&service_type.clone().without_subtype().to_string(), &subtypes));
+    }
+}
@@ -0,0 +1,19 @@
+/// What to do with an event when the channel it is sent on is full, see
+/// `MDnsResponderBuilder::overflow_policy`.
+///
+/// Events are sent from the task that reads from mDNSResponder, so with `Block` an application
+/// that does not keep up with one operation holds up the replies of all operations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverflowPolicy
+{
+    /// Waits until the channel has room, which stops reading from the socket meanwhile.
+    #[default]
+    Block,
+    /// Queues up to another buffer size of events behind the channel and drops the oldest queued
+    /// event to make room for a new one.
+    DropOldest,
+    /// Drops the new event. Dropped events are counted, see `MDnsRequester::dropped_events`.
+    DropNewest,
+    /// Queues events behind the channel without limit.
+    Unbounded,
+}
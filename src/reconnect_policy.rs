@@ -0,0 +1,29 @@
+use std::time::Duration;
+
+/// Controls how a `MDnsResponder` reconnects to mDNSResponder after the connection is lost, for
+/// example because the daemon restarted.
+///
+/// Reconnection attempts are spaced with an exponential backoff starting at `initial_backoff`
+/// and capped at `max_backoff`. Once reconnected, all active operations are issued again with
+/// their original context identifiers, so existing handles keep working.
+#[derive(Debug, Clone)]
+pub struct ReconnectPolicy
+{
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+    /// Number of attempts before giving up, `None` to retry forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectPolicy
+{
+    fn default() -> Self
+    {
+        return ReconnectPolicy
+        {
+            initial_backoff: Duration::from_millis(250),
+            max_backoff: Duration::from_secs(30),
+            max_attempts: None,
+        };
+    }
+}
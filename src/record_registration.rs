@@ -0,0 +1,94 @@
+use crate::ipc;
+use crate::mdnsresponder_error::MDnsResponderError;
+
+/// Handle to a DNS record registered with `MDnsResponder::register_record` or added to a
+/// service with `ServiceRegistration::add_record`.
+///
+/// Records are identified by their record index within the connection or service
+/// registration they belong to.
+pub struct RecordRegistration
+{
+    context: u64,
+    record_index: u32,
+    writer: ipc::Writer,
+}
+
+impl RecordRegistration
+{
+    pub(crate) fn new(context: u64, record_index: u32, writer: ipc::Writer) -> Self
+    {
+        return RecordRegistration
+        {
+            context,
+            record_index,
+            writer,
+        };
+    }
+
+    /// Returns the context identifier of the connection or service registration the record belongs to.
+    pub fn context(&self) -> u64
+    {
+        return self.context;
+    }
+
+    /// Returns the index of the record within its connection or service registration.
+    pub fn record_index(&self) -> u32
+    {
+        return self.record_index;
+    }
+
+    /// Replaces the data of the registered record.
+    ///
+    /// # Arguments
+    ///
+    /// * `rdata` - The new raw record data.
+    /// * `ttl` - The new time to live of the record in seconds, 0 for the default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut record = responder.register_record(Interface::Any, "alias.local.".to_string(), 16, 1, vec![5, b'h', b'e', b'l', b'l', b'o'], 0, false).await?;
+    ///     record.update(vec![5, b'w', b'o', b'r', b'l', b'd'], 0).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update(&mut self, rdata: Vec<u8>, ttl: u32) -> Result<(), MDnsResponderError>
+    {
+        return match self.writer.write_update_record_request(self.context, self.record_index, rdata, ttl).await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Removes the record from the network while keeping the connection or service
+    /// registration it belongs to alive.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![]).await?;
+    ///     let record = registration.add_record(10, vec![0xde, 0xad, 0xbe, 0xef], 0).await?;
+    ///     record.remove().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn remove(self) -> Result<(), MDnsResponderError>
+    {
+        return match self.writer.write_remove_record_request(self.context, self.record_index).await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+}
@@ -0,0 +1,298 @@
+// The async runtime that drives the sockets and runs the background tasks: tokio with the
+// `tokio-runtime` feature, the default, or smol with the `smol` feature. The smol backend runs
+// on async-io like async-std does, so it also serves async-std applications. The channels come
+// from `tokio::sync` with either runtime, as those do not need the tokio runtime.
+
+#[cfg(not(any(feature = "tokio-runtime", feature = "smol")))]
+compile_error!("Enable either the `tokio-runtime` or the `smol` feature");
+
+#[cfg(feature = "tokio-runtime")]
+pub(crate) use self::tokio_runtime::*;
+
+#[cfg(all(feature = "smol", not(feature = "tokio-runtime")))]
+pub(crate) use self::smol_runtime::*;
+
+// A deadline passed before the future completed.
+#[derive(Debug)]
+pub(crate) struct Elapsed;
+
+#[cfg(feature = "tokio-runtime")]
+mod tokio_runtime
+{
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    #[cfg(unix)]
+    use std::os::fd::{ AsRawFd, OwnedFd, RawFd };
+    #[cfg(unix)]
+    use std::path::Path;
+    use std::pin::Pin;
+    use std::task::{ Context, Poll };
+    use std::time::{ Duration, Instant };
+
+    use super::Elapsed;
+
+    #[cfg(unix)]
+    pub(crate) type UnixStream = tokio::net::UnixStream;
+    pub(crate) type TcpStream = tokio::net::TcpStream;
+
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(path: &Path) -> io::Result<UnixStream>
+    {
+        return UnixStream::connect(path).await;
+    }
+
+    pub(crate) async fn connect_tcp(address: SocketAddr) -> io::Result<TcpStream>
+    {
+        return TcpStream::connect(address).await;
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn unix_stream_from_std(stream: std::os::unix::net::UnixStream) -> io::Result<UnixStream>
+    {
+        stream.set_nonblocking(true)?;
+        return UnixStream::from_std(stream);
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn unix_stream_fd(stream: &UnixStream) -> RawFd
+    {
+        return stream.as_raw_fd();
+    }
+
+    // A non-blocking socket written to with system calls of its own, waiting for the runtime to
+    // report it writable when its buffer is full.
+    #[cfg(unix)]
+    pub(crate) struct WritableFd(tokio::io::unix::AsyncFd<OwnedFd>);
+
+    #[cfg(unix)]
+    impl WritableFd
+    {
+        pub(crate) fn new(fd: OwnedFd) -> io::Result<Self>
+        {
+            return Ok(WritableFd(tokio::io::unix::AsyncFd::with_interest(fd, tokio::io::Interest::WRITABLE)?));
+        }
+
+        // Runs `write` until it does not fail with `WouldBlock`.
+        pub(crate) async fn write_with<R>(&self, write: impl FnMut(&OwnedFd) -> io::Result<R>) -> io::Result<R>
+        {
+            return self.0.async_io(tokio::io::Interest::WRITABLE, write).await;
+        }
+    }
+
+    #[cfg(all(unix, feature = "multicast-fallback"))]
+    pub(crate) type UdpSocket = tokio::net::UdpSocket;
+
+    #[cfg(all(unix, feature = "multicast-fallback"))]
+    pub(crate) fn udp_socket_from_std(socket: std::net::UdpSocket) -> io::Result<UdpSocket>
+    {
+        socket.set_nonblocking(true)?;
+        return UdpSocket::from_std(socket);
+    }
+
+    // A spawned task, which keeps running when its handle is dropped.
+    pub(crate) struct Task(tokio::task::JoinHandle<()>);
+
+    impl Task
+    {
+        pub(crate) fn abort(self)
+        {
+            self.0.abort();
+        }
+    }
+
+    impl Future for Task
+    {
+        // Describes why the task did not finish, e.g. because it panicked.
+        type Output = Result<(), String>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>
+        {
+            return Pin::new(&mut self.0).poll(cx).map_err(|e| e.to_string());
+        }
+    }
+
+    pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> Task
+    {
+        return Task(tokio::spawn(future));
+    }
+
+    pub(crate) fn spawn_detached(future: impl Future<Output = ()> + Send + 'static)
+    {
+        tokio::spawn(future);
+    }
+
+    pub(crate) async fn sleep(duration: Duration)
+    {
+        tokio::time::sleep(duration).await;
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant)
+    {
+        tokio::time::sleep_until(deadline.into()).await;
+    }
+
+    pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed>
+    {
+        return tokio::time::timeout(duration, future).await.map_err(|_| Elapsed);
+    }
+
+    pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Result<F::Output, Elapsed>
+    {
+        return tokio::time::timeout_at(deadline.into(), future).await.map_err(|_| Elapsed);
+    }
+}
+
+#[cfg(all(feature = "smol", not(feature = "tokio-runtime")))]
+mod smol_runtime
+{
+    use std::future::Future;
+    use std::io;
+    use std::net::SocketAddr;
+    #[cfg(unix)]
+    use std::os::fd::{ AsRawFd, OwnedFd, RawFd };
+    #[cfg(unix)]
+    use std::path::Path;
+    use std::pin::Pin;
+    use std::task::{ Context, Poll };
+    use std::time::{ Duration, Instant };
+
+    use smol::Timer;
+    use tokio_util::compat::{ Compat, FuturesAsyncReadCompatExt };
+
+    use super::Elapsed;
+
+    // The sockets of smol implement the `futures` IO traits, the compat wrapper gives them the
+    // tokio ones that `Connection` is built on.
+    #[cfg(unix)]
+    pub(crate) type UnixStream = Compat<smol::net::unix::UnixStream>;
+    pub(crate) type TcpStream = Compat<smol::net::TcpStream>;
+
+    #[cfg(unix)]
+    pub(crate) async fn connect_unix(path: &Path) -> io::Result<UnixStream>
+    {
+        return Ok(smol::net::unix::UnixStream::connect(path).await?.compat());
+    }
+
+    pub(crate) async fn connect_tcp(address: SocketAddr) -> io::Result<TcpStream>
+    {
+        return Ok(smol::net::TcpStream::connect(address).await?.compat());
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn unix_stream_from_std(stream: std::os::unix::net::UnixStream) -> io::Result<UnixStream>
+    {
+        return Ok(smol::net::unix::UnixStream::try_from(stream)?.compat());
+    }
+
+    #[cfg(unix)]
+    pub(crate) fn unix_stream_fd(stream: &UnixStream) -> RawFd
+    {
+        return stream.get_ref().as_raw_fd();
+    }
+
+    // A non-blocking socket written to with system calls of its own, waiting for the runtime to
+    // report it writable when its buffer is full.
+    #[cfg(unix)]
+    pub(crate) struct WritableFd(smol::Async<OwnedFd>);
+
+    #[cfg(unix)]
+    impl WritableFd
+    {
+        pub(crate) fn new(fd: OwnedFd) -> io::Result<Self>
+        {
+            return Ok(WritableFd(smol::Async::new(fd)?));
+        }
+
+        // Runs `write` until it does not fail with `WouldBlock`.
+        pub(crate) async fn write_with<R>(&self, write: impl FnMut(&OwnedFd) -> io::Result<R>) -> io::Result<R>
+        {
+            return self.0.write_with(write).await;
+        }
+    }
+
+    // Unlike the streams, the UDP socket of smol is used through its own methods, which match
+    // those of tokio.
+    #[cfg(all(unix, feature = "multicast-fallback"))]
+    pub(crate) type UdpSocket = smol::net::UdpSocket;
+
+    #[cfg(all(unix, feature = "multicast-fallback"))]
+    pub(crate) fn udp_socket_from_std(socket: std::net::UdpSocket) -> io::Result<UdpSocket>
+    {
+        return UdpSocket::try_from(socket);
+    }
+
+    // A spawned task, which keeps running when its handle is dropped.
+    pub(crate) struct Task(Option<smol::Task<()>>);
+
+    impl Task
+    {
+        pub(crate) fn abort(mut self)
+        {
+            // Dropping a smol task cancels it.
+            drop(self.0.take());
+        }
+    }
+
+    impl Drop for Task
+    {
+        fn drop(&mut self)
+        {
+            if let Some(task) = self.0.take()
+            {
+                task.detach();
+            }
+        }
+    }
+
+    impl Future for Task
+    {
+        // Describes why the task did not finish. A smol task that panics passes the panic on to
+        // the one awaiting it instead.
+        type Output = Result<(), String>;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output>
+        {
+            return match self.0.as_mut()
+            {
+                Some(task) => Pin::new(task).poll(cx).map(Ok),
+                None => Poll::Ready(Err("task was aborted".to_string())),
+            };
+        }
+    }
+
+    pub(crate) fn spawn(future: impl Future<Output = ()> + Send + 'static) -> Task
+    {
+        return Task(Some(smol::spawn(future)));
+    }
+
+    pub(crate) fn spawn_detached(future: impl Future<Output = ()> + Send + 'static)
+    {
+        smol::spawn(future).detach();
+    }
+
+    pub(crate) async fn sleep(duration: Duration)
+    {
+        Timer::after(duration).await;
+    }
+
+    pub(crate) async fn sleep_until(deadline: Instant)
+    {
+        Timer::at(deadline).await;
+    }
+
+    pub(crate) async fn timeout<F: Future>(duration: Duration, future: F) -> Result<F::Output, Elapsed>
+    {
+        return timeout_at(Instant::now() + duration, future).await;
+    }
+
+    pub(crate) async fn timeout_at<F: Future>(deadline: Instant, future: F) -> Result<F::Output, Elapsed>
+    {
+        return smol::future::or(async { Ok(future.await) }, async
+        {
+            Timer::at(deadline).await;
+            Err(Elapsed)
+        })
+        .await;
+    }
+}
@@ -0,0 +1,195 @@
+use crate::ipc;
+use crate::mdnsresponder_error::MDnsResponderError;
+use crate::record_registration::RecordRegistration;
+use crate::TxtRecord;
+
+/// Handle to a service registered with `MDnsResponder::register`.
+///
+/// The handle keeps track of the registration context and the record indices used for
+/// records added to the registration, so the service can be updated and deregistered
+/// without any low-level bookkeeping by the caller.
+pub struct ServiceRegistration
+{
+    context: u64,
+    writer: ipc::Writer,
+    next_record_index: u32,
+}
+
+impl ServiceRegistration
+{
+    pub(crate) fn new(context: u64, writer: ipc::Writer) -> Self
+    {
+        return ServiceRegistration
+        {
+            context,
+            writer,
+            next_record_index: 0,
+        };
+    }
+
+    /// Returns the unique context identifier of the registration.
+    pub fn context(&self) -> u64
+    {
+        return self.context;
+    }
+
+    /// Replaces the TXT record of the registered service.
+    ///
+    /// # Arguments
+    ///
+    /// * `txt_data` - A vector of strings representing the new TXT records of the service.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec!["state=idle".to_string()]).await?;
+    ///     registration.update_txt(vec!["state=busy".to_string()]).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update_txt(&mut self, txt_data: Vec<String>) -> Result<(), MDnsResponderError>
+    {
+        let txt = crate::txt_record::encode_strings(&txt_data)?;
+
+        return match self.writer.write_update_txt_request(self.context, txt).await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Replaces the TXT record of the registered service with a record built with
+    /// `TxtRecord::builder`.
+    ///
+    /// # Arguments
+    ///
+    /// * `txt` - The new TXT record of the service.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder, TxtRecord};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![]).await?;
+    ///     registration.update_txt_record(&TxtRecord::builder().value("state", "busy").build()?).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update_txt_record(&mut self, txt: &TxtRecord) -> Result<(), MDnsResponderError>
+    {
+        return match self.writer.write_update_txt_request(self.context, txt.to_bytes()).await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Replaces the TXT record of the registered service with pre-encoded record data.
+    ///
+    /// Unlike `update_txt`, this allows TXT records containing binary values and a custom TTL.
+    ///
+    /// # Arguments
+    ///
+    /// * `rdata` - The new TXT record data as a sequence of length-prefixed strings.
+    /// * `ttl` - The time to live of the record in seconds, 0 for the default.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![]).await?;
+    ///     registration.update_txt_rdata(vec![5, b'i', b'd', b'=', 0x00, 0xff], 120).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn update_txt_rdata(&mut self, rdata: Vec<u8>, ttl: u32) -> Result<(), MDnsResponderError>
+    {
+        return match self.writer.write_update_record_request(self.context, ipc::TXT_RECORD_INDEX, rdata, ttl).await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Adds an additional record to the registered service.
+    ///
+    /// # Arguments
+    ///
+    /// * `rrtype` - The DNS resource record type of the record (e.g., 16 for TXT).
+    /// * `rdata` - The raw record data.
+    /// * `ttl` - The time to live of the record in seconds, 0 for the default.
+    ///
+    /// # Returns
+    ///
+    /// Returns a `RecordRegistration` handle tied to this registration, used to update and remove the record.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let mut registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![]).await?;
+    ///     let mut record = registration.add_record(10, vec![0xde, 0xad, 0xbe, 0xef], 0).await?;
+    ///     record.update(vec![0xca, 0xfe], 0).await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn add_record(
+        &mut self,
+        rrtype: u16,
+        rdata: Vec<u8>,
+        ttl: u32,
+    ) -> Result<RecordRegistration, MDnsResponderError>
+    {
+        let reg_index = self.next_record_index;
+
+        return match self.writer.write_add_record_request(self.context, reg_index, rrtype, rdata, ttl).await
+        {
+            Ok(_) =>
+            {
+                self.next_record_index += 1;
+                Ok(RecordRegistration::new(self.context, reg_index, self.writer.clone()))
+            }
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+
+    /// Deregisters the service, removing it and all of its added records from the network.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::{Interface, MDnsResponder};
+    ///
+    /// #[tokio::main]
+    /// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let responder = MDnsResponder::new(10).await?;
+    ///     let registration = responder.register(Interface::Any, "My Service".to_string(), "_http._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![]).await?;
+    ///     registration.deregister().await?;
+    ///     Ok(())
+    /// }
+    /// ```
+    pub async fn deregister(self) -> Result<(), MDnsResponderError>
+    {
+        return match self.writer.write_cancel_request(self.context).await
+        {
+            Ok(_) => Ok(()),
+            Err(e) => Err(MDnsResponderError::from_write_error(e)),
+        };
+    }
+}
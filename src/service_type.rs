@@ -0,0 +1,237 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::dns_name;
+use crate::MDnsResponderError;
+
+// Longest service name allowed by RFC 6335, without the leading underscore.
+const MAX_SERVICE_NAME_LENGTH: usize = 15;
+
+/// Transport protocol of a `ServiceType`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ServiceProtocol
+{
+    Tcp,
+    /// Any protocol other than TCP, as DNS-SD names all of them `_udp`.
+    Udp,
+}
+
+/// A service type such as `_http._tcp`, checked to be well-formed when it is created, with an
+/// optional subtype. There is at most one, as a browse is for a single subtype, register more
+/// with `RegisterOptions::subtype`.
+///
+/// The daemon does not report a malformed service type, a browse for one simply finds nothing.
+/// `browse`, `resolve` and `register` parse a string into a `ServiceType` before sending it and
+/// fail with `MDnsResponderError::InvalidServiceType` instead. A `ServiceType` converts to the
+/// `_name._tcp` form, or `_name._tcp,_subtype` with a subtype, which browses for or registers
+/// the subtype. A resolve ignores the subtype.
+///
+/// # Examples
+///
+/// ```rust
+/// use mdnsresponder::{ServiceProtocol, ServiceType};
+///
+/// let service_type: ServiceType = "_printer._sub._ipp._tcp".parse().unwrap();
+/// assert_eq!(service_type.name(), "ipp");
+/// assert_eq!(service_type.protocol(), ServiceProtocol::Tcp);
+/// assert_eq!(service_type.subtype(), Some("_printer"));
+/// assert_eq!(service_type.to_string(), "_ipp._tcp,_printer");
+///
+/// assert!("_ipp._tcpp".parse::<ServiceType>().is_err());
+/// assert!("_a-very-long-service-name._tcp".parse::<ServiceType>().is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ServiceType
+{
+    name: String,
+    protocol: ServiceProtocol,
+    subtype: Option<String>,
+}
+
+impl ServiceType
+{
+    /// Creates a service type from its name without the leading underscore, e.g. `http`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidServiceType)` if the name is empty, longer than 15
+    /// characters, contains anything but ASCII letters, digits and hyphens, has no letter, or
+    /// starts, ends or has two hyphens in a row, see RFC 6335.
+    pub fn new(name: &str, protocol: ServiceProtocol) -> Result<Self, MDnsResponderError>
+    {
+        if !is_valid_service_name(name)
+        {
+            return Err(MDnsResponderError::InvalidServiceType);
+        }
+
+        return Ok(ServiceType
+        {
+            name: name.to_ascii_lowercase(),
+            protocol,
+            subtype: None,
+        });
+    }
+
+    /// Narrows the service type to a subtype, e.g. `_printer`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidSubtype)` if the subtype is empty or longer than 63 bytes.
+    pub fn with_subtype(mut self, subtype: &str) -> Result<Self, MDnsResponderError>
+    {
+        if !dns_name::is_valid_subtype(subtype)
+        {
+            return Err(MDnsResponderError::InvalidSubtype);
+        }
+
+        self.subtype = Some(subtype.to_string());
+        return Ok(self);
+    }
+
+    /// Returns the service name without the leading underscore, e.g. `http`.
+    pub fn name(&self) -> &str
+    {
+        return &self.name;
+    }
+
+    pub fn protocol(&self) -> ServiceProtocol
+    {
+        return self.protocol;
+    }
+
+    pub fn subtype(&self) -> Option<&str>
+    {
+        return self.subtype.as_deref();
+    }
+
+    // The service type alone, which is what a resolve is for.
+    pub(crate) fn without_subtype(mut self) -> Self
+    {
+        self.subtype = None;
+        return self;
+    }
+}
+
+impl fmt::Display for ServiceType
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result
+    {
+        let protocol = match self.protocol
+        {
+            ServiceProtocol::Tcp => "_tcp",
+            ServiceProtocol::Udp => "_udp",
+        };
+
+        return match &self.subtype
+        {
+            Some(subtype) => write!(f, "{}", dns_name::registration_type(&format!("_{}.{}", self.name, protocol), &[subtype])),
+            None => write!(f, "_{}.{}", self.name, protocol),
+        };
+    }
+}
+
+impl FromStr for ServiceType
+{
+    type Err = MDnsResponderError;
+
+    /// Parses `_name._tcp` or `_name._udp`, optionally with a trailing `.` and with a subtype
+    /// either as `_subtype._sub._name._tcp` or as `_name._tcp,_subtype`. The subtype is escaped
+    /// like the labels of a name, and in the second form like in `dns_name::registration_type`.
+    /// More than one subtype is rejected with `MDnsResponderError::InvalidSubtype`.
+    fn from_str(service_type: &str) -> Result<Self, Self::Err>
+    {
+        let (service_type, subtype) = split_subtype(service_type).ok_or(MDnsResponderError::InvalidServiceType)?;
+
+        let subtype = match subtype
+        {
+            Some(subtype) => Some(unescape_registration_subtype(subtype)?),
+            None => None,
+        };
+
+        let labels = dns_name::unescape_name(service_type).ok_or(MDnsResponderError::InvalidServiceType)?;
+
+        let (subtype, name, protocol) = match (labels.as_slice(), subtype)
+        {
+            ([name, protocol], subtype) => (subtype, name.as_str(), protocol.as_str()),
+            ([subtype, sub, name, protocol], None) if sub.eq_ignore_ascii_case("_sub") =>
+            {
+                (Some(subtype.clone()), name.as_str(), protocol.as_str())
+            }
+            _ => return Err(MDnsResponderError::InvalidServiceType),
+        };
+
+        let protocol = match protocol.to_ascii_lowercase().as_str()
+        {
+            "_tcp" => ServiceProtocol::Tcp,
+            "_udp" => ServiceProtocol::Udp,
+            _ => return Err(MDnsResponderError::InvalidServiceType),
+        };
+
+        let Some(name) = name.strip_prefix('_') else
+        {
+            return Err(MDnsResponderError::InvalidServiceType);
+        };
+
+        let service_type = ServiceType::new(name, protocol)?;
+
+        return match subtype
+        {
+            Some(subtype) => service_type.with_subtype(&subtype),
+            None => Ok(service_type),
+        };
+    }
+}
+
+impl From<ServiceType> for String
+{
+    fn from(service_type: ServiceType) -> Self
+    {
+        return service_type.to_string();
+    }
+}
+
+fn is_valid_service_name(name: &str) -> bool
+{
+    return !name.is_empty()
+        && name.len() <= MAX_SERVICE_NAME_LENGTH
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        && name.chars().any(|c| c.is_ascii_alphabetic())
+        && !name.starts_with('-')
+        && !name.ends_with('-')
+        && !name.contains("--");
+}
+
+// Splits off what follows the first unescaped ',', the subtype of a registration type. Returns
+// `None` if the string ends within an escape.
+fn split_subtype(service_type: &str) -> Option<(&str, Option<&str>)>
+{
+    let mut escaped = false;
+
+    for (pos, c) in service_type.char_indices()
+    {
+        match c
+        {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            ',' => return Some((&service_type[..pos], Some(&service_type[pos + 1..]))),
+            _ => {}
+        }
+    }
+
+    if escaped
+    {
+        return None;
+    }
+
+    return Some((service_type, None));
+}
+
+// Reverses the escaping of a subtype by `dns_name::registration_type`, for a single subtype.
+fn unescape_registration_subtype(subtype: &str) -> Result<String, MDnsResponderError>
+{
+    return match split_subtype(subtype)
+    {
+        Some((subtype, None)) => dns_name::unescape_label(subtype).ok_or(MDnsResponderError::InvalidSubtype),
+        _ => Err(MDnsResponderError::InvalidSubtype),
+    };
+}
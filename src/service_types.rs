@@ -0,0 +1,95 @@
+//! Common DNS-SD service types, to pass to `browse`, `resolve` and `register` instead of string
+//! literals, and human-readable descriptions for them.
+//!
+//! # Examples
+//!
+//! ```rust
+//! use mdnsresponder::service_types;
+//!
+//! assert_eq!(service_types::IPP, "_ipp._tcp");
+//! assert_eq!(service_types::description("_ipp._tcp."), Some("Internet Printing Protocol"));
+//! assert_eq!(service_types::description("_unknown._tcp"), None);
+//! ```
+
+pub const HTTP: &str = "_http._tcp";
+pub const HTTPS: &str = "_https._tcp";
+pub const IPP: &str = "_ipp._tcp";
+pub const IPPS: &str = "_ipps._tcp";
+pub const PRINTER: &str = "_printer._tcp";
+pub const PDL_DATASTREAM: &str = "_pdl-datastream._tcp";
+pub const SCANNER: &str = "_uscan._tcp";
+pub const AIRPLAY: &str = "_airplay._tcp";
+pub const RAOP: &str = "_raop._tcp";
+pub const GOOGLECAST: &str = "_googlecast._tcp";
+pub const SPOTIFY_CONNECT: &str = "_spotify-connect._tcp";
+pub const HOMEKIT: &str = "_hap._tcp";
+pub const HOMEKIT_UDP: &str = "_hap._udp";
+pub const MATTER: &str = "_matter._tcp";
+pub const MATTER_COMMISSIONABLE: &str = "_matterc._udp";
+pub const THREAD_BORDER_ROUTER: &str = "_meshcop._udp";
+pub const SSH: &str = "_ssh._tcp";
+pub const SFTP: &str = "_sftp-ssh._tcp";
+pub const SMB: &str = "_smb._tcp";
+pub const AFP: &str = "_afpovertcp._tcp";
+pub const NFS: &str = "_nfs._tcp";
+pub const WEBDAV: &str = "_webdav._tcp";
+pub const FTP: &str = "_ftp._tcp";
+pub const VNC: &str = "_rfb._tcp";
+pub const RDP: &str = "_rdp._tcp";
+pub const DEVICE_INFO: &str = "_device-info._tcp";
+pub const WORKSTATION: &str = "_workstation._tcp";
+pub const COMPANION_LINK: &str = "_companion-link._tcp";
+pub const MQTT: &str = "_mqtt._tcp";
+pub const COAP: &str = "_coap._udp";
+pub const DAAP: &str = "_daap._tcp";
+pub const ESPHOME: &str = "_esphomelib._tcp";
+pub const HOME_ASSISTANT: &str = "_home-assistant._tcp";
+
+/// Every service type in this module with its description, in no particular order.
+pub const KNOWN: &[(&str, &str)] = &[
+    (HTTP, "Web server"),
+    (HTTPS, "Secure web server"),
+    (IPP, "Internet Printing Protocol"),
+    (IPPS, "Internet Printing Protocol over TLS"),
+    (PRINTER, "LPD printer"),
+    (PDL_DATASTREAM, "Raw printer (port 9100)"),
+    (SCANNER, "eSCL scanner"),
+    (AIRPLAY, "AirPlay"),
+    (RAOP, "AirPlay audio (Remote Audio Output Protocol)"),
+    (GOOGLECAST, "Google Cast"),
+    (SPOTIFY_CONNECT, "Spotify Connect"),
+    (HOMEKIT, "HomeKit accessory"),
+    (HOMEKIT_UDP, "HomeKit accessory over Thread"),
+    (MATTER, "Matter operational node"),
+    (MATTER_COMMISSIONABLE, "Matter commissionable node"),
+    (THREAD_BORDER_ROUTER, "Thread border router"),
+    (SSH, "SSH remote login"),
+    (SFTP, "SFTP file transfer"),
+    (SMB, "SMB file sharing"),
+    (AFP, "Apple Filing Protocol file sharing"),
+    (NFS, "NFS file sharing"),
+    (WEBDAV, "WebDAV file sharing"),
+    (FTP, "FTP file transfer"),
+    (VNC, "Screen sharing (VNC)"),
+    (RDP, "Remote desktop (RDP)"),
+    (DEVICE_INFO, "Device information"),
+    (WORKSTATION, "Workstation"),
+    (COMPANION_LINK, "Apple companion link"),
+    (MQTT, "MQTT broker"),
+    (COAP, "Constrained Application Protocol"),
+    (DAAP, "Digital Audio Access Protocol (iTunes sharing)"),
+    (ESPHOME, "ESPHome device"),
+    (HOME_ASSISTANT, "Home Assistant"),
+];
+
+/// Returns the description of a service type in `KNOWN`, with or without a trailing `.` and
+/// ignoring case. Returns `None` for service types that are not known.
+pub fn description(service_type: &str) -> Option<&'static str>
+{
+    let service_type = service_type.strip_suffix('.').unwrap_or(service_type);
+
+    return KNOWN
+        .iter()
+        .find(|(known, _)| known.eq_ignore_ascii_case(service_type))
+        .map(|(_, description)| *description);
+}
@@ -0,0 +1,627 @@
+//! Mock mDNSResponder for testing code built on this crate without a running daemon.
+//!
+//! The mock listens on a Unix socket in the temporary directory, records every request it
+//! receives and answers with replies scripted per request operation.
+//!
+//! # Examples
+//!
+//! ```rust,no_run
+//! use mdnsresponder::{Interface, MDnsResponder, MDnsResponderEvent};
+//! use mdnsresponder::testing::{MockDaemon, MockReply, operation};
+//!
+//! #[tokio::main]
+//! async fn main() -> Result<(), Box<dyn std::error::Error>> {
+//!     let daemon = MockDaemon::start().await?;
+//!     daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded {
+//!         name: "My Service".to_string(),
+//!         service_type: "_http._tcp.".to_string(),
+//!         domain: "local.".to_string(),
+//!     }]);
+//!
+//!     let responder = MDnsResponder::builder().socket_path(daemon.socket_path()).build().await?;
+//!     let mut browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await?;
+//!     assert!(matches!(browse.events.recv().await, Some(MDnsResponderEvent::ServiceAdded(_))));
+//!     Ok(())
+//! }
+//! ```
+
+use std::collections::HashMap;
+use std::io;
+use std::net::IpAddr;
+use std::os::fd::{ AsRawFd, FromRawFd, OwnedFd };
+use std::path::{ Path, PathBuf };
+use std::sync::{ Arc, Mutex };
+use std::time::Duration;
+
+use tokio::io::{ AsyncReadExt, AsyncWriteExt, Interest };
+use tokio::net::{ UnixListener, UnixStream };
+use tokio::sync::mpsc;
+use tokio::task;
+
+use crate::ipc::operation::{ addrinfo, browse, resolve, ReplyHeader };
+
+const HEADER_SIZE: usize = 28;
+const VERSION: u32 = 1;
+const IPC_FLAG_NO_ERR_SD: u32 = 0x4;
+
+/// Request operation codes as sent by the client.
+pub mod operation
+{
+    pub const CONNECTION: u32 = 1;
+    pub const REGISTER_RECORD: u32 = 2;
+    pub const REMOVE_RECORD: u32 = 3;
+    pub const ENUMERATION: u32 = 4;
+    pub const REGISTER_SERVICE: u32 = 5;
+    pub const BROWSE: u32 = 6;
+    pub const RESOLVE: u32 = 7;
+    pub const QUERY: u32 = 8;
+    pub const ADD_RECORD: u32 = 10;
+    pub const UPDATE_RECORD: u32 = 11;
+    pub const SET_DOMAIN: u32 = 12;
+    pub const GET_PROPERTY: u32 = 13;
+    pub const PORT_MAPPING: u32 = 14;
+    pub const ADDRESS_INFO: u32 = 15;
+    pub const CONNECTION_DELEGATE: u32 = 19;
+    pub const CANCEL: u32 = 63;
+}
+
+const REPLY_ENUMERATION: u32 = 64;
+const REPLY_REGISTER_SERVICE: u32 = 65;
+const REPLY_BROWSE: u32 = 66;
+const REPLY_RESOLVE: u32 = 67;
+const REPLY_QUERY: u32 = 68;
+const REPLY_ADDRESS_INFO: u32 = 72;
+const REPLY_ASYNC_ERROR: u32 = 73;
+
+const FLAG_MORE_COMING: u32 = 0x1;
+const FLAG_ADD: u32 = 0x2;
+const FLAG_DEFAULT: u32 = 0x4;
+
+/// A request received by the mock daemon.
+#[derive(Debug, Clone)]
+pub struct MockRequest
+{
+    pub operation: u32,
+    pub client_context: u64,
+    pub reg_index: u32,
+    /// The request body following the IPC header.
+    pub data: Vec<u8>,
+}
+
+/// A reply the mock daemon sends for a request, addressed to the request's client context.
+#[derive(Debug, Clone)]
+pub enum MockReply
+{
+    ServiceAdded { name: String, service_type: String, domain: String },
+    ServiceRemoved { name: String, service_type: String, domain: String },
+    Resolved { full_name: String, host_target: String, port: u16, txt: Vec<String> },
+    Address { hostname: String, address: IpAddr, ttl: u32 },
+    AddressRemoved { hostname: String, address: IpAddr },
+    /// An address info reply carrying a kDNSServiceErr_* code, as sent when a lookup times out or
+    /// has no answer.
+    AddressFailed { hostname: String, error: i32 },
+    Registered { name: String, service_type: String, domain: String },
+    Record { name: String, rrtype: u16, rrclass: u16, rdata: Vec<u8>, ttl: u32, add: bool },
+    /// A query reply carrying a kDNSServiceErr_* code, as sent when a query times out or is
+    /// refused.
+    RecordFailed { name: String, rrtype: u16, error: i32 },
+    Domain { name: String, is_default: bool, add: bool },
+    /// Fails the operation with the given kDNSServiceErr_* code.
+    Error(i32),
+    /// The wrapped reply with the MoreComing flag set, as sent while more replies are queued.
+    MoreComing(Box<MockReply>),
+    /// The wrapped reply with additional reply flags set, e.g. 0x2000000 for ThresholdReached.
+    WithFlags(u32, Box<MockReply>),
+    /// The wrapped reply as received on the interface with the given index.
+    OnInterface(u32, Box<MockReply>),
+    /// The wrapped reply carrying the given kDNSServiceErr_* code instead of its own.
+    WithError(i32, Box<MockReply>),
+    /// Bytes written as they are, for unframed replies such as GetProperty.
+    Raw(Vec<u8>),
+}
+
+/// Mock mDNSResponder listening on a temporary Unix socket. The socket is removed when the mock
+/// is dropped.
+pub struct MockDaemon
+{
+    socket_path: PathBuf,
+    script: Arc<Mutex<HashMap<u32, Vec<MockReply>>>>,
+    rejections: Arc<Mutex<HashMap<u32, i32>>>,
+    delays: Arc<Mutex<HashMap<u32, Duration>>>,
+    connections: Arc<Mutex<Vec<mpsc::UnboundedSender<Vec<u8>>>>>,
+    requests: tokio::sync::Mutex<mpsc::UnboundedReceiver<MockRequest>>,
+    accept_task: task::JoinHandle<()>,
+}
+
+impl MockDaemon
+{
+    /// Starts listening on a new socket in the temporary directory.
+    pub async fn start() -> io::Result<Self>
+    {
+        let socket_path = std::env::temp_dir().join(format!("mdnsresponder-mock-{:016x}.sock", rand::random::<u64>()));
+        let listener = UnixListener::bind(&socket_path)?;
+
+        let script = Arc::new(Mutex::new(HashMap::new()));
+        let rejections = Arc::new(Mutex::new(HashMap::new()));
+        let delays = Arc::new(Mutex::new(HashMap::new()));
+        let connections = Arc::new(Mutex::new(Vec::new()));
+        let (request_sender, requests) = mpsc::unbounded_channel();
+
+        let accept_task = task::spawn(Self::accept(
+            listener,
+            script.clone(),
+            rejections.clone(),
+            delays.clone(),
+            connections.clone(),
+            request_sender,
+        ));
+
+        return Ok(MockDaemon
+        {
+            socket_path,
+            script,
+            rejections,
+            delays,
+            connections,
+            requests: tokio::sync::Mutex::new(requests),
+            accept_task,
+        });
+    }
+
+    /// Returns the path of the socket to pass to `MDnsResponderBuilder::socket_path`.
+    pub fn socket_path(&self) -> &Path
+    {
+        return &self.socket_path;
+    }
+
+    /// Answers every following request with the given operation code with `replies`.
+    pub fn on_request(&self, operation: u32, replies: Vec<MockReply>)
+    {
+        self.script.lock().unwrap().insert(operation, replies);
+    }
+
+    /// Rejects every following request with the given operation code that passes a socket for
+    /// its error, as sent with `MDnsResponderBuilder::synchronous_errors`, with the given
+    /// kDNSServiceErr_* code. Rejected requests get no replies.
+    pub fn reject(&self, operation: u32, error: i32)
+    {
+        self.rejections.lock().unwrap().insert(operation, error);
+    }
+
+    /// Answers every following request with the given operation code that passes a socket for
+    /// its error only after `delay`, like a daemon that is slow to handle it. Other requests are
+    /// handled in the meantime.
+    pub fn delay_answer(&self, operation: u32, delay: Duration)
+    {
+        self.delays.lock().unwrap().insert(operation, delay);
+    }
+
+    /// Waits for the next request received on any connection, including the initial connection
+    /// request.
+    pub async fn next_request(&self) -> Option<MockRequest>
+    {
+        return self.requests.lock().await.recv().await;
+    }
+
+    /// Waits for the next request with the given operation code, skipping all others.
+    pub async fn next_request_of(&self, operation: u32) -> Option<MockRequest>
+    {
+        loop
+        {
+            let request = self.next_request().await?;
+            if request.operation == operation
+            {
+                return Some(request);
+            }
+        }
+    }
+
+    /// Sends an unsolicited reply to the given client context on every open connection, e.g. a
+    /// service appearing on the network after browsing started.
+    pub fn send(&self, client_context: u64, reply: MockReply)
+    {
+        let frame = encode_reply(client_context, &reply);
+
+        self.connections
+            .lock()
+            .unwrap()
+            .retain(|connection| connection.send(frame.clone()).is_ok());
+    }
+
+    /// Closes all open connections, as if the daemon restarted.
+    pub fn close_connections(&self)
+    {
+        self.connections.lock().unwrap().clear();
+    }
+
+    async fn accept(
+        listener: UnixListener,
+        script: Arc<Mutex<HashMap<u32, Vec<MockReply>>>>,
+        rejections: Arc<Mutex<HashMap<u32, i32>>>,
+        delays: Arc<Mutex<HashMap<u32, Duration>>>,
+        connections: Arc<Mutex<Vec<mpsc::UnboundedSender<Vec<u8>>>>>,
+        request_sender: mpsc::UnboundedSender<MockRequest>,
+    )
+    {
+        while let Ok((stream, _)) = listener.accept().await
+        {
+            // The connection is closed once its sender is dropped from the list, so it only keeps a
+            // weak one itself.
+            let (frame_sender, frame_receiver) = mpsc::unbounded_channel();
+            let weak_frame_sender = frame_sender.downgrade();
+            connections.lock().unwrap().push(frame_sender);
+
+            task::spawn(Self::serve(
+                stream,
+                script.clone(),
+                rejections.clone(),
+                delays.clone(),
+                weak_frame_sender,
+                frame_receiver,
+                request_sender.clone(),
+            ));
+        }
+    }
+
+    async fn serve(
+        stream: UnixStream,
+        script: Arc<Mutex<HashMap<u32, Vec<MockReply>>>>,
+        rejections: Arc<Mutex<HashMap<u32, i32>>>,
+        delays: Arc<Mutex<HashMap<u32, Duration>>>,
+        frame_sender: mpsc::WeakUnboundedSender<Vec<u8>>,
+        mut frame_receiver: mpsc::UnboundedReceiver<Vec<u8>>,
+        request_sender: mpsc::UnboundedSender<MockRequest>,
+    )
+    {
+        let (mut read, mut write) = stream.into_split();
+
+        // Replies are written from their own task so scripted and unsolicited replies never interleave.
+        let writer = task::spawn(async move
+        {
+            while let Some(frame) = frame_receiver.recv().await
+            {
+                if write.write_all(&frame).await.is_err()
+                {
+                    break;
+                }
+            }
+        });
+
+        loop
+        {
+            let mut header = [0u8; HEADER_SIZE];
+            if read.read_exact(&mut header).await.is_err()
+            {
+                break;
+            }
+
+            let data_length = u32::from_be_bytes(header[4..8].try_into().unwrap()) as usize;
+            let ipc_flags = u32::from_be_bytes(header[8..12].try_into().unwrap());
+            let mut data = vec![0u8; data_length];
+
+            // Without NoErrSd the last byte carries the socket to report the error on, and the
+            // data starts with an empty path for it.
+            let error_socket = if ipc_flags & IPC_FLAG_NO_ERR_SD == 0 && data_length > 0
+            {
+                if read.read_exact(&mut data[..data_length - 1]).await.is_err()
+                {
+                    break;
+                }
+
+                match recv_with_fd(read.as_ref()).await
+                {
+                    Ok((byte, fd)) =>
+                    {
+                        data[data_length - 1] = byte;
+                        data.remove(0);
+                        fd
+                    }
+                    Err(_) => break,
+                }
+            }
+            else
+            {
+                if read.read_exact(&mut data).await.is_err()
+                {
+                    break;
+                }
+
+                None
+            };
+
+            let request = MockRequest
+            {
+                operation: u32::from_be_bytes(header[12..16].try_into().unwrap()),
+                client_context: u64::from_be_bytes(header[16..24].try_into().unwrap()),
+                reg_index: u32::from_be_bytes(header[24..28].try_into().unwrap()),
+                data,
+            };
+
+            let mut replies = script.lock().unwrap().get(&request.operation).cloned().unwrap_or_default();
+
+            if let Some(error_socket) = error_socket
+            {
+                let error = rejections.lock().unwrap().get(&request.operation).copied().unwrap_or(0);
+                if error != 0
+                {
+                    replies.clear();
+                }
+
+                let mut error_socket = std::os::unix::net::UnixStream::from(error_socket);
+
+                match delays.lock().unwrap().get(&request.operation).copied()
+                {
+                    Some(delay) =>
+                    {
+                        task::spawn(async move
+                        {
+                            tokio::time::sleep(delay).await;
+                            let _ = std::io::Write::write_all(&mut error_socket, &error.to_be_bytes());
+                        });
+                    }
+                    None =>
+                    {
+                        let _ = std::io::Write::write_all(&mut error_socket, &error.to_be_bytes());
+                    }
+                }
+            }
+
+            if let Some(frame_sender) = frame_sender.upgrade()
+            {
+                for reply in &replies
+                {
+                    let _ = frame_sender.send(encode_reply(request.client_context, reply));
+                }
+            }
+
+            let _ = request_sender.send(request);
+        }
+
+        writer.abort();
+    }
+}
+
+impl Drop for MockDaemon
+{
+    fn drop(&mut self)
+    {
+        self.accept_task.abort();
+        let _ = std::fs::remove_file(&self.socket_path);
+    }
+}
+
+// Receives a single byte along with the descriptor passed with it, if any.
+async fn recv_with_fd(stream: &UnixStream) -> io::Result<(u8, Option<OwnedFd>)>
+{
+    loop
+    {
+        stream.readable().await?;
+
+        let result = stream.try_io(Interest::READABLE, ||
+            {
+                let mut data = [0u8; 1];
+                let mut iov = libc::iovec
+                {
+                    iov_base: data.as_mut_ptr() as *mut libc::c_void,
+                    iov_len: data.len(),
+                };
+
+                let mut control = [0u64; 8];
+                let mut msg: libc::msghdr = unsafe { std::mem::zeroed() };
+                msg.msg_iov = &mut iov;
+                msg.msg_iovlen = 1;
+                msg.msg_control = control.as_mut_ptr() as *mut libc::c_void;
+                msg.msg_controllen = std::mem::size_of_val(&control) as _;
+
+                let received = unsafe { libc::recvmsg(stream.as_raw_fd(), &mut msg, 0) };
+                match received
+                {
+                    -1 => return Err(io::Error::last_os_error()),
+                    0 => return Err(io::Error::from(io::ErrorKind::UnexpectedEof)),
+                    _ => {}
+                }
+
+                let fd = unsafe {
+                    let cmsg = libc::CMSG_FIRSTHDR(&msg);
+                    if cmsg.is_null() || (*cmsg).cmsg_level != libc::SOL_SOCKET || (*cmsg).cmsg_type != libc::SCM_RIGHTS
+                    {
+                        None
+                    }
+                    else
+                    {
+                        let fd = std::ptr::read_unaligned(libc::CMSG_DATA(cmsg) as *const libc::c_int);
+                        Some(OwnedFd::from_raw_fd(fd))
+                    }
+                };
+
+                return Ok((data[0], fd));
+            }
+        );
+
+        match result
+        {
+            Err(e) if e.kind() == io::ErrorKind::WouldBlock => continue,
+            result => return result,
+        }
+    }
+}
+
+fn push_cstr(buf: &mut Vec<u8>, value: &str)
+{
+    buf.extend_from_slice(value.as_bytes());
+    buf.push(0);
+}
+
+fn reply_header(buf: &mut Vec<u8>, flags: u32, error: i32)
+{
+    buf.extend_from_slice(&flags.to_be_bytes());
+    buf.extend_from_slice(&0u32.to_be_bytes()); // Interface index
+    buf.extend_from_slice(&error.to_be_bytes());
+}
+
+// Body of an address info reply of class IN.
+fn address_reply(header: ReplyHeader, hostname: &str, rrtype: u16, rdata: &[u8], ttl: u32) -> Vec<u8>
+{
+    return addrinfo::Reply
+    {
+        header,
+        name: hostname.into(),
+        rrtype,
+        rrclass: 1,
+        rdata,
+        ttl,
+        tlvs: Vec::new(),
+    }
+    .to_bytes();
+}
+
+fn encode_reply(client_context: u64, reply: &MockReply) -> Vec<u8>
+{
+    let mut body = Vec::new();
+
+    let operation = match reply
+    {
+        MockReply::ServiceAdded { name, service_type, domain } | MockReply::ServiceRemoved { name, service_type, domain } =>
+        {
+            let flags = if let MockReply::ServiceAdded { .. } = reply { FLAG_ADD } else { 0 };
+            body = browse::Reply
+            {
+                header: ReplyHeader::new(flags, 0, 0),
+                service_name: name.into(),
+                service_type: service_type.into(),
+                service_domain: domain.into(),
+            }
+            .to_bytes();
+            REPLY_BROWSE
+        }
+        MockReply::Resolved { full_name, host_target, port, txt } =>
+        {
+            let mut txt_raw = Vec::new();
+            for entry in txt
+            {
+                txt_raw.push(entry.len() as u8);
+                txt_raw.extend_from_slice(entry.as_bytes());
+            }
+
+            body = resolve::Reply
+            {
+                header: ReplyHeader::new(0, 0, 0),
+                full_name: full_name.into(),
+                host_target: host_target.into(),
+                port: *port,
+                txt_data: Vec::new(),
+                txt_raw: &txt_raw,
+            }
+            .to_bytes();
+            REPLY_RESOLVE
+        }
+        MockReply::Address { hostname, address, .. } | MockReply::AddressRemoved { hostname, address } =>
+        {
+            let (rrtype, rdata) = match address
+            {
+                IpAddr::V4(address) => (1u16, address.octets().to_vec()),
+                IpAddr::V6(address) => (28u16, address.octets().to_vec()),
+            };
+
+            let (flags, ttl) = match reply
+            {
+                MockReply::Address { ttl, .. } => (FLAG_ADD, *ttl),
+                _ => (0, 0),
+            };
+
+            body = address_reply(ReplyHeader::new(flags, 0, 0), hostname, rrtype, &rdata, ttl);
+            REPLY_ADDRESS_INFO
+        }
+        MockReply::AddressFailed { hostname, error } =>
+        {
+            body = address_reply(ReplyHeader::new(FLAG_ADD, 0, *error), hostname, 1, &[], 0); // Type A
+            REPLY_ADDRESS_INFO
+        }
+        MockReply::Registered { name, service_type, domain } =>
+        {
+            reply_header(&mut body, FLAG_ADD, 0);
+            push_cstr(&mut body, name);
+            push_cstr(&mut body, service_type);
+            push_cstr(&mut body, domain);
+            REPLY_REGISTER_SERVICE
+        }
+        MockReply::Record { name, rrtype, rrclass, rdata, ttl, add } =>
+        {
+            reply_header(&mut body, if *add { FLAG_ADD } else { 0 }, 0);
+            push_cstr(&mut body, name);
+            body.extend_from_slice(&rrtype.to_be_bytes());
+            body.extend_from_slice(&rrclass.to_be_bytes());
+            body.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+            body.extend_from_slice(rdata);
+            body.extend_from_slice(&ttl.to_be_bytes());
+            REPLY_QUERY
+        }
+        MockReply::RecordFailed { name, rrtype, error } =>
+        {
+            reply_header(&mut body, FLAG_ADD, *error);
+            push_cstr(&mut body, name);
+            body.extend_from_slice(&rrtype.to_be_bytes());
+            body.extend_from_slice(&1u16.to_be_bytes()); // Class IN
+            body.extend_from_slice(&0u16.to_be_bytes()); // No rdata
+            body.extend_from_slice(&0u32.to_be_bytes()); // TTL
+            REPLY_QUERY
+        }
+        MockReply::Domain { name, is_default, add } =>
+        {
+            let mut flags = if *add { FLAG_ADD } else { 0 };
+            if *is_default
+            {
+                flags |= FLAG_DEFAULT;
+            }
+
+            reply_header(&mut body, flags, 0);
+            push_cstr(&mut body, name);
+            REPLY_ENUMERATION
+        }
+        MockReply::Error(error) =>
+        {
+            reply_header(&mut body, 0, *error);
+            REPLY_ASYNC_ERROR
+        }
+        MockReply::MoreComing(reply) =>
+        {
+            let mut frame = encode_reply(client_context, reply);
+            frame[HEADER_SIZE + 3] |= FLAG_MORE_COMING as u8;
+            return frame;
+        }
+        MockReply::WithFlags(flags, reply) =>
+        {
+            let mut frame = encode_reply(client_context, reply);
+            let reply_flags = u32::from_be_bytes(frame[HEADER_SIZE..HEADER_SIZE + 4].try_into().unwrap()) | flags;
+            frame[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&reply_flags.to_be_bytes());
+            return frame;
+        }
+        MockReply::OnInterface(interface_index, reply) =>
+        {
+            let mut frame = encode_reply(client_context, reply);
+            frame[HEADER_SIZE + 4..HEADER_SIZE + 8].copy_from_slice(&interface_index.to_be_bytes());
+            return frame;
+        }
+        MockReply::WithError(error, reply) =>
+        {
+            let mut frame = encode_reply(client_context, reply);
+            frame[HEADER_SIZE + 8..HEADER_SIZE + 12].copy_from_slice(&error.to_be_bytes());
+            return frame;
+        }
+        MockReply::Raw(bytes) =>
+        {
+            return bytes.clone();
+        }
+    };
+
+    let mut frame = Vec::with_capacity(HEADER_SIZE + body.len());
+    frame.extend_from_slice(&VERSION.to_be_bytes());
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&0u32.to_be_bytes()); // IPC flags
+    frame.extend_from_slice(&operation.to_be_bytes());
+    frame.extend_from_slice(&client_context.to_be_bytes());
+    frame.extend_from_slice(&0u32.to_be_bytes()); // Registration index
+    frame.extend_from_slice(&body);
+
+    return frame;
+}
@@ -0,0 +1,254 @@
+use std::future::Future;
+use std::io;
+use std::net::{ Ipv4Addr, SocketAddr };
+#[cfg(unix)]
+use std::path::PathBuf;
+use std::pin::Pin;
+
+use tokio::io::{ AsyncRead, AsyncWrite };
+
+use crate::runtime;
+use crate::Delegate;
+
+/// Loopback TCP port Bonjour for Windows listens on for IPC requests.
+pub const BONJOUR_TCP_PORT: u16 = 5354;
+
+/// A connection to the daemon that carries the IPC protocol, implemented for any bidirectional
+/// byte stream.
+pub trait Connection: AsyncRead + AsyncWrite + Send + Unpin + std::any::Any {}
+
+impl<T: AsyncRead + AsyncWrite + Send + Unpin + 'static> Connection for T {}
+
+/// Future returned by `Connector::connect`.
+pub type ConnectFuture<'a> = Pin<Box<dyn Future<Output = io::Result<Box<dyn Connection>>> + Send + 'a>>;
+
+/// Opens connections to the daemon. Implemented by `Transport` for the built-in transports, and
+/// can be implemented to use other streams, for example an in-memory stream in tests or a socket
+/// handed over by launchd.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{ConnectFuture, Connector, Delegate, MDnsResponder};
+///
+/// #[derive(Debug)]
+/// struct LocalhostConnector;
+///
+/// impl Connector for LocalhostConnector {
+///     fn connect(&self, _delegate: Option<Delegate>) -> ConnectFuture<'_> {
+///         Box::pin(async {
+///             let stream = tokio::net::TcpStream::connect("127.0.0.1:5354").await?;
+///             Ok(Box::new(stream) as Box<dyn mdnsresponder::Connection>)
+///         })
+///     }
+/// }
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::builder().connector(LocalhostConnector).build().await?;
+///     Ok(())
+/// }
+/// ```
+pub trait Connector: std::fmt::Debug + Send + Sync + 'static
+{
+    /// Opens a new connection. Called once when the `MDnsResponder` is created, on every
+    /// reconnect, and for requests that need a dedicated connection.
+    ///
+    /// `delegate` is the process the requests are issued for, if any, for transports that attach
+    /// it to the connection.
+    fn connect(&self, delegate: Option<Delegate>) -> ConnectFuture<'_>;
+
+    /// Identifies the running daemon instance, so a restart can be told apart from a dropped
+    /// connection after reconnecting. `None` if it cannot be determined.
+    fn daemon_instance(&self) -> Option<u64>
+    {
+        return None;
+    }
+
+    /// Returns the transport the last connection was opened on, e.g. the socket path a
+    /// `Backend` found. Reported with `MDnsResponderEvent::Reconnected` and by
+    /// `MDnsRequester::transport`. `None` if it is not one of the built-in transports.
+    fn transport(&self) -> Option<Transport>
+    {
+        return None;
+    }
+}
+
+/// How to reach mDNSResponder. Both transports carry the same IPC protocol.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Transport
+{
+    /// Unix domain socket at the given path, used on macOS, iOS and mDNSResponder builds for
+    /// other Unix systems.
+    #[cfg(unix)]
+    Unix(PathBuf),
+    /// TCP socket at the given address, used by Bonjour for Windows.
+    Tcp(SocketAddr),
+}
+
+impl Default for Transport
+{
+    /// The platform default, `/var/run/mDNSResponder` on Unix systems and the Bonjour loopback
+    /// TCP port on other platforms.
+    fn default() -> Self
+    {
+        #[cfg(unix)]
+        {
+            return Transport::Unix(PathBuf::from(crate::builder::DEFAULT_SOCKET_PATH));
+        }
+
+        #[cfg(not(unix))]
+        {
+            return Transport::Tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, BONJOUR_TCP_PORT)));
+        }
+    }
+}
+
+impl Transport
+{
+    /// TCP transport to Bonjour for Windows on the local machine.
+    pub fn bonjour_tcp() -> Self
+    {
+        return Transport::Tcp(SocketAddr::from((Ipv4Addr::LOCALHOST, BONJOUR_TCP_PORT)));
+    }
+}
+
+impl std::fmt::Display for Transport
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self
+        {
+            #[cfg(unix)]
+            Transport::Unix(path) =>
+            {
+                write!(f, "unix:{}", path.display())
+            }
+            Transport::Tcp(address) =>
+            {
+                write!(f, "tcp:{}", address)
+            }
+        }
+    }
+}
+
+impl Connector for Transport
+{
+    fn connect(&self, delegate: Option<Delegate>) -> ConnectFuture<'_>
+    {
+        return Box::pin(async move
+        {
+            match self
+            {
+                #[cfg(unix)]
+                Transport::Unix(socket_path) =>
+                {
+                    let stream = runtime::connect_unix(socket_path).await?;
+
+                    if let Some(delegate) = &delegate
+                    {
+                        set_delegate_socket_option(&stream, delegate)?;
+                    }
+
+                    return Ok(Box::new(stream) as Box<dyn Connection>);
+                }
+                Transport::Tcp(address) =>
+                {
+                    // Only the PID is sent in the delegate request, a UUID has to be set on
+                    // the socket.
+                    if let Some(Delegate::Uuid(_)) = delegate
+                    {
+                        return Err(delegate_not_supported());
+                    }
+
+                    let stream = runtime::connect_tcp(*address).await?;
+
+                    return Ok(Box::new(stream) as Box<dyn Connection>);
+                }
+            }
+        });
+    }
+
+    // The daemon creates a new socket file when it starts, so its inode identifies the instance.
+    fn daemon_instance(&self) -> Option<u64>
+    {
+        match self
+        {
+            #[cfg(unix)]
+            Transport::Unix(socket_path) =>
+            {
+                use std::os::unix::fs::MetadataExt;
+
+                return std::fs::metadata(socket_path).ok().map(|metadata| metadata.ino());
+            }
+            Transport::Tcp(_) => return None,
+        }
+    }
+
+    fn transport(&self) -> Option<Transport>
+    {
+        return Some(self.clone());
+    }
+}
+
+// The daemon takes the delegate identity from a socket option, which is only available on
+// Apple platforms.
+#[cfg(any(target_os = "macos", target_os = "ios"))]
+fn set_delegate_socket_option(stream: &runtime::UnixStream, delegate: &Delegate) -> io::Result<()>
+{
+    const SO_DELEGATED: libc::c_int = 0x1107;
+    const SO_DELEGATED_UUID: libc::c_int = 0x1108;
+
+    let result = match delegate
+    {
+        Delegate::Pid(pid) => unsafe {
+            libc::setsockopt(
+                runtime::unix_stream_fd(stream),
+                libc::SOL_SOCKET,
+                SO_DELEGATED,
+                pid as *const i32 as *const libc::c_void,
+                std::mem::size_of::<i32>() as libc::socklen_t,
+            )
+        },
+        Delegate::Uuid(uuid) => unsafe {
+            libc::setsockopt(
+                runtime::unix_stream_fd(stream),
+                libc::SOL_SOCKET,
+                SO_DELEGATED_UUID,
+                uuid.as_ptr() as *const libc::c_void,
+                uuid.len() as libc::socklen_t,
+            )
+        },
+    };
+
+    if result == -1
+    {
+        let e = io::Error::last_os_error();
+        log::error!("Failed to set delegate on mDNSResponder socket: {}", e);
+        return Err(e);
+    }
+
+    return Ok(());
+}
+
+#[cfg(all(unix, not(any(target_os = "macos", target_os = "ios"))))]
+fn set_delegate_socket_option(_stream: &runtime::UnixStream, delegate: &Delegate) -> io::Result<()>
+{
+    return match delegate
+    {
+        Delegate::Pid(pid) =>
+        {
+            log::debug!("Delegate socket option not supported on this platform, only sending PID {}", pid);
+            Ok(())
+        }
+        Delegate::Uuid(_) => Err(delegate_not_supported()),
+    };
+}
+
+// A delegate UUID reaches the daemon only as a socket option, without it the daemon would
+// attribute the requests to the connecting process.
+pub(crate) fn delegate_not_supported() -> io::Error
+{
+    return io::Error::new(io::ErrorKind::Unsupported, crate::MDnsResponderError::DelegateNotSupported);
+}
@@ -0,0 +1,329 @@
+use log::error;
+
+use crate::mdnsresponder_error::MDnsResponderError;
+
+// Each string is prefixed with a single length byte.
+const MAX_STRING_LENGTH: usize = 255;
+// The IPC request carries the length of the TXT data as u16.
+const MAX_TXT_LENGTH: usize = u16::MAX as usize;
+
+/// DNS-SD TXT record, a list of `key=value` attributes as described in RFC 6763 section 6.
+///
+/// Keys are compared case-insensitively. A key may have no value at all (`key`), which is
+/// different from an empty value (`key=`). Values are arbitrary bytes, use `get_str` for values
+/// that are text. Only the first occurrence of a key counts, later ones are ignored as are
+/// strings with an empty key.
+///
+/// Records are equal if they have the same keys with the same values, in any order.
+///
+/// # Examples
+///
+/// ```rust,no_run
+/// use mdnsresponder::{Interface, MDnsResponder, MDnsResponderEvent};
+///
+/// #[tokio::main]
+/// async fn main() -> Result<(), Box<dyn std::error::Error>> {
+///     let responder = MDnsResponder::new(10).await?;
+///     let mut resolve = responder.resolve(Interface::Any, "Printer".to_string(), "_ipp._tcp".to_string(), "local".to_string()).await?;
+///     if let Some(MDnsResponderEvent::ServiceResolved(resolved)) = resolve.events.recv().await {
+///         if let Some(queue) = resolved.txt.get_str("rp") {
+///             println!("Queue: {}", queue);
+///         }
+///         for (key, value) in &resolved.txt {
+///             println!("{} = {:?}", key, value);
+///         }
+///     }
+///     Ok(())
+/// }
+/// ```
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TxtRecord
+{
+    entries: Vec<(String, Option<Vec<u8>>)>,
+}
+
+impl TxtRecord
+{
+    /// Returns a builder for a TXT record to register a service with.
+    ///
+    /// # Examples
+    ///
+    /// ```rust,no_run
+    /// use mdnsresponder::TxtRecord;
+    ///
+    /// fn main() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let txt = TxtRecord::builder()
+    ///         .value("txtvers", "1")
+    ///         .value("id", [0x00, 0xff])
+    ///         .flag("secure")
+    ///         .build()?;
+    ///     assert_eq!(txt.get_str("TXTVERS"), Some("1"));
+    ///     Ok(())
+    /// }
+    /// ```
+    pub fn builder() -> TxtRecordBuilder
+    {
+        return TxtRecordBuilder
+        {
+            record: TxtRecord::default(),
+            invalid: false,
+        };
+    }
+
+    /// Parses TXT record data: a sequence of strings each prefixed with its length byte. Parsing
+    /// stops at a string that runs past the end of the data. Keys that are not valid UTF-8 are
+    /// converted lossily, attributes that would then no longer fit in a string are left out.
+    pub fn from_bytes(buf: &[u8]) -> Self
+    {
+        let mut record = TxtRecord::default();
+        let mut offset = 0;
+
+        while offset < buf.len()
+        {
+            let length = buf[offset] as usize;
+            offset += 1;
+
+            if offset + length > buf.len()
+            {
+                break;
+            }
+
+            record.push_string(&buf[offset..offset + length]);
+            offset += length;
+        }
+
+        return record;
+    }
+
+    fn push_string(&mut self, string: &[u8])
+    {
+        let (key, value) = match string.iter().position(|&b| b == b'=')
+        {
+            Some(pos) => (&string[..pos], Some(string[pos + 1..].to_vec())),
+            None => (string, None),
+        };
+
+        if key.is_empty()
+        {
+            return;
+        }
+
+        let key = String::from_utf8_lossy(key).into_owned();
+
+        // Replacement characters take more bytes than the invalid bytes they stand for, and the
+        // record could not be encoded again.
+        let length = key.len() + value.as_ref().map_or(0, |value| value.len() + 1);
+        if length > MAX_STRING_LENGTH
+        {
+            error!("TXT record attribute {:?} is {} bytes after decoding its key, skipping it", key, length);
+            return;
+        }
+
+        if !self.contains_key(&key)
+        {
+            self.entries.push((key, value));
+        }
+    }
+
+    /// Returns the value of `key`: `None` if the key is not present, `Some(None)` if it is present
+    /// without a value and `Some(Some(value))` otherwise.
+    pub fn get(&self, key: &str) -> Option<Option<&[u8]>>
+    {
+        return self.entries
+            .iter()
+            .find(|(entry_key, _)| entry_key.eq_ignore_ascii_case(key))
+            .map(|(_, value)| value.as_deref());
+    }
+
+    /// Returns the value of `key` if it is present, has a value and the value is valid UTF-8.
+    pub fn get_str(&self, key: &str) -> Option<&str>
+    {
+        return self.get(key).flatten().and_then(|value| std::str::from_utf8(value).ok());
+    }
+
+    /// Returns whether `key` is present, with or without a value.
+    pub fn contains_key(&self, key: &str) -> bool
+    {
+        return self.get(key).is_some();
+    }
+
+    /// Returns the attributes in the order they appear in the record.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, Option<&[u8]>)>
+    {
+        return self.entries.iter().map(|(key, value)| (key.as_str(), value.as_deref()));
+    }
+
+    /// Encodes the record as TXT record data. A record without attributes is encoded as a single
+    /// empty string, as an empty TXT record is not allowed.
+    pub fn to_bytes(&self) -> Vec<u8>
+    {
+        if self.entries.is_empty()
+        {
+            return vec![0];
+        }
+
+        let mut buf = Vec::new();
+
+        for (key, value) in &self.entries
+        {
+            let length = key.len() + value.as_ref().map_or(0, |value| value.len() + 1);
+            buf.push(length as u8);
+            buf.extend_from_slice(key.as_bytes());
+
+            if let Some(value) = value
+            {
+                buf.push(b'=');
+                buf.extend_from_slice(value);
+            }
+        }
+
+        return buf;
+    }
+
+    pub fn len(&self) -> usize
+    {
+        return self.entries.len();
+    }
+
+    pub fn is_empty(&self) -> bool
+    {
+        return self.entries.is_empty();
+    }
+}
+
+impl PartialEq for TxtRecord
+{
+    fn eq(&self, other: &Self) -> bool
+    {
+        // Keys are unique, so matching every attribute of one record in the other is enough.
+        return self.len() == other.len()
+            && self.entries.iter().all(|(key, value)| other.get(key) == Some(value.as_deref()));
+    }
+}
+
+impl Eq for TxtRecord {}
+
+impl<'a> IntoIterator for &'a TxtRecord
+{
+    type Item = (&'a str, Option<&'a [u8]>);
+    type IntoIter = Box<dyn Iterator<Item = Self::Item> + 'a>;
+
+    fn into_iter(self) -> Self::IntoIter
+    {
+        return Box::new(self.iter());
+    }
+}
+
+/// Builder for a `TxtRecord`, created with `TxtRecord::builder`. Attributes are validated when
+/// they are added, the first invalid one makes `build` fail.
+#[derive(Debug, Clone)]
+pub struct TxtRecordBuilder
+{
+    record: TxtRecord,
+    invalid: bool,
+}
+
+impl TxtRecordBuilder
+{
+    /// Adds a key without a value, which marks a boolean attribute as true.
+    pub fn flag(self, key: &str) -> Self
+    {
+        return self.insert(key, None);
+    }
+
+    /// Adds a key with a value, which may be empty and may contain any bytes.
+    pub fn value(self, key: &str, value: impl AsRef<[u8]>) -> Self
+    {
+        return self.insert(key, Some(value.as_ref().to_vec()));
+    }
+
+    fn insert(mut self, key: &str, value: Option<Vec<u8>>) -> Self
+    {
+        if self.invalid
+        {
+            return self;
+        }
+
+        // Keys are printable US-ASCII without '=', see RFC 6763 section 6.4.
+        if key.is_empty() || !key.bytes().all(|b| (b' '..=b'~').contains(&b) && b != b'=')
+        {
+            error!("Invalid TXT record key {:?}", key);
+            self.invalid = true;
+            return self;
+        }
+
+        if self.record.contains_key(key)
+        {
+            error!("Duplicate TXT record key {:?}", key);
+            self.invalid = true;
+            return self;
+        }
+
+        let length = key.len() + value.as_ref().map_or(0, |value| value.len() + 1);
+        if length > MAX_STRING_LENGTH
+        {
+            error!("TXT record attribute {:?} is {} bytes, at most {} are allowed", key, length, MAX_STRING_LENGTH);
+            self.invalid = true;
+            return self;
+        }
+
+        self.record.entries.push((key.to_string(), value));
+        return self;
+    }
+
+    /// Builds the record.
+    ///
+    /// # Errors
+    ///
+    /// Returns `Err(MDnsResponderError::InvalidTxtRecord)` if a key was empty, not printable
+    /// ASCII, contained '=' or was added twice, if an attribute is longer than 255 bytes, or if
+    /// the whole record is longer than 65535 bytes.
+    pub fn build(self) -> Result<TxtRecord, MDnsResponderError>
+    {
+        if self.invalid
+        {
+            return Err(MDnsResponderError::InvalidTxtRecord);
+        }
+
+        if self.record.to_bytes().len() > MAX_TXT_LENGTH
+        {
+            error!("TXT record is longer than {} bytes", MAX_TXT_LENGTH);
+            return Err(MDnsResponderError::InvalidTxtRecord);
+        }
+
+        return Ok(self.record);
+    }
+}
+
+// Encodes TXT strings as they are, without interpreting them as attributes. No strings are
+// encoded as a single empty string, like an empty `TxtRecord`.
+pub(crate) fn encode_strings(txt_data: &[String]) -> Result<Vec<u8>, MDnsResponderError>
+{
+    if txt_data.is_empty()
+    {
+        return Ok(vec![0]);
+    }
+
+    let mut buf = Vec::new();
+
+    for txt in txt_data
+    {
+        if txt.len() > MAX_STRING_LENGTH
+        {
+            error!("TXT string is {} bytes, at most {} are allowed", txt.len(), MAX_STRING_LENGTH);
+            return Err(MDnsResponderError::InvalidTxtRecord);
+        }
+
+        buf.push(txt.len() as u8);
+        buf.extend_from_slice(txt.as_bytes());
+    }
+
+    if buf.len() > MAX_TXT_LENGTH
+    {
+        error!("TXT record is longer than {} bytes", MAX_TXT_LENGTH);
+        return Err(MDnsResponderError::InvalidTxtRecord);
+    }
+
+    return Ok(buf);
+}
@@ -0,0 +1,2411 @@
+use std::time::Duration;
+
+use mdnsresponder::capture::{ CaptureReader, Direction, ReplayConnector };
+use mdnsresponder::testing::{ MockDaemon, MockReply, operation };
+use mdnsresponder::{ AddrInfoOptions, BrowseOptions, DedupPolicy, EventStreamExt, Interface, MDnsResponder, MDnsResponderError, MDnsResponderEvent, OperationKind, QueryOptions, RegisterOptions, ServiceProtocol, ServiceType, TxtRecord };
+
+async fn connect(daemon: &MockDaemon) -> MDnsResponder
+{
+    return MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .build()
+        .await
+        .expect("failed to connect to mock daemon");
+}
+
+async fn next_event(events: &mut tokio::sync::mpsc::Receiver<MDnsResponderEvent>) -> MDnsResponderEvent
+{
+    loop
+    {
+        let event = tokio::time::timeout(Duration::from_secs(5), events.recv())
+            .await
+            .expect("timed out waiting for event")
+            .expect("events channel closed");
+
+        if !is_raw_frame(&event)
+        {
+            return event;
+        }
+    }
+}
+
+// Whether the event is a frame reported with `debug-frames`, which the tests skip.
+#[cfg(feature = "debug-frames")]
+fn is_raw_frame(event: &MDnsResponderEvent) -> bool
+{
+    return matches!(event, MDnsResponderEvent::RawFrame { .. });
+}
+
+#[cfg(not(feature = "debug-frames"))]
+fn is_raw_frame(_event: &MDnsResponderEvent) -> bool
+{
+    return false;
+}
+
+#[tokio::test]
+async fn browse_delivers_scripted_services()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    let request = daemon.next_request_of(operation::BROWSE).await.unwrap();
+    assert_eq!(request.client_context, browse.context());
+
+    daemon.send(browse.context(), MockReply::ServiceRemoved
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    });
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceRemoved(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn resolve_delivers_host_port_and_txt()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Printer._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec!["rp=ipp/print".to_string()],
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut resolve = responder
+        .resolve(Interface::Any, "Printer".to_string(), "_ipp._tcp".to_string(), "local".to_string())
+        .await
+        .unwrap();
+
+    match next_event(&mut resolve.events).await
+    {
+        MDnsResponderEvent::ServiceResolved(resolved) =>
+        {
+            assert_eq!(resolved.host_target, "printer.local.");
+            assert_eq!(resolved.port, 631);
+            assert_eq!(resolved.txt_data, vec!["rp=ipp/print".to_string()]);
+            assert_eq!(resolved.txt.get_str("RP"), Some("ipp/print"));
+            assert_eq!(resolved.txt_raw, b"\x0crp=ipp/print".to_vec());
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn scripted_error_fails_operation()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::Error(-65540)]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context, .. } => assert_eq!(context, browse.context()),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn browse_error_reply_fails_operation()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    // PolicyDenied, with the empty fields the daemon sends along with an error.
+    daemon.on_request(operation::BROWSE, vec![MockReply::WithError(-65570, Box::new(MockReply::ServiceAdded
+    {
+        name: String::new(),
+        service_type: String::new(),
+        domain: String::new(),
+    }))]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context, error } =>
+        {
+            assert_eq!(context, browse.context());
+            assert_eq!(error, mdnsresponder::DnsServiceError::PolicyDenied);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn resolve_error_reply_fails_operation()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    // NoSuchName, with the empty fields the daemon sends along with an error.
+    daemon.on_request(operation::RESOLVE, vec![MockReply::WithError(-65538, Box::new(MockReply::Resolved
+    {
+        full_name: String::new(),
+        host_target: String::new(),
+        port: 0,
+        txt: Vec::new(),
+    }))]);
+
+    let responder = connect(&daemon).await;
+    let mut resolve = responder
+        .resolve(Interface::Any, "Printer".to_string(), "_ipp._tcp".to_string(), "local".to_string())
+        .await
+        .unwrap();
+
+    match next_event(&mut resolve.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context, error } =>
+        {
+            assert_eq!(context, resolve.context());
+            assert_eq!(error, mdnsresponder::DnsServiceError::NoSuchName);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn captured_replies_replay_through_parser()
+{
+    let capture_path = std::env::temp_dir().join(format!("mdnsresponder-capture-{:016x}", rand_suffix()));
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .capture(&capture_path)
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    next_event(&mut browse.events).await;
+    responder.close().await;
+
+    let frames: Vec<_> = CaptureReader::open(&capture_path).unwrap().map(Result::unwrap).collect();
+    assert!(frames.iter().any(|frame| frame.direction == Direction::Outbound));
+    assert!(frames.iter().any(|frame| frame.direction == Direction::Inbound));
+
+    let mut replay = MDnsResponder::builder()
+        .connector(ReplayConnector::open(&capture_path).unwrap())
+        .build()
+        .await
+        .unwrap();
+
+    match next_event(&mut replay.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    let _ = std::fs::remove_file(&capture_path);
+}
+
+#[test]
+fn corrupt_capture_lengths_are_rejected()
+{
+    let capture_path = std::env::temp_dir().join(format!("mdnsresponder-capture-{:016x}", rand_suffix()));
+
+    let mut file = b"MDRC\x01\x01".to_vec();
+    file.extend_from_slice(&0u64.to_be_bytes()); // Timestamp
+    file.extend_from_slice(&0u32.to_be_bytes()); // Connection
+    file.extend_from_slice(&u32::MAX.to_be_bytes()); // Length
+    std::fs::write(&capture_path, file).unwrap();
+
+    let frame = CaptureReader::open(&capture_path).unwrap().next().unwrap();
+    assert_eq!(frame.unwrap_err().kind(), std::io::ErrorKind::InvalidData);
+
+    let _ = std::fs::remove_file(&capture_path);
+}
+
+fn rand_suffix() -> u64
+{
+    return std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_nanos() as u64;
+}
+
+#[tokio::test]
+async fn dedup_suppresses_repeated_answers()
+{
+    let service = MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![service.clone(), service]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .dedup_policy(DedupPolicy::Global)
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    assert!(matches!(next_event(&mut browse.events).await, MDnsResponderEvent::ServiceAdded(_)));
+
+    let removed = MockReply::ServiceRemoved
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    };
+    daemon.send(browse.context(), removed.clone());
+    daemon.send(browse.context(), removed);
+
+    // Only the last remove is delivered, with no second add in between.
+    assert!(matches!(next_event(&mut browse.events).await, MDnsResponderEvent::ServiceRemoved(_)));
+}
+
+#[tokio::test]
+async fn batching_groups_answers_until_more_coming_clears()
+{
+    let service = |name: &str| MockReply::ServiceAdded
+    {
+        name: name.to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![
+        MockReply::MoreComing(Box::new(service("First"))),
+        MockReply::MoreComing(Box::new(service("Second"))),
+        service("Third"),
+    ]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .batch_services(true)
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServicesBatch { added, removed } =>
+        {
+            let names: Vec<_> = added.iter().map(|service| service.name.as_str()).collect();
+            assert_eq!(names, vec!["First", "Second", "Third"]);
+            assert!(removed.is_empty());
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn debounce_hides_flapping_service()
+{
+    let answer = |name: &str, add: bool|
+    {
+        let name = name.to_string();
+        let service_type = "_ipp._tcp.".to_string();
+        let domain = "local.".to_string();
+
+        match add
+        {
+            true => MockReply::ServiceAdded { name, service_type, domain },
+            false => MockReply::ServiceRemoved { name, service_type, domain },
+        }
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![
+        answer("Flappy", true),
+        answer("Gone", true),
+        answer("Flappy", false),
+        answer("Flappy", true),
+        answer("Gone", false),
+    ]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .debounce_services(Duration::from_millis(100))
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    let mut events = Vec::new();
+    for _ in 0..3
+    {
+        events.push(match next_event(&mut browse.events).await
+        {
+            MDnsResponderEvent::ServiceAdded(service) => format!("+{}", service.name),
+            MDnsResponderEvent::ServiceRemoved(service) => format!("-{}", service.name),
+            event => panic!("unexpected event {:?}", event),
+        });
+    }
+
+    assert_eq!(events, vec!["+Flappy", "+Gone", "-Gone"]);
+}
+
+#[tokio::test]
+async fn services_compare_by_identity()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![
+        MockReply::ServiceAdded { name: "Printer".to_string(), service_type: "_ipp._tcp.".to_string(), domain: "local.".to_string() },
+        MockReply::ServiceRemoved { name: "printer".to_string(), service_type: "_IPP._tcp".to_string(), domain: "local".to_string() },
+        MockReply::ServiceAdded { name: "Printer\\.1".to_string(), service_type: "_ipp._tcp.".to_string(), domain: "local.".to_string() },
+        MockReply::ServiceRemoved { name: "Printer\\0461".to_string(), service_type: "_ipp._tcp.".to_string(), domain: "local.".to_string() },
+    ]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    let mut present = std::collections::HashSet::new();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert!(present.insert(service)),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceRemoved(service) => assert!(present.remove(&service)),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    // Only the escaping of the instance name differs.
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert!(present.insert(service)),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceRemoved(service) => assert!(present.remove(&service)),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    assert!(present.is_empty());
+}
+
+#[tokio::test]
+async fn current_services_tracks_live_set()
+{
+    let service = |name: &str| MockReply::ServiceAdded
+    {
+        name: name.to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![service("First"), service("Second")]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    next_event(&mut browse.events).await;
+    next_event(&mut browse.events).await;
+    assert_eq!(responder.current_services(browse.context()).len(), 2);
+
+    daemon.send(browse.context(), MockReply::ServiceRemoved
+    {
+        name: "First".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    });
+    next_event(&mut browse.events).await;
+
+    let services = responder.current_services(browse.context());
+    assert_eq!(services.len(), 1);
+    assert_eq!(services[0].name, "Second");
+
+    responder.cancel(browse.context()).await.unwrap();
+    assert!(responder.current_services(browse.context()).is_empty());
+}
+
+#[tokio::test]
+async fn stale_addresses_expire()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![MockReply::Address
+    {
+        hostname: "printer.local.".to_string(),
+        address: "192.168.1.20".parse().unwrap(),
+        ttl: 1,
+    }]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .expire_records(true)
+        .build()
+        .await
+        .unwrap();
+    let mut lookup = responder
+        .get_addr_info(Interface::Any, "printer.local".to_string(), mdnsresponder::Protocol::IPv4)
+        .await
+        .unwrap();
+
+    assert!(matches!(next_event(&mut lookup.events).await, MDnsResponderEvent::AddressInfoResolved(_)));
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::AddressExpired(address_info) => assert_eq!(address_info.hostname, "printer.local."),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn register_sends_encoded_txt_record()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let txt = TxtRecord::builder().value("id", [0x00, 0xff]).flag("secure").build().unwrap();
+    responder
+        .register_with_txt(Interface::Any, "Device".to_string(), "_hap._tcp".to_string(), "local".to_string(), "".to_string(), 8080, &txt)
+        .await
+        .unwrap();
+
+    let request = daemon.next_request_of(operation::REGISTER_SERVICE).await.unwrap();
+    assert!(request.data.ends_with(&[0, 13, 5, b'i', b'd', b'=', 0x00, 0xff, 6, b's', b'e', b'c', b'u', b'r', b'e']));
+    assert_eq!(TxtRecord::from_bytes(&txt.to_bytes()), txt);
+}
+
+#[tokio::test]
+async fn register_without_txt_sends_empty_string()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    responder
+        .register(Interface::Any, "Device".to_string(), "_hap._tcp".to_string(), "local".to_string(), "".to_string(), 8080, vec![])
+        .await
+        .unwrap();
+
+    // The same data as an empty `TxtRecord` is encoded to.
+    let request = daemon.next_request_of(operation::REGISTER_SERVICE).await.unwrap();
+    assert!(request.data.ends_with(&[0, 1, 0]));
+    assert_eq!(TxtRecord::default().to_bytes(), vec![0]);
+}
+
+#[test]
+fn txt_attributes_that_outgrow_a_string_are_skipped()
+{
+    // Each invalid byte of the key decodes to a three byte replacement character.
+    let mut data = vec![200];
+    data.extend_from_slice(&[0xff; 200]);
+    data.extend_from_slice(b"\x05rp=ip");
+
+    let txt = TxtRecord::from_bytes(&data);
+    assert_eq!(txt.len(), 1);
+    assert_eq!(txt.to_bytes(), b"\x05rp=ip".to_vec());
+}
+
+#[test]
+fn txt_records_compare_keys_ignoring_case_and_order()
+{
+    let txt = TxtRecord::from_bytes(b"\x05rp=ip\x06secure");
+
+    assert_eq!(txt, TxtRecord::from_bytes(b"\x06SECURE\x05RP=ip"));
+    assert_ne!(txt, TxtRecord::from_bytes(b"\x05rp=IP\x06secure"));
+    assert_ne!(txt, TxtRecord::from_bytes(b"\x05rp=ip\x07secure="));
+}
+
+#[tokio::test]
+async fn browse_options_are_sent()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let options = BrowseOptions::new().interface(Interface::Index(7)).domain("example.com.").include_p2p(true);
+    responder.browse_with_options("_ipp._tcp".to_string(), options).await.unwrap();
+
+    let request = daemon.next_request_of(operation::BROWSE).await.unwrap();
+    let flags = u32::from_be_bytes(request.data[0..4].try_into().unwrap());
+    let interface_index = u32::from_be_bytes(request.data[4..8].try_into().unwrap());
+
+    assert_ne!(flags & 0x20000, 0);
+    assert_eq!(interface_index, 7);
+    assert!(request.data.ends_with(b"_ipp._tcp\0example.com.\0"));
+}
+
+#[tokio::test]
+async fn long_lived_query_flag_is_sent()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let options = QueryOptions::new().long_lived_query(true);
+    responder.query_with_options("printer.example.com.".to_string(), 1, 1, options).await.unwrap();
+
+    let request = daemon.next_request_of(operation::QUERY).await.unwrap();
+    let flags = u32::from_be_bytes(request.data[0..4].try_into().unwrap());
+
+    assert_ne!(flags & 0x100, 0);
+}
+
+#[tokio::test]
+async fn timed_out_lookup_sends_timeout()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![MockReply::AddressFailed
+    {
+        hostname: "printer.local.".to_string(),
+        error: -65568,
+    }]);
+
+    let responder = connect(&daemon).await;
+    let options = AddrInfoOptions::new().timeout(true);
+    let mut lookup = responder.get_addr_info_with_options("printer.local".to_string(), options).await.unwrap();
+
+    let request = daemon.next_request_of(operation::ADDRESS_INFO).await.unwrap();
+    let flags = u32::from_be_bytes(request.data[0..4].try_into().unwrap());
+    assert_ne!(flags & 0x10000, 0);
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::Timeout { context } => assert_eq!(context, lookup.context()),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn expired_answers_are_marked()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![MockReply::WithFlags(0x80000000, Box::new(MockReply::Address
+    {
+        hostname: "printer.local.".to_string(),
+        address: std::net::IpAddr::from([192, 168, 1, 20]),
+        ttl: 120,
+    }))]);
+
+    let responder = connect(&daemon).await;
+    let options = AddrInfoOptions::new().allow_expired_answers(true);
+    let mut lookup = responder.get_addr_info_with_options("printer.local".to_string(), options).await.unwrap();
+
+    let request = daemon.next_request_of(operation::ADDRESS_INFO).await.unwrap();
+    let flags = u32::from_be_bytes(request.data[0..4].try_into().unwrap());
+    assert_ne!(flags & 0x80000000, 0);
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::AddressInfoResolved(info) => assert!(info.flags.expired),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn dnssec_status_is_decoded()
+{
+    use mdnsresponder::DnssecStatus;
+
+    let statuses = [
+        (0x200010, Some(DnssecStatus::Secure)),
+        (0x200020, Some(DnssecStatus::Insecure)),
+        (0x200040, Some(DnssecStatus::Bogus)),
+        (0x200080, Some(DnssecStatus::Indeterminate)),
+        // The status bits mean nothing without the Validate bit.
+        (0x10, None),
+    ];
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, statuses.iter().enumerate().map(|(i, (flags, _))| MockReply::WithFlags(*flags, Box::new(MockReply::Address
+    {
+        hostname: "printer.example.com.".to_string(),
+        address: std::net::IpAddr::from([192, 0, 2, i as u8 + 1]),
+        ttl: 120,
+    }))).collect());
+
+    let responder = connect(&daemon).await;
+    let options = AddrInfoOptions::new().validate(true);
+    let mut lookup = responder.get_addr_info_with_options("printer.example.com".to_string(), options).await.unwrap();
+
+    let request = daemon.next_request_of(operation::ADDRESS_INFO).await.unwrap();
+    let flags = u32::from_be_bytes(request.data[0..4].try_into().unwrap());
+    assert_ne!(flags & 0x200000, 0);
+
+    for (_, status) in statuses
+    {
+        match next_event(&mut lookup.events).await
+        {
+            MDnsResponderEvent::AddressInfoResolved(info) => assert_eq!(info.flags.dnssec, status),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
+
+#[tokio::test]
+async fn query_errors_are_reported()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::QUERY, vec![
+        MockReply::RecordFailed
+        {
+            name: "printer.example.com.".to_string(),
+            rrtype: 1,
+            error: -65538, // NoSuchName
+        },
+        MockReply::RecordFailed
+        {
+            name: "printer.example.com.".to_string(),
+            rrtype: 1,
+            error: -65568, // Timeout
+        },
+    ]);
+
+    let mut responder = connect(&daemon).await;
+    let options = QueryOptions::new().timeout(true);
+    let context = responder.query_with_options("printer.example.com.".to_string(), 1, 1, options).await.unwrap();
+
+    match next_event(&mut responder.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context: failed, error } =>
+        {
+            assert_eq!(failed, context);
+            assert_eq!(error, mdnsresponder::DnsServiceError::NoSuchName);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    match next_event(&mut responder.events).await
+    {
+        MDnsResponderEvent::Timeout { context: timed_out } => assert_eq!(timed_out, context),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn failed_lookup_sends_operation_failed()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![MockReply::AddressFailed
+    {
+        hostname: "printer.local.".to_string(),
+        error: -65570, // PolicyDenied
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut lookup = responder.get_addr_info(Interface::Any, "printer.local".to_string(), mdnsresponder::Protocol::IPv4).await.unwrap();
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context, error } =>
+        {
+            assert_eq!(context, lookup.context());
+            assert_eq!(error, mdnsresponder::DnsServiceError::PolicyDenied);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn threshold_reached_follows_answer()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::WithFlags(0x2000000, Box::new(MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }))]);
+
+    let responder = connect(&daemon).await;
+    let options = BrowseOptions::new().threshold_one(true);
+    let mut browse = responder.browse_with_options("_ipp._tcp".to_string(), options).await.unwrap();
+
+    let request = daemon.next_request_of(operation::BROWSE).await.unwrap();
+    let flags = u32::from_be_bytes(request.data[0..4].try_into().unwrap());
+    assert_ne!(flags & 0x2000000, 0);
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert!(service.flags.threshold_reached),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ThresholdReached { context } => assert_eq!(context, browse.context()),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn subtypes_are_sent_with_browse_and_register()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    responder.browse_with_options("_http._tcp".to_string(), BrowseOptions::new().subtype("_printer")).await.unwrap();
+    let request = daemon.next_request_of(operation::BROWSE).await.unwrap();
+    assert!(request.data.ends_with(b"_printer._sub._http._tcp\0\0"));
+
+    let options = RegisterOptions::new().subtype("_printer").subtype("_scanner");
+    responder.register_with_options("Office".to_string(), "_http._tcp".to_string(), 8080, options).await.unwrap();
+    let request = daemon.next_request_of(operation::REGISTER_SERVICE).await.unwrap();
+    assert!(request.data.windows(29).any(|window| window == b"_http._tcp,_printer,_scanner\0"));
+
+    let result = responder.browse_with_options("_http._tcp".to_string(), BrowseOptions::new().subtype("")).await;
+    assert!(matches!(result, Err(MDnsResponderError::InvalidSubtype)));
+}
+
+#[tokio::test]
+async fn enumerate_service_types_decodes_meta_query_answers()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "_ipp".to_string(),
+        service_type: "_tcp.local.".to_string(),
+        domain: ".".to_string(),
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut enumeration = responder.enumerate_service_types("local".to_string()).await.unwrap();
+
+    let request = daemon.next_request_of(operation::BROWSE).await.unwrap();
+    assert!(request.data.ends_with(b"_services._dns-sd._udp\0local\0"));
+
+    match next_event(&mut enumeration.events).await
+    {
+        MDnsResponderEvent::ServiceTypeDiscovered(found) =>
+        {
+            assert_eq!(found.service_type, "_ipp._tcp");
+            assert_eq!(found.domain, "local.");
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn reverse_lookup_returns_ptr_hostname()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::QUERY, vec![MockReply::Record
+    {
+        name: "20.1.168.192.in-addr.arpa.".to_string(),
+        rrtype: 12,
+        rrclass: 1,
+        rdata: b"\x07printer\x05local\x00".to_vec(),
+        ttl: 120,
+        add: true,
+    }]);
+
+    let responder = connect(&daemon).await;
+    let hostname = responder.reverse_lookup("192.168.1.20".parse().unwrap()).await.unwrap();
+    assert_eq!(hostname, "printer.local.");
+
+    let request = daemon.next_request_of(operation::QUERY).await.unwrap();
+    assert!(request.data.windows(26).any(|window| window == b"20.1.168.192.in-addr.arpa."));
+    daemon.next_request_of(operation::CANCEL).await.unwrap();
+}
+
+#[tokio::test]
+async fn resolve_srv_exposes_priority_and_weight()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::QUERY, vec![MockReply::Record
+    {
+        name: "Office._ipp._tcp.local.".to_string(),
+        rrtype: 33,
+        rrclass: 1,
+        rdata: b"\x00\x0a\x00\x05\x02\x77\x07printer\x05local\x00".to_vec(),
+        ttl: 120,
+        add: true,
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut query = responder
+        .resolve_srv(Interface::Any, "Office".to_string(), "_ipp._tcp".to_string(), "local".to_string())
+        .await
+        .unwrap();
+
+    let request = daemon.next_request_of(operation::QUERY).await.unwrap();
+    assert!(request.data.windows(24).any(|window| window == b"Office._ipp._tcp.local.\0"));
+
+    match next_event(&mut query.events).await
+    {
+        MDnsResponderEvent::RecordAdded(record) =>
+        {
+            let srv = record.srv().unwrap();
+            assert_eq!((srv.priority, srv.weight, srv.port), (10, 5, 631));
+            assert_eq!(srv.target, "printer.local.");
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn removed_address_sends_address_info_removed()
+{
+    let address: std::net::IpAddr = "192.168.1.20".parse().unwrap();
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![
+        MockReply::Address { hostname: "printer.local.".to_string(), address, ttl: 120 },
+        MockReply::AddressRemoved { hostname: "printer.local.".to_string(), address },
+    ]);
+
+    let responder = connect(&daemon).await;
+    let mut lookup = responder
+        .get_addr_info(Interface::Any, "printer.local".to_string(), mdnsresponder::Protocol::IPv4)
+        .await
+        .unwrap();
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::AddressInfoResolved(info) =>
+        {
+            assert_eq!(info.address, address);
+            assert_eq!(info.ttl, 120);
+            assert!(info.expires_at > std::time::Instant::now() + Duration::from_secs(100));
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::AddressInfoRemoved(info) => assert_eq!(info.address, address),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn negative_address_answer_sends_no_such_record()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![MockReply::AddressFailed
+    {
+        hostname: "printer.local.".to_string(),
+        error: -65554,
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut lookup = responder
+        .get_addr_info(Interface::Any, "printer.local".to_string(), mdnsresponder::Protocol::IPv4)
+        .await
+        .unwrap();
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::NoSuchRecord { hostname, protocol } =>
+        {
+            assert_eq!(hostname, "printer.local.");
+            assert_eq!(protocol, mdnsresponder::Protocol::IPv4);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn link_local_address_gets_scope_id()
+{
+    let address: std::net::IpAddr = "fe80::1".parse().unwrap();
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::ADDRESS_INFO, vec![MockReply::OnInterface(3, Box::new(MockReply::Address
+    {
+        hostname: "printer.local.".to_string(),
+        address,
+        ttl: 120,
+    }))]);
+
+    let responder = connect(&daemon).await;
+    let mut lookup = responder
+        .get_addr_info(Interface::Any, "printer.local".to_string(), mdnsresponder::Protocol::IPv6)
+        .await
+        .unwrap();
+
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::AddressInfoResolved(info) =>
+        {
+            assert_eq!(info.scope_id(), 3);
+
+            match info.socket_addr(631)
+            {
+                std::net::SocketAddr::V6(socket_addr) => assert_eq!(socket_addr.scope_id(), 3),
+                socket_addr => panic!("unexpected socket address {}", socket_addr),
+            }
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn resolve_once_returns_first_answer_and_cancels()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Office._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec![],
+    }]);
+
+    let responder = connect(&daemon).await;
+    let resolved = responder
+        .resolve_once("Office".to_string(), "_ipp._tcp".to_string(), "local".to_string(), Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert_eq!(resolved.host_target, "printer.local.");
+    assert_eq!(resolved.port, 631);
+    daemon.next_request_of(operation::CANCEL).await.unwrap();
+
+    daemon.on_request(operation::RESOLVE, vec![]);
+    let result = responder
+        .resolve_once("Missing".to_string(), "_ipp._tcp".to_string(), "local".to_string(), Duration::from_millis(50))
+        .await;
+    assert!(matches!(result, Err(MDnsResponderError::Timeout)));
+}
+
+#[tokio::test]
+async fn discover_resolves_and_looks_up_addresses()
+{
+    let address: std::net::IpAddr = "192.168.1.20".parse().unwrap();
+    let address_v6: std::net::IpAddr = "fd00::20".parse().unwrap();
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Office".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Office._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec!["rp=ipp/print".to_string()],
+    }]);
+    // The A and AAAA answers come in separate replies, without MoreComing between them.
+    daemon.on_request(operation::ADDRESS_INFO, vec![
+        MockReply::Address { hostname: "printer.local.".to_string(), address, ttl: 120 },
+        MockReply::Address { hostname: "printer.local.".to_string(), address: address_v6, ttl: 120 },
+    ]);
+
+    let responder = connect(&daemon).await;
+    let mut discovery = responder.discover("_ipp._tcp".to_string()).await.unwrap();
+
+    match next_event(&mut discovery.events).await
+    {
+        MDnsResponderEvent::ServiceDiscovered(service) =>
+        {
+            assert_eq!(service.name, "Office");
+            assert_eq!(service.host, "printer.local.");
+            assert_eq!(service.port, 631);
+            assert_eq!(service.txt.get_str("rp"), Some("ipp/print"));
+            assert_eq!(service.addresses, vec![address, address_v6]);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn discover_drops_services_removed_while_resolving()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![
+        MockReply::ServiceAdded
+        {
+            name: "Office".to_string(),
+            service_type: "_ipp._tcp.".to_string(),
+            domain: "local.".to_string(),
+        },
+        MockReply::ServiceRemoved
+        {
+            name: "Office".to_string(),
+            service_type: "_ipp._tcp.".to_string(),
+            domain: "local.".to_string(),
+        },
+    ]);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Office._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec![],
+    }]);
+    daemon.on_request(operation::ADDRESS_INFO, vec![
+        MockReply::Address { hostname: "printer.local.".to_string(), address: "192.168.1.20".parse().unwrap(), ttl: 120 },
+        MockReply::Address { hostname: "printer.local.".to_string(), address: "fd00::20".parse().unwrap(), ttl: 120 },
+    ]);
+
+    let responder = connect(&daemon).await;
+    let mut discovery = responder.discover("_ipp._tcp".to_string()).await.unwrap();
+
+    match next_event(&mut discovery.events).await
+    {
+        MDnsResponderEvent::ServiceRemoved(service) => assert_eq!(service.name, "Office"),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    // The removal superseded the resolve, which must not report the service afterwards.
+    let event = tokio::time::timeout(Duration::from_millis(200), discovery.events.recv()).await;
+    assert!(!matches!(event, Ok(Some(MDnsResponderEvent::ServiceDiscovered(_)))), "unexpected event {:?}", event);
+}
+
+#[tokio::test]
+async fn auto_resolve_merges_resolve_into_service_added()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Office".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Office._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec![],
+    }]);
+
+    let responder = connect(&daemon).await;
+    let options = BrowseOptions::new().auto_resolve(true);
+    let mut browse = responder.browse_with_options("_ipp._tcp".to_string(), options).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) =>
+        {
+            let resolved = service.resolved.unwrap();
+            assert_eq!(resolved.host_target, "printer.local.");
+            assert_eq!(resolved.port, 631);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn wait_for_service_resolves_matching_instance()
+{
+    let service = |name: &str| MockReply::ServiceAdded
+    {
+        name: name.to_string(),
+        service_type: "_hap._tcp.".to_string(),
+        domain: "local.".to_string(),
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![service("Lamp"), service("Sensor")]);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Sensor._hap._tcp.local.".to_string(),
+        host_target: "sensor.local.".to_string(),
+        port: 8080,
+        txt: vec![],
+    }]);
+
+    let responder = connect(&daemon).await;
+    let resolved = responder
+        .wait_for_service("Sensor".to_string(), "_hap._tcp".to_string(), "local".to_string(), Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert_eq!(resolved.host_target, "sensor.local.");
+
+    let request = daemon.next_request_of(operation::RESOLVE).await.unwrap();
+    assert!(request.data.windows(7).any(|window| window == b"Sensor\0"));
+}
+
+#[tokio::test]
+async fn wait_for_service_matches_display_name()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer\\.1".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Printer\\.1._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec![],
+    }]);
+
+    let responder = connect(&daemon).await;
+    let resolved = responder
+        .wait_for_service("printer.1".to_string(), "_ipp._tcp".to_string(), "local".to_string(), Duration::from_secs(5))
+        .await
+        .unwrap();
+
+    assert_eq!(resolved.host_target, "printer.local.");
+}
+
+#[tokio::test]
+async fn filter_adapters_narrow_events()
+{
+    use futures::StreamExt;
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![
+        MockReply::ServiceRemoved { name: "Old".to_string(), service_type: "_http._tcp.".to_string(), domain: "local.".to_string() },
+        MockReply::ServiceAdded { name: "Other".to_string(), service_type: "_ipp._tcp.".to_string(), domain: "local.".to_string() },
+        MockReply::ServiceAdded { name: "Web".to_string(), service_type: "_http._tcp.".to_string(), domain: "local.".to_string() },
+    ]);
+
+    let responder = connect(&daemon).await;
+    let browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await.unwrap();
+    let mut events = browse.added_only().filter_service_type("_http._tcp");
+
+    match tokio::time::timeout(Duration::from_secs(5), events.next()).await.unwrap()
+    {
+        Some(MDnsResponderEvent::ServiceAdded(service)) => assert_eq!(service.name, "Web"),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn metrics_count_requests_and_replies()
+{
+    use std::sync::Arc;
+    use std::sync::atomic::{ AtomicU32, Ordering };
+
+    #[derive(Debug, Default, Clone)]
+    struct Counters
+    {
+        requests: Arc<AtomicU32>,
+        replies: Arc<AtomicU32>,
+    }
+
+    impl mdnsresponder::MetricsSink for Counters
+    {
+        fn request_sent(&self)
+        {
+            self.requests.fetch_add(1, Ordering::Relaxed);
+        }
+
+        fn reply_parsed(&self)
+        {
+            self.replies.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let counters = Counters::default();
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .metrics(counters.clone())
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    assert!(matches!(next_event(&mut browse.events).await, MDnsResponderEvent::ServiceAdded(_)));
+    assert_eq!(counters.requests.load(Ordering::Relaxed), 1);
+    assert_eq!(counters.replies.load(Ordering::Relaxed), 1);
+}
+
+#[tokio::test]
+async fn stats_count_replies_and_events()
+{
+    let answer = |name: &str, add: bool|
+    {
+        let name = name.to_string();
+        let service_type = "_ipp._tcp.".to_string();
+        let domain = "local.".to_string();
+
+        match add
+        {
+            true => MockReply::ServiceAdded { name, service_type, domain },
+            false => MockReply::ServiceRemoved { name, service_type, domain },
+        }
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![answer("First", true), answer("Second", true), answer("First", false)]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    for _ in 0..3
+    {
+        next_event(&mut browse.events).await;
+    }
+
+    let stats = responder.stats(browse.context()).unwrap();
+    assert_eq!((stats.replies, stats.adds, stats.removes, stats.errors), (3, 2, 1, 0));
+
+    responder.cancel(browse.context()).await.unwrap();
+    assert!(responder.stats(browse.context()).is_none());
+}
+
+// Raw frames take up room in the channel as well, which changes what is dropped.
+#[cfg(not(feature = "debug-frames"))]
+#[tokio::test]
+async fn drop_newest_counts_dropped_events()
+{
+    let service = |name: &str| MockReply::ServiceAdded
+    {
+        name: name.to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![service("First"), service("Second"), service("Third")]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .operation_buffer_size(1)
+        .overflow_policy(mdnsresponder::OverflowPolicy::DropNewest)
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    // Give the listener time to handle all answers before any is received.
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(responder.dropped_events(), 2);
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "First"),
+        event => panic!("unexpected event {:?}", event),
+    }
+    assert!(browse.events.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn short_writes_still_send_whole_frames()
+{
+    use std::io;
+    use std::path::PathBuf;
+    use std::pin::Pin;
+    use std::task::{ Context, Poll };
+    use tokio::io::{ AsyncRead, AsyncWrite, ReadBuf };
+    use tokio::net::UnixStream;
+
+    // Accepts at most a few bytes per write, like a socket with a nearly full send buffer.
+    struct ShortWrites(UnixStream);
+
+    impl AsyncRead for ShortWrites
+    {
+        fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>>
+        {
+            return Pin::new(&mut self.0).poll_read(cx, buf);
+        }
+    }
+
+    impl AsyncWrite for ShortWrites
+    {
+        fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>>
+        {
+            let length = buf.len().min(5);
+            return Pin::new(&mut self.0).poll_write(cx, &buf[..length]);
+        }
+
+        fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+        {
+            return Pin::new(&mut self.0).poll_flush(cx);
+        }
+
+        fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>>
+        {
+            return Pin::new(&mut self.0).poll_shutdown(cx);
+        }
+    }
+
+    #[derive(Debug)]
+    struct ShortWriteConnector(PathBuf);
+
+    impl mdnsresponder::Connector for ShortWriteConnector
+    {
+        fn connect(&self, _delegate: Option<mdnsresponder::Delegate>) -> mdnsresponder::ConnectFuture<'_>
+        {
+            return Box::pin(async
+            {
+                let stream = UnixStream::connect(&self.0).await?;
+                return Ok(Box::new(ShortWrites(stream)) as Box<dyn mdnsresponder::Connection>);
+            });
+        }
+    }
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let responder = MDnsResponder::builder()
+        .connector(ShortWriteConnector(daemon.socket_path().to_path_buf()))
+        .build()
+        .await
+        .unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn synchronous_errors_fail_rejected_requests()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.reject(operation::BROWSE, -65540);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Printer._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec![],
+    }]);
+
+    let responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .synchronous_errors(true)
+        .build()
+        .await
+        .unwrap();
+
+    match responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await
+    {
+        Err(MDnsResponderError::DaemonError(error)) => assert_eq!(error.code(), -65540),
+        result => panic!("unexpected result {:?}", result.map(|browse| browse.context())),
+    }
+
+    let mut resolve = responder.resolve(Interface::Any, "Printer".to_string(), "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    match next_event(&mut resolve.events).await
+    {
+        MDnsResponderEvent::ServiceResolved(resolved) => assert_eq!(resolved.port, 631),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn acknowledged_browse_fails_when_rejected()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.reject(operation::BROWSE, -65570);
+
+    let responder = connect(&daemon).await;
+
+    let options = BrowseOptions::new().acknowledge(true);
+    match responder.browse_with_options("_ipp._tcp".to_string(), options).await
+    {
+        Err(MDnsResponderError::DaemonError(error)) => assert_eq!(error.code(), -65570),
+        result => panic!("unexpected result {:?}", result.map(|browse| browse.context())),
+    }
+
+    // Without waiting, the daemon is not asked to report on the request.
+    assert!(responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.is_ok());
+}
+
+#[tokio::test]
+async fn slow_acknowledgements_do_not_hold_up_other_writes()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.delay_answer(operation::BROWSE, Duration::from_secs(2));
+    let responder = connect(&daemon).await;
+
+    let acknowledged = responder.browse_with_options("_ipp._tcp".to_string(), BrowseOptions::new().acknowledge(true));
+    let unacknowledged = async
+    {
+        // Queued behind the browse, which the daemon is slow to accept.
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        tokio::time::timeout(
+            Duration::from_secs(1),
+            responder.resolve(Interface::Any, "Printer".to_string(), "_ipp._tcp".to_string(), "local".to_string()),
+        )
+        .await
+    };
+
+    let (browse, resolve) = tokio::join!(acknowledged, unacknowledged);
+    assert!(browse.is_ok());
+    assert!(matches!(resolve, Ok(Ok(_))), "resolve waited for the browse to be accepted");
+}
+
+#[tokio::test]
+async fn active_operations_list_uncancelled_requests()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    let registration = responder
+        .register(Interface::Any, "Office".to_string(), "_ipp._tcp".to_string(), "local".to_string(), "".to_string(), 631, vec![])
+        .await
+        .unwrap();
+
+    let operations = responder.active_operations();
+    assert_eq!(operations.len(), 2);
+    assert_eq!((operations[0].context, operations[0].kind), (browse.context(), OperationKind::Browse));
+    assert_eq!(operations[0].subject, "_ipp._tcp");
+    assert_eq!((operations[1].context, operations[1].kind), (registration.context(), OperationKind::Register));
+
+    responder.cancel(browse.context()).await.unwrap();
+    registration.deregister().await.unwrap();
+    assert!(responder.active_operations().is_empty());
+}
+
+#[tokio::test]
+async fn failed_operations_are_no_longer_active()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::Error(-65540)]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context, .. } => assert_eq!(context, browse.context()),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    assert!(responder.active_operations().is_empty());
+
+    // The daemon already ended the browse, so closing has nothing to cancel.
+    let report = responder.close().await;
+    assert!(report.cancelled.is_empty());
+}
+
+#[tokio::test]
+async fn close_cancels_live_operations()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    let report = responder.close().await;
+
+    assert_eq!(report.cancelled, vec![browse.context()]);
+    assert!(report.not_cancelled.is_empty());
+    assert!(report.listener_stopped);
+
+    let cancel = tokio::time::timeout(Duration::from_secs(5), daemon.next_request_of(operation::CANCEL))
+        .await
+        .expect("timed out waiting for cancel")
+        .unwrap();
+    assert_eq!(cancel.client_context, browse.context());
+}
+
+#[tokio::test]
+async fn dropping_handles_cancels_operations()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    let browse_context = browse.context();
+    drop(browse);
+
+    let cancel = tokio::time::timeout(Duration::from_secs(5), daemon.next_request_of(operation::CANCEL))
+        .await
+        .expect("timed out waiting for cancel")
+        .unwrap();
+    assert_eq!(cancel.client_context, browse_context);
+    assert!(responder.active_operations().is_empty());
+
+    let resolve = responder
+        .resolve(Interface::Any, "Office".to_string(), "_ipp._tcp".to_string(), "local".to_string())
+        .await
+        .unwrap();
+    let resolve_context = resolve.context();
+    drop(responder);
+
+    let cancel = tokio::time::timeout(Duration::from_secs(5), daemon.next_request_of(operation::CANCEL))
+        .await
+        .expect("timed out waiting for cancel")
+        .unwrap();
+    assert_eq!(cancel.client_context, resolve_context);
+
+    // Already cancelled with the responder, so dropping the handle sends nothing more.
+    drop(resolve);
+}
+
+#[tokio::test]
+async fn recv_timeout_gives_up_without_events()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let mut responder = connect(&daemon).await;
+
+    let result = responder.recv_timeout(Duration::from_millis(50)).await;
+    assert!(matches!(result, Err(MDnsResponderError::Timeout)));
+
+    let (_requester, mut events) = responder.split();
+    let result = events.recv_deadline(std::time::Instant::now() + Duration::from_millis(50)).await;
+    assert!(matches!(result, Err(MDnsResponderError::Timeout)));
+}
+
+#[tokio::test]
+async fn try_next_event_does_not_wait()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let mut responder = connect(&daemon).await;
+    assert!(responder.try_next_event().is_none());
+
+    let (_requester, mut events) = responder.split();
+    assert!(events.try_next_event().is_none());
+}
+
+#[tokio::test]
+async fn subscribers_each_observe_events()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut ui = responder.subscribe();
+    let mut logger = responder.subscribe();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    for subscriber in [&mut ui, &mut logger]
+    {
+        let event = loop
+        {
+            let event = tokio::time::timeout(Duration::from_secs(5), subscriber.recv())
+                .await
+                .expect("timed out waiting for event")
+                .unwrap();
+
+            if !is_raw_frame(&event)
+            {
+                break event;
+            }
+        };
+
+        match event
+        {
+            MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+            event => panic!("unexpected event {:?}", event),
+        }
+    }
+}
+
+#[tokio::test]
+async fn keepalive_reports_unresponsive_daemon()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let version_reply = MockReply::Raw(vec![0, 0, 0, 0, 0, 0, 0, 4, 0, 0, 0x0f, 0xa0]);
+    daemon.on_request(operation::GET_PROPERTY, vec![version_reply.clone()]);
+
+    let mut responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .keepalive(Duration::from_millis(100))
+        .build()
+        .await
+        .unwrap();
+
+    daemon.next_request_of(operation::GET_PROPERTY).await.unwrap();
+    daemon.on_request(operation::GET_PROPERTY, vec![]);
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::DaemonUnresponsive));
+
+    daemon.on_request(operation::GET_PROPERTY, vec![version_reply]);
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::DaemonResponsive));
+}
+
+// A reply frame with the given operation code and body.
+fn reply_frame(operation: u32, client_context: u64, body: &[u8]) -> Vec<u8>
+{
+    let mut frame = Vec::new();
+    frame.extend_from_slice(&1u32.to_be_bytes()); // Version
+    frame.extend_from_slice(&(body.len() as u32).to_be_bytes());
+    frame.extend_from_slice(&0u32.to_be_bytes()); // IPC flags
+    frame.extend_from_slice(&operation.to_be_bytes());
+    frame.extend_from_slice(&client_context.to_be_bytes());
+    frame.extend_from_slice(&0u32.to_be_bytes()); // Registration index
+    frame.extend_from_slice(body);
+
+    return frame;
+}
+
+#[tokio::test]
+async fn malformed_frames_are_skipped()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    daemon.next_request_of(operation::BROWSE).await.unwrap();
+
+    let mut service = Vec::new();
+    service.extend_from_slice(&2u32.to_be_bytes()); // Add
+    service.extend_from_slice(&0u32.to_be_bytes()); // Interface index
+    service.extend_from_slice(&0i32.to_be_bytes()); // No error
+    service.extend_from_slice(b"Printer\0_ipp._tcp.\0local.\0");
+
+    // A request instead of a reply, a browse reply that ends within its header and a valid one,
+    // all in a single write.
+    let mut bytes = reply_frame(operation::BROWSE, browse.context(), &[0; 16]);
+    bytes.extend(reply_frame(66, browse.context(), &[0; 4]));
+    bytes.extend(reply_frame(66, browse.context(), &service));
+    daemon.send(browse.context(), MockReply::Raw(bytes));
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn oversized_frames_are_not_waited_for()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    daemon.next_request_of(operation::BROWSE).await.unwrap();
+
+    // A browse reply header announcing more data than any frame holds.
+    let mut header = reply_frame(66, browse.context(), &[]);
+    header[4..8].copy_from_slice(&(16u32 * 1024 * 1024).to_be_bytes());
+    daemon.send(browse.context(), MockReply::Raw(header));
+
+    // Sent apart, as the bytes received along with the invalid header are dropped with it.
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    daemon.send(browse.context(), MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    });
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn unhandled_replies_are_skipped()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    daemon.next_request_of(operation::BROWSE).await.unwrap();
+
+    let mut service = Vec::new();
+    service.extend_from_slice(&2u32.to_be_bytes()); // Add
+    service.extend_from_slice(&0u32.to_be_bytes()); // Interface index
+    service.extend_from_slice(&0i32.to_be_bytes()); // No error
+    service.extend_from_slice(b"Printer\0_ipp._tcp.\0local.\0");
+
+    // An operation from a newer daemon ahead of a browse reply.
+    let mut bytes = reply_frame(90, browse.context(), &[0; 16]);
+    bytes.extend(reply_frame(66, browse.context(), &service));
+    daemon.send(browse.context(), MockReply::Raw(bytes));
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::UnhandledReply { context, operation } =>
+        {
+            assert_eq!(context, browse.context());
+            assert_eq!(operation, 90);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    assert!(matches!(next_event(&mut browse.events).await, MDnsResponderEvent::ServiceAdded(_)));
+}
+
+#[tokio::test]
+async fn typed_service_types_are_sent()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let printers: ServiceType = "_printer._sub._ipp._tcp".parse().unwrap();
+    let _browse = responder.browse_with_options(printers.clone(), BrowseOptions::new()).await.unwrap();
+    let request = daemon.next_request_of(operation::BROWSE).await.unwrap();
+    assert!(request.data.ends_with(b"_ipp._tcp,_printer\0\0"));
+
+    let _resolve = responder
+        .resolve(Interface::Any, "Office".to_string(), printers, "local".to_string())
+        .await
+        .unwrap();
+    let request = daemon.next_request_of(operation::RESOLVE).await.unwrap();
+    assert!(request.data.windows(11).any(|window| window == b"\0_ipp._tcp\0"));
+
+    assert!(matches!("_ipp".parse::<ServiceType>(), Err(MDnsResponderError::InvalidServiceType)));
+    assert!(matches!(ServiceType::new("ipp--x", ServiceProtocol::Tcp), Err(MDnsResponderError::InvalidServiceType)));
+}
+
+#[test]
+fn service_types_round_trip_with_escaped_subtypes()
+{
+    let subtyped: ServiceType = "_a\\.b._sub._ipp._tcp".parse().unwrap();
+    assert_eq!(subtyped.subtype(), Some("_a.b"));
+    assert_eq!(subtyped.to_string().parse::<ServiceType>().unwrap(), subtyped);
+
+    let registration = ServiceType::new("ipp", ServiceProtocol::Tcp).unwrap().with_subtype("_a,b").unwrap();
+    assert_eq!(registration.to_string(), "_ipp._tcp,_a\\,b");
+    assert_eq!(registration.to_string().parse::<ServiceType>().unwrap(), registration);
+
+    assert!(matches!("_ipp._tcp,_a,_b".parse::<ServiceType>(), Err(MDnsResponderError::InvalidSubtype)));
+    assert!(matches!("_a._sub._ipp._tcp,_b".parse::<ServiceType>(), Err(MDnsResponderError::InvalidServiceType)));
+}
+
+#[tokio::test]
+async fn invalid_service_types_are_not_sent()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    for service_type in ["http.tcp", "_http", "_http._tcpp", "_ipp._tcp,_a,_b"]
+    {
+        let browse = responder.browse(Interface::Any, service_type, "local".to_string()).await;
+        assert!(browse.is_err(), "{} was browsed for", service_type);
+
+        let resolve = responder.resolve(Interface::Any, "Office".to_string(), service_type, "local".to_string()).await;
+        assert!(resolve.is_err(), "{} was resolved", service_type);
+
+        let register = responder.register(Interface::Any, "Office".to_string(), service_type, String::new(), String::new(), 80, Vec::new()).await;
+        assert!(register.is_err(), "{} was registered", service_type);
+    }
+
+    let result = responder.browse(Interface::Any, "_http", "local".to_string()).await;
+    assert!(matches!(result, Err(MDnsResponderError::InvalidServiceType)));
+
+    assert!(responder.active_operations().is_empty());
+}
+
+#[tokio::test]
+async fn invalid_hostnames_are_not_looked_up()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    for hostname in ["", "printer..local", "printer.local..", "bücher.example", &format!("{}.local", "a".repeat(64))]
+    {
+        let result = responder.get_addr_info(Interface::Any, hostname.to_string(), mdnsresponder::Protocol::Both).await;
+        assert!(matches!(result, Err(MDnsResponderError::InvalidHostname(_))), "{} was accepted", hostname);
+    }
+
+    assert!(responder.active_operations().is_empty());
+}
+
+#[tokio::test]
+async fn escaped_instance_names_get_display_names()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "My\\032Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let responder = connect(&daemon).await;
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) =>
+        {
+            assert_eq!(service.name, "My\\032Printer");
+            assert_eq!(service.display_name, "My Printer");
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+// The mock daemon runs on tokio either way, the responder here runs without it.
+#[cfg(all(feature = "smol", not(feature = "tokio-runtime")))]
+#[test]
+fn browse_runs_on_smol()
+{
+    let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+    let daemon = tokio_runtime.block_on(MockDaemon::start()).unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    smol::block_on(async
+    {
+        let responder = MDnsResponder::builder().socket_path(daemon.socket_path()).build().await.unwrap();
+        let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+        match browse.events.recv().await.unwrap()
+        {
+            MDnsResponderEvent::ServiceAdded(service) => assert_eq!(service.name, "Printer"),
+            event => panic!("unexpected event {:?}", event),
+        }
+
+        responder.close().await;
+    });
+}
+
+// Blocking counterpart of `next_event`.
+#[cfg(feature = "tokio-runtime")]
+fn next_blocking_event(operation: &mut mdnsresponder::blocking::BlockingOperation) -> MDnsResponderEvent
+{
+    loop
+    {
+        let event = operation.next_event_timeout(Duration::from_secs(5))
+            .expect("timed out waiting for event")
+            .expect("events channel closed");
+
+        if !is_raw_frame(&event)
+        {
+            return event;
+        }
+    }
+}
+
+#[cfg(feature = "tokio-runtime")]
+#[test]
+fn blocking_api_browses_and_resolves()
+{
+    use mdnsresponder::blocking::BlockingMDnsResponder;
+
+    let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+    let daemon = tokio_runtime.block_on(MockDaemon::start()).unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+    daemon.on_request(operation::RESOLVE, vec![MockReply::Resolved
+    {
+        full_name: "Printer._ipp._tcp.local.".to_string(),
+        host_target: "printer.local.".to_string(),
+        port: 631,
+        txt: vec![],
+    }]);
+
+    let responder = BlockingMDnsResponder::with_builder(MDnsResponder::builder().socket_path(daemon.socket_path())).unwrap();
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp", "local").unwrap();
+
+    let service = match next_blocking_event(&mut browse)
+    {
+        MDnsResponderEvent::ServiceAdded(service) => service,
+        event => panic!("unexpected event {:?}", event),
+    };
+
+    let resolved = responder.resolve_once(&service.name, &service.service_type, &service.domain, Duration::from_secs(5)).unwrap();
+    assert_eq!(resolved.port, 631);
+
+    drop(browse);
+    assert!(responder.close().cancelled.is_empty());
+}
+
+#[cfg(feature = "ffi")]
+#[test]
+fn c_api_browses_with_callback()
+{
+    use std::ffi::{ c_void, CStr, CString };
+    use std::sync::mpsc;
+
+    use mdnsresponder::ffi::{ self, MDnsBrowseEvent, MDNS_BROWSE_EVENT_ADDED };
+
+    unsafe extern "C" fn on_browse(event: *const MDnsBrowseEvent, user_data: *mut c_void)
+    {
+        let event = unsafe { &*event };
+        let found = unsafe { &*(user_data as *const mpsc::Sender<(u32, String)>) };
+        let name = unsafe { CStr::from_ptr(event.display_name) }.to_str().unwrap().to_string();
+        found.send((event.kind, name)).unwrap();
+    }
+
+    let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+    let daemon = tokio_runtime.block_on(MockDaemon::start()).unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "My\\032Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let socket_path = CString::new(daemon.socket_path().to_str().unwrap()).unwrap();
+    let (sender, found) = mpsc::channel::<(u32, String)>();
+
+    unsafe
+    {
+        let responder = ffi::mdns_responder_new_with_socket_path(socket_path.as_ptr());
+        assert!(!responder.is_null());
+
+        assert!(ffi::mdns_responder_browse(responder, 0, c"_ipp._tcp".as_ptr(), std::ptr::null(), Some(on_browse), std::ptr::null_mut()).is_null());
+
+        let browse = ffi::mdns_responder_browse(
+            responder,
+            0,
+            c"_ipp._tcp".as_ptr(),
+            c"local".as_ptr(),
+            Some(on_browse),
+            &sender as *const mpsc::Sender<(u32, String)> as *mut c_void,
+        );
+        assert!(!browse.is_null());
+
+        assert_eq!(found.recv_timeout(Duration::from_secs(5)).unwrap(), (MDNS_BROWSE_EVENT_ADDED, "My Printer".to_string()));
+
+        // Within a runtime, as in a browse callback, browsing fails instead of panicking and
+        // cancelling still waits without blocking on the runtime of the responder.
+        let entered = tokio_runtime.enter();
+        assert!(ffi::mdns_responder_browse(responder, 0, c"_ipp._tcp".as_ptr(), c"local".as_ptr(), Some(on_browse), std::ptr::null_mut()).is_null());
+
+        ffi::mdns_browse_cancel(browse);
+        drop(entered);
+        ffi::mdns_responder_free(responder);
+    }
+}
+
+#[cfg(feature = "uniffi")]
+#[test]
+fn bindings_deliver_events_to_listeners()
+{
+    use std::sync::{ Mutex, mpsc };
+
+    use mdnsresponder::bindings::{ ClientEvent, EventListener, MDnsClient };
+
+    struct Listener(Mutex<mpsc::Sender<ClientEvent>>);
+
+    impl EventListener for Listener
+    {
+        fn on_event(&self, event: ClientEvent)
+        {
+            self.0.lock().unwrap().send(event).unwrap();
+        }
+    }
+
+    let tokio_runtime = tokio::runtime::Runtime::new().unwrap();
+    let daemon = tokio_runtime.block_on(MockDaemon::start()).unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "My\\032Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+    daemon.on_request(operation::REGISTER_SERVICE, vec![MockReply::Registered
+    {
+        name: "Web".to_string(),
+        service_type: "_http._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let (shared_sender, shared) = mpsc::channel();
+    let (browse_sender, browsed) = mpsc::channel();
+    let client = MDnsClient::with_socket_path(daemon.socket_path().to_str().unwrap().to_string(), Box::new(Listener(Mutex::new(shared_sender)))).unwrap();
+
+    let browse = client.browse(0, "_ipp._tcp".to_string(), "local".to_string(), Box::new(Listener(Mutex::new(browse_sender)))).unwrap();
+    match browsed.recv_timeout(Duration::from_secs(5)).unwrap()
+    {
+        ClientEvent::ServiceAdded { service } => assert_eq!(service.display_name, "My Printer"),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    let registration = client.register(0, "Web".to_string(), "_http._tcp".to_string(), "local".to_string(), 8080, vec![]).unwrap();
+    match shared.recv_timeout(Duration::from_secs(5)).unwrap()
+    {
+        ClientEvent::ServiceRegistered { service } => assert_eq!(service.name, "Web"),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    browse.cancel();
+    registration.deregister().unwrap();
+    client.close();
+}
+
+#[cfg(feature = "cli")]
+#[tokio::test]
+async fn cli_browses_in_table_and_json()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.on_request(operation::BROWSE, vec![MockReply::ServiceAdded
+    {
+        name: "My\\032Printer".to_string(),
+        service_type: "_ipp._tcp.".to_string(),
+        domain: "local.".to_string(),
+    }]);
+
+    let browse = |json: bool|
+    {
+        let mut command = tokio::process::Command::new(env!("CARGO_BIN_EXE_mdns"));
+        command.arg("--socket-path").arg(daemon.socket_path()).args(["--timeout", "1"]);
+        if json
+        {
+            command.arg("--json");
+        }
+
+        command.args(["browse", "_ipp._tcp", "local"]).output()
+    };
+
+    let output = browse(false).await.unwrap();
+    assert!(output.status.success());
+    let table = String::from_utf8(output.stdout).unwrap();
+    let lines: Vec<&str> = table.lines().collect();
+    assert!(lines[0].starts_with("A/R"));
+    assert!(lines[1].starts_with("Add"));
+    assert!(lines[1].ends_with("_ipp._tcp.               My Printer"));
+
+    let output = browse(true).await.unwrap();
+    assert!(output.status.success());
+    let json = String::from_utf8(output.stdout).unwrap();
+    assert!(json.lines().any(|line| line.starts_with("{\"ServiceAdded\":") && line.contains("\"display_name\":\"My Printer\"")));
+}
+
+#[tokio::test]
+async fn auto_backend_follows_socket_path_variables()
+{
+    use mdnsresponder::{ Backend, Transport };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    let missing = daemon.socket_path().with_extension("missing");
+
+    // No other test reads the variables, they all set the socket path.
+    unsafe { std::env::set_var("DNSSD_UDS_PATH", daemon.socket_path()) };
+    assert_eq!(Backend::Auto.socket_path().as_deref(), Some(daemon.socket_path()));
+
+    let responder = MDnsResponder::builder().backend(Backend::Auto).build().await.unwrap();
+    assert_eq!(responder.transport(), Some(Transport::Unix(daemon.socket_path().to_path_buf())));
+    let _browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await.unwrap();
+    assert!(daemon.next_request_of(operation::BROWSE).await.is_some());
+
+    // MDNS_UDS_PATH takes precedence, also when it names a socket that does not exist.
+    unsafe { std::env::set_var("MDNS_UDS_PATH", &missing) };
+    assert_eq!(Backend::Auto.socket_path(), None);
+    assert!(matches!(MDnsResponder::builder().build().await, Err(MDnsResponderError::IpcConnectionCreationFailed)));
+
+    unsafe { std::env::set_var("MDNS_UDS_PATH", daemon.socket_path()) };
+    unsafe { std::env::set_var("DNSSD_UDS_PATH", &missing) };
+    assert_eq!(Backend::Auto.socket_path().as_deref(), Some(daemon.socket_path()));
+
+    unsafe { std::env::remove_var("MDNS_UDS_PATH") };
+    unsafe { std::env::remove_var("DNSSD_UDS_PATH") };
+}
+
+#[tokio::test]
+async fn reconnected_event_reports_transport()
+{
+    use mdnsresponder::{ ReconnectPolicy, Transport };
+
+    let daemon = MockDaemon::start().await.unwrap();
+    let mut responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .reconnect_policy(ReconnectPolicy::default())
+        .build()
+        .await
+        .unwrap();
+    assert!(daemon.next_request_of(operation::CONNECTION).await.is_some());
+
+    daemon.close_connections();
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::ConnectionLost));
+
+    match next_event(&mut responder.events).await
+    {
+        MDnsResponderEvent::Reconnected { transport } => assert_eq!(transport, Some(Transport::Unix(daemon.socket_path().to_path_buf()))),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn reconnect_issues_only_active_requests_again()
+{
+    use mdnsresponder::ReconnectPolicy;
+
+    let daemon = MockDaemon::start().await.unwrap();
+    let mut responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .reconnect_policy(ReconnectPolicy::default())
+        .build()
+        .await
+        .unwrap();
+    assert!(daemon.next_request_of(operation::CONNECTION).await.is_some());
+
+    let mut registration = responder
+        .register(Interface::Any, "Office".to_string(), "_ipp._tcp".to_string(), "local".to_string(), "".to_string(), 631, vec![])
+        .await
+        .unwrap();
+    for version in 0..10
+    {
+        registration.update_txt(vec![format!("version={}", version)]).await.unwrap();
+    }
+
+    let mut kept = registration.add_record(16, vec![4, b'k', b'e', b'p', b't'], 0).await.unwrap();
+    kept.update(vec![7, b'u', b'p', b'd', b'a', b't', b'e', b'd'], 0).await.unwrap();
+    let removed = registration.add_record(16, vec![7, b'r', b'e', b'm', b'o', b'v', b'e', b'd'], 0).await.unwrap();
+    removed.remove().await.unwrap();
+
+    // A registration the daemon ended is not issued again.
+    let conflicting = responder
+        .register(Interface::Any, "Taken".to_string(), "_ipp._tcp".to_string(), "local".to_string(), "".to_string(), 631, vec![])
+        .await
+        .unwrap();
+    daemon.send(conflicting.context(), MockReply::Error(-65548));
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::OperationFailed { context, .. } if context == conflicting.context()));
+
+    for _ in 0..16
+    {
+        assert!(daemon.next_request().await.is_some());
+    }
+
+    daemon.close_connections();
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::ConnectionLost));
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::Reconnected { .. }));
+
+    let mut replayed = Vec::new();
+    while let Ok(Some(request)) = tokio::time::timeout(Duration::from_millis(200), daemon.next_request()).await
+    {
+        replayed.push((request.operation, request.client_context, request.reg_index, request.data));
+    }
+
+    let frames: Vec<_> = replayed.iter().map(|(operation, context, reg_index, _)| (*operation, *context, *reg_index)).collect();
+    assert_eq!(frames, vec![
+        (operation::CONNECTION, frames[0].1, 0),
+        (operation::REGISTER_SERVICE, registration.context(), 0),
+        (operation::UPDATE_RECORD, registration.context(), u32::MAX),
+        (operation::ADD_RECORD, registration.context(), 0),
+        (operation::UPDATE_RECORD, registration.context(), 0),
+    ]);
+
+    // Only the latest data of each record is sent.
+    assert!(replayed[2].3.windows(9).any(|window| window == b"version=9"));
+    assert!(replayed[4].3.windows(7).any(|window| window == b"updated"));
+}
+
+#[tokio::test]
+async fn async_error_ends_operation_and_its_events()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+    let responder = connect(&daemon).await;
+
+    let mut browse = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+    let other = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await.unwrap();
+    daemon.next_request_of(operation::BROWSE).await.unwrap();
+    daemon.next_request_of(operation::BROWSE).await.unwrap();
+
+    daemon.send(browse.context(), MockReply::Error(-65537));
+
+    match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::OperationFailed { context, error } =>
+        {
+            assert_eq!(context, browse.context());
+            assert_eq!(error.code(), -65537);
+        }
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    // The route is gone, so the handle gets no further events.
+    let end = tokio::time::timeout(Duration::from_secs(5), browse.events.recv()).await;
+    assert!(matches!(end, Ok(None)), "unexpected event {:?}", end);
+
+    let operations = responder.active_operations();
+    assert_eq!(operations.len(), 1);
+    assert_eq!(operations[0].context, other.context());
+}
+
+#[tokio::test]
+async fn reconnect_skips_failed_and_rejected_requests()
+{
+    use mdnsresponder::ReconnectPolicy;
+
+    let daemon = MockDaemon::start().await.unwrap();
+    daemon.reject(operation::RESOLVE, -65540);
+
+    let mut responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .reconnect_policy(ReconnectPolicy::default())
+        .build()
+        .await
+        .unwrap();
+    assert!(daemon.next_request_of(operation::CONNECTION).await.is_some());
+
+    let live = responder.browse(Interface::Any, "_ipp._tcp".to_string(), "local".to_string()).await.unwrap();
+
+    let mut failed = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await.unwrap();
+    daemon.send(failed.context(), MockReply::Error(-65537));
+    assert!(matches!(next_event(&mut failed.events).await, MDnsResponderEvent::OperationFailed { .. }));
+
+    let options = mdnsresponder::ResolveOptions::new().acknowledge(true);
+    let rejected = responder.resolve_with_options("Printer".to_string(), "_ipp._tcp".to_string(), options).await;
+    assert!(matches!(rejected, Err(MDnsResponderError::DaemonError(_))));
+
+    for _ in 0..3
+    {
+        assert!(daemon.next_request().await.is_some());
+    }
+
+    daemon.close_connections();
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::ConnectionLost));
+    assert!(matches!(next_event(&mut responder.events).await, MDnsResponderEvent::Reconnected { .. }));
+
+    let mut replayed = Vec::new();
+    while let Ok(Some(request)) = tokio::time::timeout(Duration::from_millis(200), daemon.next_request()).await
+    {
+        replayed.push((request.operation, request.client_context));
+    }
+
+    assert_eq!(replayed, vec![
+        (operation::CONNECTION, replayed[0].1),
+        (operation::BROWSE, live.context()),
+    ]);
+}
+
+// A response with the records of a web server, with compressed names as responders send them.
+#[cfg(feature = "multicast-fallback")]
+fn web_server_response() -> Vec<u8>
+{
+    // Appends a record and returns the offset of its data.
+    fn push_record(packet: &mut Vec<u8>, name: &[u8], rrtype: u16, ttl: u32, rdata: &[u8]) -> usize
+    {
+        packet.extend_from_slice(name);
+        packet.extend_from_slice(&rrtype.to_be_bytes());
+        packet.extend_from_slice(&0x8001u16.to_be_bytes()); // Class IN with the cache-flush bit
+        packet.extend_from_slice(&ttl.to_be_bytes());
+        packet.extend_from_slice(&(rdata.len() as u16).to_be_bytes());
+        packet.extend_from_slice(rdata);
+        return packet.len() - rdata.len();
+    }
+
+    // One answer and three additional records.
+    let mut packet = vec![0, 0, 0x84, 0, 0, 0, 0, 1, 0, 0, 0, 3];
+
+    // "local" starts 11 bytes into the service type at offset 12.
+    let instance = push_record(&mut packet, b"\x05_http\x04_tcp\x05local\x00", 12, 4500, b"\x0aWeb Server\xc0\x0c") as u8;
+    let srv = push_record(&mut packet, &[0xc0, instance], 33, 120, b"\x00\x00\x00\x00\x1f\x90\x04host\xc0\x17");
+    push_record(&mut packet, &[0xc0, instance], 16, 4500, b"\x0bpath=/index");
+    push_record(&mut packet, &[0xc0, srv as u8 + 6], 1, 120, &[127, 0, 0, 1]);
+
+    return packet;
+}
+
+#[cfg(feature = "multicast-fallback")]
+#[tokio::test]
+async fn multicast_engine_answers_from_responses()
+{
+    use mdnsresponder::multicast::MulticastConnector;
+    use mdnsresponder::{ DnsServiceError, Protocol };
+
+    // Answers every query with all records, like a responder on the loopback interface.
+    let socket = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let address = socket.local_addr().unwrap();
+    tokio::spawn(async move
+    {
+        let mut query = [0u8; 9000];
+        while let Ok((_, source)) = socket.recv_from(&mut query).await
+        {
+            let _ = socket.send_to(&web_server_response(), source).await;
+        }
+    });
+
+    let mut responder = MDnsResponder::builder()
+        .connector(MulticastConnector::with_destination(address))
+        .build()
+        .await
+        .unwrap();
+
+    let mut browse = responder.browse(Interface::Any, "_http._tcp".to_string(), "local".to_string()).await.unwrap();
+    let service = match next_event(&mut browse.events).await
+    {
+        MDnsResponderEvent::ServiceAdded(service) => service,
+        event => panic!("unexpected event {:?}", event),
+    };
+    assert_eq!(service.name, "Web\\032Server");
+    assert_eq!(service.display_name, "Web Server");
+    assert_eq!(service.service_type, "_http._tcp.");
+    assert_eq!(service.domain, "local.");
+
+    let resolved = responder.resolve_once(service.name, service.service_type, service.domain, Duration::from_secs(5)).await.unwrap();
+    assert_eq!(resolved.full_name, "Web\\032Server._http._tcp.local.");
+    assert_eq!(resolved.host_target, "host.local.");
+    assert_eq!(resolved.port, 8080);
+    assert_eq!(resolved.txt_data, vec!["path=/index".to_string()]);
+
+    let mut lookup = responder.get_addr_info(Interface::Any, "host.local".to_string(), Protocol::IPv4).await.unwrap();
+    match next_event(&mut lookup.events).await
+    {
+        MDnsResponderEvent::AddressInfoResolved(info) => assert_eq!(info.address, std::net::IpAddr::from([127, 0, 0, 1])),
+        event => panic!("unexpected event {:?}", event),
+    }
+
+    let _registration = responder.register(Interface::Any, "Mine".to_string(), "_http._tcp", String::new(), String::new(), 80, Vec::new()).await.unwrap();
+    match next_event(&mut responder.events).await
+    {
+        MDnsResponderEvent::OperationFailed { error, .. } => assert_eq!(error, DnsServiceError::Unsupported),
+        event => panic!("unexpected event {:?}", event),
+    }
+}
+
+#[tokio::test]
+async fn pid_delegate_is_sent_in_the_delegate_request()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+
+    let _responder = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .delegate(mdnsresponder::Delegate::Pid(1234))
+        .build()
+        .await
+        .unwrap();
+
+    let request = daemon.next_request_of(operation::CONNECTION_DELEGATE).await.unwrap();
+    assert_eq!(request.data, 1234i32.to_be_bytes());
+}
+
+// Only Apple platforms let the UUID be set on the socket, elsewhere the daemon would not see it.
+#[cfg(not(any(target_os = "macos", target_os = "ios")))]
+#[tokio::test]
+async fn uuid_delegate_fails_where_it_cannot_be_set()
+{
+    let daemon = MockDaemon::start().await.unwrap();
+
+    let result = MDnsResponder::builder()
+        .socket_path(daemon.socket_path())
+        .delegate(mdnsresponder::Delegate::Uuid([7; 16]))
+        .build()
+        .await;
+
+    assert!(matches!(result, Err(MDnsResponderError::DelegateNotSupported)));
+}
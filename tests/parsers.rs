@@ -0,0 +1,141 @@
+use mdnsresponder::fuzzing::{ parse_reply, AddressInfoReply, BrowseReply, IpcHeader, ReplyHeader, ResolveReply };
+use proptest::collection::vec;
+use proptest::prelude::*;
+
+// Strings as the daemon sends them, which cannot hold the NUL that terminates them.
+fn cstr() -> impl Strategy<Value = String>
+{
+    return "[^\u{0}]{0,40}";
+}
+
+// Operation codes `IpcMessageHeader::from` knows, requests and all replies.
+fn operation() -> impl Strategy<Value = u32>
+{
+    return prop_oneof![0u32..=19, Just(63u32), 64u32..];
+}
+
+// A TXT record in wire format, length-prefixed strings.
+fn txt() -> impl Strategy<Value = Vec<u8>>
+{
+    return vec(vec(any::<u8>(), 0..64), 0..8).prop_map(|strings|
+    {
+        let mut txt = Vec::new();
+        for string in strings
+        {
+            txt.push(string.len() as u8);
+            txt.extend_from_slice(&string);
+        }
+
+        return txt;
+    });
+}
+
+proptest!
+{
+    #[test]
+    fn ipc_header_round_trips(
+        version in any::<u32>(),
+        data_length in any::<u32>(),
+        ipc_flags in any::<u32>(),
+        operation in operation(),
+        client_context in any::<u64>(),
+        reg_index in any::<u32>(),
+    )
+    {
+        let header = IpcHeader { version, data_length, ipc_flags, operation, client_context, reg_index };
+        prop_assert_eq!(IpcHeader::decode(&header.encode()), Some(header));
+    }
+
+    #[test]
+    fn decoded_ipc_header_encodes_to_its_bytes(bytes in vec(any::<u8>(), 0..40))
+    {
+        if let Some(header) = IpcHeader::decode(&bytes)
+        {
+            prop_assert_eq!(header.encode(), &bytes[..28]);
+        }
+        else
+        {
+            prop_assert!(bytes.len() < 28 || (20..63).contains(&u32::from_be_bytes(bytes[12..16].try_into().unwrap())));
+        }
+    }
+
+    #[test]
+    fn reply_header_round_trips(flags in any::<u32>(), interface_index in any::<u32>(), error in any::<i32>())
+    {
+        let header = ReplyHeader { flags, interface_index, error };
+        prop_assert_eq!(ReplyHeader::decode(&header.encode()), Some(header));
+    }
+
+    #[test]
+    fn browse_reply_round_trips(
+        flags in any::<u32>(),
+        interface_index in any::<u32>(),
+        error in any::<i32>(),
+        service_name in cstr(),
+        service_type in cstr(),
+        service_domain in cstr(),
+    )
+    {
+        let reply = BrowseReply { flags, interface_index, error, service_name, service_type, service_domain };
+        prop_assert_eq!(BrowseReply::decode(&reply.encode()), Some(reply));
+    }
+
+    #[test]
+    fn resolve_reply_round_trips(
+        flags in any::<u32>(),
+        interface_index in any::<u32>(),
+        error in any::<i32>(),
+        full_name in cstr(),
+        host_target in cstr(),
+        port in any::<u16>(),
+        txt in txt(),
+    )
+    {
+        let reply = ResolveReply { flags, interface_index, error, full_name, host_target, port, txt };
+        prop_assert_eq!(ResolveReply::decode(&reply.encode()), Some(reply));
+    }
+
+    #[test]
+    fn address_info_reply_round_trips(
+        flags in any::<u32>(),
+        interface_index in any::<u32>(),
+        error in any::<i32>(),
+        name in cstr(),
+        rrtype in any::<u16>(),
+        rrclass in any::<u16>(),
+        rdata in vec(any::<u8>(), 0..64),
+        ttl in any::<u32>(),
+        tlvs in vec((any::<u16>(), vec(any::<u8>(), 0..32)), 0..4),
+    )
+    {
+        let reply = AddressInfoReply { flags, interface_index, error, name, rrtype, rrclass, rdata, ttl, tlvs };
+        prop_assert_eq!(AddressInfoReply::decode(&reply.encode()), Some(reply));
+    }
+
+    #[test]
+    fn truncated_replies_do_not_panic(
+        name in cstr(),
+        rdata in vec(any::<u8>(), 0..64),
+        txt in txt(),
+        cut in any::<prop::sample::Index>(),
+    )
+    {
+        let resolve = ResolveReply { flags: 0, interface_index: 0, error: 0, full_name: name.clone(), host_target: name.clone(), port: 80, txt };
+        let address = AddressInfoReply { flags: 0, interface_index: 0, error: 0, name, rrtype: 1, rrclass: 1, rdata, ttl: 120, tlvs: Vec::new() };
+
+        for (operation, body) in [(67, resolve.encode()), (72, address.encode())]
+        {
+            let truncated = &body[..cut.index(body.len())];
+            parse_reply(operation, truncated);
+        }
+    }
+
+    #[test]
+    fn reply_parsers_do_not_panic(operation in 60u32..80, body in vec(any::<u8>(), 0..128))
+    {
+        parse_reply(operation, &body);
+        BrowseReply::decode(&body);
+        ResolveReply::decode(&body);
+        AddressInfoReply::decode(&body);
+    }
+}